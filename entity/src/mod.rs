@@ -23,6 +23,8 @@ pub mod folder_file_junction;
 pub mod general_property_information;
 pub mod group_app_junction;
 pub mod groups;
+pub mod hive_peer;
+pub mod hive_peer_rule;
 pub mod invoice;
 pub mod invoice_product_junction;
 pub mod job;
@@ -32,6 +34,7 @@ pub mod log_notes;
 pub mod meeti;
 pub mod meeti_participants;
 pub mod music;
+pub mod network_interface;
 pub mod note;
 pub mod personal_log;
 pub mod price;
@@ -41,6 +44,7 @@ pub mod property;
 pub mod property_comment;
 pub mod property_rating;
 pub mod property_seller_message;
+pub mod resource_sample;
 pub mod sea_orm_active_enums;
 pub mod sequelize_meta;
 pub mod server_location;
@@ -21,6 +21,8 @@ pub use super::folder_file_junction::Entity as FolderFileJunction;
 pub use super::general_property_information::Entity as GeneralPropertyInformation;
 pub use super::group_app_junction::Entity as GroupAppJunction;
 pub use super::groups::Entity as Groups;
+pub use super::hive_peer::Entity as HivePeer;
+pub use super::hive_peer_rule::Entity as HivePeerRule;
 pub use super::invoice::Entity as Invoice;
 pub use super::invoice_product_junction::Entity as InvoiceProductJunction;
 pub use super::job::Entity as Job;
@@ -30,6 +32,7 @@ pub use super::log_notes::Entity as LogNotes;
 pub use super::meeti::Entity as Meeti;
 pub use super::meeti_participants::Entity as MeetiParticipants;
 pub use super::music::Entity as Music;
+pub use super::network_interface::Entity as NetworkInterface;
 pub use super::note::Entity as Note;
 pub use super::personal_log::Entity as PersonalLog;
 pub use super::price::Entity as Price;
@@ -39,6 +42,7 @@ pub use super::property::Entity as Property;
 pub use super::property_comment::Entity as PropertyComment;
 pub use super::property_rating::Entity as PropertyRating;
 pub use super::property_seller_message::Entity as PropertySellerMessage;
+pub use super::resource_sample::Entity as ResourceSample;
 pub use super::sequelize_meta::Entity as SequelizeMeta;
 pub use super::server_location::Entity as ServerLocation;
 pub use super::server_node::Entity as ServerNode;
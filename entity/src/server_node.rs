@@ -19,10 +19,16 @@ pub struct Model {
     pub system_info_id: Option<i64>,
     #[sea_orm(column_name = "systemResourceId")]
     pub system_resource_id: Option<i64>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub labels: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::hive_peer::Entity")]
+    HivePeer,
+    #[sea_orm(has_many = "super::resource_sample::Entity")]
+    ResourceSample,
     #[sea_orm(
         belongs_to = "super::server_location::Entity",
         from = "Column::ServerLocationId",
@@ -49,6 +55,18 @@ pub enum Relation {
     SystemResources,
 }
 
+impl Related<super::hive_peer::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::HivePeer.def()
+    }
+}
+
+impl Related<super::resource_sample::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ResourceSample.def()
+    }
+}
+
 impl Related<super::server_location::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::ServerLocation.def()
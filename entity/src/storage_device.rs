@@ -10,6 +10,8 @@ pub struct Model {
     pub total: i64,
     pub used: i64,
     pub name: String,
+    #[sea_orm(column_name = "mountPoint")]
+    pub mount_point: String,
     #[sea_orm(column_name = "isRemovable")]
     pub is_removable: i8,
     pub kind: String,
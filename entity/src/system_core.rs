@@ -13,6 +13,12 @@ pub struct Model {
     pub free_percentage: f32,
     #[sea_orm(column_name = "systemResourceId")]
     pub system_resource_id: Option<i64>,
+    #[sea_orm(column_name = "coreIndex")]
+    pub core_index: i32,
+    pub name: String,
+    pub vendor: String,
+    pub brand: String,
+    pub frequency: i64,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -2,17 +2,25 @@
 
 use sea_orm::entity::prelude::*;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "system-resources")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
     #[sea_orm(column_name = "evalTime")]
     pub eval_time: DateTime,
+    #[sea_orm(column_name = "physicalCores")]
+    pub physical_cores: Option<i32>,
+    #[sea_orm(column_name = "logicalCores")]
+    pub logical_cores: i32,
+    #[sea_orm(column_name = "cpuUsage", column_type = "Float")]
+    pub cpu_usage: f32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::network_interface::Entity")]
+    NetworkInterface,
     #[sea_orm(has_many = "super::server_node::Entity")]
     ServerNode,
     #[sea_orm(has_many = "super::storage_device::Entity")]
@@ -23,6 +31,12 @@ pub enum Relation {
     SystemMemory,
 }
 
+impl Related<super::network_interface::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::NetworkInterface.def()
+    }
+}
+
 impl Related<super::server_node::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::ServerNode.def()
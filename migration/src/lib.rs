@@ -1,12 +1,28 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20261018_000001_create_hive_peer_table;
+mod m20261018_000002_create_hive_peer_rule_table;
+mod m20261018_000003_add_server_node_labels;
+mod m20261018_000004_create_resource_sample_table;
+mod m20261018_000005_extend_cpu_model;
+mod m20261018_000006_create_network_interface_table;
+mod m20261018_000007_add_storage_device_mount_point;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20261018_000001_create_hive_peer_table::Migration),
+            Box::new(m20261018_000002_create_hive_peer_rule_table::Migration),
+            Box::new(m20261018_000003_add_server_node_labels::Migration),
+            Box::new(m20261018_000004_create_resource_sample_table::Migration),
+            Box::new(m20261018_000005_extend_cpu_model::Migration),
+            Box::new(m20261018_000006_create_network_interface_table::Migration),
+            Box::new(m20261018_000007_add_storage_device_mount_point::Migration),
+        ]
     }
 }
@@ -0,0 +1,76 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// Peer book of the hive
+/// 
+/// Peers found through mDNS, identify and ping, so a restarted node can redial them
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(HivePeer::Table)
+                    .if_not_exists()
+                    .col(big_integer(HivePeer::Id).auto_increment().primary_key())
+                    .col(string_uniq(HivePeer::PeerId))
+                    .col(json_null(HivePeer::Addresses))
+                    .col(string_null(HivePeer::AgentVersion))
+                    .col(string_null(HivePeer::ProtocolVersion))
+                    .col(date_time_null(HivePeer::LastSeen))
+                    .col(big_integer_null(HivePeer::RttMs))
+                    .col(big_integer_null(HivePeer::ServerNodeId))
+                    .col(date_time_null(HivePeer::CreatedAt))
+                    .col(date_time_null(HivePeer::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-hive-peer-server-node-id")
+                            .from(HivePeer::Table, HivePeer::ServerNodeId)
+                            .to(ServerNode::Table, ServerNode::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+    
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(HivePeer::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum HivePeer {
+    #[sea_orm(iden = "hive-peer")]
+    Table,
+    Id,
+    #[sea_orm(iden = "peerId")]
+    PeerId,
+    Addresses,
+    #[sea_orm(iden = "agentVersion")]
+    AgentVersion,
+    #[sea_orm(iden = "protocolVersion")]
+    ProtocolVersion,
+    #[sea_orm(iden = "lastSeen")]
+    LastSeen,
+    #[sea_orm(iden = "rttMs")]
+    RttMs,
+    #[sea_orm(iden = "serverNodeId")]
+    ServerNodeId,
+    #[sea_orm(iden = "createdAt")]
+    CreatedAt,
+    #[sea_orm(iden = "updatedAt")]
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ServerNode {
+    #[sea_orm(iden = "server-node")]
+    Table,
+    Id,
+}
@@ -0,0 +1,48 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// Access rules of the hive
+/// 
+/// Peers that are allowed or blocked by the operator
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(HivePeerRule::Table)
+                    .if_not_exists()
+                    .col(big_integer(HivePeerRule::Id).auto_increment().primary_key())
+                    .col(string_uniq(HivePeerRule::PeerId))
+                    .col(string_len(HivePeerRule::Rule, 16))
+                    .col(string_null(HivePeerRule::Reason))
+                    .col(date_time_null(HivePeerRule::CreatedAt))
+                    .col(date_time_null(HivePeerRule::UpdatedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+    
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(HivePeerRule::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum HivePeerRule {
+    #[sea_orm(iden = "hive-peer-rule")]
+    Table,
+    Id,
+    #[sea_orm(iden = "peerId")]
+    PeerId,
+    Rule,
+    Reason,
+    #[sea_orm(iden = "createdAt")]
+    CreatedAt,
+    #[sea_orm(iden = "updatedAt")]
+    UpdatedAt,
+}
@@ -0,0 +1,39 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// Server node labels
+/// 
+/// Comma separated labels that tasks can require
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ServerNode::Table)
+                    .add_column_if_not_exists(text_null(ServerNode::Labels))
+                    .to_owned(),
+            )
+            .await
+    }
+    
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ServerNode::Table)
+                    .drop_column(ServerNode::Labels)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ServerNode {
+    #[sea_orm(iden = "server-node")]
+    Table,
+    Labels,
+}
@@ -0,0 +1,96 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// Resource history of the server nodes
+/// 
+/// Raw samples and their 1-minute and 1-hour downsampled points
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ResourceSample::Table)
+                    .if_not_exists()
+                    .col(big_integer(ResourceSample::Id).auto_increment().primary_key())
+                    .col(big_integer(ResourceSample::ServerNodeId))
+                    .col(string_len(ResourceSample::Resolution, 8))
+                    .col(date_time(ResourceSample::Time))
+                    .col(integer(ResourceSample::Samples))
+                    .col(float(ResourceSample::CpuUsage))
+                    .col(float(ResourceSample::CpuUsageMax))
+                    .col(big_integer(ResourceSample::MemoryUsed))
+                    .col(big_integer(ResourceSample::MemoryUsedMax))
+                    .col(big_integer(ResourceSample::MemoryTotal))
+                    .col(big_integer(ResourceSample::StorageUsed))
+                    .col(big_integer(ResourceSample::StorageUsedMax))
+                    .col(big_integer(ResourceSample::StorageTotal))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-resource-sample-server-node-id")
+                            .from(ResourceSample::Table, ResourceSample::ServerNodeId)
+                            .to(ServerNode::Table, ServerNode::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        
+        // Series are always read by node, resolution and time range
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-resource-sample-series")
+                    .table(ResourceSample::Table)
+                    .col(ResourceSample::ServerNodeId)
+                    .col(ResourceSample::Resolution)
+                    .col(ResourceSample::Time)
+                    .to_owned(),
+            )
+            .await
+    }
+    
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ResourceSample::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ResourceSample {
+    #[sea_orm(iden = "resource-sample")]
+    Table,
+    Id,
+    #[sea_orm(iden = "serverNodeId")]
+    ServerNodeId,
+    Resolution,
+    Time,
+    Samples,
+    #[sea_orm(iden = "cpuUsage")]
+    CpuUsage,
+    #[sea_orm(iden = "cpuUsageMax")]
+    CpuUsageMax,
+    #[sea_orm(iden = "memoryUsed")]
+    MemoryUsed,
+    #[sea_orm(iden = "memoryUsedMax")]
+    MemoryUsedMax,
+    #[sea_orm(iden = "memoryTotal")]
+    MemoryTotal,
+    #[sea_orm(iden = "storageUsed")]
+    StorageUsed,
+    #[sea_orm(iden = "storageUsedMax")]
+    StorageUsedMax,
+    #[sea_orm(iden = "storageTotal")]
+    StorageTotal,
+}
+
+#[derive(DeriveIden)]
+enum ServerNode {
+    #[sea_orm(iden = "server-node")]
+    Table,
+    Id,
+}
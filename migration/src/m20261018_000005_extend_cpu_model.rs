@@ -0,0 +1,87 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// Cpu model
+/// 
+/// Cores get their index and the processor they belong to, and system resources get the core
+/// counts and the usage of every core together
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SystemCore::Table)
+                    .add_column_if_not_exists(integer(SystemCore::CoreIndex).default(0))
+                    .add_column_if_not_exists(string(SystemCore::Name).default(""))
+                    .add_column_if_not_exists(string(SystemCore::Vendor).default(""))
+                    .add_column_if_not_exists(string(SystemCore::Brand).default(""))
+                    .add_column_if_not_exists(big_integer(SystemCore::Frequency).default(0))
+                    .to_owned(),
+            )
+            .await?;
+        
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SystemResources::Table)
+                    .add_column_if_not_exists(integer_null(SystemResources::PhysicalCores))
+                    .add_column_if_not_exists(integer(SystemResources::LogicalCores).default(0))
+                    .add_column_if_not_exists(float(SystemResources::CpuUsage).default(0.0))
+                    .to_owned(),
+            )
+            .await
+    }
+    
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SystemResources::Table)
+                    .drop_column(SystemResources::PhysicalCores)
+                    .drop_column(SystemResources::LogicalCores)
+                    .drop_column(SystemResources::CpuUsage)
+                    .to_owned(),
+            )
+            .await?;
+        
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SystemCore::Table)
+                    .drop_column(SystemCore::CoreIndex)
+                    .drop_column(SystemCore::Name)
+                    .drop_column(SystemCore::Vendor)
+                    .drop_column(SystemCore::Brand)
+                    .drop_column(SystemCore::Frequency)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SystemCore {
+    #[sea_orm(iden = "system-core")]
+    Table,
+    #[sea_orm(iden = "coreIndex")]
+    CoreIndex,
+    Name,
+    Vendor,
+    Brand,
+    Frequency,
+}
+
+#[derive(DeriveIden)]
+enum SystemResources {
+    #[sea_orm(iden = "system-resources")]
+    Table,
+    #[sea_orm(iden = "physicalCores")]
+    PhysicalCores,
+    #[sea_orm(iden = "logicalCores")]
+    LogicalCores,
+    #[sea_orm(iden = "cpuUsage")]
+    CpuUsage,
+}
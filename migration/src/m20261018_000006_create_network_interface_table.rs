@@ -0,0 +1,79 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// Network interfaces of the system resources
+/// 
+/// Byte and packet totals, and the rates between the last two samples
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(NetworkInterface::Table)
+                    .if_not_exists()
+                    .col(big_integer(NetworkInterface::Id).auto_increment().primary_key())
+                    .col(string(NetworkInterface::Name))
+                    .col(string_len(NetworkInterface::MacAddress, 17))
+                    .col(text(NetworkInterface::IpAddresses))
+                    .col(big_integer(NetworkInterface::BytesReceived))
+                    .col(big_integer(NetworkInterface::BytesTransmitted))
+                    .col(big_integer(NetworkInterface::PacketsReceived))
+                    .col(big_integer(NetworkInterface::PacketsTransmitted))
+                    .col(double(NetworkInterface::ReceiveRate))
+                    .col(double(NetworkInterface::TransmitRate))
+                    .col(big_integer_null(NetworkInterface::SystemResourceId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-network-interface-system-resource-id")
+                            .from(NetworkInterface::Table, NetworkInterface::SystemResourceId)
+                            .to(SystemResources::Table, SystemResources::Id)
+                            .on_update(ForeignKeyAction::Cascade)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+    
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(NetworkInterface::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NetworkInterface {
+    #[sea_orm(iden = "network-interface")]
+    Table,
+    Id,
+    Name,
+    #[sea_orm(iden = "macAddress")]
+    MacAddress,
+    #[sea_orm(iden = "ipAddresses")]
+    IpAddresses,
+    #[sea_orm(iden = "bytesReceived")]
+    BytesReceived,
+    #[sea_orm(iden = "bytesTransmitted")]
+    BytesTransmitted,
+    #[sea_orm(iden = "packetsReceived")]
+    PacketsReceived,
+    #[sea_orm(iden = "packetsTransmitted")]
+    PacketsTransmitted,
+    #[sea_orm(iden = "receiveRate")]
+    ReceiveRate,
+    #[sea_orm(iden = "transmitRate")]
+    TransmitRate,
+    #[sea_orm(iden = "systemResourceId")]
+    SystemResourceId,
+}
+
+#[derive(DeriveIden)]
+enum SystemResources {
+    #[sea_orm(iden = "system-resources")]
+    Table,
+    Id,
+}
@@ -0,0 +1,40 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+/// Storage device mount point
+/// 
+/// Disk names like 'tmpfs' repeat, the mount point is what tells the disks of a node apart
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(StorageDevice::Table)
+                    .add_column_if_not_exists(string(StorageDevice::MountPoint).default(""))
+                    .to_owned(),
+            )
+            .await
+    }
+    
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(StorageDevice::Table)
+                    .drop_column(StorageDevice::MountPoint)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum StorageDevice {
+    #[sea_orm(iden = "storage-device")]
+    Table,
+    #[sea_orm(iden = "mountPoint")]
+    MountPoint,
+}
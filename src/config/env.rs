@@ -170,9 +170,82 @@ pub fn mysql_database() -> String {
 
 /// Secret token
 /// 
+/// Tests sign with a fixed secret when it isn't set
+pub fn secret_token() -> Result<String, String> {
+    match env::var("SECRET_TOKEN") {
+        Ok(secret) => Ok(secret),
+        Err(_) if cfg!(test) => Ok("test-secret".to_string()),
+        Err(_) => Err("Secret token is required, set it in the environment with the name 'SECRET_TOKEN'".to_string()),
+    }
+}
+
+// Hive
+/// Hive keystore path
+/// 
+/// File where the node keypair is stored, defaults to ".cache/hive/identity.key"
+pub fn hive_keystore_path() -> String {
+    env::var("HIVE_KEYSTORE_PATH").unwrap_or_else(|_| ".cache/hive/identity.key".to_string())
+}
+
+/// Hive bootstrap peers
+/// 
+/// Comma separated list of multiaddresses ending with '/p2p/<peer id>'
+pub fn hive_bootstrap_peers() -> Vec<String> {
+    comma_separated("HIVE_BOOTSTRAP_PEERS")
+}
+
+/// Hive allowed peers
+/// 
+/// Comma separated list of peer ids
+pub fn hive_allowed_peers() -> Vec<String> {
+    comma_separated("HIVE_ALLOWED_PEERS")
+}
+
+/// Hive blocked peers
+/// 
+/// Comma separated list of peer ids
+pub fn hive_blocked_peers() -> Vec<String> {
+    comma_separated("HIVE_BLOCKED_PEERS")
+}
+
+/// Hive swarm key path
+/// 
+/// Pre-shared key of the private hive network, when it's not set the network is public
+pub fn hive_swarm_key_path() -> Option<String> {
+    env::var("HIVE_SWARM_KEY_PATH").ok().filter(|path| !path.is_empty())
+}
+
+/// Hive node labels
+/// 
+/// Comma separated list of labels of this node, tasks can require them
+pub fn hive_node_labels() -> Vec<String> {
+    comma_separated("HIVE_NODE_LABELS")
+}
+
+/// Hive transfer folder
+/// 
+/// Files sent by other nodes are stored here, and only files inside it can be fetched
+pub fn hive_transfer_folder() -> String {
+    env::var("HIVE_TRANSFER_FOLDER").unwrap_or_else(|_| ".cache/hive/files".to_string())
+}
+
+/// Hive subnetwork
+/// 
+/// Nodes on the same subnetwork elect a leader, defaults to the /24 network of the computer
+pub fn hive_subnetwork() -> Option<String> {
+    env::var("HIVE_SUBNETWORK").ok().filter(|subnetwork| !subnetwork.is_empty())
+}
+
+/// Comma separated list
 /// 
-pub fn secret_token() -> String {
-    env::var("SECRET_TOKEN").expect("Secret token is required, set it in the environment with the name 'SECRET_TOKEN'")
+/// Empty items are skipped
+fn comma_separated(name: &str) -> Vec<String> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
 }
 
 /// Tests
@@ -11,6 +11,7 @@ pub mod config;
 pub mod database;
 pub mod model;
 pub mod p2p;
+pub mod scheduler;
 pub mod security;
 pub mod server;
 pub mod server_node;
@@ -1,5 +1,6 @@
 use std::error::Error;
 
+pub mod reconcile;
 pub mod server_node;
 
 /// From active model
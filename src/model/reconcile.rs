@@ -0,0 +1,263 @@
+//! Child rows reconciliation
+//!
+//! Some models own a collection of rows, like the cores or the storage devices of the system
+//! resources. Instead of inserting the whole collection again on every update, the stored rows
+//! are matched with the fresh records by a key: matching rows are updated, new records are
+//! inserted and rows that are gone are deleted.
+use sea_orm::{
+	ActiveModelBehavior, ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait,
+	IntoActiveModel, QueryFilter, QueryOrder,
+};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::error::Error;
+use std::hash::Hash;
+
+/// Model of the rows of a child record
+pub type ChildModel<R> = <<R as ChildRecord>::Entity as EntityTrait>::Model;
+
+/// Record stored as a child row of another model
+///
+///
+pub trait ChildRecord {
+	type Entity: EntityTrait;
+	type ActiveModel: ActiveModelTrait<Entity = Self::Entity> + ActiveModelBehavior + Send;
+	type Key: Eq + Hash;
+
+	/// Column with the id of the parent
+	fn parent_column() -> <Self::Entity as EntityTrait>::Column;
+
+	/// Primary key column
+	fn id_column() -> <Self::Entity as EntityTrait>::Column;
+
+	/// Primary key of a row
+	fn row_id(row: &ChildModel<Self>) -> i64;
+
+	/// Key of a stored row
+	///
+	/// Rows are given ordered by id, records without identity can use their position
+	fn row_key(row: &ChildModel<Self>, position: usize) -> Self::Key;
+
+	/// Key of the record
+	///
+	///
+	fn key(&self, position: usize) -> Self::Key;
+
+	/// Active model with the values of the record
+	///
+	/// Updates have the id of the row they replace, inserts don't
+	fn active_model(&self, parent_id: i64, id: Option<i64>) -> Result<Self::ActiveModel, Box<dyn Error>>;
+}
+
+/// Changes that reconcile the stored rows with the records
+///
+///
+#[derive(Debug)]
+pub struct Reconciliation<'a, R> {
+	// Row id and the record that replaces it
+	pub updates: Vec<(i64, &'a R)>,
+	pub inserts: Vec<&'a R>,
+	pub deletes: Vec<i64>,
+}
+
+impl<'a, R: ChildRecord> Reconciliation<'a, R> {
+	/// Match the stored rows with the records
+	///
+	/// When many rows have the same key the first one is kept and the rest are deleted
+	pub fn plan(rows: &[ChildModel<R>], records: &'a [R]) -> Self {
+		let mut rows_by_key: HashMap<R::Key, i64> = HashMap::new();
+		let mut deletes = Vec::new();
+		for (position, row) in rows.iter().enumerate() {
+			match rows_by_key.entry(R::row_key(row, position)) {
+				Entry::Occupied(_) => deletes.push(R::row_id(row)),
+				Entry::Vacant(entry) => {
+					entry.insert(R::row_id(row));
+				}
+			}
+		}
+
+		let mut updates = Vec::new();
+		let mut inserts = Vec::new();
+		for (position, record) in records.iter().enumerate() {
+			match rows_by_key.remove(&record.key(position)) {
+				Some(id) => updates.push((id, record)),
+				None => inserts.push(record),
+			}
+		}
+
+		// The rows left weren't matched by any record
+		deletes.extend(rows_by_key.into_values());
+		deletes.sort_unstable();
+
+		Self {
+			updates,
+			inserts,
+			deletes,
+		}
+	}
+
+	/// Apply the changes
+	///
+	/// Use a transaction, otherwise a failure leaves the rows half reconciled
+	pub async fn apply<C>(&self, db: &C, parent_id: i64) -> Result<(), Box<dyn Error>>
+	where
+		C: ConnectionTrait,
+		ChildModel<R>: IntoActiveModel<R::ActiveModel>,
+	{
+		for (id, record) in &self.updates {
+			let active_model = record.active_model(parent_id, Some(*id))?;
+			active_model.update(db).await?;
+		}
+
+		if !self.inserts.is_empty() {
+			let active_models = self.inserts
+				.iter()
+				.map(|record| record.active_model(parent_id, None))
+				.collect::<Result<Vec<_>, _>>()?;
+			R::Entity::insert_many(active_models).exec(db).await?;
+		}
+
+		if !self.deletes.is_empty() {
+			R::Entity::delete_many()
+				.filter(R::id_column().is_in(self.deletes.clone()))
+				.exec(db)
+				.await?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Reconcile the child rows of a parent with the records
+///
+/// The rows are read with the given connection, so on a transaction nobody changes them meanwhile
+pub async fn reconcile<R, C>(db: &C, parent_id: i64, records: &[R]) -> Result<(), Box<dyn Error>>
+where
+	R: ChildRecord,
+	C: ConnectionTrait,
+	ChildModel<R>: IntoActiveModel<R::ActiveModel>,
+{
+	let rows = R::Entity::find()
+		.filter(R::parent_column().eq(parent_id))
+		.order_by_asc(R::id_column())
+		.all(db)
+		.await?;
+
+	Reconciliation::plan(&rows, records).apply(db, parent_id).await
+}
+
+#[cfg(test)]
+mod tests {
+	use entity::{storage_device::Model as StorageDeviceModel, system_core::Model as SystemCoreModel};
+
+	use super::*;
+	use crate::server_node::resources::storage::{DiskKind, Storage};
+	use crate::server_node::resources::system_core::CpuCore;
+
+	fn storage(name: &str, mount_point: &str) -> Storage {
+		Storage {
+			total: 100,
+			used: 50,
+			kind: DiskKind::SSD,
+			name: name.to_string(),
+			mount_point: mount_point.to_string(),
+			is_removable: false,
+		}
+	}
+
+	fn storage_row(id: i64, name: &str, mount_point: &str) -> StorageDeviceModel {
+		StorageDeviceModel {
+			id,
+			total: 100,
+			used: 10,
+			name: name.to_string(),
+			mount_point: mount_point.to_string(),
+			is_removable: 0,
+			kind: "\"SSD\"".to_string(),
+			system_resource_id: Some(1),
+		}
+	}
+
+	#[test]
+	fn test_plan_by_key() {
+		let rows = vec![storage_row(1, "sda", "/"), storage_row(2, "sdb", "/home"), storage_row(3, "sda", "/")];
+		let records = vec![storage("sda", "/"), storage("sdc", "/data")];
+
+		let reconciliation = Reconciliation::plan(&rows, &records);
+
+		assert_eq!(reconciliation.updates.len(), 1);
+		assert_eq!(reconciliation.updates[0].0, 1);
+		assert_eq!(reconciliation.updates[0].1.name, "sda");
+		assert_eq!(reconciliation.inserts.len(), 1);
+		assert_eq!(reconciliation.inserts[0].name, "sdc");
+		// The disk that's gone and the repeated row
+		assert_eq!(reconciliation.deletes, vec![2, 3]);
+
+		// Updates keep the row id
+		let active_model = records[0].active_model(1, Some(1)).unwrap();
+		assert_eq!(active_model.id.clone().unwrap(), 1);
+		assert_eq!(active_model.used.clone().unwrap(), 50);
+	}
+
+	#[test]
+	fn test_plan_same_disk_names() {
+		let rows = vec![storage_row(1, "tmpfs", "/run"), storage_row(2, "tmpfs", "/dev/shm")];
+		let records = vec![storage("tmpfs", "/dev/shm"), storage("tmpfs", "/run")];
+		
+		let reconciliation = Reconciliation::plan(&rows, &records);
+		
+		// Both rows are kept and updated instead of deleted and inserted again
+		let mut updates = reconciliation
+			.updates
+			.iter()
+			.map(|(id, storage)| (*id, storage.mount_point.as_str()))
+			.collect::<Vec<_>>();
+		updates.sort();
+		assert_eq!(updates, vec![(1, "/run"), (2, "/dev/shm")]);
+		assert!(reconciliation.inserts.is_empty());
+		assert!(reconciliation.deletes.is_empty());
+	}
+	
+	#[test]
+	fn test_plan_by_index() {
+		// Row ids don't follow the core indexes
+		let rows: Vec<SystemCoreModel> = [(1, 3), (2, 0), (3, 1), (4, 2)]
+			.into_iter()
+			.map(|(id, core_index)| SystemCoreModel {
+				id,
+				usage_percentage: 0.0,
+				free_percentage: 100.0,
+				system_resource_id: Some(1),
+				core_index,
+				name: format!("cpu{core_index}"),
+				vendor: String::new(),
+				brand: String::new(),
+				frequency: 0,
+			})
+			.collect();
+		let cores = |count: u32| {
+			(0..count)
+				.map(|index| CpuCore {
+					index,
+					usage_percentage: 25.0,
+					free_percentage: 75.0,
+					..Default::default()
+				})
+				.collect::<Vec<_>>()
+		};
+
+		// Fewer cores, the rows of the last ones are deleted
+		let fewer = cores(2);
+		let reconciliation = Reconciliation::plan(&rows, &fewer);
+		assert_eq!(reconciliation.updates.iter().map(|(id, core)| (*id, core.index)).collect::<Vec<_>>(), vec![(2, 0), (3, 1)]);
+		assert!(reconciliation.inserts.is_empty());
+		assert_eq!(reconciliation.deletes, vec![1, 4]);
+
+		// More cores, the rest are inserted
+		let more = cores(6);
+		let reconciliation = Reconciliation::plan(&rows, &more);
+		assert_eq!(reconciliation.updates.len(), 4);
+		assert_eq!(reconciliation.inserts.len(), 2);
+		assert!(reconciliation.deletes.is_empty());
+	}
+}
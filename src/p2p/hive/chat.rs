@@ -0,0 +1,45 @@
+//! Stdin chat
+//! 
+//! Lines read from stdin are published as chat messages and received chat messages are printed.
+use std::error::Error;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::{io, io::AsyncBufReadExt, select};
+
+use crate::p2p::node::handle::{NodeEvent, NodeHandle};
+use crate::p2p::node::message::HivePayload;
+
+/// Chat through a node
+/// 
+/// Runs until the node stops, when stdin is closed received messages are still printed
+pub async fn main(handle: NodeHandle) -> Result<(), Box<dyn Error>> {
+    let mut stdin = io::BufReader::new(io::stdin()).lines();
+    let mut stdin_open = true;
+    let mut events = handle.events();
+    
+    println!("Enter messages via STDIN and they will be sent to connected peers using Gossipsub");
+    
+    loop {
+        select! {
+            line = stdin.next_line(), if stdin_open => match line? {
+                Some(line) => {
+                    if let Err(e) = handle.publish(HivePayload::Chat(line)).await {
+                        println!("Publish error: {e}");
+                    }
+                }
+                None => stdin_open = false,
+            },
+            event = events.recv() => match event {
+                Ok(NodeEvent::Message(message)) => {
+                    if let HivePayload::Chat(text) = &message.payload {
+                        println!("Got message: '{text}' from peer: {}", message.sender);
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Chat skipped {skipped} node events");
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
@@ -1,72 +1,370 @@
+use chrono::Utc;
 use futures::StreamExt;
-use libp2p::{
-    noise,
-    tcp,
-    yamux,
-};
 use libp2p::core::Multiaddr;
 use libp2p::core::multiaddr::Protocol;
 use libp2p::swarm::SwarmEvent;
+use libp2p::{dcutr, gossipsub, relay, Swarm};
 use std::error::Error;
 use std::net::Ipv4Addr;
-use std::time::Duration;
 use tracing_subscriber::EnvFilter;
 
 use super::HiveParameters;
-use crate::p2p::node::behavior::MyBehavior;
+use crate::p2p::node::access::AccessList;
+use crate::p2p::node::behavior::{MyBehavior, MyBehaviorEvent};
+use crate::p2p::node::bootstrap::BootstrapPeer;
+use crate::p2p::node::builder::NodeBuilder;
+use crate::p2p::node::validation::{MessageValidator, Validation};
 
-/// Hive client
+/// Get the server to connect to
 /// 
+/// The server address is required, the peer id can be given in the address or separately
+pub fn server_peer(parameters: &HiveParameters) -> Result<BootstrapPeer, Box<dyn Error>> {
+    match &parameters.server_address {
+        Some(address) => BootstrapPeer::from_parts(address, parameters.server_peer_id),
+        None => Err("Server address is required".into()),
+    }
+}
+
+/// Circuit address of a relay
 /// 
-pub async fn main(parameters: HiveParameters) -> Result<(), Box<dyn Error>> {
+/// Listening on it makes a reservation on the relay
+pub fn circuit_address(relay: &BootstrapPeer) -> Multiaddr {
+    relay.address
+        .clone()
+        .with(Protocol::P2p(relay.peer_id))
+        .with(Protocol::P2pCircuit)
+}
+
+/// Address to reach a peer through a relay
+/// 
+/// 
+pub fn peer_circuit_address(relay: &BootstrapPeer, peer_id: libp2p::PeerId) -> Multiaddr {
+    circuit_address(relay).with(Protocol::P2p(peer_id))
+}
+
+/// Dial the server and wait until the connection is established
+/// 
+/// 
+pub async fn connect(swarm: &mut Swarm<MyBehavior>, server: &BootstrapPeer) -> Result<(), Box<dyn Error>> {
+    swarm.dial(server.address.clone().with(Protocol::P2p(server.peer_id)))?;
+    
+    loop {
+        match swarm.select_next_some().await {
+            SwarmEvent::ConnectionEstablished { peer_id, .. } if peer_id == server.peer_id => {
+                println!("Connected to server {peer_id}");
+                return Ok(());
+            }
+            SwarmEvent::OutgoingConnectionError { peer_id: Some(peer_id), error, .. } if peer_id == server.peer_id => {
+                return Err(format!("Couldn't connect to server {peer_id}: {error}").into());
+            }
+            SwarmEvent::NewListenAddr { address, .. } => {
+                println!("Listening on {address:?}");
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Reserve a relay circuit
+/// 
+/// Returns the '/p2p-circuit' address, which is advertised so other peers can reach us through the relay
+pub async fn reserve(swarm: &mut Swarm<MyBehavior>, relay: &BootstrapPeer) -> Result<Multiaddr, Box<dyn Error>> {
+    let listener_id = swarm.listen_on(circuit_address(relay))?;
+    
+    loop {
+        match swarm.select_next_some().await {
+            SwarmEvent::Behaviour(MyBehaviorEvent::RelayClient(
+                relay::client::Event::ReservationReqAccepted { relay_peer_id, .. }
+            )) if relay_peer_id == relay.peer_id => {
+                let address = peer_circuit_address(relay, *swarm.local_peer_id());
+                swarm.add_external_address(address.clone());
+                
+                return Ok(address);
+            }
+            SwarmEvent::ListenerClosed { listener_id: closed, reason, .. } if closed == listener_id => {
+                return Err(format!("Relay {} refused the reservation: {reason:?}", relay.peer_id).into());
+            }
+            SwarmEvent::ListenerError { listener_id: failed, error } if failed == listener_id => {
+                return Err(format!("Relay {} reservation failed: {error}", relay.peer_id).into());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Validate a gossipsub message and report the result
+/// 
+/// The client subscribes to the hive topics, gossipsub holds their messages until they're
+/// validated, so without it the client wouldn't forward them
+pub fn report_validation(
+    swarm: &mut Swarm<MyBehavior>,
+    validator: &MessageValidator,
+    access_list: &AccessList,
+    propagation_source: libp2p::PeerId,
+    message_id: gossipsub::MessageId,
+    message: &gossipsub::Message,
+) -> Validation {
+    let validation = validator.validate(message, access_list, Utc::now());
     
-    // let address = match parameters.server_address {
-    //     Some(address) => address,
-    //     None => return Err("Server address is required".into()),
-    // };
+    if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
+        if let Err(err) = gossipsub.report_message_validation_result(&message_id, &propagation_source, validation.acceptance()) {
+            tracing::warn!("Couldn't report the validation of message {message_id}: {err}");
+        }
+    }
     
-    // let server_peer_id = match parameters.server_peer_id {
-    //     Some(peer_id) => peer_id,
-    //     None => return Err("Server peer_id is required".into()),
-    // };
+    match &validation {
+        Validation::Reject(err) => tracing::warn!("Rejected message {message_id} from peer {propagation_source}: {err}"),
+        Validation::Ignore(err) => tracing::debug!("Ignored message {message_id} from peer {propagation_source}: {err}"),
+        _ => {}
+    }
     
+    validation
+}
+
+/// Hive client
+/// 
+/// Connects to the server, when relay mode is on the server is also used as relay
+/// and direct connections are upgraded with hole punching
+pub async fn main(parameters: HiveParameters) -> Result<(), Box<dyn Error>> {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
         .try_init();
     
-    let mut swarm = libp2p::SwarmBuilder::with_new_identity()
-        .with_tokio()
-        .with_tcp(
-            tcp::Config::default(),
-            noise::Config::new,
-            yamux::Config::default
-        )?
-        .with_behaviour(|key| Ok(MyBehavior::new(key).unwrap()))?
-        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
-        .build();
-    
-    swarm.listen_on(
-        Multiaddr::empty()
-            .with(Protocol::Ip4(Ipv4Addr::UNSPECIFIED))
-            .with(Protocol::Tcp(parameters.port.unwrap_or(0)))
-    )?;
-    
-    // // Auto nat seems to not exist, dropping it sadly
-    // swarm.behaviour_mut()
-    //     .auto_nat
-    //     .add_server(server_peer_id, Some(address));
+    let server = server_peer(&parameters)?;
+    
+    // On client mode relay means reserving a circuit on the server, not relaying for others
+    let builder = NodeBuilder::from_parameters(&parameters)?.relay_server(false);
+    let transports = builder.effective_transports()?;
+    let mut swarm = builder.build_swarm()?;
+    let access_list = AccessList::from_rules(parameters.access_rules()?);
+    let validator = MessageValidator {
+        allowlist: parameters.allowlist,
+        ..Default::default()
+    };
+    
+    let port = parameters.port.unwrap_or(0);
+    if transports.tcp() {
+        swarm.listen_on(
+            Multiaddr::empty()
+                .with(Protocol::Ip4(Ipv4Addr::UNSPECIFIED))
+                .with(Protocol::Tcp(port))
+        )?;
+    }
+    // QUIC connections can't be protected with the swarm key
+    if transports.quic() {
+        swarm.listen_on(
+            Multiaddr::empty()
+                .with(Protocol::Ip4(Ipv4Addr::UNSPECIFIED))
+                .with(Protocol::Udp(port))
+                .with(Protocol::QuicV1)
+        )?;
+    }
+    
+    // Ask the server whether we're reachable
+    if let Some(auto_nat) = swarm.behaviour_mut().auto_nat.as_mut() {
+        auto_nat.add_server(server.peer_id, Some(server.address.clone()));
+    }
+    
+    connect(&mut swarm, &server).await?;
+    
+    if parameters.relay {
+        let address = reserve(&mut swarm, &server).await?;
+        println!("Reachable through the relay at {address}");
+    }
     
     loop {
         match swarm.select_next_some().await {
             SwarmEvent::NewListenAddr { address, .. } => {
                 println!("Listening on {address:?}");
             }
+            SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. } => {
+                println!("Connected to {peer_id} at {}", endpoint.get_remote_address());
+            }
+            SwarmEvent::Behaviour(MyBehaviorEvent::Dcutr(dcutr::Event { remote_peer_id, result })) => {
+                match result {
+                    Ok(_) => println!("Direct connection to {remote_peer_id} established"),
+                    Err(err) => println!("Direct connection to {remote_peer_id} failed, using the relay: {err}"),
+                }
+            }
+            SwarmEvent::Behaviour(MyBehaviorEvent::Gossipsub(gossipsub::Event::Message {
+                propagation_source,
+                message_id,
+                message,
+            })) => {
+                report_validation(&mut swarm, &validator, &access_list, propagation_source, message_id, &message);
+            }
             SwarmEvent::Behaviour(event) => {
-                println!("{event:?}");
+                tracing::debug!("{event:?}");
             }
-            e => {
-                println!("{e:?}");
+            event => {
+                tracing::debug!("{event:?}");
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+    use std::time::Duration;
+    use tokio::select;
+    
+    use crate::p2p::node::message::{HiveMessage, HivePayload, MessageKind};
+    
+    /// Create a swarm listening on a loopback port
+    /// 
+    /// Returns the swarm with its listen address
+    async fn loopback_swarm() -> (Swarm<MyBehavior>, Multiaddr) {
+        let mut swarm = NodeBuilder::new(Keypair::generate_ed25519())
+            .mdns(false)
+            .build_swarm()
+            .unwrap();
+        swarm.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        
+        loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = swarm.select_next_some().await {
+                return (swarm, address);
+            }
+        }
+    }
+    
+    /// Create a swarm subscribed to the chat topic, listening on a loopback port
+    /// 
+    /// 
+    async fn chat_swarm() -> (Swarm<MyBehavior>, Multiaddr) {
+        let mut swarm = NodeBuilder::new(Keypair::generate_ed25519())
+            .mdns(false)
+            .topic(MessageKind::Chat.topic())
+            .build_swarm()
+            .unwrap();
+        swarm.listen_on("/ip4/127.0.0.1/tcp/0".parse().unwrap()).unwrap();
+        
+        loop {
+            if let SwarmEvent::NewListenAddr { address, .. } = swarm.select_next_some().await {
+                return (swarm, address);
+            }
+        }
+    }
+    
+    /// Keep polling a swarm in the background
+    /// 
+    /// 
+    fn drive(mut swarm: Swarm<MyBehavior>) {
+        tokio::spawn(async move {
+            loop {
+                swarm.select_next_some().await;
+            }
+        });
+    }
+    
+    #[test]
+    fn test_server_peer() {
+        let peer_id = libp2p::PeerId::random();
+        let mut parameters = HiveParameters::default();
+        assert!(server_peer(&parameters).is_err());
+        
+        parameters.server_address = Some("/ip4/10.0.0.2/tcp/4001".parse().unwrap());
+        parameters.server_peer_id = Some(peer_id);
+        
+        let server = server_peer(&parameters).unwrap();
+        assert_eq!(server.peer_id, peer_id);
+        assert_eq!(
+            peer_circuit_address(&server, peer_id).to_string(),
+            format!("/ip4/10.0.0.2/tcp/4001/p2p/{peer_id}/p2p-circuit/p2p/{peer_id}")
+        );
+    }
+    
+    #[tokio::test]
+    async fn test_reach_peer_through_relay() {
+        let result = tokio::time::timeout(Duration::from_secs(60), async {
+            // Relay, it needs an external address to accept reservations
+            let (mut relay, relay_address) = loopback_swarm().await;
+            relay.add_external_address(relay_address.clone());
+            let relay_peer = BootstrapPeer::new(*relay.local_peer_id(), relay_address);
+            drive(relay);
+            
+            // Client B is reachable through the relay
+            let (mut client_b, _) = loopback_swarm().await;
+            let client_b_id = *client_b.local_peer_id();
+            connect(&mut client_b, &relay_peer).await.unwrap();
+            let circuit = reserve(&mut client_b, &relay_peer).await.unwrap();
+            assert_eq!(circuit, peer_circuit_address(&relay_peer, client_b_id));
+            drive(client_b);
+            
+            // Client A dials B through the relay
+            let (mut client_a, _) = loopback_swarm().await;
+            connect(&mut client_a, &relay_peer).await.unwrap();
+            client_a.dial(circuit).unwrap();
+            
+            loop {
+                if let SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. } = client_a.select_next_some().await {
+                    if peer_id == client_b_id {
+                        return endpoint.is_relayed();
+                    }
+                }
+            }
+        })
+        .await;
+        
+        assert_eq!(result, Ok(true), "Client A didn't reach client B through the relay");
+    }
+    
+    #[tokio::test]
+    async fn test_client_forwards_hive_messages() {
+        let result = tokio::time::timeout(Duration::from_secs(30), async {
+            // The client is the only link between the sender and the receiver
+            let (mut client, client_address) = chat_swarm().await;
+            let client_peer = BootstrapPeer::new(*client.local_peer_id(), client_address);
+            tokio::spawn(async move {
+                let validator = MessageValidator::default();
+                let access_list = AccessList::new();
+                
+                loop {
+                    if let SwarmEvent::Behaviour(MyBehaviorEvent::Gossipsub(gossipsub::Event::Message {
+                        propagation_source,
+                        message_id,
+                        message,
+                    })) = client.select_next_some().await {
+                        report_validation(&mut client, &validator, &access_list, propagation_source, message_id, &message);
+                    }
+                }
+            });
+            
+            let (mut receiver, _) = chat_swarm().await;
+            connect(&mut receiver, &client_peer).await.unwrap();
+            
+            // Published until the mesh is formed
+            let (mut sender, _) = chat_swarm().await;
+            let sender_id = *sender.local_peer_id();
+            connect(&mut sender, &client_peer).await.unwrap();
+            tokio::spawn(async move {
+                let mut publish_timer = tokio::time::interval(Duration::from_millis(500));
+                
+                loop {
+                    select! {
+                        _ = publish_timer.tick() => {
+                            let message = HiveMessage::new(sender_id, HivePayload::Chat("Hello".to_string()));
+                            if let Some(gossipsub) = sender.behaviour_mut().gossipsub.as_mut() {
+                                let _ = gossipsub.publish(MessageKind::Chat.topic(), message.encode().unwrap());
+                            }
+                        }
+                        _ = sender.select_next_some() => {}
+                    }
+                }
+            });
+            
+            loop {
+                if let SwarmEvent::Behaviour(MyBehaviorEvent::Gossipsub(gossipsub::Event::Message {
+                    message,
+                    ..
+                })) = receiver.select_next_some().await {
+                    return message.source;
+                }
+            }
+        })
+        .await;
+        
+        assert!(matches!(result, Ok(Some(_))), "The client didn't forward the message");
+    }
+}
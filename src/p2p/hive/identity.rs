@@ -0,0 +1,60 @@
+//! Hive identity command
+//! 
+//! Show, export, rotate or import the node keypair
+use clap::Subcommand;
+use std::error::Error;
+use std::path::PathBuf;
+
+use crate::p2p::node::keystore::Keystore;
+
+#[derive(Subcommand)]
+pub enum IdentityCommand {
+    /// Show the PeerId and where the keypair is stored
+    Show,
+    /// Export the keypair to a file
+    Export {
+        /// Output file
+        #[clap(short, long)]
+        output: PathBuf,
+    },
+    /// Replace the keypair with a new one, the PeerId will change
+    Rotate,
+    /// Import a keypair from a file, the current one is replaced
+    Import {
+        /// Input file
+        #[clap(short, long)]
+        input: PathBuf,
+    },
+}
+
+/// Identity main
+/// 
+/// 
+pub fn main(keystore: Keystore, command: &IdentityCommand) -> Result<(), Box<dyn Error>> {
+    match command {
+        IdentityCommand::Show => {
+            let key = keystore.load_or_generate()?;
+            
+            println!("Peer id: {}", key.public().to_peer_id());
+            println!("Keystore: {}", keystore.path.display());
+        }
+        IdentityCommand::Export { output } => {
+            keystore.export(output)?;
+            
+            println!("Keypair exported to {}", output.display());
+        }
+        IdentityCommand::Rotate => {
+            let key = keystore.rotate()?;
+            
+            println!("New peer id: {}", key.public().to_peer_id());
+            println!("Previous keypair saved at {}", keystore.backup_path().display());
+        }
+        IdentityCommand::Import { input } => {
+            let key = keystore.import(input)?;
+            
+            println!("Imported peer id: {}", key.public().to_peer_id());
+        }
+    };
+    
+    Ok(())
+}
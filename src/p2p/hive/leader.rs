@@ -0,0 +1,36 @@
+//! Hive leader command
+//! 
+//! Shows the leader of the subnetwork of a running node, started with '--api-address'
+use clap::Args;
+use reqwest::Client;
+use std::error::Error;
+
+use super::task::{api_url, read_response};
+use crate::p2p::node::election::ElectionStatus;
+
+#[derive(Args)]
+pub struct LeaderArgs {
+    /// Rest api of the node, defaults to 'http://127.0.0.1:<PORT>'
+    #[clap(long)]
+    pub api: Option<String>,
+}
+
+/// Leader main
+/// 
+/// 
+pub async fn main(args: &LeaderArgs) -> Result<(), Box<dyn Error>> {
+    let url = format!("{}/api/hive/leader", api_url(&args.api).trim_end_matches('/'));
+    let response = Client::new().get(url).send().await?;
+    let election: ElectionStatus = serde_json::from_str(&read_response(response).await?)?;
+    
+    match election.leader {
+        Some(leader) => println!(
+            "Peer {} leads subnetwork {} on term {}, its lease lasts until {}",
+            leader.peer_id, election.subnetwork, leader.term, leader.lease_until
+        ),
+        None => println!("Subnetwork {} has no leader yet", election.subnetwork),
+    }
+    println!("This node is a {}", election.role);
+    
+    Ok(())
+}
@@ -1,13 +1,85 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use libp2p::{
+    identity::Keypair,
     multiaddr::Multiaddr,
+    pnet::PreSharedKey,
     PeerId,
 };
-use rand::Rng;
 use std::error::Error;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
 
+pub mod chat;
 pub mod client;
+pub mod identity;
+pub mod leader;
+pub mod nodes;
+pub mod peers;
 pub mod server;
+pub mod swarm_key;
+pub mod task;
+pub mod transfer;
+
+use crate::config::env::{
+    hive_allowed_peers,
+    hive_blocked_peers,
+    hive_bootstrap_peers,
+    hive_swarm_key_path,
+    hive_transfer_folder,
+};
+use crate::p2p::node::access::{AccessRule, PeerRule};
+use crate::p2p::node::behavior::generate_ed25519;
+use crate::p2p::node::bootstrap::BootstrapPeer;
+use crate::p2p::node::builder::Transports;
+use crate::p2p::node::election::{default_subnetwork, ElectionConfig, DEFAULT_LEADER_LEASE, ELECTION_TIMEOUT};
+use crate::p2p::node::keystore::Keystore;
+use crate::p2p::node::liveness::{
+    LivenessConfig,
+    DEFAULT_HEARTBEAT_INTERVAL,
+    DEFAULT_MAX_PING_FAILURES,
+    DEFAULT_OFFLINE_AFTER,
+};
+use crate::p2p::node::swarm_key::SwarmKeyFile;
+use crate::p2p::node::transfer::folder::TransferFolder;
+use crate::server_node::resources::history::{RetentionPolicy, DEFAULT_RAW_RETENTION};
+use crate::server_node::resources::sampler::DEFAULT_SAMPLE_INTERVAL;
+use identity::IdentityCommand;
+use leader::LeaderArgs;
+use nodes::NodesArgs;
+use peers::PeersCommand;
+use swarm_key::SwarmKeyCommand;
+use task::TaskArgs;
+use transfer::{FetchArgs, SendArgs};
+
+/// Hive subcommands
+/// 
+/// When no subcommand is given the node is started
+#[derive(Subcommand)]
+pub enum HiveCommand {
+    /// Manage the node identity
+    #[clap(subcommand)]
+    Identity(IdentityCommand),
+    /// Allow or block peers
+    #[clap(subcommand)]
+    Peers(PeersCommand),
+    /// Manage the pre-shared key of the private network
+    #[clap(subcommand)]
+    SwarmKey(SwarmKeyCommand),
+    /// Run tasks on the hive nodes
+    Task(TaskArgs),
+    /// Send a file to the transfer folder of a peer
+    Send(SendArgs),
+    /// Fetch a file from the transfer folder of a peer
+    Fetch(FetchArgs),
+    /// Show the leader of the subnetwork
+    Leader(LeaderArgs),
+    /// List the server nodes of the hive
+    Nodes(NodesArgs),
+}
+
+/// Default seconds between Kademlia bootstraps
+pub const DEFAULT_BOOTSTRAP_INTERVAL: u64 = 300;
 
 #[derive(Parser)]
 pub struct HiveParameters {
@@ -23,30 +95,118 @@ pub struct HiveParameters {
     /// Remote server peer id
     #[clap(long)]
     pub server_peer_id: Option<PeerId>,
-    /// Key seed to generate keypair
+    /// Kademlia bootstrap peer, with the form '<multiaddr>/p2p/<peer id>', can be repeated
+    /// 
+    /// Peers on 'HIVE_BOOTSTRAP_PEERS' are also used
+    #[clap(long = "bootstrap-peer")]
+    pub bootstrap_peers: Vec<Multiaddr>,
+    /// Seconds between Kademlia bootstraps
+    #[clap(long, default_value_t = DEFAULT_BOOTSTRAP_INTERVAL)]
+    pub bootstrap_interval: u64,
+    /// Peer that is always allowed, can be repeated, peers on 'HIVE_ALLOWED_PEERS' are also allowed
+    #[clap(long = "allow-peer")]
+    pub allowed_peers: Vec<PeerId>,
+    /// Peer that is always blocked, can be repeated, peers on 'HIVE_BLOCKED_PEERS' are also blocked
+    #[clap(long = "block-peer")]
+    pub blocked_peers: Vec<PeerId>,
+    /// Only allowed peers can connect
+    #[clap(long)]
+    pub allowlist: bool,
+    /// Don't discover peers on the local network with mDNS
     #[clap(long)]
+    pub no_mdns: bool,
+    /// Transports to listen and dial on, with a swarm key only TCP can be used
+    #[clap(long, value_enum, default_value_t = Transports::Both)]
+    pub transport: Transports,
+    /// Path of the keystore file, defaults to 'HIVE_KEYSTORE_PATH'
+    #[clap(long)]
+    pub keystore: Option<PathBuf>,
+    /// Path of the swarm key, only nodes with the same key can connect, defaults to 'HIVE_SWARM_KEY_PATH'
+    /// 
+    /// Without a swarm key the network is public
+    #[clap(long)]
+    pub swarm_key: Option<PathBuf>,
+    /// Folder shared with the other nodes, defaults to 'HIVE_TRANSFER_FOLDER'
+    #[clap(long)]
+    pub transfer_folder: Option<PathBuf>,
+    /// Seconds between the heartbeats sent to the other nodes
+    #[clap(long, default_value_t = DEFAULT_HEARTBEAT_INTERVAL)]
+    pub heartbeat_interval: u64,
+    /// Seconds without hearing from a peer until it's offline
+    #[clap(long, default_value_t = DEFAULT_OFFLINE_AFTER)]
+    pub offline_after: u64,
+    /// Failed pings in a row until a peer is offline
+    #[clap(long, default_value_t = DEFAULT_MAX_PING_FAILURES)]
+    pub max_ping_failures: u32,
+    /// Subnetwork where the leader is elected, defaults to 'HIVE_SUBNETWORK'
+    #[clap(long)]
+    pub subnetwork: Option<String>,
+    /// Nodes with a higher priority are elected first
+    #[clap(long, default_value_t = 0)]
+    pub election_priority: u32,
+    /// Seconds the lease of a leader lasts, it's renewed while the leader is alive
+    #[clap(long, default_value_t = DEFAULT_LEADER_LEASE)]
+    pub leader_lease: u64,
+    /// Seconds between samples of the resources of this computer, they are announced to the hive
+    #[clap(long, default_value_t = DEFAULT_SAMPLE_INTERVAL)]
+    pub resource_interval: u64,
+    /// Hours raw resource samples are kept, older samples are downsampled to 1-minute and 1-hour points
+    #[clap(long, default_value_t = DEFAULT_RAW_RETENTION)]
+    pub resource_retention: u64,
+    /// Test only, derive the keypair from a single byte instead of using the keystore
+    #[clap(long = "test-key-seed", hide = true)]
     pub key_seed: Option<u8>,
     /// Whether to use IPV6 or IPV4
     #[clap(long)]
     pub use_ipv6: Option<bool>,
-    /// Whether it's relay or not
+    /// Whether it's relay or not, on client mode a relay circuit is reserved on the server
     #[clap(long)]
     pub relay: bool,
+    /// Chat with the other peers through stdin
+    #[clap(long)]
+    pub chat: bool,
+    /// Serve the node metrics on 'http://<address>/metrics'
+    #[clap(long)]
+    pub metrics_address: Option<SocketAddr>,
+    /// Also serve the REST API from the node process, so changes are applied to the node right away
+    #[clap(long)]
+    pub api_address: Option<SocketAddr>,
+    #[clap(subcommand)]
+    pub command: Option<HiveCommand>,
 }
 
 impl Default for HiveParameters {
     fn default() -> Self {
-        let mut rng = rand::thread_rng();
-        let key_seed = rng.gen::<u8>();
-
         HiveParameters {
             server: true,
             port: None,
             server_address: None,
             server_peer_id: None,
-            key_seed: Some(key_seed),
+            bootstrap_peers: Vec::new(),
+            bootstrap_interval: DEFAULT_BOOTSTRAP_INTERVAL,
+            allowed_peers: Vec::new(),
+            blocked_peers: Vec::new(),
+            allowlist: false,
+            no_mdns: false,
+            transport: Transports::Both,
+            keystore: None,
+            swarm_key: None,
+            transfer_folder: None,
+            heartbeat_interval: DEFAULT_HEARTBEAT_INTERVAL,
+            offline_after: DEFAULT_OFFLINE_AFTER,
+            max_ping_failures: DEFAULT_MAX_PING_FAILURES,
+            subnetwork: None,
+            election_priority: 0,
+            leader_lease: DEFAULT_LEADER_LEASE,
+            resource_interval: DEFAULT_SAMPLE_INTERVAL,
+            resource_retention: DEFAULT_RAW_RETENTION,
+            key_seed: None,
             use_ipv6: None,
             relay: false,
+            chat: false,
+            metrics_address: None,
+            api_address: None,
+            command: None,
         }
     }
 }
@@ -65,12 +225,180 @@ impl HiveParameters {
         
         port
     }
+    
+    /// Get keystore
+    /// 
+    /// 
+    pub fn keystore(&self) -> Keystore {
+        match &self.keystore {
+            Some(path) => Keystore::new(path.clone()),
+            None => Keystore::default(),
+        }
+    }
+    
+    /// Get the node keypair
+    /// 
+    /// The test key seed takes precedence, otherwise the keypair is loaded from the keystore
+    pub fn keypair(&self) -> Result<Keypair, Box<dyn Error>> {
+        match self.key_seed {
+            Some(seed) => generate_ed25519(seed),
+            None => self.keystore().load_or_generate(),
+        }
+    }
+    
+    /// Get the swarm key file
+    /// 
+    /// None when the node isn't part of a private network
+    pub fn swarm_key_file(&self) -> Option<SwarmKeyFile> {
+        match &self.swarm_key {
+            Some(path) => Some(SwarmKeyFile::new(path.clone())),
+            None => hive_swarm_key_path().map(|path| SwarmKeyFile::new(PathBuf::from(path))),
+        }
+    }
+    
+    /// Load the swarm key
+    /// 
+    /// 
+    pub fn swarm_key(&self) -> Result<Option<PreSharedKey>, Box<dyn Error>> {
+        match self.swarm_key_file() {
+            Some(key_file) => Ok(Some(key_file.load()?)),
+            None => Ok(None),
+        }
+    }
+    
+    /// Get the transfer folder
+    /// 
+    /// 
+    pub fn transfer_folder(&self) -> TransferFolder {
+        match &self.transfer_folder {
+            Some(path) => TransferFolder::new(path.clone()),
+            None => TransferFolder::new(PathBuf::from(hive_transfer_folder())),
+        }
+    }
+    
+    /// Get the liveness configuration
+    /// 
+    /// 
+    pub fn liveness_config(&self) -> LivenessConfig {
+        LivenessConfig {
+            heartbeat_interval: Duration::from_secs(self.heartbeat_interval.max(1)),
+            offline_after: Duration::from_secs(self.offline_after.max(1)),
+            max_ping_failures: self.max_ping_failures.max(1),
+        }
+    }
+    
+    /// Get the election configuration
+    /// 
+    /// 
+    pub fn election_config(&self) -> ElectionConfig {
+        ElectionConfig {
+            subnetwork: self.subnetwork.clone().unwrap_or_else(default_subnetwork),
+            priority: self.election_priority,
+            lease: Duration::from_secs(self.leader_lease.max(1)),
+            election_timeout: ELECTION_TIMEOUT,
+        }
+    }
+    
+    /// Get the time between resource samples
+    /// 
+    /// 
+    pub fn resource_interval(&self) -> Duration {
+        Duration::from_secs(self.resource_interval.max(1))
+    }
+    
+    /// Get the retention of the resource history
+    /// 
+    /// 
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            raw: Duration::from_secs(self.resource_retention.max(1) * 60 * 60),
+            ..Default::default()
+        }
+    }
+    
+    /// Get the access rules of the configuration
+    /// 
+    /// The peers given as arguments and the ones on the environment, a blocked peer stays blocked
+    /// even if it's also allowed
+    pub fn access_rules(&self) -> Result<Vec<AccessRule>, Box<dyn Error>> {
+        let reason = Some("Configuration".to_string());
+        let mut rules = Vec::new();
+        
+        let allowed_peers = hive_allowed_peers()
+            .iter()
+            .map(|peer_id| peer_id.parse::<PeerId>())
+            .collect::<Result<Vec<_>, _>>()?;
+        for peer_id in self.allowed_peers.iter().chain(&allowed_peers) {
+            rules.push(AccessRule::new(*peer_id, PeerRule::Allow, reason.clone()));
+        }
+        
+        let blocked_peers = hive_blocked_peers()
+            .iter()
+            .map(|peer_id| peer_id.parse::<PeerId>())
+            .collect::<Result<Vec<_>, _>>()?;
+        for peer_id in self.blocked_peers.iter().chain(&blocked_peers) {
+            rules.push(AccessRule::new(*peer_id, PeerRule::Block, reason.clone()));
+        }
+        
+        Ok(rules)
+    }
+    
+    /// Get the Kademlia bootstrap peers
+    /// 
+    /// The remote server, the bootstrap peers given as arguments and the ones on the environment
+    pub fn bootstrap_peers(&self) -> Result<Vec<BootstrapPeer>, Box<dyn Error>> {
+        let mut peers = Vec::new();
+        
+        if let Some(server_address) = &self.server_address {
+            peers.push(BootstrapPeer::from_parts(server_address, self.server_peer_id)?);
+        }
+        
+        for address in &self.bootstrap_peers {
+            peers.push(BootstrapPeer::from_address(address)?);
+        }
+        
+        peers.extend(BootstrapPeer::parse_list(&hive_bootstrap_peers())?);
+        
+        Ok(peers)
+    }
 }
 
 /// Main function
 /// 
 /// 
 pub async fn main(parameters: HiveParameters) -> Result<(), Box<dyn Error>> {
+    if let Some(command) = &parameters.command {
+        match command {
+            HiveCommand::Identity(command) => {
+                identity::main(parameters.keystore(), command)?;
+            }
+            HiveCommand::Peers(command) => {
+                peers::main(command).await?;
+            }
+            HiveCommand::SwarmKey(command) => {
+                let key_file = parameters.swarm_key_file().unwrap_or_default();
+                swarm_key::main(key_file, command)?;
+            }
+            HiveCommand::Task(args) => {
+                task::main(args).await?;
+            }
+            HiveCommand::Send(args) => {
+                transfer::send(args).await?;
+            }
+            HiveCommand::Fetch(args) => {
+                transfer::fetch(args).await?;
+            }
+            HiveCommand::Leader(args) => {
+                leader::main(args).await?;
+            }
+            HiveCommand::Nodes(args) => {
+                nodes::main(args).await?;
+            }
+        }
+        
+        return Ok(());
+    }
+    
     if parameters.server {
         server::main(parameters).await?;
     } else {
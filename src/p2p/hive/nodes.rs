@@ -0,0 +1,41 @@
+//! Hive nodes command
+//! 
+//! Lists the server nodes on the registry of a running node, started with '--api-address'
+use clap::Args;
+use reqwest::Client;
+use std::error::Error;
+
+use super::task::{api_url, read_response};
+use crate::p2p::node::registry::RegistryEntry;
+
+#[derive(Args)]
+pub struct NodesArgs {
+    /// Also show removed nodes
+    #[clap(short, long)]
+    pub all: bool,
+    /// Rest api of the node, defaults to 'http://127.0.0.1:<PORT>'
+    #[clap(long)]
+    pub api: Option<String>,
+}
+
+/// Nodes main
+/// 
+/// 
+pub async fn main(args: &NodesArgs) -> Result<(), Box<dyn Error>> {
+    let url = format!("{}/api/hive/registry", api_url(&args.api).trim_end_matches('/'));
+    let response = Client::new().get(url).send().await?;
+    let entries: Vec<RegistryEntry> = serde_json::from_str(&read_response(response).await?)?;
+    
+    for entry in entries {
+        match entry.server_node {
+            Some(server_node) => println!(
+                "{} {} {} ({} cores)",
+                entry.node_id, server_node.location.name, server_node.status, server_node.resources.total_cores()
+            ),
+            None if args.all => println!("{} removed", entry.node_id),
+            None => {}
+        }
+    }
+    
+    Ok(())
+}
@@ -0,0 +1,75 @@
+//! Hive peers command
+//! 
+//! Manage the access list stored on the database, running nodes pick the changes up on their next sync
+use clap::Subcommand;
+use libp2p::PeerId;
+use std::error::Error;
+
+use crate::database::mysql_connection;
+use crate::p2p::node::access::{controller::AccessListController, AccessRule, PeerRule};
+
+#[derive(Subcommand)]
+pub enum PeersCommand {
+    /// Show the allowed and blocked peers
+    List,
+    /// Allow a peer, on allowlist mode only allowed peers can connect
+    Allow {
+        peer_id: PeerId,
+        /// Why the peer is allowed
+        #[clap(short, long)]
+        reason: Option<String>,
+    },
+    /// Block a peer, it's disconnected and can't connect again
+    Block {
+        peer_id: PeerId,
+        /// Why the peer is blocked
+        #[clap(short, long)]
+        reason: Option<String>,
+    },
+    /// Remove the rule of a peer
+    Remove {
+        peer_id: PeerId,
+    },
+}
+
+/// Peers main
+/// 
+/// 
+pub async fn main(command: &PeersCommand) -> Result<(), Box<dyn Error>> {
+    let controller = AccessListController::new(mysql_connection().await?);
+    
+    match command {
+        PeersCommand::List => {
+            let access_list = controller.load().await?;
+            if access_list.is_empty() {
+                println!("There are no peer rules");
+            }
+            
+            for rule in access_list.rules() {
+                match &rule.reason {
+                    Some(reason) => println!("{} {}: {reason}", rule.rule, rule.peer_id),
+                    None => println!("{} {}", rule.rule, rule.peer_id),
+                }
+            }
+        }
+        PeersCommand::Allow { peer_id, reason } => {
+            controller.save(&AccessRule::new(*peer_id, PeerRule::Allow, reason.clone())).await?;
+            
+            println!("Peer {peer_id} allowed");
+        }
+        PeersCommand::Block { peer_id, reason } => {
+            controller.save(&AccessRule::new(*peer_id, PeerRule::Block, reason.clone())).await?;
+            
+            println!("Peer {peer_id} blocked");
+        }
+        PeersCommand::Remove { peer_id } => {
+            if controller.remove(peer_id).await? {
+                println!("Rule of peer {peer_id} removed");
+            } else {
+                println!("Peer {peer_id} had no rule");
+            }
+        }
+    };
+    
+    Ok(())
+}
@@ -2,17 +2,70 @@
 //! 
 //! It combines Gossipsub and mDNS to enable peer discovery and message propagation.
 use std::error::Error;
-use super::HiveParameters;
+use tokio::select;
+use super::{chat, HiveParameters};
 
+use crate::database::mysql_connection;
 use crate::p2p::node::Node;
+use crate::server::api::{self, AppState, StartServerOptions};
+use crate::server::signal::shutdown_signal;
 
 /// Start service
 /// 
-/// 
+/// Runs until SIGINT or SIGTERM, then the node is shut down gracefully
 pub async fn main(parameters: HiveParameters) -> Result<(), Box<dyn Error>> {
+    let use_chat = parameters.chat;
+    let api_address = parameters.api_address;
     let mut node = Node::new(parameters).await?;
     
-    node.start().await?;
+    // Discovered server nodes are stored on the database
+    let db = match mysql_connection().await {
+        Ok(db) => Some(db),
+        Err(err) => {
+            println!("Database unavailable, discovered server nodes won't be stored: {err}");
+            None
+        }
+    };
+    if let Some(db) = db.clone() {
+        node.set_database(db);
+    }
+    
+    let resources = node.resources.clone();
+    let handle = node.spawn()?;
+    
+    // The rest api talks to the node through its handle
+    match (api_address, db) {
+        (Some(address), Some(db)) => {
+            let options = StartServerOptions {
+                host: address.ip().to_string(),
+                port: address.port(),
+            };
+            let state = AppState {
+                db,
+                node: Some(handle.clone()),
+                resources: Some(resources),
+            };
+            
+            tokio::spawn(async move {
+                if let Err(err) = api::serve(options, state).await {
+                    eprintln!("Error starting server: {err}");
+                }
+            });
+        }
+        (Some(_), None) => println!("The rest api needs the database, it won't be served"),
+        _ => {}
+    };
+    
+    if use_chat {
+        select! {
+            result = chat::main(handle.clone()) => result?,
+            result = shutdown_signal() => result?,
+        }
+    } else {
+        shutdown_signal().await?;
+    }
+    
+    handle.shutdown().await?;
     
     Ok(())
 }
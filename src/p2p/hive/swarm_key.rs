@@ -0,0 +1,41 @@
+//! Hive swarm key command
+//! 
+//! Generate the pre-shared key of a private network, then copy it to every node
+use clap::Subcommand;
+use std::error::Error;
+
+use crate::p2p::node::swarm_key::SwarmKeyFile;
+
+#[derive(Subcommand)]
+pub enum SwarmKeyCommand {
+    /// Generate a new swarm key
+    Generate {
+        /// Replace the current key, nodes with the previous key won't be able to connect
+        #[clap(long)]
+        force: bool,
+    },
+    /// Show the fingerprint of the swarm key, it's the same on every node of the network
+    Fingerprint,
+}
+
+/// Swarm key main
+/// 
+/// 
+pub fn main(key_file: SwarmKeyFile, command: &SwarmKeyCommand) -> Result<(), Box<dyn Error>> {
+    match command {
+        SwarmKeyCommand::Generate { force } => {
+            let key = key_file.generate(*force)?;
+            
+            println!("Swarm key saved at {}", key_file.path.display());
+            println!("Fingerprint: {}", key.fingerprint());
+        }
+        SwarmKeyCommand::Fingerprint => {
+            let key = key_file.load()?;
+            
+            println!("Fingerprint: {}", key.fingerprint());
+            println!("Swarm key: {}", key_file.path.display());
+        }
+    };
+    
+    Ok(())
+}
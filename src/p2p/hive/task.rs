@@ -0,0 +1,291 @@
+//! Hive task command
+//! 
+//! Tasks are managed through the rest api of a running node, started with '--api-address'
+use clap::{Args, Subcommand};
+use libp2p::PeerId;
+use reqwest::{header::CONTENT_TYPE, Client, Response};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Write;
+use std::time::Duration;
+
+use crate::config::env::server_port;
+use crate::p2p::node::protocol::server_node::HIVE_NODE_USER_ID;
+use crate::p2p::node::task::controller::result_line;
+use crate::p2p::node::task::{OutputStream, TaskInfo, TaskSpec};
+use crate::security::create_token::create_token;
+use crate::server::api::routes::api::hive::tasks::SubmitTaskRequest;
+use crate::server_node::resources::requirements::ResourceRequirements;
+
+/// Time between task updates while following it
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Args)]
+pub struct TaskArgs {
+    /// Rest api of the node, defaults to 'http://127.0.0.1:<PORT>'
+    #[clap(long)]
+    pub api: Option<String>,
+    #[clap(subcommand)]
+    pub command: TaskCommand,
+}
+
+#[derive(Subcommand)]
+pub enum TaskCommand {
+    /// Run a task and follow its output
+    Run {
+        /// Peer that runs the task, defaults to the node itself
+        #[clap(long)]
+        peer: Option<PeerId>,
+        /// Environment variable with the form 'KEY=VALUE', can be repeated
+        #[clap(short, long = "env", value_parser = parse_env_var)]
+        env: Vec<(String, String)>,
+        /// Seconds until the task is killed
+        #[clap(long)]
+        timeout: Option<u64>,
+        /// Cpu cores the peer needs to have
+        #[clap(long)]
+        cpu_cores: Option<u32>,
+        /// Free memory the peer needs to have, in bytes
+        #[clap(long)]
+        memory: Option<u64>,
+        /// Free storage the peer needs to have on a disk, in bytes
+        #[clap(long)]
+        storage: Option<u64>,
+        /// Only submit the task, without following it
+        #[clap(short, long)]
+        detach: bool,
+        /// Program and its arguments
+        #[clap(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Show the tasks of the node
+    List,
+    /// Show a task and its output
+    Show {
+        task_id: String,
+    },
+    /// Cancel a task
+    Cancel {
+        task_id: String,
+    },
+}
+
+/// Parse an environment variable
+/// 
+/// 
+fn parse_env_var(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("Expected 'KEY=VALUE', found '{value}'")),
+    }
+}
+
+/// Task client
+/// 
+/// Talks to the tasks endpoint of the rest api
+pub struct TaskClient {
+    pub api: String,
+    client: Client,
+}
+
+impl TaskClient {
+    pub fn new(api: &str) -> Self {
+        Self {
+            api: api.trim_end_matches('/').to_string(),
+            client: Client::new(),
+        }
+    }
+    
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/hive/tasks{path}", self.api)
+    }
+    
+    /// Submit a task
+    /// 
+    /// 
+    pub async fn submit(&self, peer_id: Option<PeerId>, spec: TaskSpec) -> Result<TaskInfo, Box<dyn Error>> {
+        let body = SubmitTaskRequest {
+            peer_id: peer_id.map(|peer_id| peer_id.to_string()),
+            spec,
+        };
+        
+        let response = self.client
+            .post(self.url(""))
+            .bearer_auth(api_token()?)
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_string(&body)?)
+            .send()
+            .await?;
+        
+        Ok(serde_json::from_str(&read_response(response).await?)?)
+    }
+    
+    /// List the tasks
+    /// 
+    /// 
+    pub async fn list(&self) -> Result<Vec<TaskInfo>, Box<dyn Error>> {
+        let response = self.client.get(self.url("")).send().await?;
+        
+        Ok(serde_json::from_str(&read_response(response).await?)?)
+    }
+    
+    /// Get a task
+    /// 
+    /// 
+    pub async fn get(&self, task_id: &str) -> Result<TaskInfo, Box<dyn Error>> {
+        let response = self.client.get(self.url(&format!("/{task_id}"))).send().await?;
+        
+        Ok(serde_json::from_str(&read_response(response).await?)?)
+    }
+    
+    /// Cancel a task
+    /// 
+    /// 
+    pub async fn cancel(&self, task_id: &str) -> Result<(), Box<dyn Error>> {
+        let response = self.client
+            .post(self.url(&format!("/{task_id}/cancel")))
+            .bearer_auth(api_token()?)
+            .send()
+            .await?;
+        read_response(response).await?;
+        
+        Ok(())
+    }
+}
+
+/// Read the body of a response
+/// 
+/// Error responses are turned into errors
+pub(super) async fn read_response(response: Response) -> Result<String, Box<dyn Error>> {
+    let status = response.status();
+    let body = response.text().await?;
+    
+    if !status.is_success() {
+        return Err(format!("{status}: {body}").into());
+    }
+    
+    Ok(body)
+}
+
+/// Url of the rest api
+/// 
+/// 
+pub(super) fn api_url(api: &Option<String>) -> String {
+    match api {
+        Some(api) => api.clone(),
+        None => format!("http://127.0.0.1:{}", server_port()),
+    }
+}
+
+/// Token for the rest api
+/// 
+/// Signed with the secret token, the node has to share it
+pub(super) fn api_token() -> Result<String, Box<dyn Error>> {
+    create_token(HIVE_NODE_USER_ID, Duration::from_secs(60))
+}
+
+/// Print output that wasn't printed yet
+/// 
+/// Returns how many chunks were printed in total
+fn print_output(task: &TaskInfo, printed: usize) -> usize {
+    for chunk in task.output.iter().skip(printed) {
+        match chunk.stream {
+            OutputStream::Stdout => print!("{}", chunk.data),
+            OutputStream::Stderr => eprint!("{}", chunk.data),
+        }
+    }
+    let _ = std::io::stdout().flush();
+    
+    task.output.len()
+}
+
+/// Follow a task until it finishes
+/// 
+/// Fails if the task didn't succeed
+async fn follow(client: &TaskClient, task_id: &str) -> Result<(), Box<dyn Error>> {
+    let mut printed = 0;
+    
+    loop {
+        let task = client.get(task_id).await?;
+        printed = print_output(&task, printed);
+        
+        if task.status.is_finished() {
+            return match task.exit_code {
+                Some(0) => Ok(()),
+                _ => Err(result_line(&task).into()),
+            };
+        }
+        
+        tokio::time::sleep(FOLLOW_INTERVAL).await;
+    }
+}
+
+/// Task main
+/// 
+/// 
+pub async fn main(args: &TaskArgs) -> Result<(), Box<dyn Error>> {
+    let client = TaskClient::new(&api_url(&args.api));
+    
+    match &args.command {
+        TaskCommand::Run { peer, env, timeout, cpu_cores, memory, storage, detach, command } => {
+            let spec = TaskSpec {
+                program: command[0].clone(),
+                args: command[1..].to_vec(),
+                env: env.iter().cloned().collect::<HashMap<_, _>>(),
+                timeout: *timeout,
+                requirements: ResourceRequirements {
+                    cpu_cores: *cpu_cores,
+                    memory: *memory,
+                    storage: *storage,
+                },
+            };
+            
+            let task = client.submit(*peer, spec).await?;
+            println!("Task {} submitted to peer {}", task.id, task.runner);
+            
+            if !detach {
+                follow(&client, &task.id).await?;
+            }
+        }
+        TaskCommand::List => {
+            let tasks = client.list().await?;
+            if tasks.is_empty() {
+                println!("There are no tasks");
+            }
+            
+            for task in tasks {
+                println!("{} {} on {}: {}", task.id, task.status, task.runner, task.spec.command_line());
+            }
+        }
+        TaskCommand::Show { task_id } => {
+            let task = client.get(task_id).await?;
+            
+            println!("Task: {}", task.id);
+            println!("Command: {}", task.spec.command_line());
+            println!("Owner: {}", task.owner);
+            println!("Runner: {}", task.runner);
+            println!("{}", result_line(&task));
+            print_output(&task, 0);
+        }
+        TaskCommand::Cancel { task_id } => {
+            client.cancel(task_id).await?;
+            
+            println!("Task {task_id} is being cancelled");
+        }
+    };
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_env_var() {
+        assert_eq!(parse_env_var("KEY=a=b").unwrap(), ("KEY".to_string(), "a=b".to_string()));
+        assert_eq!(parse_env_var("EMPTY=").unwrap(), ("EMPTY".to_string(), String::new()));
+        assert!(parse_env_var("KEY").is_err());
+        assert!(parse_env_var("=value").is_err());
+    }
+}
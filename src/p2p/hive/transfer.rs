@@ -0,0 +1,194 @@
+//! Hive send and fetch commands
+//! 
+//! Files are transferred by a running node, started with '--api-address', the command only
+//! starts the transfer and shows its progress.
+use clap::Args;
+use libp2p::PeerId;
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde::Serialize;
+use std::error::Error;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::task::{api_token, api_url, read_response};
+use crate::p2p::node::transfer::{TransferInfo, TransferStatus};
+use crate::server::api::routes::api::hive::transfers::{FetchFileRequest, SendFileRequest};
+
+/// Time between transfer updates while following it
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Args)]
+pub struct SendArgs {
+    /// Peer that receives the file
+    pub peer: PeerId,
+    /// File to send, it's stored with the same name on the transfer folder of the peer
+    pub path: PathBuf,
+    /// Maximum bytes per second
+    #[clap(long)]
+    pub limit: Option<u64>,
+    /// Only start the transfer, without following it
+    #[clap(short, long)]
+    pub detach: bool,
+    /// Rest api of the node, defaults to 'http://127.0.0.1:<PORT>'
+    #[clap(long)]
+    pub api: Option<String>,
+}
+
+#[derive(Args)]
+pub struct FetchArgs {
+    /// Peer that has the file
+    pub peer: PeerId,
+    /// Path of the file, relative to the transfer folder of the peer
+    pub remote_path: String,
+    /// Where to store the file, defaults to the transfer folder of the node
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+    /// Maximum bytes per second
+    #[clap(long)]
+    pub limit: Option<u64>,
+    /// Only start the transfer, without following it
+    #[clap(short, long)]
+    pub detach: bool,
+    /// Rest api of the node, defaults to 'http://127.0.0.1:<PORT>'
+    #[clap(long)]
+    pub api: Option<String>,
+}
+
+/// Transfer client
+/// 
+/// Talks to the transfers endpoint of the rest api
+pub struct TransferClient {
+    pub api: String,
+    client: Client,
+}
+
+impl TransferClient {
+    pub fn new(api: &str) -> Self {
+        Self {
+            api: api.trim_end_matches('/').to_string(),
+            client: Client::new(),
+        }
+    }
+    
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/hive/transfers{path}", self.api)
+    }
+    
+    async fn post(&self, path: &str, body: &impl Serialize) -> Result<TransferInfo, Box<dyn Error>> {
+        let response = self.client
+            .post(self.url(path))
+            .bearer_auth(api_token()?)
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_string(body)?)
+            .send()
+            .await?;
+        
+        Ok(serde_json::from_str(&read_response(response).await?)?)
+    }
+    
+    /// Send a file
+    /// 
+    /// 
+    pub async fn send(&self, body: &SendFileRequest) -> Result<TransferInfo, Box<dyn Error>> {
+        self.post("/send", body).await
+    }
+    
+    /// Fetch a file
+    /// 
+    /// 
+    pub async fn fetch(&self, body: &FetchFileRequest) -> Result<TransferInfo, Box<dyn Error>> {
+        self.post("/fetch", body).await
+    }
+    
+    /// Get a transfer
+    /// 
+    /// 
+    pub async fn get(&self, transfer_id: &str) -> Result<TransferInfo, Box<dyn Error>> {
+        let response = self.client.get(self.url(&format!("/{transfer_id}"))).send().await?;
+        
+        Ok(serde_json::from_str(&read_response(response).await?)?)
+    }
+}
+
+/// Progress line
+/// 
+/// 
+fn progress_line(transfer: &TransferInfo) -> String {
+    match (transfer.size, transfer.progress()) {
+        (Some(size), Some(progress)) => format!("{}/{size} bytes ({:.0}%)", transfer.transferred, progress * 100.0),
+        _ => format!("{} bytes", transfer.transferred),
+    }
+}
+
+/// Follow a transfer until it finishes
+/// 
+/// Fails if the transfer didn't complete
+async fn follow(client: &TransferClient, transfer_id: &str) -> Result<(), Box<dyn Error>> {
+    loop {
+        let transfer = client.get(transfer_id).await?;
+        print!("\r{}", progress_line(&transfer));
+        let _ = std::io::stdout().flush();
+        
+        if transfer.status.is_finished() {
+            println!();
+            
+            return match transfer.status {
+                TransferStatus::Completed => {
+                    println!("Transfer completed, md5 {}", transfer.md5.unwrap_or_default());
+                    Ok(())
+                }
+                _ => Err(format!("Transfer {}: {}", transfer.status, transfer.error.unwrap_or_default()).into()),
+            };
+        }
+        
+        tokio::time::sleep(FOLLOW_INTERVAL).await;
+    }
+}
+
+/// Send main
+/// 
+/// The path is made absolute, the node may run on another folder
+pub async fn send(args: &SendArgs) -> Result<(), Box<dyn Error>> {
+    let client = TransferClient::new(&api_url(&args.api));
+    let body = SendFileRequest {
+        peer_id: args.peer.to_string(),
+        path: std::fs::canonicalize(&args.path)?,
+        rate_limit: args.limit,
+    };
+    
+    let transfer = client.send(&body).await?;
+    println!("Transfer {} sending '{}' to peer {}", transfer.id, transfer.local_path.display(), transfer.peer);
+    
+    if !args.detach {
+        follow(&client, &transfer.id).await?;
+    }
+    
+    Ok(())
+}
+
+/// Fetch main
+/// 
+/// 
+pub async fn fetch(args: &FetchArgs) -> Result<(), Box<dyn Error>> {
+    let client = TransferClient::new(&api_url(&args.api));
+    let output = match &args.output {
+        Some(output) => Some(std::path::absolute(output)?),
+        None => None,
+    };
+    let body = FetchFileRequest {
+        peer_id: args.peer.to_string(),
+        remote_path: args.remote_path.clone(),
+        output,
+        rate_limit: args.limit,
+    };
+    
+    let transfer = client.fetch(&body).await?;
+    println!("Transfer {} fetching '{}' into '{}'", transfer.id, transfer.remote_path, transfer.local_path.display());
+    
+    if !args.detach {
+        follow(&client, &transfer.id).await?;
+    }
+    
+    Ok(())
+}
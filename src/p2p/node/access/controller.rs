@@ -0,0 +1,137 @@
+use chrono::Utc;
+use entity::hive_peer_rule::{
+    self,
+    ActiveModel as HivePeerRuleActiveModel,
+    Entity as HivePeerRuleEntity,
+    Model as HivePeerRuleModel,
+};
+use libp2p::PeerId;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use std::error::Error;
+
+use super::{AccessList, AccessRule};
+
+/// Access list controller
+/// 
+/// Rules are stored on the 'hive-peer-rule' table
+pub struct AccessListController {
+    pub db: DatabaseConnection,
+}
+
+impl AccessListController {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+    
+    /// Load the access list
+    /// 
+    /// Rows that can't be parsed are skipped
+    pub async fn load(&self) -> Result<AccessList, Box<dyn Error>> {
+        let models = HivePeerRuleEntity::find().all(&self.db).await?;
+        
+        let rules = models
+            .into_iter()
+            .filter_map(|model| match rule_from_model(model) {
+                Ok(rule) => Some(rule),
+                Err(err) => {
+                    tracing::warn!("Skipping stored peer rule: {err}");
+                    None
+                }
+            });
+        
+        Ok(AccessList::from_rules(rules))
+    }
+    
+    /// Save a rule
+    /// 
+    /// The rule of the peer is replaced
+    pub async fn save(&self, rule: &AccessRule) -> Result<(), Box<dyn Error>> {
+        HivePeerRuleEntity::insert(active_model_from_rule(rule))
+            .on_conflict(
+                OnConflict::column(hive_peer_rule::Column::PeerId)
+                    .update_columns([
+                        hive_peer_rule::Column::Rule,
+                        hive_peer_rule::Column::Reason,
+                        hive_peer_rule::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.db)
+            .await?;
+        
+        Ok(())
+    }
+    
+    /// Remove the rule of a peer
+    /// 
+    /// Returns whether the peer had a rule
+    pub async fn remove(&self, peer_id: &PeerId) -> Result<bool, Box<dyn Error>> {
+        let result = HivePeerRuleEntity::delete_many()
+            .filter(hive_peer_rule::Column::PeerId.eq(peer_id.to_string()))
+            .exec(&self.db)
+            .await?;
+        
+        Ok(result.rows_affected > 0)
+    }
+}
+
+/// Create an active model from a rule
+/// 
+/// 
+pub fn active_model_from_rule(rule: &AccessRule) -> HivePeerRuleActiveModel {
+    let now = Utc::now().naive_utc();
+    
+    HivePeerRuleActiveModel {
+        id: ActiveValue::NotSet,
+        peer_id: ActiveValue::Set(rule.peer_id.to_string()),
+        rule: ActiveValue::Set(rule.rule.to_string()),
+        reason: ActiveValue::Set(rule.reason.clone()),
+        created_at: ActiveValue::Set(Some(now)),
+        updated_at: ActiveValue::Set(Some(now)),
+    }
+}
+
+/// Create a rule from a model
+/// 
+/// 
+pub fn rule_from_model(model: HivePeerRuleModel) -> Result<AccessRule, Box<dyn Error>> {
+    Ok(AccessRule {
+        peer_id: model.peer_id.parse()?,
+        rule: model.rule.parse()?,
+        reason: model.reason,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p2p::node::access::PeerRule;
+    use sea_orm::TryIntoModel;
+    
+    #[test]
+    fn test_rule_round_trip() {
+        let rule = AccessRule::new(PeerId::random(), PeerRule::Block, Some("Spam".to_string()));
+        
+        let mut active_model = active_model_from_rule(&rule);
+        active_model.id = ActiveValue::Set(1);
+        let model = active_model.try_into_model().unwrap();
+        assert_eq!(model.rule, "block");
+        
+        assert_eq!(rule_from_model(model).unwrap(), rule);
+    }
+    
+    #[test]
+    fn test_invalid_rule() {
+        let model = HivePeerRuleModel {
+            id: 1,
+            peer_id: PeerId::random().to_string(),
+            rule: "ignore".to_string(),
+            reason: None,
+            created_at: None,
+            updated_at: None,
+        };
+        
+        assert!(rule_from_model(model).is_err());
+    }
+}
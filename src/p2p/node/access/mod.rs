@@ -0,0 +1,199 @@
+//! Peer access list
+//! 
+//! Peers allowed or blocked by the operator. Blocked peers can't connect, allowed peers are the
+//! only ones that can connect when the node runs in allowlist mode.
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use strum_macros::{Display, EnumString};
+
+pub mod controller;
+
+/// Peer rule
+/// 
+/// 
+#[derive(Clone, Copy, Debug, Display, EnumString, PartialEq, Eq, Deserialize, Serialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PeerRule {
+    Allow,
+    Block,
+}
+
+/// Access rule of a peer
+/// 
+/// 
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AccessRule {
+    pub peer_id: PeerId,
+    pub rule: PeerRule,
+    /// Why the rule was added
+    pub reason: Option<String>,
+}
+
+impl AccessRule {
+    pub fn new(peer_id: PeerId, rule: PeerRule, reason: Option<String>) -> Self {
+        Self {
+            peer_id,
+            rule,
+            reason,
+        }
+    }
+    
+    pub fn allow(peer_id: PeerId) -> Self {
+        Self::new(peer_id, PeerRule::Allow, None)
+    }
+    
+    pub fn block(peer_id: PeerId) -> Self {
+        Self::new(peer_id, PeerRule::Block, None)
+    }
+}
+
+/// Access list
+/// 
+/// A peer has at most one rule
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccessList {
+    rules: HashMap<PeerId, AccessRule>,
+}
+
+impl AccessList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn from_rules(rules: impl IntoIterator<Item = AccessRule>) -> Self {
+        let mut access_list = Self::new();
+        for rule in rules {
+            access_list.set(rule);
+        }
+        
+        access_list
+    }
+    
+    pub fn get(&self, peer_id: &PeerId) -> Option<&AccessRule> {
+        self.rules.get(peer_id)
+    }
+    
+    /// Rules sorted by peer id
+    /// 
+    /// 
+    pub fn rules(&self) -> Vec<AccessRule> {
+        let mut rules: Vec<_> = self.rules.values().cloned().collect();
+        rules.sort_by_key(|rule| rule.peer_id.to_base58());
+        
+        rules
+    }
+    
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+    
+    pub fn is_blocked(&self, peer_id: &PeerId) -> bool {
+        matches!(self.get(peer_id), Some(rule) if rule.rule == PeerRule::Block)
+    }
+    
+    pub fn is_allowed(&self, peer_id: &PeerId) -> bool {
+        matches!(self.get(peer_id), Some(rule) if rule.rule == PeerRule::Allow)
+    }
+    
+    /// Whether a peer is kept out of the hive
+    /// 
+    /// On allowlist mode every peer that isn't allowed is
+    pub fn is_denied(&self, peer_id: &PeerId, allowlist: bool) -> bool {
+        self.is_blocked(peer_id) || (allowlist && !self.is_allowed(peer_id))
+    }
+    
+    /// Set the rule of a peer
+    /// 
+    /// Returns the previous rule
+    pub fn set(&mut self, rule: AccessRule) -> Option<AccessRule> {
+        self.rules.insert(rule.peer_id, rule)
+    }
+    
+    /// Remove the rule of a peer
+    /// 
+    /// 
+    pub fn remove(&mut self, peer_id: &PeerId) -> Option<AccessRule> {
+        self.rules.remove(peer_id)
+    }
+    
+    /// Changes needed to turn this list into another one
+    /// 
+    /// Returns the rules that are new or different, and the peers whose rule was removed
+    pub fn changes(&self, next: &AccessList) -> (Vec<AccessRule>, Vec<PeerId>) {
+        let changed = next.rules
+            .values()
+            .filter(|rule| self.get(&rule.peer_id).map(|current| current.rule) != Some(rule.rule))
+            .cloned()
+            .collect();
+        
+        let removed = self.rules
+            .keys()
+            .filter(|peer_id| next.get(peer_id).is_none())
+            .cloned()
+            .collect();
+        
+        (changed, removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_set_and_remove() {
+        let peer_id = PeerId::random();
+        let mut access_list = AccessList::new();
+        
+        assert!(access_list.set(AccessRule::allow(peer_id)).is_none());
+        assert!(!access_list.is_blocked(&peer_id));
+        
+        // A peer only has one rule
+        let previous = access_list.set(AccessRule::block(peer_id)).unwrap();
+        assert_eq!(previous.rule, PeerRule::Allow);
+        assert!(access_list.is_blocked(&peer_id));
+        assert_eq!(access_list.len(), 1);
+        
+        access_list.remove(&peer_id);
+        assert!(!access_list.is_blocked(&peer_id));
+        assert!(access_list.is_empty());
+    }
+    
+    #[test]
+    fn test_changes() {
+        let kept = PeerId::random();
+        let switched = PeerId::random();
+        let removed = PeerId::random();
+        let added = PeerId::random();
+        
+        let current = AccessList::from_rules([
+            AccessRule::block(kept),
+            AccessRule::allow(switched),
+            AccessRule::block(removed),
+        ]);
+        let next = AccessList::from_rules([
+            AccessRule::block(kept),
+            AccessRule::block(switched),
+            AccessRule::allow(added),
+        ]);
+        
+        let (mut changed, removed_peers) = current.changes(&next);
+        changed.sort_by_key(|rule| rule.peer_id == added);
+        
+        assert_eq!(changed, vec![AccessRule::block(switched), AccessRule::allow(added)]);
+        assert_eq!(removed_peers, vec![removed]);
+    }
+    
+    #[test]
+    fn test_rule_names() {
+        assert_eq!(PeerRule::Block.to_string(), "block");
+        assert_eq!("allow".parse::<PeerRule>().unwrap(), PeerRule::Allow);
+        assert_eq!(serde_json::to_string(&PeerRule::Allow).unwrap(), "\"allow\"");
+    }
+}
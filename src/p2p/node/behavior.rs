@@ -1,10 +1,14 @@
 use libp2p_identity::Keypair;
-use libp2p::swarm::NetworkBehaviour;
+use libp2p::swarm::{behaviour::toggle::Toggle, NetworkBehaviour};
+use libp2p::StreamProtocol;
 use libp2p::{
+    allow_block_list::{self, AllowedPeers, BlockedPeers},
     autonat,
+    dcutr,
     gossipsub,
     identify,
     identity,
+    kad,
     mdns,
     ping,
     relay,
@@ -15,9 +19,17 @@ use std::hash::{Hash, Hasher};
 use std::time::Duration;
 use tokio::io;
 
-/// Generate ed25519
+use super::protocol::{file, registry, server_node, task};
+use super::validation::{peer_score_params, peer_score_thresholds, MAX_MESSAGE_SIZE};
+
+/// Kademlia protocol of the hive
 /// 
+/// Different from the public IPFS DHT, so the hive doesn't mix with other networks
+pub const KADEMLIA_PROTOCOL: StreamProtocol = StreamProtocol::new("/hive/kad/1.0.0");
+
+/// Generate ed25519
 /// 
+/// Test only, there are just 256 possible keys, use the keystore for real nodes
 pub fn generate_ed25519(secret_key_seed: u8) -> Result<identity::Keypair, Box<dyn Error>> {
     let mut bytes = [0u8; 32];
     bytes[0] = secret_key_seed;
@@ -25,24 +37,84 @@ pub fn generate_ed25519(secret_key_seed: u8) -> Result<identity::Keypair, Box<dy
     Ok(identity::Keypair::ed25519_from_bytes(bytes)?)
 }
 
+/// Default gossipsub heartbeat
+/// 
+/// This is set to aid debugging by not cluttering the log space
+pub const DEFAULT_GOSSIPSUB_HEARTBEAT: Duration = Duration::from_secs(10);
+
+/// Behaviours of a node
+/// 
+/// Everything is enabled by default, the hive protocols and the block list are always enabled
+#[derive(Clone, Debug)]
+pub struct BehaviourConfig {
+    pub autonat: bool,
+    pub gossipsub: bool,
+    pub identify: bool,
+    pub kademlia: bool,
+    pub mdns: bool,
+    pub ping: bool,
+    /// Reserve circuits on relays, hole punching is enabled with it
+    pub relay_client: bool,
+    /// Relay the connections of other peers
+    pub relay_server: bool,
+    /// Only allowed peers can connect
+    pub allowlist: bool,
+    pub gossipsub_heartbeat: Duration,
+}
+
+impl Default for BehaviourConfig {
+    fn default() -> Self {
+        Self {
+            autonat: true,
+            gossipsub: true,
+            identify: true,
+            kademlia: true,
+            mdns: true,
+            ping: true,
+            relay_client: true,
+            relay_server: true,
+            allowlist: false,
+            gossipsub_heartbeat: DEFAULT_GOSSIPSUB_HEARTBEAT,
+        }
+    }
+}
+
 /// We create a custom network behaviour that combines Gossipsub and Mdns.
 /// 
 /// This macro creates 'MyBehaviorEvent'
 #[derive(NetworkBehaviour)]
 pub struct MyBehavior {
-    pub auto_nat: autonat::Behaviour,
-    pub gossipsub: gossipsub::Behaviour,
-    pub identify: identify::Behaviour,
-    pub mdns: mdns::tokio::Behaviour,
-    pub ping: ping::Behaviour,
-    pub relay: relay::Behaviour,
+    /// Only enabled on allowlist mode
+    pub allowed_peers: Toggle<allow_block_list::Behaviour<AllowedPeers>>,
+    pub auto_nat: Toggle<autonat::Behaviour>,
+    pub blocked_peers: allow_block_list::Behaviour<BlockedPeers>,
+    /// Enabled with the relay client
+    pub dcutr: Toggle<dcutr::Behaviour>,
+    pub file: file::Behaviour,
+    pub gossipsub: Toggle<gossipsub::Behaviour>,
+    pub identify: Toggle<identify::Behaviour>,
+    pub kademlia: Toggle<kad::Behaviour<kad::store::MemoryStore>>,
+    pub mdns: Toggle<mdns::tokio::Behaviour>,
+    pub ping: Toggle<ping::Behaviour>,
+    pub registry: registry::Behaviour,
+    pub relay: Toggle<relay::Behaviour>,
+    pub relay_client: Toggle<relay::client::Behaviour>,
+    pub server_node: server_node::Behaviour,
+    pub task: task::Behaviour,
 }
 
 impl MyBehavior {
     /// Create new behavior
     /// 
-    /// 
-    pub fn new(key: &Keypair) -> Result<Self, Box<dyn Error>> {
+    /// The relay client is created by the swarm builder, because it's also a transport,
+    /// it's dropped when disabled
+    pub fn new(
+        key: &Keypair,
+        relay_client: relay::client::Behaviour,
+        config: &BehaviourConfig,
+    ) -> Result<Self, Box<dyn Error>> {
+        let peer_id = key.public().to_peer_id();
+        
         // To content-address message, we can take the hash of message and use it as an ID.
         let message_id_fn = |message: &gossipsub::Message| {
             let mut s = DefaultHasher::new();
@@ -52,37 +124,74 @@ impl MyBehavior {
         
         // Set a custom gossipsub configuration
         let gossipsub_config = gossipsub::ConfigBuilder::default()
-            .heartbeat_interval(Duration::from_secs(10)) // This is set to aid debugging by not cluttering the log space
+            .heartbeat_interval(config.gossipsub_heartbeat)
             .validation_mode(gossipsub::ValidationMode::Strict) // This sets the kind of message validation. The default is Strict (enforce message signing)
             .message_id_fn(message_id_fn) // content-address messages. No two messages of the same content will be propagated.
+            .validate_messages() // Messages are only forwarded once the node reports them as valid
+            .max_transmit_size(MAX_MESSAGE_SIZE) // The same limit the messages are validated with
             .build()
             .map_err(|msg| io::Error::new(io::ErrorKind::Other, msg))?; // Temporary hack because `build` does not return a proper `std::error::Error`.
         
         // build a gossipsub network behaviour
-        let gossipsub = gossipsub::Behaviour::new(
-            gossipsub::MessageAuthenticity::Signed(key.clone()),
-            gossipsub_config,
-        )?;
+        let gossipsub = match config.gossipsub {
+            true => {
+                let mut gossipsub = gossipsub::Behaviour::new(
+                    gossipsub::MessageAuthenticity::Signed(key.clone()),
+                    gossipsub_config,
+                )?;
+                
+                // Peers that keep sending invalid messages are graylisted
+                gossipsub.with_peer_score(peer_score_params(), peer_score_thresholds())?;
+                
+                Some(gossipsub)
+            }
+            false => None,
+        };
+        
+        let mdns = match config.mdns {
+            true => Some(mdns::tokio::Behaviour::new(mdns::Config::default(), peer_id)?),
+            false => None,
+        };
         
-        let mdns =
-            mdns::tokio::Behaviour::new(mdns::Config::default(), key.public().to_peer_id())?;
+        // Kademlia, to find peers outside of the local network
+        let kademlia = config.kademlia.then(|| {
+            let mut kademlia = kad::Behaviour::with_config(
+                peer_id,
+                kad::store::MemoryStore::new(peer_id),
+                kad::Config::new(KADEMLIA_PROTOCOL),
+            );
+            // Hive peers are usually on private addresses, which aren't confirmed as external
+            // so the automatic mode would leave every node as a client
+            kademlia.set_mode(Some(kad::Mode::Server));
+            
+            kademlia
+        });
         
         Ok(MyBehavior {
-            auto_nat: autonat::Behaviour::new(
-                key.public().to_peer_id(),
+            allowed_peers: Toggle::from(config.allowlist.then(allow_block_list::Behaviour::default)),
+            auto_nat: Toggle::from(config.autonat.then(|| autonat::Behaviour::new(
+                peer_id,
                 autonat::Config {
                     only_global_ips: false,
                     ..Default::default()
                 }
-            ),
-            gossipsub,
-            mdns,
-            identify: identify::Behaviour::new(identify::Config::new(
+            ))),
+            blocked_peers: allow_block_list::Behaviour::default(),
+            dcutr: Toggle::from(config.relay_client.then(|| dcutr::Behaviour::new(peer_id))),
+            file: file::new_behaviour(),
+            gossipsub: Toggle::from(gossipsub),
+            kademlia: Toggle::from(kademlia),
+            mdns: Toggle::from(mdns),
+            identify: Toggle::from(config.identify.then(|| identify::Behaviour::new(identify::Config::new(
                 "/ipfs/0.1.0".into(),
                 key.public()
-            )),
-            relay: relay::Behaviour::new(key.public().to_peer_id(), Default::default()),
-            ping: ping::Behaviour::new(ping::Config::new()),
+            )))),
+            relay: Toggle::from(config.relay_server.then(|| relay::Behaviour::new(peer_id, Default::default()))),
+            relay_client: Toggle::from(config.relay_client.then_some(relay_client)),
+            ping: Toggle::from(config.ping.then(|| ping::Behaviour::new(ping::Config::new()))),
+            registry: registry::new_behaviour(),
+            server_node: server_node::new_behaviour(),
+            task: task::new_behaviour(),
         })
     }
 }
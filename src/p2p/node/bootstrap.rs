@@ -0,0 +1,106 @@
+//! Kademlia bootstrap peers
+//! 
+//! Peers outside of the local network can't be found through mDNS, the DHT is bootstrapped
+//! from a list of known peers instead.
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use std::error::Error;
+
+/// Bootstrap peer
+/// 
+/// 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BootstrapPeer {
+    pub peer_id: PeerId,
+    pub address: Multiaddr,
+}
+
+impl BootstrapPeer {
+    pub fn new(peer_id: PeerId, address: Multiaddr) -> Self {
+        Self { peer_id, address }
+    }
+    
+    /// Create from an address ending with '/p2p/<peer id>'
+    /// 
+    /// The peer id is removed from the stored address
+    pub fn from_address(address: &Multiaddr) -> Result<Self, Box<dyn Error>> {
+        let mut address = address.clone();
+        
+        match address.pop() {
+            Some(Protocol::P2p(peer_id)) => Ok(Self::new(peer_id, address)),
+            _ => Err(format!("Bootstrap address '{address}' doesn't end with '/p2p/<peer id>'").into()),
+        }
+    }
+    
+    /// Create from an address and an optional peer id
+    /// 
+    /// The peer id is only required when the address doesn't have it
+    pub fn from_parts(address: &Multiaddr, peer_id: Option<PeerId>) -> Result<Self, Box<dyn Error>> {
+        match (address.iter().last(), peer_id) {
+            (Some(Protocol::P2p(_)), _) => Self::from_address(address),
+            (_, Some(peer_id)) => Ok(Self::new(peer_id, address.clone())),
+            _ => Err(format!("The peer id of '{address}' is unknown").into()),
+        }
+    }
+    
+    /// Parse a list of addresses
+    /// 
+    /// 
+    pub fn parse_list(addresses: &[String]) -> Result<Vec<Self>, Box<dyn Error>> {
+        addresses
+            .iter()
+            .map(|address| Self::from_address(&address.parse()?))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_from_address() {
+        let peer_id = PeerId::random();
+        let address: Multiaddr = format!("/ip4/10.0.0.2/tcp/4001/p2p/{peer_id}").parse().unwrap();
+        
+        let peer = BootstrapPeer::from_address(&address).unwrap();
+        assert_eq!(peer.peer_id, peer_id);
+        assert_eq!(peer.address, "/ip4/10.0.0.2/tcp/4001".parse::<Multiaddr>().unwrap());
+        
+        // Without peer id
+        let address: Multiaddr = "/ip4/10.0.0.2/tcp/4001".parse().unwrap();
+        assert!(BootstrapPeer::from_address(&address).is_err());
+    }
+    
+    #[test]
+    fn test_from_parts() {
+        let peer_id = PeerId::random();
+        let address: Multiaddr = "/ip4/10.0.0.2/udp/4001/quic-v1".parse().unwrap();
+        
+        let peer = BootstrapPeer::from_parts(&address, Some(peer_id)).unwrap();
+        assert_eq!(peer, BootstrapPeer::new(peer_id, address.clone()));
+        
+        assert!(BootstrapPeer::from_parts(&address, None).is_err());
+        
+        // The peer id of the address is used
+        let with_peer_id = address.clone().with(Protocol::P2p(peer_id));
+        let peer = BootstrapPeer::from_parts(&with_peer_id, None).unwrap();
+        assert_eq!(peer, BootstrapPeer::new(peer_id, address));
+    }
+    
+    #[test]
+    fn test_parse_list() {
+        let first = PeerId::random();
+        let second = PeerId::random();
+        let addresses = vec![
+            format!("/ip4/10.0.0.2/tcp/4001/p2p/{first}"),
+            format!("/dns4/hive.example.com/tcp/4001/p2p/{second}"),
+        ];
+        
+        let peers = BootstrapPeer::parse_list(&addresses).unwrap();
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[0].peer_id, first);
+        assert_eq!(peers[1].peer_id, second);
+        
+        assert!(BootstrapPeer::parse_list(&["not an address".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,453 @@
+//! Node builder
+//! 
+//! Every mode builds its swarm here, the callers choose the behaviours and the transports.
+//! Hive nodes take them from their parameters.
+use chrono::Utc;
+use clap::ValueEnum;
+use libp2p::{
+    core::{muxing::StreamMuxerBox, transport::Boxed, upgrade, Transport},
+    gossipsub,
+    identity::Keypair,
+    noise,
+    pnet::{PnetConfig, PreSharedKey},
+    quic,
+    tcp,
+    yamux,
+    PeerId,
+    Swarm,
+    SwarmBuilder,
+};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tracing::Dispatch;
+use tracing_subscriber::EnvFilter;
+
+use crate::config::env::secret_token;
+use crate::p2p::hive::HiveParameters;
+use crate::server_node::resources::sampler::ResourceSampler;
+use crate::server_node::ServerStatus;
+use crate::test::folder::hive_folder_test_suite::hive_server_node::HiveServerNode;
+
+use super::access::AccessList;
+use super::behavior::{BehaviourConfig, MyBehavior};
+use super::election::Election;
+use super::liveness::LivenessTracker;
+use super::message::MessageKind;
+use super::metrics::NodeMetrics;
+use super::peer_book::PeerBook;
+use super::protocol::task::TaskOutbox;
+use super::registry::NodeRegistry;
+use super::task::runner::TaskRunner;
+use super::task::TaskBook;
+use super::transfer::runner::TransferRunner;
+use super::transfer::TransferBook;
+use super::validation::MessageValidator;
+use super::{Node, EVENT_BUFFER};
+
+/// Default time an idle connection is kept open
+pub const DEFAULT_IDLE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Transports to listen and dial on
+/// 
+/// 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Transports {
+    Tcp,
+    Quic,
+    #[default]
+    Both,
+}
+
+impl Transports {
+    pub fn tcp(&self) -> bool {
+        matches!(self, Transports::Tcp | Transports::Both)
+    }
+    
+    pub fn quic(&self) -> bool {
+        matches!(self, Transports::Quic | Transports::Both)
+    }
+}
+
+/// Node builder
+/// 
+/// Every behaviour is enabled and both transports are used by default
+pub struct NodeBuilder {
+    local_key: Keypair,
+    swarm_key: Option<PreSharedKey>,
+    transports: Transports,
+    behaviours: BehaviourConfig,
+    idle_connection_timeout: Duration,
+    topics: Vec<gossipsub::IdentTopic>,
+    test_handler: Option<HiveServerNode>,
+}
+
+impl NodeBuilder {
+    pub fn new(local_key: Keypair) -> Self {
+        Self {
+            local_key,
+            swarm_key: None,
+            transports: Transports::default(),
+            behaviours: BehaviourConfig::default(),
+            idle_connection_timeout: DEFAULT_IDLE_CONNECTION_TIMEOUT,
+            topics: Vec::new(),
+            test_handler: None,
+        }
+    }
+    
+    /// Builder of a hive node
+    /// 
+    /// The keypair, the private network, the transports, the discovery and whether it relays
+    /// come from the parameters, and it's subscribed to the topic of every message kind
+    pub fn from_parameters(parameters: &HiveParameters) -> Result<Self, Box<dyn Error>> {
+        let mut builder = Self::new(parameters.keypair()?)
+            .swarm_key(parameters.swarm_key()?)
+            .transports(parameters.transport)
+            .mdns(!parameters.no_mdns)
+            .relay_server(parameters.relay)
+            .allowlist(parameters.allowlist);
+        
+        for kind in MessageKind::ALL {
+            builder = builder.topic(kind.topic());
+        }
+        
+        Ok(builder)
+    }
+    
+    /// Private network key
+    /// 
+    /// Only TCP can be protected with it
+    pub fn swarm_key(mut self, swarm_key: Option<PreSharedKey>) -> Self {
+        self.swarm_key = swarm_key;
+        self
+    }
+    
+    pub fn transports(mut self, transports: Transports) -> Self {
+        self.transports = transports;
+        self
+    }
+    
+    pub fn autonat(mut self, enabled: bool) -> Self {
+        self.behaviours.autonat = enabled;
+        self
+    }
+    
+    pub fn gossipsub(mut self, enabled: bool) -> Self {
+        self.behaviours.gossipsub = enabled;
+        self
+    }
+    
+    pub fn identify(mut self, enabled: bool) -> Self {
+        self.behaviours.identify = enabled;
+        self
+    }
+    
+    pub fn kademlia(mut self, enabled: bool) -> Self {
+        self.behaviours.kademlia = enabled;
+        self
+    }
+    
+    pub fn mdns(mut self, enabled: bool) -> Self {
+        self.behaviours.mdns = enabled;
+        self
+    }
+    
+    pub fn ping(mut self, enabled: bool) -> Self {
+        self.behaviours.ping = enabled;
+        self
+    }
+    
+    /// Relay client, hole punching is enabled with it
+    /// 
+    /// 
+    pub fn relay_client(mut self, enabled: bool) -> Self {
+        self.behaviours.relay_client = enabled;
+        self
+    }
+    
+    pub fn relay_server(mut self, enabled: bool) -> Self {
+        self.behaviours.relay_server = enabled;
+        self
+    }
+    
+    /// Only allowed peers can connect
+    /// 
+    /// 
+    pub fn allowlist(mut self, enabled: bool) -> Self {
+        self.behaviours.allowlist = enabled;
+        self
+    }
+    
+    pub fn idle_connection_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_connection_timeout = timeout;
+        self
+    }
+    
+    pub fn gossipsub_heartbeat(mut self, heartbeat: Duration) -> Self {
+        self.behaviours.gossipsub_heartbeat = heartbeat;
+        self
+    }
+    
+    /// Subscribe to a topic once built
+    /// 
+    /// 
+    pub fn topic(mut self, topic: gossipsub::IdentTopic) -> Self {
+        self.topics.push(topic);
+        self
+    }
+    
+    /// Write the logs of the node to the folder of a test server node
+    /// 
+    /// 
+    pub fn test_handler(mut self, test_handler: HiveServerNode) -> Self {
+        self.test_handler = Some(test_handler);
+        self
+    }
+    
+    /// Transports that are actually used
+    /// 
+    /// With a swarm key only TCP is used, because QUIC connections can't be protected with it
+    pub fn effective_transports(&self) -> Result<Transports, Box<dyn Error>> {
+        match (self.swarm_key, self.transports) {
+            (None, transports) => Ok(transports),
+            (Some(_), Transports::Quic) => Err("QUIC connections can't be protected with the swarm key".into()),
+            (Some(_), _) => Ok(Transports::Tcp),
+        }
+    }
+    
+    /// Build the swarm
+    /// 
+    /// It's subscribed to the topics but it isn't listening yet
+    pub fn build_swarm(&self) -> Result<Swarm<MyBehavior>, Box<dyn Error>> {
+        let transports = self.effective_transports()?;
+        let swarm_key = self.swarm_key;
+        let behaviours = &self.behaviours;
+        let idle_connection_timeout = self.idle_connection_timeout;
+        
+        let mut swarm = SwarmBuilder::with_existing_identity(self.local_key.clone())
+            .with_tokio()
+            .with_other_transport(|key| transport(key, transports, swarm_key))?
+            .with_relay_client(noise::Config::new, yamux::Config::default)?
+            .with_behaviour(|key, relay_client| {
+                MyBehavior::new(key, relay_client, behaviours).map_err(|err| err.to_string().into())
+            })?
+            .with_swarm_config(|c| c.with_idle_connection_timeout(idle_connection_timeout))
+            .build();
+        
+        if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
+            for topic in &self.topics {
+                gossipsub.subscribe(topic)?;
+            }
+        }
+        
+        Ok(swarm)
+    }
+    
+    /// Build a hive node
+    /// 
+    /// With a test handler the node logs to its folder, otherwise to the global subscriber
+    pub fn build(self, parameters: HiveParameters) -> Result<Node, Box<dyn Error>> {
+        // Requests of peers can't be signed nor verified without it
+        secret_token()?;
+        
+        let swarm = self.build_swarm()?;
+        let transports = self.effective_transports()?;
+        
+        let (log_guard, log_dispatch) = match &self.test_handler {
+            Some(test_handler) => {
+                // It's only used by the node task instead of set as the global subscriber,
+                // so many nodes can run on the same process
+                let file_appender = tracing_appender::rolling::daily(test_handler.get_log_folder(), "log");
+                let (non_blocking_writer, log_guard) = tracing_appender::non_blocking(file_appender);
+                let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+                let log_dispatch = Dispatch::new(
+                    tracing_subscriber::fmt()
+                        .with_env_filter(filter)
+                        .with_writer(non_blocking_writer)
+                        .with_ansi(false)
+                        .finish()
+                );
+                
+                (Some(log_guard), Some(log_dispatch))
+            }
+            None => {
+                let _ = tracing_subscriber::fmt()
+                    .with_env_filter(EnvFilter::from_default_env())
+                    .try_init();
+                
+                (None, None)
+            }
+        };
+        
+        let local_peer_id = self.local_key.public().to_peer_id();
+        let (task_runner, task_events) = TaskRunner::new();
+        let (transfer_runner, transfer_events) = TransferRunner::new();
+        let (file_responder, file_replies) = mpsc::unbounded_channel();
+        let transfer_folder = parameters.transfer_folder();
+        let liveness = LivenessTracker::new(parameters.liveness_config());
+        let election = Election::new(parameters.election_config(), local_peer_id, Utc::now());
+        let resources = ResourceSampler::spawn(parameters.resource_interval())?;
+        let validator = MessageValidator {
+            allowlist: parameters.allowlist,
+            ..Default::default()
+        };
+        
+        Ok(Node {
+            parameters,
+            local_key: self.local_key,
+            swarm_key: self.swarm_key,
+            transports,
+            swarm,
+            db: None,
+            requested_peers: HashSet::new(),
+            test_handler: self.test_handler,
+            events: broadcast::channel(EVENT_BUFFER).0,
+            peer_book: PeerBook::new(),
+            registry: NodeRegistry::new(local_peer_id),
+            status: ServerStatus::Online,
+            liveness,
+            election,
+            access_list: AccessList::new(),
+            metrics: NodeMetrics::new(),
+            resources,
+            validator,
+            tasks: TaskBook::new(),
+            task_runner,
+            task_events,
+            task_recorder: None,
+            task_requests: HashMap::new(),
+            task_outbox: TaskOutbox::new(),
+            transfers: TransferBook::new(),
+            transfer_folder,
+            transfer_runner,
+            transfer_events,
+            file_requests: HashMap::new(),
+            file_responder,
+            file_replies,
+            listeners: Vec::new(),
+            metrics_server: None,
+            log_guard,
+            log_dispatch,
+        })
+    }
+}
+
+/// Transport of a node
+/// 
+/// With a swarm key the pnet handshake fails with peers that don't have the same key
+fn transport(
+    key: &Keypair,
+    transports: Transports,
+    swarm_key: Option<PreSharedKey>,
+) -> Result<Boxed<(PeerId, StreamMuxerBox)>, Box<dyn Error + Send + Sync>> {
+    let noise_config = noise::Config::new(key)?;
+    let tcp_transport = tcp::tokio::Transport::new(tcp::Config::default());
+    let tcp_transport = match swarm_key {
+        Some(swarm_key) => tcp_transport
+            .and_then(move |socket, _| PnetConfig::new(swarm_key).handshake(socket))
+            .upgrade(upgrade::Version::V1Lazy)
+            .authenticate(noise_config)
+            .multiplex(yamux::Config::default())
+            .boxed(),
+        None => tcp_transport
+            .upgrade(upgrade::Version::V1Lazy)
+            .authenticate(noise_config)
+            .multiplex(yamux::Config::default())
+            .boxed(),
+    };
+    
+    let quic_transport = quic::tokio::Transport::new(quic::Config::new(key))
+        .map(|(peer_id, connection), _| (peer_id, StreamMuxerBox::new(connection)))
+        .boxed();
+    
+    let transport = match transports {
+        Transports::Tcp => tcp_transport,
+        Transports::Quic => quic_transport,
+        Transports::Both => tcp_transport
+            .or_transport(quic_transport)
+            .map(|output, _| output.into_inner())
+            .boxed(),
+    };
+    
+    Ok(transport)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p2p::node::behavior::generate_ed25519;
+    use crate::test::hive_simulation::{eventually, SIMULATION_TIMEOUT};
+    use libp2p::{multiaddr::Protocol, Multiaddr};
+    use std::net::Ipv4Addr;
+    
+    #[tokio::test]
+    async fn test_build_swarm() {
+        // Only the chosen behaviours are enabled
+        let swarm = NodeBuilder::new(generate_ed25519(154).unwrap())
+            .mdns(false)
+            .kademlia(false)
+            .relay_server(false)
+            .topic(MessageKind::Chat.topic())
+            .build_swarm()
+            .unwrap();
+        let behaviour = swarm.behaviour();
+        assert!(!behaviour.mdns.is_enabled());
+        assert!(!behaviour.kademlia.is_enabled());
+        assert!(!behaviour.relay.is_enabled());
+        assert!(behaviour.relay_client.is_enabled());
+        assert_eq!(behaviour.gossipsub.as_ref().unwrap().topics().count(), 1);
+        
+        // QUIC can't be protected with a swarm key
+        let builder = NodeBuilder::new(generate_ed25519(155).unwrap())
+            .swarm_key(Some(PreSharedKey::new([7; 32])));
+        assert_eq!(builder.effective_transports().unwrap(), Transports::Tcp);
+        assert!(builder.transports(Transports::Quic).build_swarm().is_err());
+        
+        // Nodes only relay when it's asked for
+        let parameters = |relay| HiveParameters {
+            key_seed: Some(158),
+            no_mdns: true,
+            relay,
+            ..Default::default()
+        };
+        let swarm = NodeBuilder::from_parameters(&parameters(false)).unwrap().build_swarm().unwrap();
+        assert!(!swarm.behaviour().relay.is_enabled());
+        let swarm = NodeBuilder::from_parameters(&parameters(true)).unwrap().build_swarm().unwrap();
+        assert!(swarm.behaviour().relay.is_enabled());
+    }
+    
+    #[tokio::test]
+    async fn test_quic_nodes() {
+        let parameters = |key_seed| HiveParameters {
+            key_seed: Some(key_seed),
+            no_mdns: true,
+            transport: Transports::Quic,
+            ..Default::default()
+        };
+        let first = Node::new(parameters(156)).await.unwrap().spawn().unwrap();
+        let second = Node::new(parameters(157)).await.unwrap().spawn().unwrap();
+        
+        // Only QUIC addresses are listened on
+        let is_loopback = |address: &Multiaddr| address.iter().next() == Some(Protocol::Ip4(Ipv4Addr::LOCALHOST));
+        let listening = eventually(SIMULATION_TIMEOUT, || async {
+            second.listen_addresses().await.is_ok_and(|addresses| addresses.iter().any(is_loopback))
+        })
+        .await;
+        assert!(listening, "The node isn't listening on loopback");
+        
+        let addresses = second.listen_addresses().await.unwrap();
+        assert!(addresses.iter().all(|address| address.iter().any(|protocol| protocol == Protocol::QuicV1)));
+        
+        let address = addresses.into_iter().find(is_loopback).unwrap();
+        first.dial(address).await.unwrap();
+        let connected = eventually(SIMULATION_TIMEOUT, || async {
+            first.peers().await.is_ok_and(|peers| peers.contains(&second.peer_id()))
+        })
+        .await;
+        assert!(connected, "The nodes didn't connect over QUIC");
+        
+        first.shutdown().await.unwrap();
+        second.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,547 @@
+//! Leader election
+//! 
+//! Elects the main server of each subnetwork, the one that tracks the other servers of its network
+//! and is the entry point for outside servers.
+//! It's a bully election with leases: while the lease of a leader is valid every node follows it,
+//! when the lease expires or the leader stops answering pings a new election starts and the
+//! candidate with the highest rank wins.
+use chrono::{DateTime, TimeDelta, Utc};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+use std::time::Duration;
+use strum_macros::Display;
+
+use crate::config::env::hive_subnetwork;
+use crate::server_node::server_info::get_computer_ip;
+
+/// Default seconds a leader lease lasts
+pub const DEFAULT_LEADER_LEASE: u64 = 15;
+
+/// Time a candidate waits for higher ranked nodes before leading
+pub const ELECTION_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Time between election checks
+pub const ELECTION_TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Subnetwork of this computer
+/// 
+/// Set with 'HIVE_SUBNETWORK', otherwise the /24 network of the computer ip
+pub fn default_subnetwork() -> String {
+    if let Some(subnetwork) = hive_subnetwork() {
+        return subnetwork;
+    }
+    
+    let ip = get_computer_ip()
+        .ok()
+        .and_then(|ip| ip.parse::<Ipv4Addr>().ok())
+        .unwrap_or(Ipv4Addr::UNSPECIFIED);
+    let [a, b, c, _] = ip.octets();
+    
+    format!("{a}.{b}.{c}.0/24")
+}
+
+/// Election configuration
+/// 
+/// 
+#[derive(Clone, Debug)]
+pub struct ElectionConfig {
+    pub subnetwork: String,
+    // Nodes with a higher priority win the elections, ties are broken by the peer id
+    pub priority: u32,
+    pub lease: Duration,
+    pub election_timeout: Duration,
+}
+
+impl Default for ElectionConfig {
+    fn default() -> Self {
+        Self {
+            subnetwork: default_subnetwork(),
+            priority: 0,
+            lease: Duration::from_secs(DEFAULT_LEADER_LEASE),
+            election_timeout: ELECTION_TIMEOUT,
+        }
+    }
+}
+
+/// Rank of a node
+/// 
+/// Compared by priority first and peer id second
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Rank {
+    pub priority: u32,
+    pub peer_id: PeerId,
+}
+
+/// Election message kind
+/// 
+/// 
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ElectionKind {
+    /// The sender wants to lead
+    Candidate,
+    /// The sender outranks a candidate, which has to wait for the new leader
+    Alive,
+    /// The sender leads the subnetwork, sent again on every renewal of the lease
+    Leader {
+        lease: Duration,
+    },
+    /// The sender doesn't lead anymore
+    Resign,
+}
+
+/// Election message
+/// 
+/// Messages of other subnetworks are ignored
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ElectionMessage {
+    pub subnetwork: String,
+    pub term: u64,
+    pub priority: u32,
+    pub kind: ElectionKind,
+}
+
+/// Role of this node
+/// 
+/// 
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, Deserialize, Serialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+/// Leader of the subnetwork
+/// 
+/// 
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct LeaderInfo {
+    pub subnetwork: String,
+    pub peer_id: PeerId,
+    pub priority: u32,
+    pub term: u64,
+    pub lease_until: DateTime<Utc>,
+}
+
+impl LeaderInfo {
+    pub fn rank(&self) -> Rank {
+        Rank {
+            priority: self.priority,
+            peer_id: self.peer_id,
+        }
+    }
+}
+
+/// Election status
+/// 
+/// What this node knows about the election of its subnetwork
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ElectionStatus {
+    pub subnetwork: String,
+    pub term: u64,
+    pub role: Role,
+    pub leader: Option<LeaderInfo>,
+}
+
+/// Election
+/// 
+/// Every method returns the messages that have to be published
+#[derive(Clone, Debug)]
+pub struct Election {
+    pub config: ElectionConfig,
+    local_peer_id: PeerId,
+    term: u64,
+    role: Role,
+    leader: Option<LeaderInfo>,
+    // Followers without a leader start an election at this time, candidates lead at this time
+    deadline: DateTime<Utc>,
+    // Leaders renew their lease every third of it
+    renewed_at: DateTime<Utc>,
+    // Nodes on maintenance don't stand for election
+    eligible: bool,
+    // Last leader reported by 'changed'
+    reported: Option<PeerId>,
+}
+
+impl Election {
+    /// Create an election
+    /// 
+    /// New nodes wait for a running leader before starting an election
+    pub fn new(config: ElectionConfig, local_peer_id: PeerId, now: DateTime<Utc>) -> Self {
+        let deadline = now + delta(config.election_timeout);
+        
+        Self {
+            config,
+            local_peer_id,
+            term: 0,
+            role: Role::Follower,
+            leader: None,
+            deadline,
+            renewed_at: now,
+            eligible: true,
+            reported: None,
+        }
+    }
+    
+    pub fn rank(&self) -> Rank {
+        Rank {
+            priority: self.config.priority,
+            peer_id: self.local_peer_id,
+        }
+    }
+    
+    pub fn term(&self) -> u64 {
+        self.term
+    }
+    
+    /// Leader with a valid lease
+    /// 
+    /// 
+    pub fn leader(&self, now: DateTime<Utc>) -> Option<&LeaderInfo> {
+        self.leader.as_ref().filter(|leader| leader.lease_until > now)
+    }
+    
+    pub fn is_leader(&self, now: DateTime<Utc>) -> bool {
+        self.role == Role::Leader && self.leader(now).is_some()
+    }
+    
+    /// Election status
+    /// 
+    /// 
+    pub fn status(&self, now: DateTime<Utc>) -> ElectionStatus {
+        ElectionStatus {
+            subnetwork: self.config.subnetwork.clone(),
+            term: self.term,
+            role: self.role,
+            leader: self.leader(now).cloned(),
+        }
+    }
+    
+    /// Whether the leader changed since the last call
+    /// 
+    /// 
+    pub fn changed(&mut self, now: DateTime<Utc>) -> bool {
+        let leader = self.leader(now).map(|leader| leader.peer_id);
+        if leader == self.reported {
+            return false;
+        }
+        
+        self.reported = leader;
+        true
+    }
+    
+    /// Check the timeouts
+    /// 
+    /// Leaders renew their lease, candidates lead once nobody outranked them and followers without
+    /// a leader start an election
+    pub fn tick(&mut self, now: DateTime<Utc>) -> Vec<ElectionMessage> {
+        match self.role {
+            Role::Leader if self.leader(now).is_none() || now - self.renewed_at >= delta(self.config.lease / 3) => {
+                vec![self.lead(now)]
+            }
+            Role::Candidate if now >= self.deadline => vec![self.lead(now)],
+            Role::Follower if self.eligible && self.leader(now).is_none() && now >= self.deadline => {
+                vec![self.start_election(now)]
+            }
+            _ => Vec::new(),
+        }
+    }
+    
+    /// Handle a message of another node
+    /// 
+    /// 
+    pub fn handle(&mut self, sender: PeerId, message: ElectionMessage, now: DateTime<Utc>) -> Vec<ElectionMessage> {
+        if message.subnetwork != self.config.subnetwork || sender == self.local_peer_id {
+            return Vec::new();
+        }
+        
+        let sender_rank = Rank {
+            priority: message.priority,
+            peer_id: sender,
+        };
+        
+        match message.kind {
+            ElectionKind::Candidate => {
+                // The candidate follows a valid lease
+                if self.is_leader(now) {
+                    return vec![self.lead(now)];
+                }
+                
+                self.term = self.term.max(message.term);
+                if self.leader(now).is_some() {
+                    return Vec::new();
+                }
+                
+                if self.eligible && self.rank() > sender_rank {
+                    let mut messages = vec![self.message(ElectionKind::Alive)];
+                    if self.role == Role::Follower {
+                        messages.push(self.start_election(now));
+                    }
+                    
+                    return messages;
+                }
+                
+                self.wait_for_leader(now);
+                Vec::new()
+            }
+            ElectionKind::Alive => {
+                if self.role == Role::Candidate && sender_rank > self.rank() {
+                    self.wait_for_leader(now);
+                }
+                
+                Vec::new()
+            }
+            ElectionKind::Leader { lease } => {
+                let leader = LeaderInfo {
+                    subnetwork: message.subnetwork,
+                    peer_id: sender,
+                    priority: message.priority,
+                    term: message.term,
+                    lease_until: now + delta(lease),
+                };
+                
+                // Two leaders, the one with the highest term and rank stays
+                let current = self.leader(now).filter(|current| current.peer_id != sender);
+                if let Some(current) = current {
+                    if (current.term, current.rank()) > (leader.term, leader.rank()) {
+                        return match self.role {
+                            Role::Leader => vec![self.lead(now)],
+                            _ => Vec::new(),
+                        };
+                    }
+                }
+                
+                self.term = self.term.max(leader.term);
+                self.role = Role::Follower;
+                self.deadline = leader.lease_until;
+                self.leader = Some(leader);
+                
+                Vec::new()
+            }
+            ElectionKind::Resign => {
+                if self.leader.as_ref().is_some_and(|leader| leader.peer_id == sender) {
+                    return self.leader_lost(now);
+                }
+                
+                Vec::new()
+            }
+        }
+    }
+    
+    /// A peer went offline or on maintenance
+    /// 
+    /// When it's the leader a new election starts right away
+    pub fn peer_unavailable(&mut self, peer_id: PeerId, now: DateTime<Utc>) -> Vec<ElectionMessage> {
+        if self.role != Role::Leader && self.leader.as_ref().is_some_and(|leader| leader.peer_id == peer_id) {
+            return self.leader_lost(now);
+        }
+        
+        Vec::new()
+    }
+    
+    /// Whether this node can lead
+    /// 
+    /// A leader that isn't eligible anymore resigns
+    pub fn set_eligible(&mut self, eligible: bool, now: DateTime<Utc>) -> Vec<ElectionMessage> {
+        if eligible {
+            // Give the running leader time to renew its lease first
+            if !self.eligible {
+                self.deadline = now + delta(self.config.election_timeout);
+            }
+            self.eligible = true;
+            
+            return Vec::new();
+        }
+        
+        self.eligible = false;
+        match std::mem::replace(&mut self.role, Role::Follower) {
+            Role::Leader => {
+                self.leader = None;
+                vec![self.message(ElectionKind::Resign)]
+            }
+            _ => Vec::new(),
+        }
+    }
+    
+    /// The leader is gone
+    /// 
+    /// 
+    fn leader_lost(&mut self, now: DateTime<Utc>) -> Vec<ElectionMessage> {
+        self.leader = None;
+        self.role = Role::Follower;
+        self.deadline = now;
+        
+        match self.eligible {
+            true => vec![self.start_election(now)],
+            false => Vec::new(),
+        }
+    }
+    
+    /// Stand for election on a new term
+    /// 
+    /// 
+    fn start_election(&mut self, now: DateTime<Utc>) -> ElectionMessage {
+        self.term += 1;
+        self.role = Role::Candidate;
+        self.deadline = now + delta(self.config.election_timeout);
+        
+        self.message(ElectionKind::Candidate)
+    }
+    
+    /// Give up the election
+    /// 
+    /// A higher ranked candidate will lead, if it doesn't another election is started
+    fn wait_for_leader(&mut self, now: DateTime<Utc>) {
+        self.role = Role::Follower;
+        self.deadline = now + delta(self.config.election_timeout * 2);
+    }
+    
+    /// Lead or renew the lease
+    /// 
+    /// 
+    fn lead(&mut self, now: DateTime<Utc>) -> ElectionMessage {
+        self.role = Role::Leader;
+        self.renewed_at = now;
+        self.leader = Some(LeaderInfo {
+            subnetwork: self.config.subnetwork.clone(),
+            peer_id: self.local_peer_id,
+            priority: self.config.priority,
+            term: self.term,
+            lease_until: now + delta(self.config.lease),
+        });
+        
+        self.message(ElectionKind::Leader {
+            lease: self.config.lease,
+        })
+    }
+    
+    fn message(&self, kind: ElectionKind) -> ElectionMessage {
+        ElectionMessage {
+            subnetwork: self.config.subnetwork.clone(),
+            term: self.term,
+            priority: self.config.priority,
+            kind,
+        }
+    }
+}
+
+fn delta(duration: Duration) -> TimeDelta {
+    TimeDelta::from_std(duration).unwrap_or(TimeDelta::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn config() -> ElectionConfig {
+        ElectionConfig {
+            subnetwork: "10.0.0.0/24".to_string(),
+            ..Default::default()
+        }
+    }
+    
+    /// Deliver messages to every other election
+    /// 
+    /// Until nobody has anything else to say
+    fn deliver(elections: &mut [Election], from: usize, messages: Vec<ElectionMessage>, now: DateTime<Utc>) {
+        let sender = elections[from].local_peer_id;
+        
+        for message in messages {
+            for index in 0..elections.len() {
+                if index == from {
+                    continue;
+                }
+                
+                let replies = elections[index].handle(sender, message.clone(), now);
+                deliver(elections, index, replies, now);
+            }
+        }
+    }
+    
+    /// Tick every election
+    /// 
+    /// 
+    fn tick(elections: &mut [Election], now: DateTime<Utc>) {
+        for index in 0..elections.len() {
+            let messages = elections[index].tick(now);
+            deliver(elections, index, messages, now);
+        }
+    }
+    
+    #[test]
+    fn test_highest_rank_leads() {
+        let now = Utc::now();
+        let mut elections: Vec<_> = (0..3)
+            .map(|_| Election::new(config(), PeerId::random(), now))
+            .collect();
+        let highest = elections.iter().map(|election| election.rank()).max().unwrap().peer_id;
+        
+        // Nobody leads until the timeouts expire
+        tick(&mut elections, now);
+        assert!(elections.iter().all(|election| election.leader(now).is_none()));
+        
+        let mut now = now + TimeDelta::seconds(3);
+        for _ in 0..10 {
+            tick(&mut elections, now);
+            now += TimeDelta::seconds(1);
+        }
+        
+        for election in &elections {
+            assert_eq!(election.leader(now).map(|leader| leader.peer_id), Some(highest));
+        }
+        assert_eq!(elections.iter().filter(|election| election.role == Role::Leader).count(), 1);
+        
+        // The lease is renewed while the leader is alive
+        for _ in 0..(DEFAULT_LEADER_LEASE * 2) {
+            tick(&mut elections, now);
+            now += TimeDelta::seconds(1);
+        }
+        assert!(elections.iter().all(|election| election.leader(now).is_some_and(|leader| leader.peer_id == highest)));
+    }
+    
+    #[test]
+    fn test_reelection_when_the_leader_is_gone() {
+        let now = Utc::now();
+        let mut elections: Vec<_> = (0..3)
+            .map(|_| Election::new(config(), PeerId::random(), now))
+            .collect();
+        elections.sort_by_key(|election| election.rank());
+        
+        let mut now = now + TimeDelta::seconds(3);
+        for _ in 0..10 {
+            tick(&mut elections, now);
+            now += TimeDelta::seconds(1);
+        }
+        let leader = elections.pop().unwrap();
+        
+        // It stops answering pings
+        for index in 0..elections.len() {
+            let messages = elections[index].peer_unavailable(leader.local_peer_id, now);
+            assert_eq!(messages.len(), 1);
+            deliver(&mut elections, index, messages, now);
+        }
+        
+        now += TimeDelta::seconds(3);
+        tick(&mut elections, now);
+        
+        let second = elections[1].local_peer_id;
+        assert!(elections.iter().all(|election| election.leader(now).is_some_and(|leader| leader.peer_id == second)));
+        assert!(elections[0].term() > leader.term());
+        
+        // Other subnetworks are ignored
+        let mut other = Election::new(ElectionConfig {
+            subnetwork: "10.0.1.0/24".to_string(),
+            ..config()
+        }, PeerId::random(), now);
+        let messages = other.tick(now + TimeDelta::seconds(3));
+        assert!(elections[0].handle(other.local_peer_id, messages[0].clone(), now).is_empty());
+        
+        // Resigning
+        let messages = elections[1].set_eligible(false, now);
+        deliver(&mut elections, 1, messages, now);
+        assert!(elections[0].leader(now).is_none());
+        assert_eq!(elections[0].role, Role::Candidate);
+    }
+}
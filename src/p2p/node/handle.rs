@@ -0,0 +1,418 @@
+//! Node handle
+//! 
+//! The node runs on its own task, it's driven through commands and it reports what happens with events.
+use libp2p::{gossipsub, Multiaddr, PeerId};
+use std::error::Error;
+use std::path::PathBuf;
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use super::access::AccessRule;
+use super::election::{ElectionStatus, LeaderInfo};
+use super::liveness::PeerLiveness;
+use super::message::{HiveMessage, HivePayload};
+use super::registry::RegistryEntry;
+use super::task::{OutputChunk, TaskId, TaskInfo, TaskSpec};
+use super::transfer::{TransferId, TransferInfo};
+use crate::server_node::{ServerNode, ServerStatus};
+
+/// Node command
+/// 
+/// Every command carries a channel to send the reply back
+#[derive(Debug)]
+pub enum NodeCommand {
+    /// Publish a payload on the topic of its kind
+    Publish {
+        payload: Box<HivePayload>,
+        reply: oneshot::Sender<Result<gossipsub::MessageId, String>>,
+    },
+    /// Dial an address
+    Dial {
+        address: Multiaddr,
+        reply: oneshot::Sender<Result<(), String>>,
+    },
+    /// Peers with an open connection
+    ListPeers {
+        reply: oneshot::Sender<Vec<PeerId>>,
+    },
+    /// Addresses the node is listening on
+    ListenAddresses {
+        reply: oneshot::Sender<Vec<Multiaddr>>,
+    },
+    /// Gossipsub score of a peer
+    PeerScore {
+        peer_id: PeerId,
+        reply: oneshot::Sender<Option<f64>>,
+    },
+    /// Allowed and blocked peers
+    ListPeerRules {
+        reply: oneshot::Sender<Vec<AccessRule>>,
+    },
+    /// Allow or block a peer, blocked peers are disconnected
+    SetPeerRule {
+        rule: AccessRule,
+        reply: oneshot::Sender<Result<(), String>>,
+    },
+    /// Remove the rule of a peer
+    RemovePeerRule {
+        peer_id: PeerId,
+        reply: oneshot::Sender<Result<bool, String>>,
+    },
+    /// Server nodes on the registry
+    ListServerNodes {
+        reply: oneshot::Sender<Vec<(PeerId, ServerNode)>>,
+    },
+    /// Every registry entry, removed nodes included
+    ListRegistry {
+        reply: oneshot::Sender<Vec<RegistryEntry>>,
+    },
+    /// Submit a task to a peer, it may be this node
+    SubmitTask {
+        peer_id: PeerId,
+        spec: TaskSpec,
+        reply: oneshot::Sender<Result<TaskInfo, String>>,
+    },
+    /// Cancel a task, it's killed by the node that runs it
+    CancelTask {
+        task_id: TaskId,
+        reply: oneshot::Sender<Result<(), String>>,
+    },
+    /// Tasks submitted by this node and tasks run for others
+    ListTasks {
+        reply: oneshot::Sender<Vec<TaskInfo>>,
+    },
+    GetTask {
+        task_id: TaskId,
+        reply: oneshot::Sender<Option<TaskInfo>>,
+    },
+    /// Send a local file to the transfer folder of a peer
+    SendFile {
+        peer_id: PeerId,
+        path: PathBuf,
+        rate_limit: Option<u64>,
+        reply: oneshot::Sender<Result<TransferInfo, String>>,
+    },
+    /// Fetch a file from the transfer folder of a peer
+    FetchFile {
+        peer_id: PeerId,
+        remote_path: String,
+        output: Option<PathBuf>,
+        rate_limit: Option<u64>,
+        reply: oneshot::Sender<Result<TransferInfo, String>>,
+    },
+    /// Transfers started by this node
+    ListTransfers {
+        reply: oneshot::Sender<Vec<TransferInfo>>,
+    },
+    GetTransfer {
+        transfer_id: TransferId,
+        reply: oneshot::Sender<Option<TransferInfo>>,
+    },
+    /// Set the status of this node, it's announced to the peers
+    SetStatus {
+        status: ServerStatus,
+        reply: oneshot::Sender<Result<(), String>>,
+    },
+    /// Status of this node and liveness of the peers
+    GetLiveness {
+        reply: oneshot::Sender<(ServerStatus, Vec<PeerLiveness>)>,
+    },
+    /// Leader election of our subnetwork
+    GetElection {
+        reply: oneshot::Sender<ElectionStatus>,
+    },
+    /// Subscribe to a gossipsub topic
+    Subscribe {
+        topic: String,
+        reply: oneshot::Sender<Result<bool, String>>,
+    },
+    /// Stop the node
+    Shutdown {
+        reply: oneshot::Sender<()>,
+    },
+}
+
+/// Node event
+/// 
+/// 
+#[derive(Clone, Debug)]
+pub enum NodeEvent {
+    /// The node is listening on a new address
+    Listening(Multiaddr),
+    /// Found a peer through mDNS or Kademlia
+    PeerDiscovered(PeerId),
+    /// First connection with a peer
+    PeerConnected(PeerId),
+    /// Last connection with a peer was closed
+    PeerDisconnected(PeerId),
+    /// A peer announced a new status or its liveness changed, offline peers are going away
+    PeerStatusChanged {
+        peer_id: PeerId,
+        status: ServerStatus,
+    },
+    /// Hive message received
+    Message(Box<HiveMessage>),
+    /// Message received on a topic that isn't a hive message kind
+    TopicMessage {
+        topic: String,
+        source: Option<PeerId>,
+        data: Vec<u8>,
+    },
+    /// A peer shared its server node
+    ServerNodeReceived {
+        peer_id: PeerId,
+        server_node: Box<ServerNode>,
+    },
+    /// The status of a task has changed
+    TaskUpdated(Box<TaskInfo>),
+    /// Output of a task
+    TaskOutput {
+        task_id: TaskId,
+        chunk: OutputChunk,
+    },
+    /// A transfer made progress or finished
+    TransferUpdated(Box<TransferInfo>),
+    /// Our subnetwork has a new leader, or none while it's elected
+    LeaderChanged(Option<Box<LeaderInfo>>),
+}
+
+/// Node handle
+/// 
+/// Cheap to clone, every clone talks to the same node
+#[derive(Clone)]
+pub struct NodeHandle {
+    peer_id: PeerId,
+    commands: mpsc::Sender<NodeCommand>,
+    events: broadcast::Sender<NodeEvent>,
+}
+
+impl NodeHandle {
+    pub fn new(
+        peer_id: PeerId,
+        commands: mpsc::Sender<NodeCommand>,
+        events: broadcast::Sender<NodeEvent>,
+    ) -> Self {
+        Self {
+            peer_id,
+            commands,
+            events,
+        }
+    }
+    
+    /// Peer id of the node
+    /// 
+    /// 
+    pub fn peer_id(&self) -> PeerId {
+        self.peer_id
+    }
+    
+    /// Subscribe to node events
+    /// 
+    /// Only events sent after subscribing are received
+    pub fn events(&self) -> broadcast::Receiver<NodeEvent> {
+        self.events.subscribe()
+    }
+    
+    /// Send a command and wait for the reply
+    /// 
+    /// 
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> NodeCommand,
+    ) -> Result<T, Box<dyn Error>> {
+        let (reply, response) = oneshot::channel();
+        
+        self.commands
+            .send(command(reply))
+            .await
+            .map_err(|_| "Node is not running")?;
+        
+        Ok(response.await.map_err(|_| "Node stopped before replying")?)
+    }
+    
+    /// Publish a payload
+    /// 
+    /// 
+    pub async fn publish(&self, payload: HivePayload) -> Result<gossipsub::MessageId, Box<dyn Error>> {
+        let payload = Box::new(payload);
+        
+        Ok(self.request(|reply| NodeCommand::Publish { payload, reply }).await??)
+    }
+    
+    /// Dial an address
+    /// 
+    /// Returns once the dial has started, the connection is reported with 'NodeEvent::PeerConnected'
+    pub async fn dial(&self, address: Multiaddr) -> Result<(), Box<dyn Error>> {
+        Ok(self.request(|reply| NodeCommand::Dial { address, reply }).await??)
+    }
+    
+    /// Connected peers
+    /// 
+    /// 
+    pub async fn peers(&self) -> Result<Vec<PeerId>, Box<dyn Error>> {
+        self.request(|reply| NodeCommand::ListPeers { reply }).await
+    }
+    
+    /// Listen addresses
+    /// 
+    /// 
+    pub async fn listen_addresses(&self) -> Result<Vec<Multiaddr>, Box<dyn Error>> {
+        self.request(|reply| NodeCommand::ListenAddresses { reply }).await
+    }
+    
+    /// Gossipsub score of a peer
+    /// 
+    /// Peers under the graylist threshold are ignored, there's no score for unknown peers
+    pub async fn peer_score(&self, peer_id: PeerId) -> Result<Option<f64>, Box<dyn Error>> {
+        self.request(|reply| NodeCommand::PeerScore { peer_id, reply }).await
+    }
+    
+    /// Allowed and blocked peers
+    /// 
+    /// 
+    pub async fn peer_rules(&self) -> Result<Vec<AccessRule>, Box<dyn Error>> {
+        self.request(|reply| NodeCommand::ListPeerRules { reply }).await
+    }
+    
+    /// Allow or block a peer
+    /// 
+    /// The rule is stored and applied right away, blocked peers are disconnected
+    pub async fn set_peer_rule(&self, rule: AccessRule) -> Result<(), Box<dyn Error>> {
+        Ok(self.request(|reply| NodeCommand::SetPeerRule { rule, reply }).await??)
+    }
+    
+    /// Remove the rule of a peer
+    /// 
+    /// Returns whether the peer had a rule
+    pub async fn remove_peer_rule(&self, peer_id: PeerId) -> Result<bool, Box<dyn Error>> {
+        Ok(self.request(|reply| NodeCommand::RemovePeerRule { peer_id, reply }).await??)
+    }
+    
+    /// Server nodes of the hive
+    /// 
+    /// From the replicated registry, so this node is included and the database isn't needed
+    pub async fn server_nodes(&self) -> Result<Vec<(PeerId, ServerNode)>, Box<dyn Error>> {
+        self.request(|reply| NodeCommand::ListServerNodes { reply }).await
+    }
+    
+    /// Entries of the node registry
+    /// 
+    /// 
+    pub async fn registry(&self) -> Result<Vec<RegistryEntry>, Box<dyn Error>> {
+        self.request(|reply| NodeCommand::ListRegistry { reply }).await
+    }
+    
+    /// Submit a task to a peer
+    /// 
+    /// Returns the pending task, its progress is reported with 'NodeEvent::TaskUpdated' and 'NodeEvent::TaskOutput'
+    pub async fn submit_task(&self, peer_id: PeerId, spec: TaskSpec) -> Result<TaskInfo, Box<dyn Error>> {
+        Ok(self.request(|reply| NodeCommand::SubmitTask { peer_id, spec, reply }).await??)
+    }
+    
+    /// Cancel a task
+    /// 
+    /// Returns once the cancellation was sent, the task is cancelled when 'NodeEvent::TaskUpdated' says so
+    pub async fn cancel_task(&self, task_id: &str) -> Result<(), Box<dyn Error>> {
+        let task_id = task_id.to_string();
+        
+        Ok(self.request(|reply| NodeCommand::CancelTask { task_id, reply }).await??)
+    }
+    
+    /// Tasks known by the node
+    /// 
+    /// 
+    pub async fn tasks(&self) -> Result<Vec<TaskInfo>, Box<dyn Error>> {
+        self.request(|reply| NodeCommand::ListTasks { reply }).await
+    }
+    
+    /// Get a task
+    /// 
+    /// 
+    pub async fn task(&self, task_id: &str) -> Result<Option<TaskInfo>, Box<dyn Error>> {
+        let task_id = task_id.to_string();
+        
+        self.request(|reply| NodeCommand::GetTask { task_id, reply }).await
+    }
+    
+    /// Send a file to a peer
+    /// 
+    /// The file is stored on the transfer folder of the peer, its progress is reported with 'NodeEvent::TransferUpdated'
+    pub async fn send_file(&self, peer_id: PeerId, path: PathBuf, rate_limit: Option<u64>) -> Result<TransferInfo, Box<dyn Error>> {
+        Ok(self.request(|reply| NodeCommand::SendFile { peer_id, path, rate_limit, reply }).await??)
+    }
+    
+    /// Fetch a file from a peer
+    /// 
+    /// The remote path is relative to the transfer folder of the peer, the file is stored on our
+    /// own transfer folder unless an output is given
+    pub async fn fetch_file(
+        &self,
+        peer_id: PeerId,
+        remote_path: &str,
+        output: Option<PathBuf>,
+        rate_limit: Option<u64>,
+    ) -> Result<TransferInfo, Box<dyn Error>> {
+        let remote_path = remote_path.to_string();
+        
+        Ok(self.request(|reply| NodeCommand::FetchFile { peer_id, remote_path, output, rate_limit, reply }).await??)
+    }
+    
+    /// Transfers started by this node
+    /// 
+    /// 
+    pub async fn transfers(&self) -> Result<Vec<TransferInfo>, Box<dyn Error>> {
+        self.request(|reply| NodeCommand::ListTransfers { reply }).await
+    }
+    
+    /// Get a transfer
+    /// 
+    /// 
+    pub async fn transfer(&self, transfer_id: &str) -> Result<Option<TransferInfo>, Box<dyn Error>> {
+        let transfer_id = transfer_id.to_string();
+        
+        self.request(|reply| NodeCommand::GetTransfer { transfer_id, reply }).await
+    }
+    
+    /// Set the status of this node
+    /// 
+    /// Setting it on maintenance is the only way to keep it there, automatic changes don't leave it
+    pub async fn set_status(&self, status: ServerStatus) -> Result<(), Box<dyn Error>> {
+        Ok(self.request(|reply| NodeCommand::SetStatus { status, reply }).await??)
+    }
+    
+    /// Status of this node and liveness of the peers
+    /// 
+    /// 
+    pub async fn liveness(&self) -> Result<(ServerStatus, Vec<PeerLiveness>), Box<dyn Error>> {
+        self.request(|reply| NodeCommand::GetLiveness { reply }).await
+    }
+    
+    /// Leader election of our subnetwork
+    /// 
+    /// 
+    pub async fn election(&self) -> Result<ElectionStatus, Box<dyn Error>> {
+        self.request(|reply| NodeCommand::GetElection { reply }).await
+    }
+    
+    /// Leader of our subnetwork
+    /// 
+    /// None while it's elected
+    pub async fn leader(&self) -> Result<Option<LeaderInfo>, Box<dyn Error>> {
+        Ok(self.election().await?.leader)
+    }
+    
+    /// Subscribe to a gossipsub topic
+    /// 
+    /// Returns false if it was already subscribed
+    pub async fn subscribe(&self, topic: &str) -> Result<bool, Box<dyn Error>> {
+        let topic = topic.to_string();
+        
+        Ok(self.request(|reply| NodeCommand::Subscribe { topic, reply }).await??)
+    }
+    
+    /// Stop the node
+    /// 
+    /// 
+    pub async fn shutdown(&self) -> Result<(), Box<dyn Error>> {
+        self.request(|reply| NodeCommand::Shutdown { reply }).await
+    }
+}
@@ -0,0 +1,237 @@
+//! Node identity keystore
+//! 
+//! Stores the node keypair on disk, so the PeerId stays the same between restarts.
+use libp2p::{identity, PeerId};
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::env::hive_keystore_path;
+
+/// Keystore
+/// 
+/// The keypair is stored with the protobuf encoding used by libp2p
+#[derive(Clone, Debug)]
+pub struct Keystore {
+    pub path: PathBuf,
+}
+
+impl Default for Keystore {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from(hive_keystore_path()),
+        }
+    }
+}
+
+impl Keystore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+    
+    /// Whether the key file exists
+    /// 
+    /// 
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+    
+    /// Load keypair
+    /// 
+    /// 
+    pub fn load(&self) -> Result<identity::Keypair, Box<dyn Error>> {
+        read_keypair(&self.path)
+    }
+    
+    /// Save keypair
+    /// 
+    /// The file is only readable by the owner
+    pub fn save(&self, key: &identity::Keypair) -> Result<(), Box<dyn Error>> {
+        write_keypair(&self.path, key)
+    }
+    
+    /// Load the keypair or generate a new one
+    /// 
+    /// On first start a random keypair is generated and saved
+    pub fn load_or_generate(&self) -> Result<identity::Keypair, Box<dyn Error>> {
+        if self.exists() {
+            return self.load();
+        }
+        
+        let key = identity::Keypair::generate_ed25519();
+        self.save(&key)?;
+        
+        Ok(key)
+    }
+    
+    /// Get the PeerId of the stored keypair
+    /// 
+    /// 
+    pub fn peer_id(&self) -> Result<PeerId, Box<dyn Error>> {
+        Ok(self.load()?.public().to_peer_id())
+    }
+    
+    /// Rotate
+    /// 
+    /// Replace the stored keypair with a new random one, the previous key is kept on a backup file
+    pub fn rotate(&self) -> Result<identity::Keypair, Box<dyn Error>> {
+        if self.exists() {
+            let previous = self.load()?;
+            write_keypair(&self.backup_path(), &previous)?;
+        }
+        
+        let key = identity::Keypair::generate_ed25519();
+        self.save(&key)?;
+        
+        Ok(key)
+    }
+    
+    /// Export the keypair to another file
+    /// 
+    /// 
+    pub fn export(&self, output: &Path) -> Result<(), Box<dyn Error>> {
+        let key = self.load()?;
+        write_keypair(output, &key)
+    }
+    
+    /// Import a keypair from another file
+    /// 
+    /// The current keypair is replaced
+    pub fn import(&self, input: &Path) -> Result<identity::Keypair, Box<dyn Error>> {
+        let key = read_keypair(input)?;
+        self.save(&key)?;
+        
+        Ok(key)
+    }
+    
+    /// Backup path
+    /// 
+    /// 
+    pub fn backup_path(&self) -> PathBuf {
+        let mut file_name = self.path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".old");
+        
+        self.path.with_file_name(file_name)
+    }
+}
+
+/// Read keypair from a file
+/// 
+/// 
+fn read_keypair(path: &Path) -> Result<identity::Keypair, Box<dyn Error>> {
+    let bytes = fs::read(path)?;
+    
+    Ok(identity::Keypair::from_protobuf_encoding(&bytes)?)
+}
+
+/// Write keypair to a file
+/// 
+/// 
+fn write_keypair(path: &Path, key: &identity::Keypair) -> Result<(), Box<dyn Error>> {
+    write_secret_file(path, &key.to_protobuf_encoding()?)
+}
+
+/// Write a file only readable by the owner
+/// 
+/// Parent folders are created if they don't exist
+pub(crate) fn write_secret_file(path: &Path, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    
+    let mut file = options.open(path)?;
+    file.write_all(bytes)?;
+    
+    // The mode is only applied on creation, an existing file may have other permissions
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::folder::hive_test_folder::HiveTestFolder;
+    
+    /// Create a keystore on a unique test path
+    /// 
+    /// 
+    fn test_keystore() -> Keystore {
+        let test_folder = HiveTestFolder::default();
+        test_folder.create().unwrap();
+        
+        let mut path = PathBuf::from(&test_folder.path);
+        path.push(format!("keystore_{}", nanoid::nanoid!(6)));
+        path.push("identity.key");
+        
+        Keystore::new(path)
+    }
+    
+    #[test]
+    fn test_load_or_generate_is_stable() {
+        let keystore = test_keystore();
+        assert!(!keystore.exists());
+        
+        let first = keystore.load_or_generate().unwrap();
+        assert!(keystore.exists());
+        
+        let second = keystore.load_or_generate().unwrap();
+        assert_eq!(first.public().to_peer_id(), second.public().to_peer_id());
+    }
+    
+    #[test]
+    fn test_rotate_changes_peer_id() {
+        let keystore = test_keystore();
+        let previous = keystore.load_or_generate().unwrap();
+        
+        let rotated = keystore.rotate().unwrap();
+        assert_ne!(previous.public().to_peer_id(), rotated.public().to_peer_id());
+        assert_eq!(keystore.peer_id().unwrap(), rotated.public().to_peer_id());
+        
+        // The previous key is kept
+        let backup = Keystore::new(keystore.backup_path());
+        assert_eq!(backup.peer_id().unwrap(), previous.public().to_peer_id());
+    }
+    
+    #[test]
+    fn test_export_and_import() {
+        let keystore = test_keystore();
+        let key = keystore.load_or_generate().unwrap();
+        
+        let exported = keystore.path.with_file_name("exported.key");
+        keystore.export(&exported).unwrap();
+        
+        let other = test_keystore();
+        let imported = other.import(&exported).unwrap();
+        assert_eq!(key.public().to_peer_id(), imported.public().to_peer_id());
+        assert_eq!(other.peer_id().unwrap(), key.public().to_peer_id());
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_key_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let keystore = test_keystore();
+        keystore.load_or_generate().unwrap();
+        
+        let mode = fs::metadata(&keystore.path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
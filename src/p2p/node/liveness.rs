@@ -0,0 +1,244 @@
+//! Peer liveness
+//! 
+//! Peers are online while they answer pings and keep sending gossip, they go offline after
+//! too many failed pings, when mDNS stops seeing them or when they stay silent for too long.
+//! Maintenance is only set and cleared explicitly, by the peer itself or by an operator,
+//! the automatic checks never leave it.
+use chrono::{DateTime, Utc};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::server_node::ServerStatus;
+
+/// Default seconds between heartbeats
+pub const DEFAULT_HEARTBEAT_INTERVAL: u64 = 30;
+
+/// Default seconds of silence until a peer is offline
+pub const DEFAULT_OFFLINE_AFTER: u64 = 90;
+
+/// Default failed pings in a row until a peer is offline
+pub const DEFAULT_MAX_PING_FAILURES: u32 = 3;
+
+/// Time between liveness checks
+pub const LIVENESS_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Liveness configuration
+/// 
+/// 
+#[derive(Clone, Debug)]
+pub struct LivenessConfig {
+    // Time between our own heartbeats
+    pub heartbeat_interval: Duration,
+    // Silence until a peer is offline
+    pub offline_after: Duration,
+    pub max_ping_failures: u32,
+}
+
+impl Default for LivenessConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Duration::from_secs(DEFAULT_HEARTBEAT_INTERVAL),
+            offline_after: Duration::from_secs(DEFAULT_OFFLINE_AFTER),
+            max_ping_failures: DEFAULT_MAX_PING_FAILURES,
+        }
+    }
+}
+
+/// Liveness of a peer
+/// 
+/// 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PeerLiveness {
+    pub peer_id: PeerId,
+    pub status: ServerStatus,
+    // Failed pings since the last sign of life
+    pub ping_failures: u32,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Liveness tracker
+/// 
+/// Every method returns the new status of the peer when it changes
+#[derive(Clone, Debug, Default)]
+pub struct LivenessTracker {
+    pub config: LivenessConfig,
+    peers: HashMap<PeerId, PeerLiveness>,
+}
+
+impl LivenessTracker {
+    pub fn new(config: LivenessConfig) -> Self {
+        Self {
+            config,
+            peers: HashMap::new(),
+        }
+    }
+    
+    pub fn get(&self, peer_id: &PeerId) -> Option<&PeerLiveness> {
+        self.peers.get(peer_id)
+    }
+    
+    /// Every tracked peer
+    /// 
+    /// 
+    pub fn peers(&self) -> Vec<PeerLiveness> {
+        let mut peers: Vec<_> = self.peers.values().cloned().collect();
+        peers.sort_by_key(|peer| peer.peer_id);
+        
+        peers
+    }
+    
+    /// The peer answered a ping, sent a message or connected
+    /// 
+    /// Unknown and offline peers are online again
+    pub fn seen(&mut self, peer_id: PeerId, now: DateTime<Utc>) -> Option<ServerStatus> {
+        let peer = self.peers.entry(peer_id).or_insert_with(|| PeerLiveness {
+            peer_id,
+            status: ServerStatus::Offline,
+            ping_failures: 0,
+            last_seen: now,
+        });
+        peer.ping_failures = 0;
+        peer.last_seen = now;
+        
+        match peer.status {
+            ServerStatus::Offline => {
+                peer.status = ServerStatus::Online;
+                Some(ServerStatus::Online)
+            }
+            _ => None,
+        }
+    }
+    
+    /// A ping to the peer failed
+    /// 
+    /// 
+    pub fn ping_failed(&mut self, peer_id: PeerId) -> Option<ServerStatus> {
+        let max_ping_failures = self.config.max_ping_failures;
+        let peer = self.peers.get_mut(&peer_id)?;
+        peer.ping_failures += 1;
+        
+        match peer.ping_failures >= max_ping_failures {
+            true => go_offline(peer),
+            false => None,
+        }
+    }
+    
+    /// mDNS doesn't see the peer anymore
+    /// 
+    /// Only called for peers without connections, the others are still reachable
+    pub fn expired(&mut self, peer_id: PeerId) -> Option<ServerStatus> {
+        go_offline(self.peers.get_mut(&peer_id)?)
+    }
+    
+    /// Peers that have been silent for too long
+    /// 
+    /// 
+    pub fn check(&mut self, now: DateTime<Utc>) -> Vec<(PeerId, ServerStatus)> {
+        let offline_after = self.config.offline_after;
+        
+        self.peers
+            .values_mut()
+            .filter(|peer| (now - peer.last_seen).to_std().is_ok_and(|silence| silence >= offline_after))
+            .filter_map(|peer| go_offline(peer).map(|status| (peer.peer_id, status)))
+            .collect()
+    }
+    
+    /// Set the status explicitly
+    /// 
+    /// Announced by the peer itself or set by an operator, it's the only way out of maintenance
+    pub fn set_status(&mut self, peer_id: PeerId, status: ServerStatus, now: DateTime<Utc>) -> Option<ServerStatus> {
+        match self.peers.get_mut(&peer_id) {
+            Some(peer) if peer.status == status => None,
+            Some(peer) => {
+                peer.status = status.clone();
+                Some(status)
+            }
+            None => {
+                self.peers.insert(peer_id, PeerLiveness {
+                    peer_id,
+                    status: status.clone(),
+                    ping_failures: 0,
+                    last_seen: now,
+                });
+                Some(status)
+            }
+        }
+    }
+}
+
+/// Set a peer offline
+/// 
+/// Peers on maintenance stay on maintenance
+fn go_offline(peer: &mut PeerLiveness) -> Option<ServerStatus> {
+    match peer.status {
+        ServerStatus::Online => {
+            peer.status = ServerStatus::Offline;
+            Some(ServerStatus::Offline)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+    
+    #[test]
+    fn test_online_and_offline() {
+        let mut tracker = LivenessTracker::default();
+        let peer_id = PeerId::random();
+        let now = Utc::now();
+        
+        assert_eq!(tracker.ping_failed(peer_id), None);
+        assert_eq!(tracker.seen(peer_id, now), Some(ServerStatus::Online));
+        assert_eq!(tracker.seen(peer_id, now), None);
+        
+        // Failures have to be in a row
+        assert_eq!(tracker.ping_failed(peer_id), None);
+        assert_eq!(tracker.ping_failed(peer_id), None);
+        tracker.seen(peer_id, now);
+        assert_eq!(tracker.ping_failed(peer_id), None);
+        assert_eq!(tracker.ping_failed(peer_id), None);
+        assert_eq!(tracker.ping_failed(peer_id), Some(ServerStatus::Offline));
+        assert_eq!(tracker.ping_failed(peer_id), None);
+        
+        assert_eq!(tracker.seen(peer_id, now), Some(ServerStatus::Online));
+        assert_eq!(tracker.expired(peer_id), Some(ServerStatus::Offline));
+        
+        // Silence
+        tracker.seen(peer_id, now);
+        assert!(tracker.check(now + TimeDelta::seconds(10)).is_empty());
+        assert_eq!(
+            tracker.check(now + TimeDelta::seconds(DEFAULT_OFFLINE_AFTER as i64)),
+            vec![(peer_id, ServerStatus::Offline)]
+        );
+    }
+    
+    #[test]
+    fn test_maintenance_is_sticky() {
+        let mut tracker = LivenessTracker::default();
+        let peer_id = PeerId::random();
+        let now = Utc::now();
+        
+        tracker.seen(peer_id, now);
+        assert_eq!(
+            tracker.set_status(peer_id, ServerStatus::Maintenance, now),
+            Some(ServerStatus::Maintenance)
+        );
+        
+        for _ in 0..DEFAULT_MAX_PING_FAILURES {
+            assert_eq!(tracker.ping_failed(peer_id), None);
+        }
+        assert_eq!(tracker.expired(peer_id), None);
+        assert!(tracker.check(now + TimeDelta::days(1)).is_empty());
+        assert_eq!(tracker.seen(peer_id, now), None);
+        assert_eq!(tracker.get(&peer_id).unwrap().status, ServerStatus::Maintenance);
+        
+        // Cleared by an operator
+        assert_eq!(tracker.set_status(peer_id, ServerStatus::Online, now), Some(ServerStatus::Online));
+        assert_eq!(tracker.expired(peer_id), Some(ServerStatus::Offline));
+    }
+}
@@ -0,0 +1,325 @@
+//! Hive messages
+//! 
+//! Typed and versioned envelope for everything that is published through gossipsub,
+//! every message kind is published on its own topic.
+use chrono::{DateTime, Utc};
+use libp2p::{gossipsub, PeerId};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+use super::election::ElectionMessage;
+use super::registry::RegistryEntry;
+use crate::server_node::resources::Resources;
+use crate::server_node::{ServerNode, ServerStatus};
+
+/// Protocol version
+/// 
+/// Increase it when the envelope or the payloads change in an incompatible way
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Message kind
+/// 
+/// Each kind has its own gossipsub topic
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    NodeAnnouncement,
+    StatusChange,
+    ResourceUpdate,
+    Heartbeat,
+    Election,
+    Registry,
+    Chat,
+}
+
+impl MessageKind {
+    pub const ALL: [MessageKind; 7] = [
+        MessageKind::NodeAnnouncement,
+        MessageKind::StatusChange,
+        MessageKind::ResourceUpdate,
+        MessageKind::Heartbeat,
+        MessageKind::Election,
+        MessageKind::Registry,
+        MessageKind::Chat,
+    ];
+    
+    /// Topic name
+    /// 
+    /// 
+    pub fn topic_name(&self) -> &'static str {
+        match self {
+            MessageKind::NodeAnnouncement => "hive/node-announcement",
+            MessageKind::StatusChange => "hive/status-change",
+            MessageKind::ResourceUpdate => "hive/resource-update",
+            MessageKind::Heartbeat => "hive/heartbeat",
+            MessageKind::Election => "hive/election",
+            MessageKind::Registry => "hive/registry",
+            MessageKind::Chat => "hive/chat",
+        }
+    }
+    
+    /// Gossipsub topic
+    /// 
+    /// 
+    pub fn topic(&self) -> gossipsub::IdentTopic {
+        gossipsub::IdentTopic::new(self.topic_name())
+    }
+    
+    /// Find the message kind of a topic
+    /// 
+    /// 
+    pub fn from_topic(topic: &gossipsub::TopicHash) -> Option<Self> {
+        MessageKind::ALL
+            .into_iter()
+            .find(|kind| kind.topic().hash() == *topic)
+    }
+}
+
+/// Message payload
+/// 
+/// 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum HivePayload {
+    /// A node joined the hive or refreshed its information
+    NodeAnnouncement(Box<ServerNode>),
+    /// The sender status has changed
+    StatusChange(ServerStatus),
+    /// Fresh resources of the sender
+    ResourceUpdate(Resources),
+    /// The sender is alive and on this status, peers that stop sending them go offline
+    Heartbeat(ServerStatus),
+    /// Leader election of the sender subnetwork
+    Election(ElectionMessage),
+    /// Write on the node registry
+    RegistryDelta(Box<RegistryEntry>),
+    /// Free-form chat
+    Chat(String),
+}
+
+impl HivePayload {
+    pub fn kind(&self) -> MessageKind {
+        match self {
+            HivePayload::NodeAnnouncement(_) => MessageKind::NodeAnnouncement,
+            HivePayload::StatusChange(_) => MessageKind::StatusChange,
+            HivePayload::ResourceUpdate(_) => MessageKind::ResourceUpdate,
+            HivePayload::Heartbeat(_) => MessageKind::Heartbeat,
+            HivePayload::Election(_) => MessageKind::Election,
+            HivePayload::RegistryDelta(_) => MessageKind::Registry,
+            HivePayload::Chat(_) => MessageKind::Chat,
+        }
+    }
+}
+
+/// Hive message envelope
+/// 
+/// 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HiveMessage {
+    pub version: u32,
+    pub sender: PeerId,
+    pub timestamp: DateTime<Utc>,
+    pub payload: HivePayload,
+}
+
+impl HiveMessage {
+    /// Create a message with the current protocol version and time
+    /// 
+    /// 
+    pub fn new(sender: PeerId, payload: HivePayload) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            sender,
+            timestamp: Utc::now(),
+            payload,
+        }
+    }
+    
+    pub fn kind(&self) -> MessageKind {
+        self.payload.kind()
+    }
+    
+    /// Topic where this message has to be published
+    /// 
+    /// 
+    pub fn topic(&self) -> gossipsub::IdentTopic {
+        self.kind().topic()
+    }
+    
+    /// Encode
+    /// 
+    /// 
+    pub fn encode(&self) -> Result<Vec<u8>, HiveMessageError> {
+        serde_json::to_vec(self).map_err(HiveMessageError::Encode)
+    }
+    
+    /// Decode
+    /// 
+    /// Messages with a different protocol version are rejected
+    pub fn decode(data: &[u8]) -> Result<Self, HiveMessageError> {
+        let message: HiveMessage = serde_json::from_slice(data).map_err(HiveMessageError::Decode)?;
+        
+        if message.version != PROTOCOL_VERSION {
+            return Err(HiveMessageError::UnsupportedVersion(message.version));
+        }
+        
+        Ok(message)
+    }
+    
+    /// Decode a gossipsub message
+    /// 
+    /// Besides decoding, the topic has to match the payload kind and the sender has to be the signer of the message
+    pub fn from_gossipsub(message: &gossipsub::Message) -> Result<Self, HiveMessageError> {
+        let kind = match MessageKind::from_topic(&message.topic) {
+            Some(kind) => kind,
+            None => return Err(HiveMessageError::UnknownTopic(message.topic.to_string())),
+        };
+        
+        let hive_message = Self::decode(&message.data)?;
+        
+        if hive_message.kind() != kind {
+            return Err(HiveMessageError::TopicMismatch {
+                topic: message.topic.to_string(),
+                kind: hive_message.kind(),
+            });
+        }
+        
+        if message.source != Some(hive_message.sender) {
+            return Err(HiveMessageError::SenderMismatch(hive_message.sender));
+        }
+        
+        Ok(hive_message)
+    }
+}
+
+/// Hive message error
+/// 
+/// 
+#[derive(Debug)]
+pub enum HiveMessageError {
+    Encode(serde_json::Error),
+    Decode(serde_json::Error),
+    UnsupportedVersion(u32),
+    UnknownTopic(String),
+    TopicMismatch {
+        topic: String,
+        kind: MessageKind,
+    },
+    SenderMismatch(PeerId),
+}
+
+impl fmt::Display for HiveMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HiveMessageError::Encode(err) => write!(f, "Failed to encode message: {err}"),
+            HiveMessageError::Decode(err) => write!(f, "Failed to decode message: {err}"),
+            HiveMessageError::UnsupportedVersion(version) => write!(
+                f,
+                "Unsupported protocol version {version}, expected {PROTOCOL_VERSION}"
+            ),
+            HiveMessageError::UnknownTopic(topic) => write!(f, "Unknown topic '{topic}'"),
+            HiveMessageError::TopicMismatch { topic, kind } => write!(
+                f,
+                "Message of kind {kind:?} was published on topic '{topic}'"
+            ),
+            HiveMessageError::SenderMismatch(sender) => write!(
+                f,
+                "Message sender {sender} isn't the author of the message"
+            ),
+        }
+    }
+}
+
+impl Error for HiveMessageError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Create a gossipsub message as it would be received
+    /// 
+    /// 
+    fn gossipsub_message(source: Option<PeerId>, topic: &str, data: Vec<u8>) -> gossipsub::Message {
+        gossipsub::Message {
+            source,
+            data,
+            sequence_number: None,
+            topic: gossipsub::IdentTopic::new(topic).hash(),
+        }
+    }
+    
+    #[test]
+    fn test_encode_and_decode() {
+        let sender = PeerId::random();
+        let message = HiveMessage::new(sender, HivePayload::Chat("Hello".to_string()));
+        
+        let decoded = HiveMessage::decode(&message.encode().unwrap()).unwrap();
+        
+        assert_eq!(decoded.version, PROTOCOL_VERSION);
+        assert_eq!(decoded.sender, sender);
+        assert_eq!(decoded.timestamp, message.timestamp);
+        assert!(matches!(decoded.payload, HivePayload::Chat(text) if text == "Hello"));
+    }
+    
+    #[test]
+    fn test_every_kind_has_its_own_topic() {
+        for kind in MessageKind::ALL {
+            assert_eq!(MessageKind::from_topic(&kind.topic().hash()), Some(kind));
+            
+            let others = MessageKind::ALL
+                .into_iter()
+                .filter(|other| other.topic_name() == kind.topic_name())
+                .count();
+            assert_eq!(others, 1);
+        }
+    }
+    
+    #[test]
+    fn test_reject_invalid_data() {
+        let result = HiveMessage::decode(b"Hello world");
+        
+        assert!(matches!(result, Err(HiveMessageError::Decode(_))));
+    }
+    
+    #[test]
+    fn test_reject_unsupported_version() {
+        let mut message = HiveMessage::new(PeerId::random(), HivePayload::Chat("Hello".to_string()));
+        message.version = PROTOCOL_VERSION + 1;
+        
+        let result = HiveMessage::decode(&message.encode().unwrap());
+        
+        assert!(matches!(result, Err(HiveMessageError::UnsupportedVersion(_))));
+    }
+    
+    #[test]
+    fn test_from_gossipsub() {
+        let sender = PeerId::random();
+        let message = HiveMessage::new(sender, HivePayload::StatusChange(ServerStatus::Maintenance));
+        let data = message.encode().unwrap();
+        
+        // Valid
+        let received = gossipsub_message(Some(sender), MessageKind::StatusChange.topic_name(), data.clone());
+        let decoded = HiveMessage::from_gossipsub(&received).unwrap();
+        assert!(matches!(decoded.payload, HivePayload::StatusChange(ServerStatus::Maintenance)));
+        
+        // Published on the wrong topic
+        let received = gossipsub_message(Some(sender), MessageKind::Chat.topic_name(), data.clone());
+        assert!(matches!(
+            HiveMessage::from_gossipsub(&received),
+            Err(HiveMessageError::TopicMismatch { .. })
+        ));
+        
+        // Unknown topic
+        let received = gossipsub_message(Some(sender), "chat-net", data.clone());
+        assert!(matches!(
+            HiveMessage::from_gossipsub(&received),
+            Err(HiveMessageError::UnknownTopic(_))
+        ));
+        
+        // Signed by someone else
+        let received = gossipsub_message(Some(PeerId::random()), MessageKind::StatusChange.topic_name(), data);
+        assert!(matches!(
+            HiveMessage::from_gossipsub(&received),
+            Err(HiveMessageError::SenderMismatch(_))
+        ));
+    }
+}
@@ -0,0 +1,207 @@
+//! Node metrics
+//! 
+//! Swarm events counted by libp2p and the resources of this computer, served on '/metrics'
+//! with the OpenMetrics text format.
+use actix_web::{dev::Server, web, App, HttpResponse, HttpServer};
+use libp2p::metrics::{Metrics, Recorder, Registry};
+use prometheus_client::encoding::text::encode;
+use prometheus_client::metrics::gauge::Gauge;
+use std::error::Error;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+
+use crate::server_node::resources::Resources;
+
+/// OpenMetrics content type
+pub const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Resource gauges
+/// 
+/// 
+#[derive(Default)]
+struct ResourceGauges {
+    cpu_cores: Gauge,
+    cpu_usage: Gauge<f64, AtomicU64>,
+    memory_total: Gauge,
+    memory_used: Gauge,
+    storage_total: Gauge,
+    storage_used: Gauge,
+}
+
+impl ResourceGauges {
+    /// Register the gauges
+    /// 
+    /// 
+    fn register(&self, registry: &mut Registry) {
+        registry.register("cpu_cores", "Number of CPU cores", self.cpu_cores.clone());
+        registry.register("cpu_usage_percentage", "Average usage of the CPU cores", self.cpu_usage.clone());
+        registry.register("memory_total_bytes", "Total memory", self.memory_total.clone());
+        registry.register("memory_used_bytes", "Used memory", self.memory_used.clone());
+        registry.register("storage_total_bytes", "Total space of every disk", self.storage_total.clone());
+        registry.register("storage_used_bytes", "Used space of every disk", self.storage_used.clone());
+    }
+}
+
+/// Node metrics
+/// 
+/// The registry is shared with the metrics endpoint, metrics are updated through atomics
+pub struct NodeMetrics {
+    registry: Arc<Registry>,
+    libp2p: Metrics,
+    resources: ResourceGauges,
+}
+
+impl Default for NodeMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NodeMetrics {
+    pub fn new() -> Self {
+        let mut registry = Registry::default();
+        
+        // Registered under the 'libp2p' prefix
+        let libp2p = Metrics::new(&mut registry);
+        
+        let resources = ResourceGauges::default();
+        resources.register(registry.sub_registry_with_prefix("hive_resources"));
+        
+        Self {
+            registry: Arc::new(registry),
+            libp2p,
+            resources,
+        }
+    }
+    
+    /// Registry
+    /// 
+    /// 
+    pub fn registry(&self) -> Arc<Registry> {
+        self.registry.clone()
+    }
+    
+    /// Record a swarm or behaviour event
+    /// 
+    /// 
+    pub fn record<E>(&self, event: &E)
+    where
+        Metrics: Recorder<E>,
+    {
+        self.libp2p.record(event);
+    }
+    
+    /// Update the resource gauges
+    /// 
+    /// 
+    pub fn update_resources(&self, resources: &Resources) {
+        let gauges = &self.resources;
+        
+        gauges.cpu_cores.set(resources.cpus.len() as i64);
+        gauges.cpu_usage.set(resources.cpu_usage);
+        
+        gauges.memory_total.set(resources.memory.total as i64);
+        gauges.memory_used.set(resources.memory.used as i64);
+        
+        gauges.storage_total.set(resources.storage.iter().map(|storage| storage.total as i64).sum());
+        gauges.storage_used.set(resources.storage.iter().map(|storage| storage.used as i64).sum());
+    }
+    
+    /// Encode the metrics with the OpenMetrics text format
+    /// 
+    /// 
+    pub fn encode(&self) -> Result<String, Box<dyn Error>> {
+        encode_registry(&self.registry)
+    }
+}
+
+/// Encode a registry with the OpenMetrics text format
+/// 
+/// 
+pub fn encode_registry(registry: &Registry) -> Result<String, Box<dyn Error>> {
+    let mut buffer = String::new();
+    encode(&mut buffer, registry)?;
+    
+    Ok(buffer)
+}
+
+/// Metrics route
+/// 
+/// 
+async fn metrics(registry: web::Data<Registry>) -> HttpResponse {
+    match encode_registry(&registry) {
+        Ok(body) => HttpResponse::Ok()
+            .content_type(OPENMETRICS_CONTENT_TYPE)
+            .body(body),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+/// Serve the metrics
+/// 
+/// Small dedicated listener, so the node doesn't depend on the REST server
+pub fn serve(registry: Arc<Registry>, address: SocketAddr) -> io::Result<Server> {
+    let registry = web::Data::from(registry);
+    
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(registry.clone())
+            .route("/metrics", web::get().to(metrics))
+    })
+    .workers(1)
+    // The node stops the server on shutdown
+    .disable_signals()
+    .bind(address)?
+    .run();
+    
+    Ok(server)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::{swarm::ConnectionId, PeerId};
+    use std::time::Duration;
+    
+    #[test]
+    fn test_encode_resources() {
+        let metrics = NodeMetrics::new();
+        metrics.update_resources(&Resources::fetch_resources().unwrap());
+        
+        let encoded = metrics.encode().unwrap();
+        assert!(encoded.contains("hive_resources_cpu_cores"));
+        assert!(encoded.contains("hive_resources_memory_total_bytes"));
+        assert!(encoded.ends_with("# EOF\n"));
+    }
+    
+    #[test]
+    fn test_record_ping() {
+        let metrics = NodeMetrics::new();
+        metrics.record(&libp2p::ping::Event {
+            peer: PeerId::random(),
+            connection: ConnectionId::new_unchecked(0),
+            result: Ok(Duration::from_millis(20)),
+        });
+        
+        let encoded = metrics.encode().unwrap();
+        assert!(encoded.contains("libp2p_ping_rtt_seconds_count 1"));
+    }
+    
+    #[actix_web::test]
+    async fn test_metrics_route() {
+        let metrics = NodeMetrics::new();
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::from(metrics.registry()))
+                .route("/metrics", web::get().to(super::metrics))
+        ).await;
+        
+        let request = actix_web::test::TestRequest::get().uri("/metrics").to_request();
+        let response = actix_web::test::call_service(&app, request).await;
+        
+        assert!(response.status().is_success());
+        assert_eq!(response.headers().get("content-type").unwrap(), OPENMETRICS_CONTENT_TYPE);
+    }
+}
@@ -1,33 +1,105 @@
-use futures::executor::block_on;
+use actix_web::dev::ServerHandle;
+use chrono::Utc;
 use futures::StreamExt;
 use libp2p::swarm::SwarmEvent;
 use libp2p::{
+    core::transport::ListenerId,
     gossipsub,
     identify,
     identity,
+    kad,
     mdns,
     multiaddr::Protocol,
-    noise,
-    tcp,
-    yamux,
+    ping,
+    pnet::PreSharedKey,
+    request_response,
+    swarm::dial_opts::DialOpts,
     Multiaddr,
-    SwarmBuilder
+    PeerId,
 };
+use rand::seq::IteratorRandom;
+use sea_orm::DatabaseConnection;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::net::{
     Ipv4Addr,
     Ipv6Addr,
 };
+use std::path::{Path, PathBuf};
 use std::time::Duration;
-use tokio::{io, io::AsyncBufReadExt, select};
-use tracing_subscriber::EnvFilter;
+use tokio::select;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tracing::instrument::WithSubscriber;
+use tracing::Dispatch;
+use tracing_appender::non_blocking::WorkerGuard;
 
 use crate::p2p::hive::HiveParameters;
+use crate::server_node::controller::ServerNodeController;
+use crate::server_node::resources::history::controller::ResourceHistoryController;
+use crate::server_node::resources::history::COMPACTION_INTERVAL;
+use crate::server_node::resources::sampler::ResourceWatch;
+use crate::server_node::resources::Resources;
+use crate::server_node::{ServerNode, ServerStatus};
 use crate::test::folder::hive_folder_test_suite::hive_server_node::HiveServerNode;
 
+pub mod access;
 pub mod behavior;
+pub mod bootstrap;
+pub mod builder;
+pub mod election;
+pub mod handle;
+pub mod keystore;
+pub mod liveness;
+pub mod message;
+pub mod metrics;
+pub mod peer_book;
+pub mod protocol;
+pub mod registry;
+pub mod swarm_key;
+pub mod task;
+pub mod transfer;
+pub mod validation;
 
-use behavior::{generate_ed25519, MyBehavior, MyBehaviorEvent};
+use access::{controller::AccessListController, AccessList, AccessRule, PeerRule};
+use behavior::{MyBehavior, MyBehaviorEvent};
+use builder::{NodeBuilder, Transports};
+use election::{Election, ElectionMessage, ELECTION_TICK_INTERVAL};
+use handle::{NodeCommand, NodeEvent, NodeHandle};
+use liveness::{LivenessTracker, LIVENESS_CHECK_INTERVAL};
+use message::{HiveMessage, HivePayload};
+use metrics::NodeMetrics;
+use peer_book::{controller::PeerBookController, PeerBook};
+use registry::{NodeRegistry, RegistryEntry, REGISTRY_SYNC_INTERVAL};
+use protocol::file::{FileAction, FileRequest, FileResponse};
+use protocol::registry::{RegistryRequest, RegistryResponse};
+use protocol::server_node::{ServerNodeRequest, ServerNodeResponse};
+use protocol::task::{TaskOutbox, TaskRequest, TaskResponse};
+use task::recorder::{TaskRecord, TaskRecorder};
+use task::runner::{RunnerEvent, TaskRunner};
+use task::{new_task_id, OutputChunk, TaskBook, TaskId, TaskInfo, TaskSpec, TaskStatus};
+use transfer::controller::TransferController;
+use transfer::folder::TransferFolder;
+use transfer::runner::{TransferEvent, TransferRunner};
+use transfer::{new_transfer_id, TransferBook, TransferDirection, TransferInfo, TransferStatus};
+use validation::{MessageValidator, Validation, ValidationError, MAX_MESSAGE_SIZE};
+
+/// Commands waiting to be handled by the node
+const COMMAND_BUFFER: usize = 64;
+
+/// Events kept for slow handles
+const EVENT_BUFFER: usize = 256;
+
+/// Time between peer book saves
+const PEER_BOOK_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time between access list syncs, rules may be changed by other processes
+const ACCESS_LIST_SYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Time given to the swarm to send the last messages before closing
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_millis(500);
+
+/// Response to a file request of a peer
+type FileReply = (PeerId, request_response::ResponseChannel<FileResponse>, FileResponse);
 
 /// Use this computer to join the swarm network
 /// 
@@ -35,128 +107,1491 @@ use behavior::{generate_ed25519, MyBehavior, MyBehaviorEvent};
 pub struct Node {
     pub parameters: HiveParameters,
     pub local_key: identity::Keypair,
+    // Pre-shared key of the private network, QUIC is disabled when it's set
+    pub swarm_key: Option<PreSharedKey>,
+    // Transports the swarm was built with
+    pub transports: Transports,
     pub swarm: libp2p::Swarm<MyBehavior>,
-    pub topic: gossipsub::IdentTopic,
+    // Database where discovered server nodes are stored
+    pub db: Option<DatabaseConnection>,
+    // Peers whose server node was already requested
+    pub requested_peers: HashSet<PeerId>,
     // Test handler to manage testing information
     pub test_handler: Option<HiveServerNode>,
+    // Events sent to the node handles
+    pub events: broadcast::Sender<NodeEvent>,
+    // Known peers, stored on the database
+    pub peer_book: PeerBook,
+    // Server nodes of the hive, replicated between the peers
+    pub registry: NodeRegistry,
+    // Status of this node, announced with every heartbeat
+    pub status: ServerStatus,
+    // Online, offline and maintenance peers
+    pub liveness: LivenessTracker,
+    // Leader of our subnetwork
+    pub election: Election,
+    // Allowed and blocked peers, stored on the database
+    pub access_list: AccessList,
+    // Swarm and resource metrics
+    pub metrics: NodeMetrics,
+    // Latest resources of this computer, sampled on the background
+    pub resources: ResourceWatch,
+    // Decides which gossipsub messages are forwarded
+    pub validator: MessageValidator,
+    // Tasks submitted by this node and tasks run for others
+    pub tasks: TaskBook,
+    // Runs the tasks of this node on subprocesses
+    task_runner: TaskRunner,
+    task_events: mpsc::UnboundedReceiver<RunnerEvent>,
+    // Writes the tasks on the database, started with the node when it has one
+    task_recorder: Option<TaskRecorder>,
+    // Task requests waiting for a response, a failed submission rejects its task
+    task_requests: HashMap<request_response::OutboundRequestId, TaskId>,
+    // Output and results waiting for the previous request of their task
+    task_outbox: TaskOutbox,
+    // Transfers started by this node
+    pub transfers: TransferBook,
+    // Files are sent to and fetched from this folder
+    pub transfer_folder: TransferFolder,
+    transfer_runner: TransferRunner,
+    transfer_events: mpsc::UnboundedReceiver<TransferEvent>,
+    // File requests of the transfers waiting for a response
+    file_requests: HashMap<request_response::OutboundRequestId, oneshot::Sender<Result<FileResponse, String>>>,
+    // Responses to the file requests of peers, worked out on their own tasks because files are hashed
+    file_responder: mpsc::UnboundedSender<FileReply>,
+    file_replies: mpsc::UnboundedReceiver<FileReply>,
+    // Listeners closed on shutdown
+    listeners: Vec<ListenerId>,
+    // Metrics endpoint, stopped on shutdown
+    metrics_server: Option<ServerHandle>,
+    // Flushes the log file when dropped
+    log_guard: Option<WorkerGuard>,
+    // Logger of the node task, so nodes sharing a process log to their own folder
+    log_dispatch: Option<Dispatch>,
 }
 
 impl Node {
+    /// New node
+    /// 
+    /// Built from the parameters, see 'NodeBuilder' to choose the behaviours and transports
     pub async fn new(parameters: HiveParameters) -> Result<Self, Box<dyn Error>> {
-        // Fetch key seed
-        let key_seed = match parameters.key_seed {
-            Some(seed) => seed,
-            None => return Err("Key seed is required".into()),
+        NodeBuilder::from_parameters(&parameters)?.build(parameters)
+    }
+    
+    /// New with test handler
+    /// 
+    /// The node writes its logs to the folder of the test handler
+    pub async fn new_with_test_handler(
+        parameters: HiveParameters,
+        test_handler: HiveServerNode,
+    ) -> Result<Self, Box<dyn Error>> {
+        NodeBuilder::from_parameters(&parameters)?
+            .test_handler(test_handler)
+            .build(parameters)
+    }
+    
+    /// Set test handler
+    /// 
+    /// 
+    pub fn set_test_handler(&mut self, test_handler: HiveServerNode) {
+        self.test_handler = Some(test_handler);
+    }
+    
+    /// Set database
+    /// 
+    /// Server nodes of discovered peers are stored on it
+    pub fn set_database(&mut self, db: DatabaseConnection) {
+        self.db = Some(db);
+    }
+    
+    /// Bootstrap Kademlia
+    /// 
+    /// Bootstrap peers are added to the routing table and a bootstrap query is started,
+    /// it's fine to call it again to refresh the routing table
+    pub fn bootstrap(&mut self) -> Result<(), Box<dyn Error>> {
+        for peer in self.parameters.bootstrap_peers()? {
+            self.add_kademlia_address(&peer.peer_id, peer.address);
+        }
+        
+        // Without known peers there's nothing to bootstrap, peers found through mDNS will fill the table
+        if let Some(kademlia) = self.swarm.behaviour_mut().kademlia.as_mut() {
+            if let Err(err) = kademlia.bootstrap() {
+                tracing::debug!("Kademlia bootstrap skipped: {err}");
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Handle Kademlia events
+    /// 
+    /// Peers found through the DHT join gossipsub like mDNS peers
+    fn handle_kademlia_event(&mut self, event: kad::Event) {
+        match event {
+            kad::Event::RoutingUpdated { peer, .. } if self.is_denied(&peer) => {
+                if let Some(kademlia) = self.swarm.behaviour_mut().kademlia.as_mut() {
+                    kademlia.remove_peer(&peer);
+                }
+            }
+            kad::Event::RoutingUpdated { peer, is_new_peer, .. } => {
+                if is_new_peer {
+                    println!("Kademlia discovered a new peer: {peer}");
+                    self.emit(NodeEvent::PeerDiscovered(peer));
+                }
+                
+                self.add_explicit_peer(&peer);
+                self.request_server_node(peer);
+            }
+            kad::Event::OutboundQueryProgressed {
+                result: kad::QueryResult::Bootstrap(Err(err)),
+                ..
+            } => {
+                tracing::warn!("Kademlia bootstrap failed: {err:?}");
+            }
+            _ => {}
+        }
+    }
+    
+    /// Request the server node of a peer
+    /// 
+    /// Peers are only asked once, unless the request fails
+    pub fn request_server_node(&mut self, peer_id: PeerId) {
+        if !self.requested_peers.insert(peer_id) {
+            return;
+        }
+        
+        let request = match ServerNodeRequest::new() {
+            Ok(request) => request,
+            Err(err) => {
+                tracing::warn!("Couldn't create server node request: {err}");
+                self.requested_peers.remove(&peer_id);
+                return;
+            }
+        };
+        
+        self.swarm
+            .behaviour_mut().server_node
+            .send_request(&peer_id, request);
+    }
+    
+    /// Handle server node protocol events
+    /// 
+    /// 
+    async fn handle_server_node_event(
+        &mut self,
+        event: request_response::Event<ServerNodeRequest, ServerNodeResponse>,
+    ) {
+        match event {
+            request_response::Event::Message { peer, message } => match message {
+                request_response::Message::Request { request, channel, .. } => {
+                    let local_node = self.registry.get(self.swarm.local_peer_id());
+                    let response = ServerNodeResponse::respond(&request, local_node);
+                    
+                    if self.swarm
+                        .behaviour_mut().server_node
+                        .send_response(channel, response)
+                        .is_err() {
+                        tracing::warn!("Couldn't send server node to peer {peer}");
+                    }
+                }
+                request_response::Message::Response { response, .. } => match response {
+                    ServerNodeResponse::Node(server_node) => {
+                        println!("Received server node '{}' from peer {peer}", server_node.location.name);
+                        
+                        self.emit(NodeEvent::ServerNodeReceived {
+                            peer_id: peer,
+                            server_node: server_node.clone(),
+                        });
+                        self.register_peer_node(peer, (*server_node).clone());
+                        
+                        // Peers are asked again after restarts and when they come back online
+                        let known_id = self.peer_book
+                            .get(&peer)
+                            .and_then(|record| record.server_node_id);
+                        match Self::store_server_node(self.db.clone(), *server_node, known_id).await {
+                            Ok(Some(server_node_id)) => self.peer_book.set_server_node_id(peer, server_node_id),
+                            Ok(None) => {}
+                            Err(err) => tracing::warn!("Couldn't store server node of peer {peer}: {err}"),
+                        }
+                    }
+                    ServerNodeResponse::Unauthorized => {
+                        tracing::warn!("Peer {peer} refused to share its server node");
+                    }
+                    ServerNodeResponse::Error(err) => {
+                        tracing::warn!("Peer {peer} failed to create its server node: {err}");
+                    }
+                },
+            },
+            request_response::Event::OutboundFailure { peer, error, .. } => {
+                tracing::warn!("Server node request to peer {peer} failed: {error}");
+                
+                // Allow asking again
+                self.requested_peers.remove(&peer);
+            }
+            _ => {}
+        }
+    }
+    
+    /// Store server node
+    /// 
+    /// The row of the known id is updated, a new one is only inserted when there's none.
+    /// Returns the id of the row, does nothing if there's no database
+    async fn store_server_node(
+        db: Option<DatabaseConnection>,
+        server_node: ServerNode,
+        known_id: Option<i64>,
+    ) -> Result<Option<i64>, Box<dyn Error>> {
+        let db = match db {
+            Some(db) => db,
+            None => return Ok(None),
+        };
+        
+        if let Some(server_node_id) = known_id {
+            if ServerNodeController::update_server_node(&db, server_node_id, &server_node).await? {
+                return Ok(Some(server_node_id));
+            }
+        }
+        
+        let mut server_node_controller = ServerNodeController::new_bare(db)?;
+        server_node_controller
+            .insert_server_node(server_node)
+            .await?;
+        let server_node_id = server_node_controller.id().await?;
+        
+        Ok(Some(server_node_id))
+    }
+    
+    /// Load the peer book from the database
+    /// 
+    /// 
+    async fn load_peer_book(&mut self) {
+        let db = match self.db.clone() {
+            Some(db) => db,
+            None => return,
+        };
+        
+        match PeerBookController::new(db).load().await {
+            Ok(peer_book) => {
+                println!("Loaded {} known peers", peer_book.len());
+                self.peer_book = peer_book;
+            }
+            Err(err) => tracing::warn!("Couldn't load the peer book: {err}"),
+        }
+    }
+    
+    /// Save the peers changed since the last save
+    /// 
+    /// 
+    async fn flush_peer_book(&mut self) {
+        let db = match self.db.clone() {
+            Some(db) => db,
+            None => return,
+        };
+        
+        let records = self.peer_book.take_dirty();
+        if records.is_empty() {
+            return;
+        }
+        
+        if let Err(err) = PeerBookController::new(db).save(&records).await {
+            tracing::warn!("Couldn't save the peer book: {err}");
+            
+            // Try again on the next flush
+            for record in records {
+                self.peer_book.mark_dirty(record.peer_id);
+            }
+        }
+    }
+    
+    /// Redial the peers of the peer book
+    /// 
+    /// Their addresses are also given to Kademlia
+    fn redial_known_peers(&mut self) {
+        let peers: Vec<_> = self.peer_book
+            .records()
+            .filter(|record| !record.addresses.is_empty())
+            .map(|record| (record.peer_id, record.addresses.clone()))
+            .collect();
+        
+        let local_peer_id = *self.swarm.local_peer_id();
+        for (peer_id, addresses) in peers {
+            // Our own server node row is also on the peer book
+            if peer_id == local_peer_id || self.is_denied(&peer_id) {
+                continue;
+            }
+            
+            for address in &addresses {
+                self.add_kademlia_address(&peer_id, address.clone());
+            }
+            
+            let dial = DialOpts::peer_id(peer_id).addresses(addresses).build();
+            if let Err(err) = self.swarm.dial(dial) {
+                tracing::debug!("Couldn't redial known peer {peer_id}: {err}");
+            }
+        }
+    }
+    
+    /// Load the access list
+    /// 
+    /// Rules of the configuration are stored with the ones on the database
+    async fn load_access_list(&mut self) {
+        let rules = match self.parameters.access_rules() {
+            Ok(rules) => rules,
+            Err(err) => {
+                tracing::warn!("Invalid peer rules on the configuration: {err}");
+                Vec::new()
+            }
+        };
+        
+        let db = match self.db.clone() {
+            Some(db) => db,
+            None => {
+                self.apply_access_list(AccessList::from_rules(rules));
+                return;
+            }
+        };
+        
+        let controller = AccessListController::new(db);
+        for rule in &rules {
+            if let Err(err) = controller.save(rule).await {
+                tracing::warn!("Couldn't store the rule of peer {}: {err}", rule.peer_id);
+            }
+        }
+        
+        self.sync_access_list().await;
+    }
+    
+    /// Apply the access list stored on the database
+    /// 
+    /// 
+    async fn sync_access_list(&mut self) {
+        let db = match self.db.clone() {
+            Some(db) => db,
+            None => return,
+        };
+        
+        match AccessListController::new(db).load().await {
+            Ok(access_list) => self.apply_access_list(access_list),
+            Err(err) => tracing::warn!("Couldn't load the access list: {err}"),
+        }
+    }
+    
+    /// Replace the access list
+    /// 
+    /// Only the rules that changed are applied
+    fn apply_access_list(&mut self, access_list: AccessList) {
+        let (changed, removed) = self.access_list.changes(&access_list);
+        
+        for peer_id in removed {
+            self.clear_peer_rule(peer_id);
+        }
+        
+        for rule in changed {
+            self.enforce_peer_rule(&rule);
+        }
+        
+        self.access_list = access_list;
+    }
+    
+    /// Set the rule of a peer
+    /// 
+    /// 
+    async fn set_peer_rule(&mut self, rule: AccessRule) -> Result<(), Box<dyn Error>> {
+        if let Some(db) = self.db.clone() {
+            AccessListController::new(db).save(&rule).await?;
+        }
+        
+        self.enforce_peer_rule(&rule);
+        self.access_list.set(rule);
+        
+        Ok(())
+    }
+    
+    /// Remove the rule of a peer
+    /// 
+    /// 
+    async fn remove_peer_rule(&mut self, peer_id: PeerId) -> Result<bool, Box<dyn Error>> {
+        if let Some(db) = self.db.clone() {
+            AccessListController::new(db).remove(&peer_id).await?;
+        }
+        
+        self.clear_peer_rule(peer_id);
+        
+        Ok(self.access_list.remove(&peer_id).is_some())
+    }
+    
+    /// Apply the rule of a peer to the swarm
+    /// 
+    /// Blocked peers are disconnected and forgotten by gossipsub and Kademlia
+    fn enforce_peer_rule(&mut self, rule: &AccessRule) {
+        let peer_id = rule.peer_id;
+        let behaviour = self.swarm.behaviour_mut();
+        
+        match rule.rule {
+            PeerRule::Allow => {
+                behaviour.blocked_peers.unblock_peer(peer_id);
+                if let Some(allowed_peers) = behaviour.allowed_peers.as_mut() {
+                    allowed_peers.allow_peer(peer_id);
+                }
+            }
+            PeerRule::Block => {
+                println!("Blocking peer {peer_id}");
+                
+                behaviour.blocked_peers.block_peer(peer_id);
+                if let Some(allowed_peers) = behaviour.allowed_peers.as_mut() {
+                    allowed_peers.disallow_peer(peer_id);
+                }
+                if let Some(gossipsub) = behaviour.gossipsub.as_mut() {
+                    gossipsub.remove_explicit_peer(&peer_id);
+                }
+                if let Some(kademlia) = behaviour.kademlia.as_mut() {
+                    kademlia.remove_peer(&peer_id);
+                }
+                
+                self.requested_peers.remove(&peer_id);
+                let _ = self.swarm.disconnect_peer_id(peer_id);
+            }
+        }
+    }
+    
+    /// Remove the rule of a peer from the swarm
+    /// 
+    /// On allowlist mode the peer is disconnected, because it's not allowed anymore
+    fn clear_peer_rule(&mut self, peer_id: PeerId) {
+        let behaviour = self.swarm.behaviour_mut();
+        
+        behaviour.blocked_peers.unblock_peer(peer_id);
+        if let Some(allowed_peers) = behaviour.allowed_peers.as_mut() {
+            allowed_peers.disallow_peer(peer_id);
+        }
+    }
+    
+    /// Whether a peer can't connect
+    /// 
+    /// 
+    fn is_denied(&self, peer_id: &PeerId) -> bool {
+        self.access_list.is_denied(peer_id, self.parameters.allowlist)
+    }
+    
+    /// Publish a payload
+    /// 
+    /// The payload is wrapped on a hive message and published on the topic of its kind
+    pub fn publish(&mut self, payload: HivePayload) -> Result<gossipsub::MessageId, Box<dyn Error>> {
+        let message = HiveMessage::new(self.local_key.public().to_peer_id(), payload);
+        let data = message.encode()?;
+        if data.len() > MAX_MESSAGE_SIZE {
+            return Err(ValidationError::TooLarge { size: data.len(), max: MAX_MESSAGE_SIZE }.into());
+        }
+        
+        let message_id = self.swarm
+            .behaviour_mut().gossipsub
+            .as_mut()
+            .ok_or("Gossipsub is disabled")?
+            .publish(message.topic(), data)?;
+        
+        Ok(message_id)
+    }
+    
+    /// Send every message of our topics to a peer
+    /// 
+    /// 
+    fn add_explicit_peer(&mut self, peer_id: &PeerId) {
+        if let Some(gossipsub) = self.swarm.behaviour_mut().gossipsub.as_mut() {
+            gossipsub.add_explicit_peer(peer_id);
+        }
+    }
+    
+    /// Stop sending every message to a peer
+    /// 
+    /// 
+    fn remove_explicit_peer(&mut self, peer_id: &PeerId) {
+        if let Some(gossipsub) = self.swarm.behaviour_mut().gossipsub.as_mut() {
+            gossipsub.remove_explicit_peer(peer_id);
+        }
+    }
+    
+    /// Add an address to the Kademlia routing table
+    /// 
+    /// 
+    fn add_kademlia_address(&mut self, peer_id: &PeerId, address: Multiaddr) {
+        if let Some(kademlia) = self.swarm.behaviour_mut().kademlia.as_mut() {
+            kademlia.add_address(peer_id, address);
+        }
+    }
+    
+    /// Handle a hive message
+    /// 
+    /// Chat messages are left to the consumers of the node events
+    async fn handle_message(&mut self, message: HiveMessage) {
+        let sender = message.sender;
+        tracing::debug!("Message on {} from peer {sender}", message.kind().topic_name());
+        
+        // Status changes are explicit, they aren't a sign of life
+        if !matches!(message.payload, HivePayload::StatusChange(_)) {
+            let status = self.liveness.seen(sender, Utc::now());
+            self.liveness_changed(sender, status).await;
+        }
+        
+        match &message.payload {
+            HivePayload::NodeAnnouncement(server_node) => {
+                println!("Node announcement from {sender}: {}", server_node.location.name);
+                self.register_peer_node(sender, (**server_node).clone());
+            }
+            HivePayload::StatusChange(status) => {
+                println!("Peer {sender} is now {status}");
+                self.update_peer_status(sender, status.clone()).await;
+            }
+            HivePayload::ResourceUpdate(resources) => {
+                println!("Peer {sender} resources updated, {} cores", resources.total_cores());
+                // Every peer received the update too, there's no need to replicate it
+                if let Some(mut server_node) = self.registry.get(&sender).cloned() {
+                    server_node.resources = resources.clone();
+                    if let Err(err) = self.registry.set(sender, server_node) {
+                        tracing::warn!("Couldn't update the registry entry of peer {sender}: {err}");
+                    }
+                }
+            }
+            HivePayload::Heartbeat(status) => {
+                self.update_peer_status(sender, status.clone()).await;
+            }
+            HivePayload::Election(message) => {
+                let messages = self.election.handle(sender, message.clone(), Utc::now());
+                self.publish_election(messages);
+            }
+            HivePayload::RegistryDelta(entry) => {
+                self.merge_registry(vec![(**entry).clone()]).await;
+            }
+            HivePayload::Chat(_) => {}
+        }
+        
+        self.emit(NodeEvent::Message(Box::new(message)));
+    }
+    
+    /// Update the status of a peer
+    /// 
+    /// Announced by the peer itself, so it's applied even if the peer was on maintenance
+    async fn update_peer_status(&mut self, peer_id: PeerId, status: ServerStatus) {
+        if let Some(status) = self.liveness.set_status(peer_id, status, Utc::now()) {
+            self.apply_peer_status(peer_id, status, false).await;
+        }
+    }
+    
+    /// Apply an automatic liveness change
+    /// 
+    /// 
+    async fn liveness_changed(&mut self, peer_id: PeerId, status: Option<ServerStatus>) {
+        if let Some(status) = status {
+            println!("Peer {peer_id} is now {status}");
+            self.apply_peer_status(peer_id, status, true).await;
+        }
+    }
+    
+    /// Store and announce the new status of a peer
+    /// 
+    /// Offline peers are removed right away, without waiting for the mDNS expiry.
+    /// Automatic changes don't leave maintenance, when the stored node is on maintenance the peer is set on it instead
+    async fn apply_peer_status(&mut self, peer_id: PeerId, mut status: ServerStatus, automatic: bool) {
+        let server_node_id = self.peer_book
+            .get(&peer_id)
+            .and_then(|record| record.server_node_id);
+        match Self::store_status(self.db.clone(), server_node_id, status.clone(), automatic).await {
+            Ok(true) => {}
+            Ok(false) => {
+                status = ServerStatus::Maintenance;
+                self.liveness.set_status(peer_id, status.clone(), Utc::now());
+            }
+            Err(err) => tracing::warn!("Couldn't update the status of peer {peer_id}: {err}"),
+        }
+        
+        match status {
+            ServerStatus::Offline => {
+                self.remove_explicit_peer(&peer_id);
+                
+                // Ask for its server node again once it's back
+                self.requested_peers.remove(&peer_id);
+            }
+            ServerStatus::Online if !self.is_denied(&peer_id) => {
+                self.add_explicit_peer(&peer_id);
+            }
+            _ => {}
+        }
+        
+        // An unavailable leader is replaced right away
+        if status != ServerStatus::Online {
+            let messages = self.election.peer_unavailable(peer_id, Utc::now());
+            self.publish_election(messages);
+        }
+        
+        // Every peer finds out by itself, deltas are only accepted from the node they're about
+        if let Err(err) = self.registry.set_status(peer_id, status.clone()) {
+            tracing::warn!("Couldn't update the registry entry of peer {peer_id}: {err}");
+        }
+        
+        self.emit(NodeEvent::PeerStatusChanged { peer_id, status });
+    }
+    
+    /// Write a status on the database
+    /// 
+    /// Automatic changes don't overwrite maintenance, returns false when the stored node is on maintenance
+    async fn store_status(
+        db: Option<DatabaseConnection>,
+        server_node_id: Option<i64>,
+        status: ServerStatus,
+        automatic: bool,
+    ) -> Result<bool, String> {
+        let (db, server_node_id) = match (db, server_node_id) {
+            (Some(db), Some(server_node_id)) => (db, server_node_id),
+            _ => return Ok(true),
+        };
+        
+        let result = match automatic {
+            true => ServerNodeController::update_status_unless_maintenance(&db, server_node_id, status).await,
+            false => ServerNodeController::update_status_by_id(&db, server_node_id, status).await.map(|_| true),
+        };
+        
+        result.map_err(|err| err.to_string())
+    }
+    
+    /// Set the status of this node
+    /// 
+    /// The peers are told right away, the next heartbeats carry it too
+    async fn set_status(&mut self, status: ServerStatus) -> Result<(), String> {
+        let local_peer_id = *self.swarm.local_peer_id();
+        self.status = status.clone();
+        
+        // Only online nodes lead
+        let messages = self.election.set_eligible(status == ServerStatus::Online, Utc::now());
+        self.publish_election(messages);
+        
+        match self.registry.set_status(local_peer_id, status.clone()) {
+            Ok(Some(entry)) => self.publish_registry(entry),
+            Ok(None) => {}
+            Err(err) => tracing::warn!("Couldn't update our registry entry: {err}"),
+        }
+        
+        // Nobody may be listening yet, they'll get it with the next heartbeat
+        if let Err(err) = self.publish(HivePayload::StatusChange(status.clone())) {
+            tracing::debug!("Couldn't announce our status: {err}");
+        }
+        
+        let server_node_id = self.peer_book
+            .get(&local_peer_id)
+            .and_then(|record| record.server_node_id);
+        Self::store_status(self.db.clone(), server_node_id, status.clone(), false).await?;
+        
+        println!("Node {local_peer_id} is now {status}");
+        self.emit(NodeEvent::PeerStatusChanged { peer_id: local_peer_id, status });
+        
+        Ok(())
+    }
+    
+    /// Tell the peers that we're alive
+    /// 
+    /// 
+    fn send_heartbeat(&mut self) {
+        if let Err(err) = self.publish(HivePayload::Heartbeat(self.status.clone())) {
+            tracing::debug!("Couldn't send heartbeat: {err}");
+        }
+    }
+    
+    /// Publish election messages
+    /// 
+    /// A new leader is told to the node handles
+    fn publish_election(&mut self, messages: Vec<ElectionMessage>) {
+        for message in messages {
+            if let Err(err) = self.publish(HivePayload::Election(message)) {
+                tracing::debug!("Couldn't publish election message: {err}");
+            }
+        }
+        
+        let now = Utc::now();
+        if self.election.changed(now) {
+            let leader = self.election.leader(now).cloned();
+            match &leader {
+                Some(leader) => println!("Peer {} leads subnetwork {}", leader.peer_id, leader.subnetwork),
+                None => println!("Subnetwork {} has no leader", self.election.config.subnetwork),
+            }
+            
+            self.emit(NodeEvent::LeaderChanged(leader.map(Box::new)));
+        }
+    }
+    
+    /// Store the server node of a peer on the registry
+    /// 
+    /// It's sent by the peer itself, the status is the one we know. It isn't published, the peer
+    /// publishes its own entry
+    fn register_peer_node(&mut self, peer_id: PeerId, mut server_node: ServerNode) {
+        if let Some(liveness) = self.liveness.get(&peer_id) {
+            server_node.status = liveness.status.clone();
+        }
+        
+        if let Err(err) = self.registry.set(peer_id, server_node) {
+            tracing::warn!("Couldn't update the registry entry of peer {peer_id}: {err}");
+        }
+    }
+    
+    /// Store our own server node on the registry
+    /// 
+    /// Waits for the first resource sample
+    async fn register_local_node(&mut self) {
+        let result = self.resources
+            .latest()
+            .await
+            .and_then(|resources| ServerNode::with_resources(resources).map_err(|err| err.to_string()));
+        
+        match result {
+            Ok(mut server_node) => {
+                server_node.status = self.status.clone();
+                
+                match self.registry.set(*self.swarm.local_peer_id(), server_node) {
+                    Ok(entry) => self.publish_registry(entry),
+                    Err(err) => tracing::warn!("Couldn't update our registry entry: {err}"),
+                }
+            }
+            Err(err) => tracing::warn!("Couldn't create our server node: {err}"),
+        }
+    }
+    
+    /// Publish a write on the registry
+    /// 
+    /// Peers that miss it get it on the next sync
+    fn publish_registry(&mut self, entry: RegistryEntry) {
+        if let Err(err) = self.publish(HivePayload::RegistryDelta(Box::new(entry))) {
+            tracing::debug!("Couldn't publish registry entry: {err}");
+        }
+    }
+    
+    /// Merge registry entries written by other nodes
+    /// 
+    /// Others can't tell better than us how this node is, wrong writes about it are replaced
+    async fn merge_registry(&mut self, entries: Vec<RegistryEntry>) {
+        let local_peer_id = *self.swarm.local_peer_id();
+        
+        for entry in entries {
+            let node_id = entry.node_id;
+            match self.registry.merge(entry) {
+                Ok(true) if node_id == local_peer_id => {}
+                Ok(_) => continue,
+                Err(err) => {
+                    tracing::debug!("Couldn't merge the registry entry of {node_id}: {err}");
+                    continue;
+                }
+            }
+            
+            match self.registry.get(&local_peer_id) {
+                Some(_) => match self.registry.set_status(local_peer_id, self.status.clone()) {
+                    Ok(Some(entry)) => self.publish_registry(entry),
+                    Ok(None) => {}
+                    Err(err) => tracing::warn!("Couldn't update our registry entry: {err}"),
+                },
+                None => self.register_local_node().await,
+            }
+        }
+    }
+    
+    /// Ask a peer for the registry entries we're missing
+    /// 
+    /// Without a peer a random connected one is used
+    fn sync_registry(&mut self, peer_id: Option<PeerId>) {
+        let peer_id = match peer_id {
+            Some(peer_id) => peer_id,
+            None => match self.swarm.connected_peers().choose(&mut rand::thread_rng()) {
+                Some(peer_id) => *peer_id,
+                None => return,
+            },
+        };
+        
+        match RegistryRequest::new(self.registry.digest()) {
+            Ok(request) => {
+                self.swarm.behaviour_mut().registry.send_request(&peer_id, request);
+            }
+            Err(err) => tracing::warn!("Couldn't create registry request: {err}"),
+        }
+    }
+    
+    /// Handle registry protocol events
+    /// 
+    /// 
+    async fn handle_registry_event(&mut self, event: request_response::Event<RegistryRequest, RegistryResponse>) {
+        match event {
+            request_response::Event::Message { peer, message } => match message {
+                request_response::Message::Request { request, channel, .. } => {
+                    let response = RegistryResponse::respond(&self.registry, &request);
+                    
+                    if self.swarm
+                        .behaviour_mut().registry
+                        .send_response(channel, response)
+                        .is_err() {
+                        tracing::warn!("Couldn't send registry entries to peer {peer}");
+                    }
+                }
+                request_response::Message::Response { response, .. } => match response {
+                    RegistryResponse::Entries(entries) => self.merge_registry(entries).await,
+                    RegistryResponse::Unauthorized => {
+                        tracing::warn!("Peer {peer} refused to share its registry");
+                    }
+                },
+            },
+            request_response::Event::OutboundFailure { peer, error, .. } => {
+                tracing::debug!("Registry sync with peer {peer} failed: {error}");
+            }
+            _ => {}
+        }
+    }
+    
+    /// Set silent peers offline
+    /// 
+    /// 
+    async fn check_liveness(&mut self) {
+        for (peer_id, status) in self.liveness.check(Utc::now()) {
+            self.liveness_changed(peer_id, Some(status)).await;
+        }
+    }
+    
+    /// Store the server node of this node
+    /// 
+    /// The row is linked to our own peer id on the peer book, so it's reused between restarts
+    async fn register_server_node(&mut self) {
+        let db = match self.db.clone() {
+            Some(db) => db,
+            None => return,
+        };
+        
+        let local_peer_id = *self.swarm.local_peer_id();
+        let known_id = self.peer_book
+            .get(&local_peer_id)
+            .and_then(|record| record.server_node_id);
+        
+        let result = match known_id {
+            Some(server_node_id) => ServerNodeController::update_status_unless_maintenance(&db, server_node_id, ServerStatus::Online)
+                .await
+                .map(|updated| (server_node_id, updated)),
+            None => match self.resources.latest().await {
+                Ok(resources) => Self::store_local_server_node(db, resources)
+                    .await
+                    .map(|server_node_id| (server_node_id, true)),
+                Err(err) => Err(err.into()),
+            },
+        };
+        
+        match result {
+            Ok((server_node_id, updated)) => {
+                self.peer_book.set_server_node_id(local_peer_id, server_node_id);
+                
+                // Put on maintenance while the node was stopped
+                if !updated {
+                    self.status = ServerStatus::Maintenance;
+                    self.election.set_eligible(false, Utc::now());
+                }
+            }
+            Err(err) => tracing::warn!("Couldn't store our server node: {err}"),
+        }
+    }
+    
+    /// Insert the server node of this computer
+    /// 
+    /// 
+    async fn store_local_server_node(db: DatabaseConnection, resources: Resources) -> Result<i64, Box<dyn Error>> {
+        let server_node = ServerNode::with_resources(resources)?;
+        
+        match Self::store_server_node(Some(db), server_node, None).await? {
+            Some(server_node_id) => Ok(server_node_id),
+            None => Err("Server node wasn't stored".into()),
+        }
+    }
+    
+    /// Shut down gracefully
+    /// 
+    /// Peers are told that we're going offline before the listeners are closed,
+    /// unless we're on maintenance
+    async fn shutdown(&mut self) {
+        let local_peer_id = *self.swarm.local_peer_id();
+        
+        // Let the peers elect another leader
+        let messages = self.election.set_eligible(false, Utc::now());
+        self.publish_election(messages);
+        
+        if self.status != ServerStatus::Maintenance {
+            if let Err(err) = self.publish(HivePayload::StatusChange(ServerStatus::Offline)) {
+                tracing::warn!("Couldn't announce that the node is going offline: {err}");
+            }
+        }
+        
+        if let Some(gossipsub) = self.swarm.behaviour_mut().gossipsub.as_mut() {
+            let topics: Vec<_> = gossipsub.topics().cloned().collect();
+            for topic in topics {
+                let topic = gossipsub::IdentTopic::new(topic.into_string());
+                if let Err(err) = gossipsub.unsubscribe(&topic) {
+                    tracing::warn!("Couldn't unsubscribe from '{topic}': {err}");
+                }
+            }
+        }
+        
+        // Let the swarm send the announcement and the unsubscriptions
+        let _ = tokio::time::timeout(SHUTDOWN_GRACE_PERIOD, async {
+            loop {
+                self.swarm.select_next_some().await;
+            }
+        })
+        .await;
+        
+        for listener in self.listeners.drain(..) {
+            self.swarm.remove_listener(listener);
+        }
+        
+        // Our own row
+        let server_node_id = self.peer_book
+            .get(&local_peer_id)
+            .and_then(|record| record.server_node_id);
+        if let Err(err) = Self::store_status(self.db.clone(), server_node_id, ServerStatus::Offline, true).await {
+            tracing::warn!("Couldn't set our server node offline: {err}");
+        }
+        
+        self.flush_peer_book().await;
+        
+        if let Some(metrics_server) = self.metrics_server.take() {
+            metrics_server.stop(true).await;
+        }
+        
+        println!("Node {local_peer_id} stopped");
+        
+        // Flush the log file
+        self.log_guard.take();
+    }
+    
+    /// Announce a new sample of the resources of this computer
+    /// 
+    /// Peers update our registry entry when they receive it, and it's stored on our history
+    async fn announce_resources(&mut self, resources: Resources) {
+        self.metrics.update_resources(&resources);
+        self.record_resources(&resources).await;
+        
+        let local_peer_id = *self.swarm.local_peer_id();
+        if let Some(mut server_node) = self.registry.get(&local_peer_id).cloned() {
+            server_node.resources = resources.clone();
+            if let Err(err) = self.registry.set(local_peer_id, server_node) {
+                tracing::warn!("Couldn't update our registry entry: {err}");
+            }
+        }
+        
+        if let Err(err) = self.publish(HivePayload::ResourceUpdate(resources)) {
+            tracing::debug!("Couldn't announce our resources: {err}");
+        }
+    }
+    
+    /// Id of our server node on the database
+    /// 
+    /// None until it's stored
+    fn local_server_node_id(&self) -> Option<i64> {
+        self.peer_book
+            .get(self.swarm.local_peer_id())
+            .and_then(|record| record.server_node_id)
+    }
+    
+    /// Store a sample of our resources on the history
+    /// 
+    /// Every node only stores its own samples, so the database doesn't get one copy per node
+    async fn record_resources(&mut self, resources: &Resources) {
+        let (db, server_node_id) = match (self.db.clone(), self.local_server_node_id()) {
+            (Some(db), Some(server_node_id)) => (db, server_node_id),
+            _ => return,
+        };
+        
+        if let Err(err) = ResourceHistoryController::new(db).record(server_node_id, resources).await {
+            tracing::warn!("Couldn't record our resources: {err}");
+        }
+    }
+    
+    /// Downsample and remove the expired points of our resource history
+    /// 
+    /// 
+    async fn compact_resource_history(&mut self) {
+        let (db, server_node_id) = match (self.db.clone(), self.local_server_node_id()) {
+            (Some(db), Some(server_node_id)) => (db, server_node_id),
+            _ => return,
         };
+        let policy = self.parameters.retention_policy();
         
-        // Logger
-        let _ = tracing_subscriber::fmt()
-            .with_env_filter(EnvFilter::from_default_env())
-            .try_init();
+        if let Err(err) = ResourceHistoryController::new(db).compact(server_node_id, &policy, Utc::now()).await {
+            tracing::warn!("Couldn't compact our resource history: {err}");
+        }
+    }
+    
+    /// Submit a task to a peer
+    /// 
+    /// Tasks submitted to this node are started right away
+    async fn submit_task(&mut self, peer_id: PeerId, spec: TaskSpec) -> Result<TaskInfo, String> {
+        let local_peer_id = *self.swarm.local_peer_id();
+        let mut task = TaskInfo::new(new_task_id(), local_peer_id, peer_id, spec);
         
-        let local_key: identity::Keypair = generate_ed25519(key_seed)?;
+        if peer_id == local_peer_id {
+            if let Err(reason) = self.start_task(task.clone()).await {
+                task.set_status(TaskStatus::Rejected, None, Some(reason));
+                self.tasks.insert(task.clone());
+                self.emit(NodeEvent::TaskUpdated(Box::new(task.clone())));
+            }
+            
+            return Ok(self.tasks.get(&task.id).cloned().unwrap_or(task));
+        }
         
-        // Create swarm
-        let mut swarm = SwarmBuilder::with_existing_identity(local_key.clone())
-            .with_tokio()
-            .with_tcp(
-                tcp::Config::default(),
-                noise::Config::new,
-                yamux::Config::default,
-            )?
-            .with_quic()
-            .with_behaviour(|key| {
-                Ok(MyBehavior::new(key).unwrap())
-            })?
-            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
-            .build();
+        let request = TaskRequest::submit(task.id.clone(), task.spec.clone()).map_err(|err| err.to_string())?;
+        self.send_task_request(peer_id, request);
+        self.tasks.insert(task.clone());
         
-        // Create a Gossipsub topic
-        let topic = gossipsub::IdentTopic::new("chat-net");
-        // subscribes to our topic
-        swarm.behaviour_mut().gossipsub.subscribe(&topic)?;
-        
-        Ok(Node {
-            parameters,
-            local_key,
-            swarm,
-            topic,
-            test_handler: None,
-        })
+        Ok(task)
     }
     
-    /// New with test handler
+    /// Start a task on this node
     /// 
+    /// The error is the reason why the task was rejected
+    async fn start_task(&mut self, mut task: TaskInfo) -> Result<(), String> {
+        if self.tasks.get(&task.id).is_some() {
+            return Err(format!("There's already a task with id '{}'", task.id));
+        }
+        
+        if !task.spec.requirements.is_empty() {
+            let resources = self.resources.latest().await?;
+            task.spec.requirements.check(&resources)?;
+        }
+        
+        let pid = self.task_runner
+            .run(task.id.clone(), &task.spec)
+            .map_err(|err| format!("Couldn't start '{}': {err}", task.spec.program))?;
+        println!("Running task {} for peer {}: {}", task.id, task.owner, task.spec.command_line());
+        
+        task.set_status(TaskStatus::Running, None, None);
+        if let Some(task_recorder) = &self.task_recorder {
+            task_recorder.record(TaskRecord::Started { task: task.clone(), pid });
+        }
+        
+        self.tasks.insert(task.clone());
+        self.emit(NodeEvent::TaskUpdated(Box::new(task)));
+        
+        Ok(())
+    }
+    
+    /// Cancel a task
     /// 
-    pub async fn new_with_test_handler(
-        parameters: HiveParameters,
-        test_handler: HiveServerNode,
-    ) -> Result<Self, Box<dyn Error>> {
-        // Fetch key seed
-        let key_seed = match parameters.key_seed {
-            Some(seed) => seed,
-            None => return Err("Key seed is required".into()),
+    /// Tasks run by a peer are cancelled through it
+    fn cancel_task(&mut self, task_id: &str) -> Result<(), String> {
+        let task = match self.tasks.get(task_id) {
+            Some(task) => task,
+            None => return Err(format!("Unknown task '{task_id}'")),
         };
+        if task.status.is_finished() {
+            return Err(format!("Task '{task_id}' has already finished"));
+        }
         
-        // In tests this fails, because there are two global subscribers set
-        // if let Some(subscriber) = tracing::subscriber::global_default() {
-        //     // A subscriber is already set, handle it or exit
-        // } else {
-        //     // No subscriber is set, set one or handle the case
-        // }
-        
-        // Logger, because we know we have a test handler, we want to write logs to a folder
-        let file_appender = tracing_appender::rolling::daily(test_handler.get_log_folder(), "log");
-        let (non_blocking_writer, _guard) = tracing_appender::non_blocking(file_appender);
-        tracing_subscriber::fmt()
-            .with_env_filter(EnvFilter::from_default_env())
-            .with_writer(non_blocking_writer)
-            .init();
-        
-        // Create swarm
-        let local_key: identity::Keypair = generate_ed25519(key_seed)?;
-        let mut swarm = SwarmBuilder::with_existing_identity(local_key.clone())
-            .with_tokio()
-            .with_tcp(
-                tcp::Config::default(),
-                noise::Config::new,
-                yamux::Config::default,
-            )?
-            .with_quic()
-            .with_behaviour(|key| {
-                Ok(MyBehavior::new(key).unwrap())
-            })?
-            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
-            .build();
+        if task.runner == *self.swarm.local_peer_id() {
+            return match self.task_runner.cancel(task_id) {
+                true => Ok(()),
+                false => Err(format!("Task '{task_id}' isn't running")),
+            };
+        }
         
-        // Create and subscribe to topics
-        let topic = gossipsub::IdentTopic::new("chat-net");
-        swarm.behaviour_mut()
-            .gossipsub.subscribe(&topic)?;
-        swarm.behaviour_mut()
-            .gossipsub.subscribe(&gossipsub::IdentTopic::new("test-chat"))?;
-        
-        Ok(Node {
-            parameters,
-            local_key,
-            swarm,
-            topic,
-            test_handler: None,
-        })
+        let runner = task.runner;
+        let request = TaskRequest::cancel(task_id.to_string()).map_err(|err| err.to_string())?;
+        self.send_task_request(runner, request);
+        
+        Ok(())
     }
     
-    /// Set test handler
+    /// Send a task request
     /// 
     /// 
-    pub fn set_test_handler(&mut self, test_handler: HiveServerNode) {
-        self.test_handler = Some(test_handler);
+    fn send_task_request(&mut self, peer_id: PeerId, request: TaskRequest) {
+        let task_id = request.task_id().clone();
+        let request_id = self.swarm.behaviour_mut().task.send_request(&peer_id, request);
+        
+        self.task_requests.insert(request_id, task_id);
+    }
+    
+    /// Send a task request after the previous requests of its task
+    /// 
+    /// 
+    fn queue_task_request(&mut self, peer_id: PeerId, request: TaskRequest) {
+        if let Some((peer_id, request)) = self.task_outbox.push(peer_id, request) {
+            self.send_task_request(peer_id, request);
+        }
+    }
+    
+    /// A task request was answered or failed
+    /// 
+    /// The next queued request of the task is sent, returns the task of the request
+    fn task_request_done(&mut self, request_id: request_response::OutboundRequestId) -> Option<TaskId> {
+        let task_id = self.task_requests.remove(&request_id)?;
+        
+        if let Some((peer_id, request)) = self.task_outbox.next(&task_id) {
+            self.send_task_request(peer_id, request);
+        }
+        
+        Some(task_id)
+    }
+    
+    /// Update the status of a task
+    /// 
+    /// Handles are only told about actual changes
+    fn update_task(&mut self, task_id: &str, status: TaskStatus, exit_code: Option<i32>, error: Option<String>) -> Option<TaskInfo> {
+        let task = self.tasks.get_mut(task_id)?;
+        if task.status == status || !task.set_status(status, exit_code, error) {
+            return None;
+        }
+        
+        let task = task.clone();
+        self.emit(NodeEvent::TaskUpdated(Box::new(task.clone())));
+        
+        Some(task)
+    }
+    
+    /// Append output to a task
+    /// 
+    /// 
+    fn push_task_output(&mut self, task_id: &str, chunk: OutputChunk) {
+        if let Some(task) = self.tasks.get_mut(task_id) {
+            task.push_output(chunk.clone());
+            self.emit(NodeEvent::TaskOutput {
+                task_id: task_id.to_string(),
+                chunk,
+            });
+        }
+    }
+    
+    /// Handle what happens to the tasks run by this node
+    /// 
+    /// Output and results are recorded and sent to the owner of the task
+    async fn handle_runner_event(&mut self, event: RunnerEvent) {
+        let local_peer_id = *self.swarm.local_peer_id();
+        
+        match event {
+            RunnerEvent::Output { task_id, chunk } => {
+                let owner = match self.tasks.get(&task_id) {
+                    Some(task) => task.owner,
+                    None => return,
+                };
+                
+                if let Some(task_recorder) = &self.task_recorder {
+                    task_recorder.record(TaskRecord::Output { task_id: task_id.clone(), chunk: chunk.clone() });
+                }
+                
+                self.push_task_output(&task_id, chunk.clone());
+                if owner != local_peer_id {
+                    self.queue_task_request(owner, TaskRequest::Output { task_id, chunk });
+                }
+            }
+            RunnerEvent::Finished { task_id, status, exit_code, error } => {
+                self.task_runner.finished(&task_id);
+                
+                let task = match self.update_task(&task_id, status, exit_code, error) {
+                    Some(task) => task,
+                    None => return,
+                };
+                println!("Task {task_id} finished: {}", task.status);
+                
+                if let Some(task_recorder) = &self.task_recorder {
+                    task_recorder.record(TaskRecord::Finished { task: task.clone() });
+                }
+                
+                if task.owner != local_peer_id {
+                    let request = TaskRequest::Finished {
+                        task_id,
+                        status: task.status,
+                        exit_code: task.exit_code,
+                        error: task.error,
+                    };
+                    self.queue_task_request(task.owner, request);
+                }
+            }
+        }
+    }
+    
+    /// Respond a task request
+    /// 
+    /// Only the owner of a task can cancel it and only its runner can report on it
+    async fn respond_task_request(&mut self, peer: PeerId, request: TaskRequest) -> TaskResponse {
+        let task_id = request.task_id().clone();
+        if !request.is_authorized() {
+            return TaskResponse::Unauthorized { task_id };
+        }
+        
+        let task = self.tasks.get(&task_id);
+        match request {
+            TaskRequest::Submit { spec, .. } => {
+                let task = TaskInfo::new(task_id.clone(), peer, *self.swarm.local_peer_id(), spec);
+                
+                match self.start_task(task).await {
+                    Ok(()) => TaskResponse::Accepted { task_id },
+                    Err(reason) => TaskResponse::Rejected { task_id, reason },
+                }
+            }
+            TaskRequest::Cancel { .. } if task.is_some_and(|task| task.owner == peer) => {
+                match self.task_runner.cancel(&task_id) {
+                    true => TaskResponse::Ack,
+                    false => TaskResponse::Rejected {
+                        task_id,
+                        reason: "The task isn't running".to_string(),
+                    },
+                }
+            }
+            TaskRequest::Output { chunk, .. } if task.is_some_and(|task| task.runner == peer) => {
+                self.push_task_output(&task_id, chunk);
+                TaskResponse::Ack
+            }
+            TaskRequest::Finished { status, exit_code, error, .. } if task.is_some_and(|task| task.runner == peer) => {
+                self.update_task(&task_id, status, exit_code, error);
+                TaskResponse::Ack
+            }
+            _ => TaskResponse::Rejected {
+                task_id,
+                reason: "Unknown task".to_string(),
+            },
+        }
+    }
+    
+    /// Handle task protocol events
+    /// 
+    /// 
+    async fn handle_task_event(&mut self, event: request_response::Event<TaskRequest, TaskResponse>) {
+        match event {
+            request_response::Event::Message { peer, message } => match message {
+                request_response::Message::Request { request, channel, .. } => {
+                    let response = self.respond_task_request(peer, request).await;
+                    
+                    if self.swarm
+                        .behaviour_mut().task
+                        .send_response(channel, response)
+                        .is_err() {
+                        tracing::warn!("Couldn't respond the task request of peer {peer}");
+                    }
+                }
+                request_response::Message::Response { request_id, response } => {
+                    self.task_request_done(request_id);
+                    
+                    match response {
+                        TaskResponse::Accepted { task_id } => {
+                            self.update_task(&task_id, TaskStatus::Running, None, None);
+                        }
+                        TaskResponse::Rejected { task_id, reason } => {
+                            tracing::warn!("Peer {peer} rejected a request about task {task_id}: {reason}");
+                            if self.tasks.get(&task_id).is_some_and(|task| task.status == TaskStatus::Pending) {
+                                self.update_task(&task_id, TaskStatus::Rejected, None, Some(reason));
+                            }
+                        }
+                        TaskResponse::Unauthorized { task_id } => {
+                            tracing::warn!("Peer {peer} refused a request about task {task_id}");
+                            self.update_task(&task_id, TaskStatus::Rejected, None, Some("Unauthorized".to_string()));
+                        }
+                        TaskResponse::Ack => {}
+                    }
+                }
+            },
+            request_response::Event::OutboundFailure { peer, request_id, error } => {
+                tracing::warn!("Task request to peer {peer} failed: {error}");
+                
+                // Submissions that can't be delivered reject their task
+                let task_id = self.task_request_done(request_id);
+                if let Some(task_id) = task_id.filter(|task_id| {
+                    self.tasks.get(task_id).is_some_and(|task| task.status == TaskStatus::Pending)
+                }) {
+                    self.update_task(&task_id, TaskStatus::Rejected, None, Some(error.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    /// Fail the tasks run by a peer that went away
+    /// 
+    /// Its result can't arrive anymore
+    fn fail_tasks_of(&mut self, peer_id: PeerId) {
+        let task_ids: Vec<TaskId> = self.tasks
+            .tasks()
+            .into_iter()
+            .filter(|task| task.runner == peer_id && !task.status.is_finished())
+            .map(|task| task.id)
+            .collect();
+        
+        for task_id in task_ids {
+            self.update_task(&task_id, TaskStatus::Failed, None, Some(format!("Lost the connection with peer {peer_id}")));
+        }
+    }
+    
+    /// Send a file to a peer
+    /// 
+    /// It's stored with the same name on the transfer folder of the peer
+    fn send_file(&mut self, peer_id: PeerId, path: PathBuf, rate_limit: Option<u64>) -> Result<TransferInfo, String> {
+        let name = match path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => return Err(format!("'{}' doesn't have a file name", path.display())),
+        };
+        
+        self.start_transfer(peer_id, TransferDirection::Send, path, name, rate_limit)
+    }
+    
+    /// Fetch a file from a peer
+    /// 
+    /// Without an output the file is stored with the same name on our transfer folder
+    fn fetch_file(
+        &mut self,
+        peer_id: PeerId,
+        remote_path: String,
+        output: Option<PathBuf>,
+        rate_limit: Option<u64>,
+    ) -> Result<TransferInfo, String> {
+        let local_path = match (output, Path::new(&remote_path).file_name()) {
+            (Some(output), _) => output,
+            (None, Some(name)) => self.transfer_folder.path.join(name),
+            (None, None) => return Err(format!("'{remote_path}' doesn't have a file name")),
+        };
+        
+        self.start_transfer(peer_id, TransferDirection::Fetch, local_path, remote_path, rate_limit)
+    }
+    
+    /// Start a transfer
+    /// 
+    /// 
+    fn start_transfer(
+        &mut self,
+        peer_id: PeerId,
+        direction: TransferDirection,
+        local_path: PathBuf,
+        remote_path: String,
+        rate_limit: Option<u64>,
+    ) -> Result<TransferInfo, String> {
+        if peer_id == *self.swarm.local_peer_id() {
+            return Err("Files can't be transferred to the node itself".to_string());
+        }
+        
+        let transfer = TransferInfo::new(new_transfer_id(), peer_id, direction, local_path, remote_path, rate_limit);
+        println!(
+            "Starting transfer {} with peer {peer_id}: {} {} '{}'",
+            transfer.id, transfer.direction, transfer.local_path.display(), transfer.remote_path
+        );
+        
+        self.transfers.insert(transfer.clone());
+        self.transfer_runner.start(transfer.clone());
+        
+        Ok(transfer)
+    }
+    
+    /// Handle what the transfers need and how they progress
+    /// 
+    /// Fetched files are recorded once they're complete
+    async fn handle_transfer_event(&mut self, event: TransferEvent) {
+        match event {
+            TransferEvent::Request { peer_id, request, reply } => {
+                let request_id = self.swarm.behaviour_mut().file.send_request(&peer_id, *request);
+                self.file_requests.insert(request_id, reply);
+            }
+            TransferEvent::Updated(transfer) => {
+                if transfer.status.is_finished() {
+                    println!("Transfer {} {}", transfer.id, transfer.status);
+                }
+                
+                if transfer.status == TransferStatus::Completed && transfer.direction == TransferDirection::Fetch {
+                    if let Some(md5) = &transfer.md5 {
+                        Self::record_file(self.db.clone(), &transfer.local_path, md5).await;
+                    }
+                }
+                
+                self.transfers.insert((*transfer).clone());
+                self.emit(NodeEvent::TransferUpdated(transfer));
+            }
+        }
+    }
+    
+    /// Handle file protocol events
+    /// 
+    /// Files that peers send to us are recorded once they're complete
+    async fn handle_file_event(&mut self, event: request_response::Event<FileRequest, FileResponse>) {
+        match event {
+            request_response::Event::Message { peer, message } => match message {
+                request_response::Message::Request { request, channel, .. } => {
+                    let folder = self.transfer_folder.clone();
+                    let db = self.db.clone();
+                    let file_responder = self.file_responder.clone();
+                    
+                    // Whole files are hashed, the event loop doesn't wait for it
+                    tokio::spawn(async move {
+                        let completed_md5 = match &request.action {
+                            FileAction::Complete { md5, .. } => Some(md5.clone()),
+                            _ => None,
+                        };
+                        
+                        let response = FileResponse::respond(&folder, request).await;
+                        if let (Some(md5), FileResponse::Completed { path }) = (completed_md5, &response) {
+                            println!("Received '{}' from peer {peer}", path.display());
+                            Self::record_file(db, path, &md5).await;
+                        }
+                        
+                        let _ = file_responder.send((peer, channel, response));
+                    }.with_current_subscriber());
+                }
+                request_response::Message::Response { request_id, response } => {
+                    if let Some(reply) = self.file_requests.remove(&request_id) {
+                        let _ = reply.send(Ok(response));
+                    }
+                }
+            },
+            request_response::Event::OutboundFailure { peer, request_id, error } => {
+                tracing::warn!("File request to peer {peer} failed: {error}");
+                
+                if let Some(reply) = self.file_requests.remove(&request_id) {
+                    let _ = reply.send(Err(error.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    /// Send the response to a file request
+    /// 
+    /// 
+    fn send_file_response(&mut self, (peer, channel, response): FileReply) {
+        if self.swarm
+            .behaviour_mut().file
+            .send_response(channel, response)
+            .is_err() {
+            tracing::warn!("Couldn't respond the file request of peer {peer}");
+        }
+    }
+    
+    /// Record a complete file on the database
+    /// 
+    /// 
+    async fn record_file(db: Option<DatabaseConnection>, path: &Path, md5: &str) {
+        let db = match db {
+            Some(db) => db,
+            None => return,
+        };
+        
+        let size = match tokio::fs::metadata(path).await {
+            Ok(metadata) => metadata.len(),
+            Err(err) => {
+                tracing::warn!("Couldn't record '{}': {err}", path.display());
+                return;
+            }
+        };
+        
+        if let Err(err) = TransferController::new(db).record_file(path, size, md5).await {
+            tracing::warn!("Couldn't record '{}': {err}", path.display());
+        }
     }
     
-    pub async fn start(&mut self) -> Result<(), Box<dyn Error>> {
+    /// Send an event to the handles
+    /// 
+    /// It's fine if nobody is listening
+    fn emit(&self, event: NodeEvent) {
+        let _ = self.events.send(event);
+    }
+    
+    /// Listen
+    /// 
+    /// Listen on all interfaces, relay nodes also listen on the configured port
+    fn listen(&mut self) -> Result<(), Box<dyn Error>> {
         let port = self.parameters.get_port();
         
         // Relay
-        if self.parameters.relay {
+        if self.parameters.relay && self.transports.tcp() {
             // Listen on all interfaces
             let listen_addr_tcp = Multiaddr::empty()
                 .with(match self.parameters.use_ipv6 {
@@ -165,8 +1600,11 @@ impl Node {
                     None => Protocol::Ip4(Ipv4Addr::UNSPECIFIED),
                 })
                 .with(Protocol::Tcp(port));
-            self.swarm.listen_on(listen_addr_tcp)?;
-            
+            self.listeners.push(self.swarm.listen_on(listen_addr_tcp)?);
+        }
+        
+        // QUIC connections can't be protected with the swarm key, it's disabled with it
+        if self.parameters.relay && self.transports.quic() {
             let listen_addr_quic = Multiaddr::empty()
                 .with(match self.parameters.use_ipv6 {
                     Some(true) => Protocol::Ip6(Ipv6Addr::UNSPECIFIED),
@@ -176,128 +1614,422 @@ impl Node {
                 .with(Protocol::Udp(port))
                 .with(Protocol::QuicV1);
             
-            self.swarm.listen_on(listen_addr_quic)?;
+            self.listeners.push(self.swarm.listen_on(listen_addr_quic)?);
         }
         
-        // Read lines from stdin
-        let mut stdin = io::BufReader::new(io::stdin()).lines();
-        
         // Listen on all interfaces and whatever port the OS assigns
-        self.swarm.listen_on("/ip4/0.0.0.0/udp/0/quic-v1".parse()?)?;
-        self.swarm.listen_on("/ip4/0.0.0.0/tcp/0".parse()?)?;
+        if self.transports.quic() {
+            self.listeners.push(self.swarm.listen_on("/ip4/0.0.0.0/udp/0/quic-v1".parse()?)?);
+        }
+        if self.transports.tcp() {
+            self.listeners.push(self.swarm.listen_on("/ip4/0.0.0.0/tcp/0".parse()?)?);
+            
+            // Autonat server
+            self.listeners.push(self.swarm.listen_on(
+                Multiaddr::empty()
+                    .with(Protocol::Ip4(Ipv4Addr::UNSPECIFIED))
+                    .with(Protocol::Tcp(0)),
+            )?);
+        }
         
-        // Autonat server
-        self.swarm.listen_on(
-            Multiaddr::empty()
-                .with(Protocol::Ip4(Ipv4Addr::UNSPECIFIED))
-                .with(Protocol::Tcp(0)),
-        )?;
+        Ok(())
+    }
+    
+    /// Spawn the node on its own task
+    /// 
+    /// The returned handle is used to send commands to the node and to receive its events
+    pub fn spawn(mut self) -> Result<NodeHandle, Box<dyn Error>> {
+        self.listen()?;
         
-        println!("Enter messages via STDIN and they will be sent to connected peers using Gossipsub");
+        // Find peers outside of the local network
+        self.bootstrap()?;
         
-        // Kick it off
-        block_on(async {
-            loop {
-                select! {
-                    Ok(Some(line)) = stdin.next_line() => {
-                        if let Err(e) = self.swarm
-                            .behaviour_mut().gossipsub
-                            .publish(self.topic.clone(), line.as_bytes()) {
-                            println!("Publish error: {e:?}");
-                        }
+        if let Some(address) = self.parameters.metrics_address {
+            let server = metrics::serve(self.metrics.registry(), address)?;
+            self.metrics_server = Some(server.handle());
+            tokio::spawn(server);
+            
+            println!("Metrics served on http://{address}/metrics");
+        }
+        
+        self.task_recorder = self.db.clone().map(TaskRecorder::spawn);
+        
+        let (commands, receiver) = mpsc::channel(COMMAND_BUFFER);
+        let handle = NodeHandle::new(
+            self.local_key.public().to_peer_id(),
+            commands,
+            self.events.clone(),
+        );
+        
+        match self.log_dispatch.clone() {
+            Some(dispatch) => tokio::spawn(self.run(receiver).with_subscriber(dispatch)),
+            None => tokio::spawn(self.run(receiver)),
+        };
+        
+        Ok(handle)
+    }
+    
+    /// Event loop
+    /// 
+    /// Runs until the node is shut down or every handle is dropped
+    async fn run(mut self, mut commands: mpsc::Receiver<NodeCommand>) {
+        let mut bootstrap_timer = tokio::time::interval(Duration::from_secs(self.parameters.bootstrap_interval.max(1)));
+        // The first tick is immediate and we've just bootstrapped
+        bootstrap_timer.tick().await;
+        
+        let mut peer_book_timer = tokio::time::interval(PEER_BOOK_FLUSH_INTERVAL);
+        peer_book_timer.tick().await;
+        
+        // The sampler runs on the background, every new sample is announced
+        let mut resource_updates = self.resources.clone();
+        let mut history_timer = tokio::time::interval(COMPACTION_INTERVAL);
+        
+        let mut access_list_timer = tokio::time::interval(ACCESS_LIST_SYNC_INTERVAL);
+        access_list_timer.tick().await;
+        
+        let mut heartbeat_timer = tokio::time::interval(self.liveness.config.heartbeat_interval);
+        
+        let mut liveness_timer = tokio::time::interval(LIVENESS_CHECK_INTERVAL);
+        liveness_timer.tick().await;
+        
+        let mut election_timer = tokio::time::interval(ELECTION_TICK_INTERVAL);
+        
+        // Known peers from previous runs
+        self.load_peer_book().await;
+        self.load_access_list().await;
+        self.redial_known_peers();
+        self.register_server_node().await;
+        self.register_local_node().await;
+        
+        let mut registry_timer = tokio::time::interval(REGISTRY_SYNC_INTERVAL);
+        registry_timer.tick().await;
+        
+        tracing::info!("Node {} started", self.local_key.public().to_peer_id());
+        
+        let reply = loop {
+            select! {
+                _ = bootstrap_timer.tick() => {
+                    if let Err(err) = self.bootstrap() {
+                        tracing::warn!("Kademlia bootstrap error: {err}");
                     }
-                    event = self.swarm.select_next_some() => match event {
-                        SwarmEvent::Behaviour(event) => {
-                            println!("{event:?}");
-                            
-                            match event {
-                                // MyBehavior event is an enum created with select!
-                                // MDNS
-                                MyBehaviorEvent::Mdns(event) => {
-                                    match event {
-                                        mdns::Event::Discovered(list) => {
-                                            for (peer_id, multiaddr) in list {
-                                                println!("mDNS discovered a new peer: {peer_id}");
-                                                
-                                                // I need to fetch node name and information
-                                                // Extract IP address from multiaddr
-                                                let components: Vec<_> = multiaddr.iter().collect();
-                                                match components[0] {
-                                                    Protocol::Ip4(ipv4_addr) => println!("IP address: {}", ipv4_addr),
-                                                    Protocol::Ip6(ipv6_addr) => println!("IP address: {}", ipv6_addr),
-                                                    _ => println!("Unsupported protocol"),
-                                                }
-                                                
-                                                self.swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer_id);
-                                            }
-                                        }
-                                        mdns::Event::Expired(list) => {
-                                            for (peer_id, _multiaddr) in list {
-                                                println!("mDNS discover peer has expired: {peer_id}");
-                                                self.swarm.behaviour_mut().gossipsub.remove_explicit_peer(&peer_id);
-                                            }
-                                        }
+                }
+                _ = peer_book_timer.tick() => {
+                    self.flush_peer_book().await;
+                }
+                _ = access_list_timer.tick() => {
+                    self.sync_access_list().await;
+                }
+                _ = heartbeat_timer.tick() => {
+                    self.send_heartbeat();
+                }
+                _ = liveness_timer.tick() => {
+                    self.check_liveness().await;
+                }
+                _ = registry_timer.tick() => {
+                    self.sync_registry(None);
+                }
+                _ = election_timer.tick() => {
+                    let messages = self.election.tick(Utc::now());
+                    self.publish_election(messages);
+                }
+                Some(resources) = resource_updates.changed() => {
+                    self.announce_resources(resources).await;
+                }
+                _ = history_timer.tick() => {
+                    self.compact_resource_history().await;
+                }
+                Some(event) = self.task_events.recv() => {
+                    self.handle_runner_event(event).await;
+                }
+                Some(event) = self.transfer_events.recv() => {
+                    self.handle_transfer_event(event).await;
+                }
+                Some(reply) = self.file_replies.recv() => {
+                    self.send_file_response(reply);
+                }
+                command = commands.recv() => match command {
+                    Some(NodeCommand::Shutdown { reply }) => break Some(reply),
+                    Some(command) => self.handle_command(command).await,
+                    // Nobody can talk to the node anymore
+                    None => break None,
+                },
+                event = self.swarm.select_next_some() => {
+                    self.handle_swarm_event(event).await;
+                }
+            }
+        };
+        
+        self.shutdown().await;
+        
+        // Close the connections before replying
+        drop(self);
+        if let Some(reply) = reply {
+            let _ = reply.send(());
+        }
+    }
+    
+    /// Handle a command sent through a node handle
+    /// 
+    /// 
+    async fn handle_command(&mut self, command: NodeCommand) {
+        match command {
+            NodeCommand::Publish { payload, reply } => {
+                let result = self.publish(*payload).map_err(|err| err.to_string());
+                let _ = reply.send(result);
+            }
+            NodeCommand::Dial { address, reply } => {
+                let result = self.swarm.dial(address).map_err(|err| err.to_string());
+                let _ = reply.send(result);
+            }
+            NodeCommand::ListPeers { reply } => {
+                let _ = reply.send(self.swarm.connected_peers().cloned().collect());
+            }
+            NodeCommand::ListenAddresses { reply } => {
+                let _ = reply.send(self.swarm.listeners().cloned().collect());
+            }
+            NodeCommand::PeerScore { peer_id, reply } => {
+                let score = self.swarm
+                    .behaviour()
+                    .gossipsub
+                    .as_ref()
+                    .and_then(|gossipsub| gossipsub.peer_score(&peer_id));
+                let _ = reply.send(score);
+            }
+            NodeCommand::ListPeerRules { reply } => {
+                let _ = reply.send(self.access_list.rules());
+            }
+            NodeCommand::SetPeerRule { rule, reply } => {
+                let result = self.set_peer_rule(rule).await.map_err(|err| err.to_string());
+                let _ = reply.send(result);
+            }
+            NodeCommand::RemovePeerRule { peer_id, reply } => {
+                let result = self.remove_peer_rule(peer_id).await.map_err(|err| err.to_string());
+                let _ = reply.send(result);
+            }
+            NodeCommand::SubmitTask { peer_id, spec, reply } => {
+                let _ = reply.send(self.submit_task(peer_id, spec).await);
+            }
+            NodeCommand::CancelTask { task_id, reply } => {
+                let _ = reply.send(self.cancel_task(&task_id));
+            }
+            NodeCommand::ListServerNodes { reply } => {
+                let _ = reply.send(self.registry.nodes());
+            }
+            NodeCommand::ListRegistry { reply } => {
+                let _ = reply.send(self.registry.entries());
+            }
+            NodeCommand::ListTasks { reply } => {
+                let _ = reply.send(self.tasks.tasks());
+            }
+            NodeCommand::GetTask { task_id, reply } => {
+                let _ = reply.send(self.tasks.get(&task_id).cloned());
+            }
+            NodeCommand::SendFile { peer_id, path, rate_limit, reply } => {
+                let _ = reply.send(self.send_file(peer_id, path, rate_limit));
+            }
+            NodeCommand::FetchFile { peer_id, remote_path, output, rate_limit, reply } => {
+                let _ = reply.send(self.fetch_file(peer_id, remote_path, output, rate_limit));
+            }
+            NodeCommand::ListTransfers { reply } => {
+                let _ = reply.send(self.transfers.transfers());
+            }
+            NodeCommand::GetTransfer { transfer_id, reply } => {
+                let _ = reply.send(self.transfers.get(&transfer_id).cloned());
+            }
+            NodeCommand::SetStatus { status, reply } => {
+                let _ = reply.send(self.set_status(status).await);
+            }
+            NodeCommand::GetLiveness { reply } => {
+                let _ = reply.send((self.status.clone(), self.liveness.peers()));
+            }
+            NodeCommand::GetElection { reply } => {
+                let _ = reply.send(self.election.status(Utc::now()));
+            }
+            NodeCommand::Subscribe { topic, reply } => {
+                let result = match self.swarm.behaviour_mut().gossipsub.as_mut() {
+                    Some(gossipsub) => gossipsub
+                        .subscribe(&gossipsub::IdentTopic::new(topic))
+                        .map_err(|err| err.to_string()),
+                    None => Err("Gossipsub is disabled".to_string()),
+                };
+                let _ = reply.send(result);
+            }
+            // Handled by the event loop
+            NodeCommand::Shutdown { .. } => {}
+        }
+    }
+    
+    /// Handle swarm events
+    /// 
+    /// 
+    async fn handle_swarm_event(&mut self, event: SwarmEvent<MyBehaviorEvent>) {
+        self.metrics.record(&event);
+        
+        match event {
+            SwarmEvent::Behaviour(event) => {
+                println!("{event:?}");
+                
+                match &event {
+                    MyBehaviorEvent::Dcutr(event) => self.metrics.record(event),
+                    MyBehaviorEvent::Gossipsub(event) => self.metrics.record(event),
+                    MyBehaviorEvent::Identify(event) => self.metrics.record(event),
+                    MyBehaviorEvent::Kademlia(event) => self.metrics.record(event),
+                    MyBehaviorEvent::Ping(event) => self.metrics.record(event),
+                    MyBehaviorEvent::Relay(event) => self.metrics.record(event),
+                    _ => {}
+                }
+                
+                match event {
+                    // MyBehavior event is an enum created with select!
+                    // MDNS
+                    MyBehaviorEvent::Mdns(event) => {
+                        match event {
+                            mdns::Event::Discovered(list) => {
+                                for (peer_id, multiaddr) in list {
+                                    println!("mDNS discovered a new peer: {peer_id}");
+                                    
+                                    // I need to fetch node name and information
+                                    // Extract IP address from multiaddr
+                                    let components: Vec<_> = multiaddr.iter().collect();
+                                    match components[0] {
+                                        Protocol::Ip4(ipv4_addr) => println!("IP address: {}", ipv4_addr),
+                                        Protocol::Ip6(ipv6_addr) => println!("IP address: {}", ipv6_addr),
+                                        _ => println!("Unsupported protocol"),
                                     }
+                                    
+                                    self.peer_book.add_address(peer_id, multiaddr);
+                                    if self.is_denied(&peer_id) {
+                                        continue;
+                                    }
+                                    
+                                    self.add_explicit_peer(&peer_id);
+                                    self.request_server_node(peer_id);
+                                    self.emit(NodeEvent::PeerDiscovered(peer_id));
                                 }
-                                // Gossipsub
-                                MyBehaviorEvent::Gossipsub(event) => {
-                                    match event {
-                                        gossipsub::Event::Message {
-                                            propagation_source: peer_id,
-                                            message_id: id,
-                                            message,
-                                        } => {
-                                            let topic = message.topic;
-                                            let topic_name = topic.to_string();
-                                            let topic_name = topic_name.as_str();
-                                            
-                                            match topic_name {
-                                                "chat-net" => {
-                                                    println!(
-                                                        "Got message: '{}' with id: {id} from peer: {peer_id}",
-                                                        String::from_utf8_lossy(&message.data),
-                                                    );
-                                                }
-                                                "test-chat" => {
-                                                    println!("test-chat: {}", String::from_utf8_lossy(&message.data));
-                                                }
-                                                _ => {}
-                                            };
-                                        }
-                                        _ => {}
-                                    };
-                                }
-                                // Add relay nodes
-                                MyBehaviorEvent::Identify(event) => {
-                                    match event {
-                                        identify::Event::Received {
-                                            info,
-                                            ..
-                                        } => {
-                                            let observed_addr = info.observed_addr;
-                                            
-                                            // If we're a relay node, add the peer's address to our swarm
-                                            // If we're not a relay node, we don't need to do anything here
-                                            if self.parameters.relay {
-                                                self.swarm.add_external_address(observed_addr.clone());
-                                            }
-                                        }
-                                        _ => { }
-                                    };
+                            }
+                            mdns::Event::Expired(list) => {
+                                for (peer_id, _multiaddr) in list {
+                                    println!("mDNS discover peer has expired: {peer_id}");
+                                    self.remove_explicit_peer(&peer_id);
+                                    
+                                    // Connected peers are still reachable
+                                    if !self.swarm.is_connected(&peer_id) {
+                                        let status = self.liveness.expired(peer_id);
+                                        self.liveness_changed(peer_id, status).await;
+                                    }
                                 }
-                                _ => {}
                             }
                         }
-                        SwarmEvent::NewListenAddr { listener_id, address, } => {
-                            println!("Local node {} is listening on {address}", listener_id.to_string());
+                    }
+                    // Gossipsub
+                    MyBehaviorEvent::Gossipsub(gossipsub::Event::Message {
+                        propagation_source: peer_id,
+                        message_id: id,
+                        message,
+                    }) => {
+                        let validation = self.validator.validate(&message, &self.access_list, Utc::now());
+                        
+                        // The message is only forwarded once it's accepted
+                        if let Some(gossipsub) = self.swarm.behaviour_mut().gossipsub.as_mut() {
+                            if let Err(err) = gossipsub.report_message_validation_result(&id, &peer_id, validation.acceptance()) {
+                                tracing::warn!("Couldn't report the validation of message {id}: {err}");
+                            }
+                        }
+                        
+                        match validation {
+                            Validation::Hive(message) => self.handle_message(*message).await,
+                            // Topics subscribed through the handle don't carry hive messages
+                            Validation::Topic => self.emit(NodeEvent::TopicMessage {
+                                topic: message.topic.to_string(),
+                                source: message.source,
+                                data: message.data,
+                            }),
+                            Validation::Reject(err) => {
+                                tracing::warn!("Rejected message {id} from peer {peer_id}: {err}");
+                            }
+                            Validation::Ignore(err) => {
+                                tracing::debug!("Ignored message {id} from peer {peer_id}: {err}");
+                            }
+                        };
+                    }
+                    // Add relay nodes
+                    MyBehaviorEvent::Identify(identify::Event::Received {
+                        peer_id,
+                        info,
+                        ..
+                    }) => {
+                        self.peer_book.record_identify(peer_id, &info);
+                        
+                        let observed_addr = info.observed_addr;
+                        
+                        // If we're a relay node, add the peer's address to our swarm
+                        // If we're not a relay node, we don't need to do anything here
+                        if self.parameters.relay {
+                            self.swarm.add_external_address(observed_addr.clone());
+                        }
+                        
+                        // Share the addresses of peers speaking our DHT protocol
+                        if info.protocols.contains(&behavior::KADEMLIA_PROTOCOL) {
+                            for address in info.listen_addrs {
+                                self.add_kademlia_address(&peer_id, address);
+                            }
                         }
-                        _ => {}
+                        
+                        // Fetch its server node
+                        self.request_server_node(peer_id);
+                    }
+                    MyBehaviorEvent::Kademlia(event) => {
+                        self.handle_kademlia_event(event);
+                    }
+                    MyBehaviorEvent::Ping(ping::Event { peer, result: Ok(rtt), .. }) => {
+                        self.peer_book.record_ping(peer, rtt);
+                        
+                        let status = self.liveness.seen(peer, Utc::now());
+                        self.liveness_changed(peer, status).await;
+                    }
+                    MyBehaviorEvent::Ping(ping::Event { peer, result: Err(err), .. }) => {
+                        tracing::debug!("Ping to peer {peer} failed: {err}");
+                        
+                        let status = self.liveness.ping_failed(peer);
+                        self.liveness_changed(peer, status).await;
                     }
+                    MyBehaviorEvent::ServerNode(event) => {
+                        self.handle_server_node_event(event).await;
+                    }
+                    MyBehaviorEvent::File(event) => {
+                        self.handle_file_event(event).await;
+                    }
+                    MyBehaviorEvent::Registry(event) => {
+                        self.handle_registry_event(event).await;
+                    }
+                    MyBehaviorEvent::Task(event) => {
+                        self.handle_task_event(event).await;
+                    }
+                    _ => {}
                 }
             }
-        });
-        
-        Ok(())
+            SwarmEvent::NewListenAddr { listener_id, address, } => {
+                println!("Local node {listener_id} is listening on {address}");
+                tracing::info!("Listening on {address}");
+                self.emit(NodeEvent::Listening(address));
+            }
+            SwarmEvent::ConnectionEstablished { peer_id, num_established, .. } if num_established.get() == 1 => {
+                tracing::info!("Connected to peer {peer_id}");
+                self.emit(NodeEvent::PeerConnected(peer_id));
+                
+                let status = self.liveness.seen(peer_id, Utc::now());
+                self.liveness_changed(peer_id, status).await;
+                
+                // Catch up with the registry of the peer
+                self.sync_registry(Some(peer_id));
+            }
+            SwarmEvent::ConnectionClosed { peer_id, num_established: 0, .. } => {
+                tracing::info!("Disconnected from peer {peer_id}");
+                self.emit(NodeEvent::PeerDisconnected(peer_id));
+                self.fail_tasks_of(peer_id);
+            }
+            _ => {}
+        }
     }
 }
 
@@ -309,6 +2041,9 @@ mod tests {
     // use std::str::FromStr;
 
     use super::*;
+    use crate::test::hive_simulation::{loopback_address, HiveSimulation};
+    use libp2p::{noise, tcp, yamux, SwarmBuilder};
+    use message::MessageKind;
     // use libp2p::identity::Keypair;
     // use libp2p::swarm::Swarm;
     // use libp2p::PeerId;
@@ -320,6 +2055,7 @@ mod tests {
     // use libp2p::Multiaddr;
     // use std::time::Duration;
     
+    use transfer::DEFAULT_CHUNK_SIZE;
     use crate::test::folder::{
 		hive_folder_test_suite::HiveFolderTestSuite,
 		hive_test_folder::HiveTestFolder
@@ -343,6 +2079,28 @@ mod tests {
             port: Some(45829),
             server_address: None,
             server_peer_id: None,
+            bootstrap_peers: Vec::new(),
+            bootstrap_interval: 300,
+            allowed_peers: Vec::new(),
+            blocked_peers: Vec::new(),
+            allowlist: false,
+            no_mdns: false,
+            transport: Transports::Both,
+            keystore: None,
+            swarm_key: None,
+            transfer_folder: None,
+            heartbeat_interval: 30,
+            offline_after: 90,
+            max_ping_failures: 3,
+            subnetwork: None,
+            election_priority: 0,
+            leader_lease: 15,
+            resource_interval: 15,
+            resource_retention: 24,
+            chat: false,
+            metrics_address: None,
+            api_address: None,
+            command: None,
         };
         
         let result = Node::new(parameters).await;
@@ -380,7 +2138,633 @@ mod tests {
     //     node.test_handler.unwrap().save_config().unwrap();
     // }
     
-    // TODO: Test that the chat works, by starting two nodes and sending a private key or something
+    #[tokio::test]
+    async fn test_server_node_of_peer_is_reused() {
+        use entity::{server_node::Entity as ServerNodeEntity, system_core::{self, Entity as SystemCoreEntity}};
+        use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+        use crate::database::mysql_connection;
+        
+        let db = mysql_connection().await.unwrap();
+        let server_node = ServerNode::new().unwrap();
+        
+        // The first receipt inserts the server node
+        let server_node_id = Node::store_server_node(Some(db.clone()), server_node.clone(), None)
+            .await
+            .unwrap()
+            .unwrap();
+        let stored = ServerNodeEntity::find_by_id(server_node_id).one(&db).await.unwrap().unwrap();
+        
+        // The next one updates the same rows
+        let mut received = server_node.clone();
+        received.labels = vec!["gpu".to_string()];
+        let stored_id = Node::store_server_node(Some(db.clone()), received, Some(server_node_id))
+            .await
+            .unwrap();
+        assert_eq!(stored_id, Some(server_node_id));
+        
+        let updated = ServerNodeEntity::find_by_id(server_node_id).one(&db).await.unwrap().unwrap();
+        assert_eq!(updated.server_location_id, stored.server_location_id);
+        assert_eq!(updated.system_resource_id, stored.system_resource_id);
+        assert_eq!(updated.labels, Some("gpu".to_string()));
+        
+        // The cores were reconciled, not inserted again
+        let cores = SystemCoreEntity::find()
+            .filter(system_core::Column::SystemResourceId.eq(stored.system_resource_id))
+            .count(&db)
+            .await
+            .unwrap();
+        assert_eq!(cores as usize, server_node.resources.cpus.len());
+        
+        // A server node that was removed is inserted again
+        ServerNodeEntity::delete_by_id(server_node_id).exec(&db).await.unwrap();
+        let stored_id = Node::store_server_node(Some(db.clone()), server_node, Some(server_node_id))
+            .await
+            .unwrap();
+        assert!(stored_id.is_some_and(|id| id != server_node_id));
+    }
+    
+    /// Parameters of a node listening on random ports
+    /// 
+    /// 
+    fn spawn_parameters(key_seed: u8) -> HiveParameters {
+        HiveParameters {
+            key_seed: Some(key_seed),
+            ..Default::default()
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_node_handle() {
+        // Server nodes are requested from connected peers
+        let first = Node::new(spawn_parameters(121)).await.unwrap().spawn().unwrap();
+        let second = Node::new(spawn_parameters(122)).await.unwrap().spawn().unwrap();
+        let mut first_events = first.events();
+        
+        assert!(first.subscribe("test-topic").await.unwrap());
+        assert!(!first.subscribe("test-topic").await.unwrap());
+        
+        let connected = tokio::time::timeout(Duration::from_secs(30), async {
+            // Dial the second node on loopback
+            let address = loopback_address(&second).await.unwrap();
+            first.dial(address).await.unwrap();
+            
+            loop {
+                if let Ok(NodeEvent::PeerConnected(peer_id)) = first_events.recv().await {
+                    if peer_id == second.peer_id() {
+                        return;
+                    }
+                }
+            }
+        })
+        .await;
+        assert!(connected.is_ok(), "The nodes didn't connect");
+        assert!(first.peers().await.unwrap().contains(&second.peer_id()));
+        
+        // Commands fail once the node is stopped
+        first.shutdown().await.unwrap();
+        assert!(first.peers().await.is_err());
+        
+        second.shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_offline_announcement() {
+        let leaving = Node::new(spawn_parameters(123)).await.unwrap().spawn().unwrap();
+        let staying = Node::new(spawn_parameters(124)).await.unwrap().spawn().unwrap();
+        let mut staying_events = staying.events();
+        
+        let result = tokio::time::timeout(Duration::from_secs(30), async {
+            let address = loopback_address(&leaving).await.unwrap();
+            staying.dial(address).await.unwrap();
+            
+            loop {
+                if let Ok(NodeEvent::PeerConnected(peer_id)) = staying_events.recv().await {
+                    if peer_id == leaving.peer_id() {
+                        break;
+                    }
+                }
+            }
+            
+            // Give the nodes time to exchange their subscriptions
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            leaving.shutdown().await.unwrap();
+            
+            // The peer went online when it connected
+            loop {
+                if let Ok(NodeEvent::PeerStatusChanged { peer_id, status }) = staying_events.recv().await {
+                    if status != ServerStatus::Online {
+                        return (peer_id, status);
+                    }
+                }
+            }
+        })
+        .await;
+        
+        assert_eq!(result.ok(), Some((leaving.peer_id(), ServerStatus::Offline)));
+        
+        staying.shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_maintenance_is_sticky() {
+        // Silent peers go offline quickly
+        let parameters = |key_seed| HiveParameters {
+            heartbeat_interval: 1,
+            offline_after: 1,
+            ..spawn_parameters(key_seed)
+        };
+        let leaving = Node::new(parameters(141)).await.unwrap().spawn().unwrap();
+        let staying = Node::new(parameters(142)).await.unwrap().spawn().unwrap();
+        let mut staying_events = staying.events();
+        
+        let result = tokio::time::timeout(Duration::from_secs(30), async {
+            let address = loopback_address(&leaving).await.unwrap();
+            staying.dial(address).await.unwrap();
+            
+            loop {
+                if let Ok(NodeEvent::PeerStatusChanged { peer_id, status }) = staying_events.recv().await {
+                    if peer_id == leaving.peer_id() && status == ServerStatus::Online {
+                        break;
+                    }
+                }
+            }
+            
+            // Give the nodes time to exchange their subscriptions
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            leaving.set_status(ServerStatus::Maintenance).await.unwrap();
+            
+            loop {
+                if let Ok(NodeEvent::PeerStatusChanged { peer_id, status }) = staying_events.recv().await {
+                    if peer_id == leaving.peer_id() {
+                        return status;
+                    }
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.ok(), Some(ServerStatus::Maintenance));
+        
+        let (status, _) = leaving.liveness().await.unwrap();
+        assert_eq!(status, ServerStatus::Maintenance);
+        
+        // Neither the shutdown nor the silence take it out of maintenance
+        leaving.shutdown().await.unwrap();
+        tokio::time::sleep(LIVENESS_CHECK_INTERVAL + Duration::from_secs(2)).await;
+        
+        let (_, peers) = staying.liveness().await.unwrap();
+        let peer = peers.into_iter().find(|peer| peer.peer_id == leaving.peer_id()).unwrap();
+        assert_eq!(peer.status, ServerStatus::Maintenance);
+        
+        staying.shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_leader_election() {
+        let parameters = |key_seed| HiveParameters {
+            subnetwork: Some("test-election".to_string()),
+            ..spawn_parameters(key_seed)
+        };
+        let first = Node::new(parameters(144)).await.unwrap().spawn().unwrap();
+        let second = Node::new(parameters(145)).await.unwrap().spawn().unwrap();
+        let first_events = first.events();
+        let second_events = second.events();
+        
+        let address = loopback_address(&first).await.unwrap();
+        second.dial(address).await.unwrap();
+        
+        // Both nodes follow the same leader, each one led itself until they connected
+        let leaders = tokio::time::timeout(Duration::from_secs(30), async {
+            loop {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                
+                let first_leader = first.leader().await.unwrap().map(|leader| leader.peer_id);
+                let second_leader = second.leader().await.unwrap().map(|leader| leader.peer_id);
+                if let (Some(first_leader), Some(second_leader)) = (first_leader, second_leader) {
+                    if first_leader == second_leader {
+                        return first_leader;
+                    }
+                }
+            }
+        })
+        .await;
+        let leader = leaders.expect("The nodes didn't agree on a leader");
+        
+        // The follower elects itself once the leader is gone
+        let (leaving, staying, mut staying_events) = match leader == first.peer_id() {
+            true => (first, second.clone(), second_events),
+            false => (second, first.clone(), first_events),
+        };
+        leaving.shutdown().await.unwrap();
+        
+        // Earlier changes are still queued
+        let reelected = tokio::time::timeout(Duration::from_secs(30), async {
+            loop {
+                if let Ok(NodeEvent::LeaderChanged(Some(leader))) = staying_events.recv().await {
+                    if leader.peer_id == staying.peer_id() {
+                        break;
+                    }
+                }
+            }
+        })
+        .await;
+        assert!(reelected.is_ok(), "The staying node wasn't elected");
+        assert_eq!(staying.leader().await.unwrap().map(|leader| leader.peer_id), Some(staying.peer_id()));
+        
+        staying.shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_registry_is_replicated() {
+        let first = Node::new(spawn_parameters(147)).await.unwrap().spawn().unwrap();
+        let second = Node::new(spawn_parameters(148)).await.unwrap().spawn().unwrap();
+        
+        let address = loopback_address(&first).await.unwrap();
+        second.dial(address).await.unwrap();
+        
+        // Both nodes list each other
+        let expected = {
+            let mut peers = vec![first.peer_id(), second.peer_id()];
+            peers.sort();
+            peers
+        };
+        let replicated = tokio::time::timeout(Duration::from_secs(30), async {
+            loop {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                
+                let first_nodes: Vec<_> = first.server_nodes().await.unwrap().into_iter().map(|(peer_id, _)| peer_id).collect();
+                let second_nodes: Vec<_> = second.server_nodes().await.unwrap().into_iter().map(|(peer_id, _)| peer_id).collect();
+                if first_nodes == expected && second_nodes == expected {
+                    break;
+                }
+            }
+        })
+        .await;
+        assert!(replicated.is_ok(), "The registries didn't converge");
+        
+        // Changes are published
+        first.set_status(ServerStatus::Maintenance).await.unwrap();
+        let updated = tokio::time::timeout(Duration::from_secs(30), async {
+            loop {
+                let nodes = second.server_nodes().await.unwrap();
+                if nodes.iter().any(|(peer_id, node)| *peer_id == first.peer_id() && node.status == ServerStatus::Maintenance) {
+                    break;
+                }
+                
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+        })
+        .await;
+        assert!(updated.is_ok(), "The status change wasn't replicated");
+        
+        first.shutdown().await.unwrap();
+        second.shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_misbehaving_peer_is_graylisted() {
+        let node = Node::new(spawn_parameters(125)).await.unwrap().spawn().unwrap();
+        let mut node_events = node.events();
+        
+        // A peer that speaks gossipsub but publishes junk on the hive topics
+        let key = behavior::generate_ed25519(126).unwrap();
+        let mut misbehaving = SwarmBuilder::with_existing_identity(key)
+            .with_tokio()
+            .with_tcp(
+                tcp::Config::default(),
+                noise::Config::new,
+                yamux::Config::default,
+            )
+            .unwrap()
+            .with_behaviour(|key| {
+                gossipsub::Behaviour::<gossipsub::IdentityTransform>::new(
+                    gossipsub::MessageAuthenticity::Signed(key.clone()),
+                    gossipsub::Config::default(),
+                )
+                .unwrap()
+            })
+            .unwrap()
+            .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+            .build();
+        let misbehaving_peer_id = *misbehaving.local_peer_id();
+        misbehaving.behaviour_mut().subscribe(&MessageKind::Chat.topic()).unwrap();
+        
+        let graylist_threshold = validation::peer_score_thresholds().graylist_threshold;
+        let graylisted = tokio::time::timeout(Duration::from_secs(30), async {
+            let address = loopback_address(&node).await.unwrap();
+            misbehaving.dial(address).unwrap();
+            
+            let mut junk_timer = tokio::time::interval(Duration::from_millis(200));
+            let mut sent = 0;
+            loop {
+                select! {
+                    _ = misbehaving.select_next_some() => {}
+                    _ = junk_timer.tick() => {
+                        // Fails until the node subscription is known
+                        let junk = format!("Junk message {sent}").into_bytes();
+                        if misbehaving.behaviour_mut().publish(MessageKind::Chat.topic(), junk).is_ok() {
+                            sent += 1;
+                        }
+                        
+                        if let Some(score) = node.peer_score(misbehaving_peer_id).await.unwrap() {
+                            if score < graylist_threshold {
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+        })
+        .await;
+        assert!(graylisted.is_ok(), "The misbehaving peer wasn't graylisted");
+        
+        // Even valid messages of a graylisted peer are ignored
+        let message = HiveMessage::new(misbehaving_peer_id, HivePayload::Chat("Hello".to_string()));
+        misbehaving.behaviour_mut().publish(message.topic(), message.encode().unwrap()).unwrap();
+        let delivered = tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                select! {
+                    _ = misbehaving.select_next_some() => {}
+                    event = node_events.recv() => {
+                        if let Ok(NodeEvent::Message(message)) = event {
+                            if message.sender == misbehaving_peer_id {
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+        })
+        .await;
+        assert!(delivered.is_err(), "A message of a graylisted peer was delivered");
+        
+        node.shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_private_network() {
+        let test_folder = HiveTestFolder::default();
+        test_folder.create().unwrap();
+        let mut path = std::path::PathBuf::from(&test_folder.path);
+        path.push(format!("private_network_{}", nanoid::nanoid!(6)));
+        path.push("swarm.key");
+        swarm_key::SwarmKeyFile::new(path.clone()).generate(false).unwrap();
+        
+        let private_parameters = |key_seed| HiveParameters {
+            swarm_key: Some(path.clone()),
+            ..spawn_parameters(key_seed)
+        };
+        let node = Node::new(private_parameters(127)).await.unwrap().spawn().unwrap();
+        let member = Node::new(private_parameters(128)).await.unwrap().spawn().unwrap();
+        let mut member_events = member.events();
+        
+        // A node of the public network
+        let mut outsider = NodeBuilder::new(behavior::generate_ed25519(129).unwrap())
+            .mdns(false)
+            .build_swarm()
+            .unwrap();
+        
+        let result = tokio::time::timeout(Duration::from_secs(30), async {
+            let address = loopback_address(&node).await.unwrap();
+            
+            // Holders of the key can connect
+            member.dial(address.clone()).await.unwrap();
+            loop {
+                if let Ok(NodeEvent::PeerConnected(peer_id)) = member_events.recv().await {
+                    if peer_id == node.peer_id() {
+                        break;
+                    }
+                }
+            }
+            
+            // Everybody else can't
+            outsider.dial(address).unwrap();
+            loop {
+                match outsider.select_next_some().await {
+                    SwarmEvent::ConnectionEstablished { .. } => return false,
+                    SwarmEvent::OutgoingConnectionError { .. } => return true,
+                    _ => {}
+                }
+            }
+        })
+        .await;
+        
+        assert_eq!(result.ok(), Some(true), "The outsider connected to the private network");
+        assert!(!node.peers().await.unwrap().contains(outsider.local_peer_id()));
+        
+        node.shutdown().await.unwrap();
+        member.shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_blocked_peer_is_disconnected() {
+        let node = Node::new(spawn_parameters(131)).await.unwrap().spawn().unwrap();
+        let peer = Node::new(spawn_parameters(132)).await.unwrap().spawn().unwrap();
+        let mut node_events = node.events();
+        
+        let result = tokio::time::timeout(Duration::from_secs(30), async {
+            let address = loopback_address(&node).await.unwrap();
+            peer.dial(address.clone()).await.unwrap();
+            loop {
+                if let Ok(NodeEvent::PeerConnected(peer_id)) = node_events.recv().await {
+                    if peer_id == peer.peer_id() {
+                        break;
+                    }
+                }
+            }
+            
+            node.set_peer_rule(AccessRule::block(peer.peer_id())).await.unwrap();
+            loop {
+                if let Ok(NodeEvent::PeerDisconnected(peer_id)) = node_events.recv().await {
+                    if peer_id == peer.peer_id() {
+                        break;
+                    }
+                }
+            }
+            
+            address
+        })
+        .await;
+        let address = result.expect("The blocked peer wasn't disconnected");
+        
+        // It can't connect again
+        peer.dial(address).await.unwrap();
+        let reconnected = tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                if let Ok(NodeEvent::PeerConnected(peer_id)) = node_events.recv().await {
+                    if peer_id == peer.peer_id() {
+                        return;
+                    }
+                }
+            }
+        })
+        .await;
+        assert!(reconnected.is_err(), "The blocked peer connected again");
+        assert_eq!(node.peer_rules().await.unwrap(), vec![AccessRule::block(peer.peer_id())]);
+        
+        node.shutdown().await.unwrap();
+        peer.shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_remote_task() {
+        let owner = Node::new(spawn_parameters(134)).await.unwrap().spawn().unwrap();
+        let runner = Node::new(spawn_parameters(135)).await.unwrap().spawn().unwrap();
+        let mut owner_events = owner.events();
+        
+        let result = tokio::time::timeout(Duration::from_secs(30), async {
+            let address = loopback_address(&runner).await.unwrap();
+            owner.dial(address).await.unwrap();
+            loop {
+                if let Ok(NodeEvent::PeerConnected(peer_id)) = owner_events.recv().await {
+                    if peer_id == runner.peer_id() {
+                        break;
+                    }
+                }
+            }
+            
+            let mut spec = TaskSpec::new("sh", &["-c", "echo \"$GREETING\"; exit 2"]);
+            spec.env.insert("GREETING".to_string(), "Hello".to_string());
+            let task = owner.submit_task(runner.peer_id(), spec).await.unwrap();
+            assert_eq!(task.status, TaskStatus::Pending);
+            
+            let mut output = String::new();
+            loop {
+                match owner_events.recv().await {
+                    Ok(NodeEvent::TaskOutput { task_id, chunk }) if task_id == task.id => output.push_str(&chunk.data),
+                    Ok(NodeEvent::TaskUpdated(task)) if task.status.is_finished() => return (*task, output),
+                    _ => {}
+                }
+            }
+        })
+        .await;
+        let (task, output) = result.expect("The task didn't finish");
+        
+        assert_eq!(task.status, TaskStatus::Failed);
+        assert_eq!(task.exit_code, Some(2));
+        assert_eq!(output, "Hello\n");
+        assert_eq!(owner.task(&task.id).await.unwrap().unwrap().output.len(), task.output.len());
+        
+        // The runner keeps its own record
+        let ran = runner.task(&task.id).await.unwrap().unwrap();
+        assert_eq!(ran.owner, owner.peer_id());
+        assert_eq!(ran.status, TaskStatus::Failed);
+        
+        // Tasks that can't be started are rejected
+        let task = owner.submit_task(runner.peer_id(), TaskSpec::new("hive-missing-program", &[])).await.unwrap();
+        let rejected = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let Ok(NodeEvent::TaskUpdated(updated)) = owner_events.recv().await {
+                    if updated.id == task.id {
+                        return updated.status;
+                    }
+                }
+            }
+        })
+        .await;
+        assert_eq!(rejected.ok(), Some(TaskStatus::Rejected));
+        
+        owner.shutdown().await.unwrap();
+        runner.shutdown().await.unwrap();
+    }
     
-    // TODO: Test that the relay works
+    /// Wait until a transfer finishes
+    /// 
+    /// 
+    async fn finished_transfer(events: &mut broadcast::Receiver<NodeEvent>, transfer_id: &str) -> TransferInfo {
+        let result = tokio::time::timeout(Duration::from_secs(30), async {
+            loop {
+                if let Ok(NodeEvent::TransferUpdated(transfer)) = events.recv().await {
+                    if transfer.id == transfer_id && transfer.status.is_finished() {
+                        return *transfer;
+                    }
+                }
+            }
+        })
+        .await;
+        
+        result.expect("The transfer didn't finish")
+    }
+    
+    #[tokio::test]
+    async fn test_file_transfer() {
+        let test_folder = HiveTestFolder::default();
+        let mut root = PathBuf::from(&test_folder.path);
+        root.push(format!("transfer_{}", nanoid::nanoid!(6)));
+        let parameters = |key_seed, folder: &str| HiveParameters {
+            key_seed: Some(key_seed),
+            transfer_folder: Some(root.join(folder)),
+            ..Default::default()
+        };
+        
+        let sender = Node::new(parameters(138, "sender")).await.unwrap().spawn().unwrap();
+        let receiver = Node::new(parameters(139, "receiver")).await.unwrap().spawn().unwrap();
+        let mut sender_events = sender.events();
+        
+        let connected = tokio::time::timeout(Duration::from_secs(30), async {
+            let address = loopback_address(&receiver).await.unwrap();
+            sender.dial(address).await.unwrap();
+            loop {
+                if let Ok(NodeEvent::PeerConnected(peer_id)) = sender_events.recv().await {
+                    if peer_id == receiver.peer_id() {
+                        break;
+                    }
+                }
+            }
+        })
+        .await;
+        assert!(connected.is_ok(), "The nodes didn't connect");
+        
+        // A few chunks, the last one is shorter
+        let data: Vec<u8> = (0..700 * 1024).map(|i: u32| (i % 251) as u8).collect();
+        let source = root.join("data.bin");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(&source, &data).unwrap();
+        
+        let transfer = sender.send_file(receiver.peer_id(), source.clone(), None).await.unwrap();
+        let sent = finished_transfer(&mut sender_events, &transfer.id).await;
+        assert_eq!(sent.status, TransferStatus::Completed, "{:?}", sent.error);
+        assert_eq!(sent.transferred, data.len() as u64);
+        assert_eq!(std::fs::read(root.join("receiver/data.bin")).unwrap(), data);
+        
+        // Half of the file was fetched before
+        let output = root.join("sender/fetched.bin");
+        let md5 = sent.md5.unwrap();
+        std::fs::create_dir_all(root.join("sender")).unwrap();
+        std::fs::write(transfer::folder::partial_path(&output, &md5).unwrap(), &data[..DEFAULT_CHUNK_SIZE as usize]).unwrap();
+        
+        let transfer = sender
+            .fetch_file(receiver.peer_id(), "data.bin", Some(output.clone()), Some(16 * 1024 * 1024))
+            .await
+            .unwrap();
+        let fetched = finished_transfer(&mut sender_events, &transfer.id).await;
+        assert_eq!(fetched.status, TransferStatus::Completed, "{:?}", fetched.error);
+        assert_eq!(fetched.resumed_from, DEFAULT_CHUNK_SIZE);
+        assert_eq!(std::fs::read(&output).unwrap(), data);
+        
+        // Only the transfer folder is shared
+        let transfer = sender.fetch_file(receiver.peer_id(), "../data.bin", None, None).await.unwrap();
+        let refused = finished_transfer(&mut sender_events, &transfer.id).await;
+        assert_eq!(refused.status, TransferStatus::Failed);
+        assert_eq!(sender.transfers().await.unwrap().len(), 3);
+        
+        sender.shutdown().await.unwrap();
+        receiver.shutdown().await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_chat() {
+        let simulation = HiveSimulation::start(3, 152).await.unwrap();
+        simulation.connect_all().await.unwrap();
+        
+        // Everyone hears everyone
+        for from in 0..simulation.nodes.len() {
+            let text = format!("Hello from node {from}");
+            simulation
+                .message_reaches_all(from, HivePayload::Chat(text), Duration::from_secs(10))
+                .await
+                .unwrap();
+        }
+        
+        simulation.shutdown().await.unwrap();
+    }
 }
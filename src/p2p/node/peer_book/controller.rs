@@ -0,0 +1,168 @@
+use chrono::{DateTime, Utc};
+use entity::hive_peer::{
+    self,
+    ActiveModel as HivePeerActiveModel,
+    Entity as HivePeerEntity,
+    Model as HivePeerModel,
+};
+use libp2p::{Multiaddr, PeerId};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ActiveValue, DatabaseConnection, EntityTrait};
+use std::error::Error;
+use std::time::Duration;
+
+use super::{PeerBook, PeerRecord};
+
+/// Peer book controller
+/// 
+/// Peers are stored on the 'hive-peer' table
+pub struct PeerBookController {
+    pub db: DatabaseConnection,
+}
+
+impl PeerBookController {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+    
+    /// Load the peer book
+    /// 
+    /// Rows that can't be parsed are skipped
+    pub async fn load(&self) -> Result<PeerBook, Box<dyn Error>> {
+        let models = HivePeerEntity::find().all(&self.db).await?;
+        
+        let records = models
+            .into_iter()
+            .filter_map(|model| match record_from_model(model) {
+                Ok(record) => Some(record),
+                Err(err) => {
+                    tracing::warn!("Skipping stored peer: {err}");
+                    None
+                }
+            })
+            .collect();
+        
+        Ok(PeerBook::from_records(records))
+    }
+    
+    /// Save peers
+    /// 
+    /// Peers are inserted or updated by peer id
+    pub async fn save(&self, records: &[PeerRecord]) -> Result<(), Box<dyn Error>> {
+        for record in records {
+            let active_model = active_model_from_record(record)?;
+            
+            HivePeerEntity::insert(active_model)
+                .on_conflict(
+                    OnConflict::column(hive_peer::Column::PeerId)
+                        .update_columns([
+                            hive_peer::Column::Addresses,
+                            hive_peer::Column::AgentVersion,
+                            hive_peer::Column::ProtocolVersion,
+                            hive_peer::Column::LastSeen,
+                            hive_peer::Column::RttMs,
+                            hive_peer::Column::ServerNodeId,
+                            hive_peer::Column::UpdatedAt,
+                        ])
+                        .to_owned(),
+                )
+                .exec(&self.db)
+                .await?;
+        }
+        
+        Ok(())
+    }
+}
+
+/// Create an active model from a peer record
+/// 
+/// 
+pub fn active_model_from_record(record: &PeerRecord) -> Result<HivePeerActiveModel, Box<dyn Error>> {
+    let addresses: Vec<String> = record.addresses
+        .iter()
+        .map(|address| address.to_string())
+        .collect();
+    let now = Utc::now().naive_utc();
+    
+    Ok(HivePeerActiveModel {
+        id: ActiveValue::NotSet,
+        peer_id: ActiveValue::Set(record.peer_id.to_string()),
+        addresses: ActiveValue::Set(Some(serde_json::to_value(addresses)?)),
+        agent_version: ActiveValue::Set(record.agent_version.clone()),
+        protocol_version: ActiveValue::Set(record.protocol_version.clone()),
+        last_seen: ActiveValue::Set(record.last_seen.map(|last_seen| last_seen.naive_utc())),
+        rtt_ms: ActiveValue::Set(record.rtt.map(|rtt| rtt.as_millis() as i64)),
+        server_node_id: ActiveValue::Set(record.server_node_id),
+        created_at: ActiveValue::Set(Some(now)),
+        updated_at: ActiveValue::Set(Some(now)),
+    })
+}
+
+/// Create a peer record from a model
+/// 
+/// 
+pub fn record_from_model(model: HivePeerModel) -> Result<PeerRecord, Box<dyn Error>> {
+    let peer_id: PeerId = model.peer_id.parse()?;
+    
+    let addresses: Vec<String> = match model.addresses {
+        Some(addresses) => serde_json::from_value(addresses)?,
+        None => Vec::new(),
+    };
+    let addresses = addresses
+        .iter()
+        .map(|address| address.parse::<Multiaddr>())
+        .collect::<Result<Vec<_>, _>>()?;
+    
+    Ok(PeerRecord {
+        peer_id,
+        addresses,
+        agent_version: model.agent_version,
+        protocol_version: model.protocol_version,
+        last_seen: model.last_seen.map(|last_seen| DateTime::from_naive_utc_and_offset(last_seen, Utc)),
+        rtt: model.rtt_ms.map(|rtt| Duration::from_millis(rtt as u64)),
+        server_node_id: model.server_node_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::TryIntoModel;
+    
+    #[test]
+    fn test_record_round_trip() {
+        let peer_id = PeerId::random();
+        let mut record = PeerRecord::new(peer_id);
+        record.add_address("/ip4/10.0.0.2/tcp/4001".parse().unwrap());
+        record.add_address("/ip4/10.0.0.2/udp/4001/quic-v1".parse().unwrap());
+        record.agent_version = Some("rust-libp2p/0.45.0".to_string());
+        record.rtt = Some(Duration::from_millis(25));
+        record.last_seen = Some(DateTime::from_timestamp(1_700_000_000, 0).unwrap());
+        record.server_node_id = Some(3);
+        
+        let mut active_model = active_model_from_record(&record).unwrap();
+        active_model.id = ActiveValue::Set(1);
+        let model = active_model.try_into_model().unwrap();
+        assert_eq!(model.peer_id, peer_id.to_string());
+        
+        assert_eq!(record_from_model(model).unwrap(), record);
+    }
+    
+    #[test]
+    fn test_invalid_model() {
+        let model = HivePeerModel {
+            id: 1,
+            peer_id: "not a peer id".to_string(),
+            addresses: None,
+            agent_version: None,
+            protocol_version: None,
+            last_seen: None,
+            rtt_ms: None,
+            server_node_id: None,
+            created_at: None,
+            updated_at: None,
+        };
+        
+        assert!(record_from_model(model).is_err());
+    }
+}
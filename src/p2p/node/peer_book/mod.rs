@@ -0,0 +1,251 @@
+//! Peer book
+//! 
+//! Everything the node knows about other peers, it's persisted so a restarted node can redial them.
+use chrono::{DateTime, Utc};
+use libp2p::{identify, multiaddr::Protocol, Multiaddr, PeerId};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+pub mod controller;
+
+/// Addresses kept for each peer
+/// 
+/// The newest ones are kept
+pub const MAX_ADDRESSES: usize = 16;
+
+/// What we know about a peer
+/// 
+/// 
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeerRecord {
+    pub peer_id: PeerId,
+    /// Known addresses, newest first
+    pub addresses: Vec<Multiaddr>,
+    pub agent_version: Option<String>,
+    pub protocol_version: Option<String>,
+    /// Last time the peer answered a ping
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Round trip time of the last ping
+    pub rtt: Option<Duration>,
+    /// Row of the peer on the 'server-node' table
+    pub server_node_id: Option<i64>,
+}
+
+impl PeerRecord {
+    pub fn new(peer_id: PeerId) -> Self {
+        Self {
+            peer_id,
+            addresses: Vec::new(),
+            agent_version: None,
+            protocol_version: None,
+            last_seen: None,
+            rtt: None,
+            server_node_id: None,
+        }
+    }
+    
+    /// Add an address
+    /// 
+    /// The peer id suffix is removed, returns whether the address is new
+    pub fn add_address(&mut self, mut address: Multiaddr) -> bool {
+        if let Some(Protocol::P2p(_)) = address.iter().last() {
+            address.pop();
+        }
+        
+        if address.is_empty() || self.addresses.contains(&address) {
+            return false;
+        }
+        
+        self.addresses.insert(0, address);
+        self.addresses.truncate(MAX_ADDRESSES);
+        
+        true
+    }
+}
+
+/// Peer book
+/// 
+/// Changed peers are tracked, so only those have to be stored
+#[derive(Default)]
+pub struct PeerBook {
+    peers: HashMap<PeerId, PeerRecord>,
+    dirty: HashSet<PeerId>,
+}
+
+impl PeerBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Create from stored records
+    /// 
+    /// 
+    pub fn from_records(records: Vec<PeerRecord>) -> Self {
+        Self {
+            peers: records
+                .into_iter()
+                .map(|record| (record.peer_id, record))
+                .collect(),
+            dirty: HashSet::new(),
+        }
+    }
+    
+    pub fn get(&self, peer_id: &PeerId) -> Option<&PeerRecord> {
+        self.peers.get(peer_id)
+    }
+    
+    pub fn records(&self) -> impl Iterator<Item = &PeerRecord> {
+        self.peers.values()
+    }
+    
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+    
+    /// Get the record of a peer, it's created if it doesn't exist
+    /// 
+    /// 
+    fn record_mut(&mut self, peer_id: PeerId) -> &mut PeerRecord {
+        self.peers
+            .entry(peer_id)
+            .or_insert_with(|| PeerRecord::new(peer_id))
+    }
+    
+    /// Add an address of a peer
+    /// 
+    /// 
+    pub fn add_address(&mut self, peer_id: PeerId, address: Multiaddr) {
+        if self.record_mut(peer_id).add_address(address) {
+            self.dirty.insert(peer_id);
+        }
+    }
+    
+    /// Record the information a peer sent through identify
+    /// 
+    /// 
+    pub fn record_identify(&mut self, peer_id: PeerId, info: &identify::Info) {
+        let record = self.record_mut(peer_id);
+        
+        for address in &info.listen_addrs {
+            record.add_address(address.clone());
+        }
+        record.agent_version = Some(info.agent_version.clone());
+        record.protocol_version = Some(info.protocol_version.clone());
+        
+        self.dirty.insert(peer_id);
+    }
+    
+    /// Record a successful ping
+    /// 
+    /// 
+    pub fn record_ping(&mut self, peer_id: PeerId, rtt: Duration) {
+        let record = self.record_mut(peer_id);
+        record.last_seen = Some(Utc::now());
+        record.rtt = Some(rtt);
+        
+        self.dirty.insert(peer_id);
+    }
+    
+    /// Link a peer to its server node row
+    /// 
+    /// 
+    pub fn set_server_node_id(&mut self, peer_id: PeerId, server_node_id: i64) {
+        let record = self.record_mut(peer_id);
+        if record.server_node_id == Some(server_node_id) {
+            return;
+        }
+        record.server_node_id = Some(server_node_id);
+        
+        self.dirty.insert(peer_id);
+    }
+    
+    /// Mark a peer as changed
+    /// 
+    /// Used when storing it failed
+    pub fn mark_dirty(&mut self, peer_id: PeerId) {
+        if self.peers.contains_key(&peer_id) {
+            self.dirty.insert(peer_id);
+        }
+    }
+    
+    /// Take the peers changed since the last call
+    /// 
+    /// 
+    pub fn take_dirty(&mut self) -> Vec<PeerRecord> {
+        self.dirty
+            .drain()
+            .filter_map(|peer_id| self.peers.get(&peer_id).cloned())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+    
+    #[test]
+    fn test_add_address() {
+        let peer_id = PeerId::random();
+        let mut peer_book = PeerBook::new();
+        let address: Multiaddr = "/ip4/10.0.0.2/tcp/4001".parse().unwrap();
+        
+        peer_book.add_address(peer_id, address.clone());
+        assert_eq!(peer_book.take_dirty().len(), 1);
+        
+        // The same address with the peer id doesn't change anything
+        peer_book.add_address(peer_id, address.clone().with(Protocol::P2p(peer_id)));
+        assert!(peer_book.take_dirty().is_empty());
+        assert_eq!(peer_book.get(&peer_id).unwrap().addresses, vec![address]);
+    }
+    
+    #[test]
+    fn test_addresses_are_limited() {
+        let mut record = PeerRecord::new(PeerId::random());
+        
+        for port in 0..(MAX_ADDRESSES as u16 + 4) {
+            record.add_address(format!("/ip4/10.0.0.2/tcp/{port}").parse().unwrap());
+        }
+        
+        assert_eq!(record.addresses.len(), MAX_ADDRESSES);
+        assert_eq!(record.addresses[0], format!("/ip4/10.0.0.2/tcp/{}", MAX_ADDRESSES + 3).parse().unwrap());
+    }
+    
+    #[test]
+    fn test_record_identify_and_ping() {
+        let key = Keypair::generate_ed25519();
+        let peer_id = key.public().to_peer_id();
+        let mut peer_book = PeerBook::new();
+        
+        let info = identify::Info {
+            public_key: key.public(),
+            protocol_version: "/ipfs/0.1.0".to_string(),
+            agent_version: "rust-libp2p/0.45.0".to_string(),
+            listen_addrs: vec!["/ip4/10.0.0.2/tcp/4001".parse().unwrap()],
+            protocols: Vec::new(),
+            observed_addr: "/ip4/10.0.0.3/tcp/4001".parse().unwrap(),
+        };
+        peer_book.record_identify(peer_id, &info);
+        peer_book.record_ping(peer_id, Duration::from_millis(12));
+        peer_book.set_server_node_id(peer_id, 7);
+        
+        let records = peer_book.take_dirty();
+        assert_eq!(records.len(), 1);
+        
+        let record = &records[0];
+        assert_eq!(record.agent_version.as_deref(), Some("rust-libp2p/0.45.0"));
+        assert_eq!(record.protocol_version.as_deref(), Some("/ipfs/0.1.0"));
+        assert_eq!(record.addresses.len(), 1);
+        assert_eq!(record.rtt, Some(Duration::from_millis(12)));
+        assert!(record.last_seen.is_some());
+        assert_eq!(record.server_node_id, Some(7));
+        
+        // Nothing changed
+        peer_book.set_server_node_id(peer_id, 7);
+        assert!(peer_book.take_dirty().is_empty());
+    }
+}
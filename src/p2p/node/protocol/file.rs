@@ -0,0 +1,278 @@
+//! File protocol
+//! 
+//! Every request moves one chunk, so a transfer is a sequence of requests driven by the node
+//! that started it. A fetch asks for the size and md5 of a file and then reads it, a send offers
+//! the file, writes it and completes it.
+use libp2p::{request_response, StreamProtocol};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::server_node::HIVE_NODE_USER_ID;
+use crate::p2p::node::transfer::folder::{self, TransferFolder};
+use crate::p2p::node::transfer::{chunk_hash, file_md5, MAX_CHUNK_SIZE};
+use crate::security::{create_token::create_token, verify_token::verify_token};
+
+/// Protocol name
+pub const PROTOCOL: StreamProtocol = StreamProtocol::new("/hive/file/1.0.0");
+
+/// File behaviour
+pub type Behaviour = request_response::json::Behaviour<FileRequest, FileResponse>;
+
+/// File request
+/// 
+/// The token has to be signed with the hive secret token
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FileRequest {
+    pub token: String,
+    pub action: FileAction,
+}
+
+impl FileRequest {
+    /// Create a request with a fresh token
+    /// 
+    /// 
+    pub fn new(action: FileAction) -> Result<Self, Box<dyn Error>> {
+        let token = create_token(HIVE_NODE_USER_ID, Duration::from_secs(60))?;
+        
+        Ok(Self { token, action })
+    }
+}
+
+/// File action
+/// 
+/// Paths and names are relative to the transfer folder of the peer
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum FileAction {
+    /// Size and md5 of a file
+    Stat {
+        path: String,
+    },
+    Read {
+        path: String,
+        offset: u64,
+        length: u64,
+    },
+    /// A file is about to be sent
+    Offer {
+        name: String,
+        size: u64,
+        md5: String,
+    },
+    Write {
+        name: String,
+        md5: String,
+        offset: u64,
+        #[serde(with = "base64_data")]
+        data: Vec<u8>,
+        hash: String,
+    },
+    /// Every byte was written, the file is checked and moved to its place
+    Complete {
+        name: String,
+        md5: String,
+    },
+}
+
+/// File response
+/// 
+/// 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum FileResponse {
+    Stat {
+        size: u64,
+        md5: String,
+    },
+    Chunk {
+        offset: u64,
+        #[serde(with = "base64_data")]
+        data: Vec<u8>,
+        hash: String,
+    },
+    /// Bytes of the file the peer has, writing continues from here
+    Offset {
+        offset: u64,
+    },
+    /// The file is complete, with its path on the peer
+    Completed {
+        path: PathBuf,
+    },
+    Unauthorized,
+    Error(String),
+}
+
+impl FileResponse {
+    /// Respond a request
+    /// 
+    /// Only authenticated peers can read and write files
+    pub async fn respond(folder: &TransferFolder, request: FileRequest) -> Self {
+        if verify_token(&request.token).is_err() {
+            return FileResponse::Unauthorized;
+        }
+        
+        match Self::respond_action(folder, request.action).await {
+            Ok(response) => response,
+            Err(err) => FileResponse::Error(err),
+        }
+    }
+    
+    async fn respond_action(folder: &TransferFolder, action: FileAction) -> Result<Self, String> {
+        match action {
+            FileAction::Stat { path } => {
+                let path = folder.resolve(&path)?;
+                let metadata = tokio::fs::metadata(&path).await.map_err(|err| err.to_string())?;
+                if !metadata.is_file() {
+                    return Err(format!("'{}' isn't a file", path.display()));
+                }
+                
+                Ok(FileResponse::Stat {
+                    size: metadata.len(),
+                    md5: file_md5(&path).await.map_err(|err| err.to_string())?,
+                })
+            }
+            FileAction::Read { path, offset, length } => {
+                let path = folder.resolve(&path)?;
+                let data = folder::read_chunk(&path, offset, length.min(MAX_CHUNK_SIZE))
+                    .await
+                    .map_err(|err| err.to_string())?;
+                
+                Ok(FileResponse::Chunk {
+                    offset,
+                    hash: chunk_hash(&data),
+                    data,
+                })
+            }
+            FileAction::Offer { name, size, md5 } => {
+                let destination = folder.resolve(&name)?;
+                
+                Ok(FileResponse::Offset {
+                    offset: folder::existing_bytes(&destination, size, &md5).await?,
+                })
+            }
+            FileAction::Write { name, md5, offset, data, hash } => {
+                if chunk_hash(&data) != hash {
+                    return Err(format!("The chunk at offset {offset} doesn't match its hash"));
+                }
+                
+                let destination = folder.resolve(&name)?;
+                let offset = folder::append_chunk(&folder::partial_path(&destination, &md5)?, offset, &data)
+                    .await
+                    .map_err(|err| err.to_string())?;
+                
+                Ok(FileResponse::Offset { offset })
+            }
+            FileAction::Complete { name, md5 } => {
+                let destination = folder.resolve(&name)?;
+                folder::complete(&destination, &md5).await?;
+                
+                Ok(FileResponse::Completed { path: destination })
+            }
+        }
+    }
+}
+
+/// Chunks are sent as base64, json would turn every byte into a number
+/// 
+/// 
+mod base64_data {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{Deserialize, Deserializer, Serializer};
+    
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(data))
+    }
+    
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        
+        STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Create behaviour
+/// 
+/// 
+pub fn new_behaviour() -> Behaviour {
+    request_response::json::Behaviour::new(
+        [(PROTOCOL, request_response::ProtocolSupport::Full)],
+        request_response::Config::default().with_request_timeout(Duration::from_secs(30)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::folder::hive_test_folder::HiveTestFolder;
+    
+    #[tokio::test]
+    async fn test_write_and_read() {
+        let test_folder = HiveTestFolder::default();
+        let mut path = PathBuf::from(&test_folder.path);
+        path.push(format!("file_protocol_{}", nanoid::nanoid!(6)));
+        let folder = TransferFolder::new(path);
+        
+        let data = b"Hello hive".to_vec();
+        let md5 = format!("{:x}", <md5::Md5 as md5::Digest>::digest(&data));
+        let respond = |action| async {
+            FileResponse::respond(&folder, FileRequest::new(action).unwrap()).await
+        };
+        
+        let response = respond(FileAction::Offer { name: "hello.txt".to_string(), size: 10, md5: md5.clone() }).await;
+        assert!(matches!(response, FileResponse::Offset { offset: 0 }));
+        
+        // Corrupted chunks are refused
+        let response = respond(FileAction::Write {
+            name: "hello.txt".to_string(),
+            md5: md5.clone(),
+            offset: 0,
+            data: data.clone(),
+            hash: chunk_hash(b"Hello"),
+        }).await;
+        assert!(matches!(response, FileResponse::Error(_)));
+        
+        let response = respond(FileAction::Write {
+            name: "hello.txt".to_string(),
+            md5: md5.clone(),
+            offset: 0,
+            hash: chunk_hash(&data),
+            data: data.clone(),
+        }).await;
+        assert!(matches!(response, FileResponse::Offset { offset: 10 }));
+        
+        let response = respond(FileAction::Complete { name: "hello.txt".to_string(), md5: md5.clone() }).await;
+        assert!(matches!(response, FileResponse::Completed { .. }));
+        
+        let response = respond(FileAction::Stat { path: "hello.txt".to_string() }).await;
+        assert!(matches!(response, FileResponse::Stat { size: 10, md5: stat_md5 } if stat_md5 == md5));
+        
+        let response = respond(FileAction::Read { path: "hello.txt".to_string(), offset: 6, length: 100 }).await;
+        assert!(matches!(response, FileResponse::Chunk { data, .. } if data == b"hive"));
+        
+        // Outside of the folder
+        let response = respond(FileAction::Stat { path: "../../Cargo.toml".to_string() }).await;
+        assert!(matches!(response, FileResponse::Error(_)));
+        
+        // The md5 is part of the incomplete file name, so it can't be a path
+        let evil = "x/../../../../evil".to_string();
+        let response = respond(FileAction::Offer { name: "evil.txt".to_string(), size: 10, md5: evil.clone() }).await;
+        assert!(matches!(response, FileResponse::Error(_)));
+        let response = respond(FileAction::Write {
+            name: "evil.txt".to_string(),
+            md5: evil.clone(),
+            offset: 0,
+            hash: chunk_hash(&data),
+            data: data.clone(),
+        }).await;
+        assert!(matches!(response, FileResponse::Error(_)));
+        let response = respond(FileAction::Complete { name: "evil.txt".to_string(), md5: evil }).await;
+        assert!(matches!(response, FileResponse::Error(_)));
+        assert!(!folder.path.join(".evil.txt.x/../../../../evil.part").exists());
+        
+        let request = FileRequest {
+            token: "not-a-token".to_string(),
+            action: FileAction::Stat { path: "hello.txt".to_string() },
+        };
+        assert!(matches!(FileResponse::respond(&folder, request).await, FileResponse::Unauthorized));
+    }
+}
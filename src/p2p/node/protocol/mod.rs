@@ -0,0 +1,7 @@
+//! Request-response protocols
+//! 
+//! Protocols used to ask a single peer for something, instead of broadcasting it through gossipsub
+pub mod file;
+pub mod registry;
+pub mod server_node;
+pub mod task;
@@ -0,0 +1,104 @@
+//! Registry protocol
+//! 
+//! Anti-entropy sync of the node registry, a node sends the versions it knows and the peer
+//! answers with the entries that are newer
+use libp2p::{request_response, StreamProtocol};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::Duration;
+
+use super::server_node::HIVE_NODE_USER_ID;
+use crate::p2p::node::registry::{Digest, NodeRegistry, RegistryEntry};
+use crate::security::{create_token::create_token, verify_token::verify_token};
+
+/// Protocol name
+pub const PROTOCOL: StreamProtocol = StreamProtocol::new("/hive/registry/1.0.0");
+
+/// Registry behaviour
+pub type Behaviour = request_response::json::Behaviour<RegistryRequest, RegistryResponse>;
+
+/// Registry request
+/// 
+/// The token has to be signed with the hive secret token
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RegistryRequest {
+    pub token: String,
+    pub digest: Digest,
+}
+
+impl RegistryRequest {
+    /// Create a request with a fresh token
+    /// 
+    /// 
+    pub fn new(digest: Digest) -> Result<Self, Box<dyn Error>> {
+        let token = create_token(HIVE_NODE_USER_ID, Duration::from_secs(60))?;
+        
+        Ok(Self { token, digest })
+    }
+}
+
+/// Registry response
+/// 
+/// 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum RegistryResponse {
+    Entries(Vec<RegistryEntry>),
+    Unauthorized,
+}
+
+impl RegistryResponse {
+    /// Respond a request
+    /// 
+    /// Only authenticated peers receive the entries
+    pub fn respond(registry: &NodeRegistry, request: &RegistryRequest) -> Self {
+        if verify_token(&request.token).is_err() {
+            return RegistryResponse::Unauthorized;
+        }
+        
+        RegistryResponse::Entries(registry.newer_than(&request.digest))
+    }
+}
+
+/// Create behaviour
+/// 
+/// 
+pub fn new_behaviour() -> Behaviour {
+    request_response::json::Behaviour::new(
+        [(PROTOCOL, request_response::ProtocolSupport::Full)],
+        request_response::Config::default().with_request_timeout(Duration::from_secs(30)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::PeerId;
+    
+    use crate::server_node::ServerNode;
+    
+    #[test]
+    fn test_respond() {
+        let mut registry = NodeRegistry::new(PeerId::random());
+        let known = registry.set(PeerId::random(), ServerNode::new().unwrap()).unwrap();
+        let missing = registry.set(PeerId::random(), ServerNode::new().unwrap()).unwrap();
+        
+        // The digest goes through json
+        let digest = Digest::from([(known.node_id, known.version)]);
+        let request = RegistryRequest::new(digest).unwrap();
+        let request: RegistryRequest = serde_json::from_str(&serde_json::to_string(&request).unwrap()).unwrap();
+        
+        match RegistryResponse::respond(&registry, &request) {
+            RegistryResponse::Entries(entries) => {
+                assert_eq!(entries.len(), 1);
+                assert_eq!(entries[0].node_id, missing.node_id);
+            }
+            response => panic!("Unexpected response {response:?}"),
+        }
+        
+        let request = RegistryRequest {
+            token: "not-a-token".to_string(),
+            digest: Digest::new(),
+        };
+        assert!(matches!(RegistryResponse::respond(&registry, &request), RegistryResponse::Unauthorized));
+    }
+}
@@ -0,0 +1,110 @@
+//! Server node protocol
+//! 
+//! Lets a node fetch the 'ServerNode' information of a peer
+use libp2p::{request_response, StreamProtocol};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::Duration;
+
+use crate::security::{create_token::create_token, verify_token::verify_token};
+use crate::server_node::ServerNode;
+
+/// Protocol name
+pub const PROTOCOL: StreamProtocol = StreamProtocol::new("/hive/server-node/1.0.0");
+
+/// User id used on the tokens that nodes create for each other
+pub const HIVE_NODE_USER_ID: i64 = 0;
+
+/// Server node behaviour
+pub type Behaviour = request_response::json::Behaviour<ServerNodeRequest, ServerNodeResponse>;
+
+/// Server node request
+/// 
+/// The token has to be signed with the hive secret token
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ServerNodeRequest {
+    pub token: String,
+}
+
+impl ServerNodeRequest {
+    /// Create a request with a fresh token
+    /// 
+    /// 
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        let token = create_token(HIVE_NODE_USER_ID, Duration::from_secs(60))?;
+        
+        Ok(Self { token })
+    }
+}
+
+/// Server node response
+/// 
+/// 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ServerNodeResponse {
+    Node(Box<ServerNode>),
+    Unauthorized,
+    Error(String),
+}
+
+impl ServerNodeResponse {
+    /// Respond a request with our own server node
+    /// 
+    /// Only authenticated peers receive the server node, it's none until the first resource sample
+    pub fn respond(request: &ServerNodeRequest, local_node: Option<&ServerNode>) -> Self {
+        if verify_token(&request.token).is_err() {
+            return ServerNodeResponse::Unauthorized;
+        }
+        
+        match local_node {
+            Some(server_node) => ServerNodeResponse::Node(Box::new(server_node.clone())),
+            None => ServerNodeResponse::Error("The server node isn't ready yet".to_string()),
+        }
+    }
+}
+
+/// Create behaviour
+/// 
+/// 
+pub fn new_behaviour() -> Behaviour {
+    request_response::json::Behaviour::new(
+        [(PROTOCOL, request_response::ProtocolSupport::Full)],
+        request_response::Config::default().with_request_timeout(Duration::from_secs(30)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_respond_authenticated() {
+        let request = ServerNodeRequest::new().unwrap();
+        let local_node = ServerNode::new().unwrap();
+        
+        match ServerNodeResponse::respond(&request, Some(&local_node)) {
+            ServerNodeResponse::Node(server_node) => {
+                assert_eq!(server_node.location.name, local_node.location.name);
+            }
+            response => panic!("Unexpected response {response:?}"),
+        }
+        
+        // Before the first sample there's nothing to share
+        assert!(matches!(
+            ServerNodeResponse::respond(&request, None),
+            ServerNodeResponse::Error(_)
+        ));
+    }
+    
+    #[test]
+    fn test_respond_unauthenticated() {
+        let request = ServerNodeRequest {
+            token: "not-a-token".to_string(),
+        };
+        
+        assert!(matches!(
+            ServerNodeResponse::respond(&request, None),
+            ServerNodeResponse::Unauthorized
+        ));
+    }
+}
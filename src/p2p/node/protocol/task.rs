@@ -0,0 +1,215 @@
+//! Task protocol
+//! 
+//! The owner of a task submits and cancels it, the runner sends the output and the result back
+//! with requests of its own.
+use libp2p::{request_response, PeerId, StreamProtocol};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::time::Duration;
+
+use super::server_node::HIVE_NODE_USER_ID;
+use crate::p2p::node::task::{OutputChunk, TaskId, TaskSpec, TaskStatus};
+use crate::security::{create_token::create_token, verify_token::verify_token};
+
+/// Protocol name
+pub const PROTOCOL: StreamProtocol = StreamProtocol::new("/hive/task/1.0.0");
+
+/// Task behaviour
+pub type Behaviour = request_response::json::Behaviour<TaskRequest, TaskResponse>;
+
+/// Task request
+/// 
+/// Submissions and cancellations carry a token signed with the hive secret token,
+/// output and results are only accepted from the runner of the task
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum TaskRequest {
+    Submit {
+        token: String,
+        task_id: TaskId,
+        spec: TaskSpec,
+    },
+    Cancel {
+        token: String,
+        task_id: TaskId,
+    },
+    Output {
+        task_id: TaskId,
+        chunk: OutputChunk,
+    },
+    Finished {
+        task_id: TaskId,
+        status: TaskStatus,
+        exit_code: Option<i32>,
+        error: Option<String>,
+    },
+}
+
+impl TaskRequest {
+    /// Submit a task with a fresh token
+    /// 
+    /// 
+    pub fn submit(task_id: TaskId, spec: TaskSpec) -> Result<Self, Box<dyn Error>> {
+        Ok(TaskRequest::Submit {
+            token: new_token()?,
+            task_id,
+            spec,
+        })
+    }
+    
+    /// Cancel a task with a fresh token
+    /// 
+    /// 
+    pub fn cancel(task_id: TaskId) -> Result<Self, Box<dyn Error>> {
+        Ok(TaskRequest::Cancel {
+            token: new_token()?,
+            task_id,
+        })
+    }
+    
+    pub fn task_id(&self) -> &TaskId {
+        match self {
+            TaskRequest::Submit { task_id, .. }
+            | TaskRequest::Cancel { task_id, .. }
+            | TaskRequest::Output { task_id, .. }
+            | TaskRequest::Finished { task_id, .. } => task_id,
+        }
+    }
+    
+    /// Whether the token is valid
+    /// 
+    /// Output and results don't have a token
+    pub fn is_authorized(&self) -> bool {
+        match self {
+            TaskRequest::Submit { token, .. } | TaskRequest::Cancel { token, .. } => verify_token(token).is_ok(),
+            TaskRequest::Output { .. } | TaskRequest::Finished { .. } => true,
+        }
+    }
+}
+
+/// Task response
+/// 
+/// 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum TaskResponse {
+    /// The task is running
+    Accepted {
+        task_id: TaskId,
+    },
+    Rejected {
+        task_id: TaskId,
+        reason: String,
+    },
+    Unauthorized {
+        task_id: TaskId,
+    },
+    Ack,
+}
+
+/// Task outbox
+/// 
+/// Every request travels on its own stream, so they may arrive in any order.
+/// Requests about the same task are sent one after the other, so the output arrives before the result
+#[derive(Debug, Default)]
+pub struct TaskOutbox {
+    // A task has a queue while one of its requests is being sent
+    queues: HashMap<TaskId, VecDeque<(PeerId, TaskRequest)>>,
+}
+
+impl TaskOutbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Queue a request
+    /// 
+    /// Returns the request if it can be sent right away
+    pub fn push(&mut self, peer_id: PeerId, request: TaskRequest) -> Option<(PeerId, TaskRequest)> {
+        match self.queues.get_mut(request.task_id()) {
+            Some(queue) => {
+                queue.push_back((peer_id, request));
+                None
+            }
+            None => {
+                self.queues.insert(request.task_id().clone(), VecDeque::new());
+                Some((peer_id, request))
+            }
+        }
+    }
+    
+    /// The last request of a task was delivered or failed
+    /// 
+    /// Returns the next request of the task
+    pub fn next(&mut self, task_id: &str) -> Option<(PeerId, TaskRequest)> {
+        let queue = self.queues.get_mut(task_id)?;
+        
+        match queue.pop_front() {
+            Some(next) => Some(next),
+            None => {
+                self.queues.remove(task_id);
+                None
+            }
+        }
+    }
+}
+
+/// Create a token for another node
+/// 
+/// 
+fn new_token() -> Result<String, Box<dyn Error>> {
+    create_token(HIVE_NODE_USER_ID, Duration::from_secs(60))
+}
+
+/// Create behaviour
+/// 
+/// 
+pub fn new_behaviour() -> Behaviour {
+    request_response::json::Behaviour::new(
+        [(PROTOCOL, request_response::ProtocolSupport::Full)],
+        request_response::Config::default().with_request_timeout(Duration::from_secs(30)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_authorization() {
+        let request = TaskRequest::submit("task".to_string(), TaskSpec::new("true", &[])).unwrap();
+        assert!(request.is_authorized());
+        assert_eq!(request.task_id(), "task");
+        
+        let request = TaskRequest::Cancel {
+            token: "not-a-token".to_string(),
+            task_id: "task".to_string(),
+        };
+        assert!(!request.is_authorized());
+    }
+    
+    #[test]
+    fn test_outbox_keeps_the_order() {
+        let peer_id = PeerId::random();
+        let output = |task_id: &str, data: &str| TaskRequest::Output {
+            task_id: task_id.to_string(),
+            chunk: OutputChunk {
+                stream: crate::p2p::node::task::OutputStream::Stdout,
+                data: data.to_string(),
+            },
+        };
+        let mut outbox = TaskOutbox::new();
+        
+        assert!(outbox.push(peer_id, output("first", "a")).is_some());
+        assert!(outbox.push(peer_id, output("first", "b")).is_none());
+        // Other tasks don't wait
+        assert!(outbox.push(peer_id, output("second", "a")).is_some());
+        
+        let next = outbox.next("first").unwrap().1;
+        assert!(matches!(next, TaskRequest::Output { chunk, .. } if chunk.data == "b"));
+        assert!(outbox.next("first").is_none());
+        
+        // Nothing is in flight anymore
+        assert!(outbox.next("first").is_none());
+        assert!(outbox.push(peer_id, output("first", "c")).is_some());
+    }
+}
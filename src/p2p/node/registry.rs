@@ -0,0 +1,308 @@
+//! Node registry
+//! 
+//! Every node keeps a replicated map of the server nodes of the hive, keyed by their peer id,
+//! which is stable between restarts. It's a last-writer-wins map: every write is versioned with a
+//! Lamport clock and the writer peer id, so all the nodes keep the same entry no matter in which
+//! order they receive the writes.
+//! Writes are published as deltas through gossipsub, and nodes periodically pull the entries they
+//! missed from a peer, so the hive can list its nodes even when the database is unreachable.
+use chrono::{DateTime, Utc};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use crate::server_node::{ServerNode, ServerStatus};
+
+/// Time between anti-entropy syncs
+pub const REGISTRY_SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Highest counter of an entry
+/// 
+/// A hive doesn't write that many times, higher counters only come from broken or malicious peers
+pub const MAX_COUNTER: u64 = u32::MAX as u64;
+
+/// Registry error
+/// 
+/// 
+#[derive(Debug)]
+pub enum RegistryError {
+    /// The counter of an entry is over the maximum
+    InvalidCounter(u64),
+    /// The clock reached the maximum counter, nothing can be written after it
+    ClockExhausted,
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::InvalidCounter(counter) => write!(
+                f,
+                "Entry counter {counter} is over the maximum of {MAX_COUNTER}"
+            ),
+            RegistryError::ClockExhausted => write!(f, "The registry clock reached {MAX_COUNTER}"),
+        }
+    }
+}
+
+impl Error for RegistryError {}
+
+/// Version of an entry
+/// 
+/// Compared by counter first and writer second
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub struct Version {
+    pub counter: u64,
+    pub writer: PeerId,
+}
+
+/// Registry entry
+/// 
+/// Removed nodes are kept without server node, so the removal wins over older writes
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RegistryEntry {
+    pub node_id: PeerId,
+    pub server_node: Option<ServerNode>,
+    pub version: Version,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Versions known by a node
+/// 
+/// Sent on anti-entropy syncs, the peer answers with the entries that are newer
+pub type Digest = HashMap<PeerId, Version>;
+
+/// Node registry
+/// 
+/// Local writes return the entry that has to be published
+#[derive(Clone, Debug)]
+pub struct NodeRegistry {
+    local_peer_id: PeerId,
+    // Highest counter seen, local writes go after it
+    clock: u64,
+    entries: HashMap<PeerId, RegistryEntry>,
+}
+
+impl NodeRegistry {
+    pub fn new(local_peer_id: PeerId) -> Self {
+        Self {
+            local_peer_id,
+            clock: 0,
+            entries: HashMap::new(),
+        }
+    }
+    
+    /// Server node of a node
+    /// 
+    /// None for unknown and removed nodes
+    pub fn get(&self, node_id: &PeerId) -> Option<&ServerNode> {
+        self.entries.get(node_id)?.server_node.as_ref()
+    }
+    
+    /// Every known server node
+    /// 
+    /// 
+    pub fn nodes(&self) -> Vec<(PeerId, ServerNode)> {
+        let mut nodes: Vec<_> = self.entries
+            .values()
+            .filter_map(|entry| Some((entry.node_id, entry.server_node.clone()?)))
+            .collect();
+        nodes.sort_by_key(|(node_id, _)| *node_id);
+        
+        nodes
+    }
+    
+    /// Every entry, removed nodes included
+    /// 
+    /// 
+    pub fn entries(&self) -> Vec<RegistryEntry> {
+        let mut entries: Vec<_> = self.entries.values().cloned().collect();
+        entries.sort_by_key(|entry| entry.node_id);
+        
+        entries
+    }
+    
+    /// Store a server node
+    /// 
+    /// 
+    pub fn set(&mut self, node_id: PeerId, server_node: ServerNode) -> Result<RegistryEntry, RegistryError> {
+        self.write(node_id, Some(server_node))
+    }
+    
+    /// Update the status of a known node
+    /// 
+    /// Nothing is written if it's unknown or already had it
+    pub fn set_status(&mut self, node_id: PeerId, status: ServerStatus) -> Result<Option<RegistryEntry>, RegistryError> {
+        let mut server_node = match self.get(&node_id) {
+            Some(server_node) if server_node.status != status => server_node.clone(),
+            _ => return Ok(None),
+        };
+        server_node.status = status;
+        
+        self.set(node_id, server_node).map(Some)
+    }
+    
+    /// Remove a node
+    /// 
+    /// 
+    pub fn remove(&mut self, node_id: PeerId) -> Result<Option<RegistryEntry>, RegistryError> {
+        if self.get(&node_id).is_none() {
+            return Ok(None);
+        }
+        
+        self.write(node_id, None).map(Some)
+    }
+    
+    /// Merge an entry written by another node
+    /// 
+    /// Returns whether it was newer than ours, entries with a counter over the maximum are refused
+    /// so they can't push the clock out of range
+    pub fn merge(&mut self, entry: RegistryEntry) -> Result<bool, RegistryError> {
+        if entry.version.counter > MAX_COUNTER {
+            return Err(RegistryError::InvalidCounter(entry.version.counter));
+        }
+        self.clock = self.clock.max(entry.version.counter);
+        
+        if self.entries.get(&entry.node_id).is_some_and(|current| current.version >= entry.version) {
+            return Ok(false);
+        }
+        
+        self.entries.insert(entry.node_id, entry);
+        Ok(true)
+    }
+    
+    /// Versions of every entry
+    /// 
+    /// 
+    pub fn digest(&self) -> Digest {
+        self.entries
+            .values()
+            .map(|entry| (entry.node_id, entry.version))
+            .collect()
+    }
+    
+    /// Entries that are newer than the ones on a digest
+    /// 
+    /// 
+    pub fn newer_than(&self, digest: &Digest) -> Vec<RegistryEntry> {
+        self.entries
+            .values()
+            .filter(|entry| digest.get(&entry.node_id).is_none_or(|version| entry.version > *version))
+            .cloned()
+            .collect()
+    }
+    
+    fn write(&mut self, node_id: PeerId, server_node: Option<ServerNode>) -> Result<RegistryEntry, RegistryError> {
+        self.clock = self.clock
+            .checked_add(1)
+            .filter(|clock| *clock <= MAX_COUNTER)
+            .ok_or(RegistryError::ClockExhausted)?;
+        
+        let entry = RegistryEntry {
+            node_id,
+            server_node,
+            version: Version {
+                counter: self.clock,
+                writer: self.local_peer_id,
+            },
+            updated_at: Utc::now(),
+        };
+        self.entries.insert(node_id, entry.clone());
+        
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_concurrent_writes_converge() {
+        let server_node = ServerNode::new().unwrap();
+        let node_id = PeerId::random();
+        let mut first = NodeRegistry::new(PeerId::random());
+        let mut second = NodeRegistry::new(PeerId::random());
+        
+        // Unknown nodes can't be updated
+        assert!(second.set_status(node_id, ServerStatus::Offline).unwrap().is_none());
+        
+        // Both write the same node at the same time
+        let first_write = first.set(node_id, server_node.clone()).unwrap();
+        let mut offline = server_node.clone();
+        offline.status = ServerStatus::Offline;
+        let second_write = second.set(node_id, offline).unwrap();
+        
+        // Delivered in different orders
+        first.merge(second_write.clone()).unwrap();
+        second.merge(first_write.clone()).unwrap();
+        second.merge(second_write).unwrap();
+        first.merge(first_write).unwrap();
+        
+        // Both keep the same write
+        assert_eq!(first.entries[&node_id].version, second.entries[&node_id].version);
+        assert_eq!(first.get(&node_id).unwrap().status, second.get(&node_id).unwrap().status);
+        
+        // Later writes win, the clock moved past the merged entries
+        let update = first.set_status(node_id, ServerStatus::Maintenance).unwrap().unwrap();
+        assert!(second.merge(update).unwrap());
+        assert_eq!(second.get(&node_id).unwrap().status, ServerStatus::Maintenance);
+        
+        // Removals too
+        let removal = second.remove(node_id).unwrap().unwrap();
+        assert!(first.merge(removal).unwrap());
+        assert!(first.nodes().is_empty());
+        assert_eq!(first.entries().len(), 1);
+    }
+    
+    #[test]
+    fn test_anti_entropy() {
+        let server_node = ServerNode::new().unwrap();
+        let mut first = NodeRegistry::new(PeerId::random());
+        let mut second = NodeRegistry::new(PeerId::random());
+        
+        let shared = PeerId::random();
+        let entry = first.set(shared, server_node.clone()).unwrap();
+        second.merge(entry).unwrap();
+        first.set(PeerId::random(), server_node.clone()).unwrap();
+        first.set(PeerId::random(), server_node).unwrap();
+        
+        // Only the missing entries are sent
+        let missing = first.newer_than(&second.digest());
+        assert_eq!(missing.len(), 2);
+        for entry in missing {
+            assert!(second.merge(entry).unwrap());
+        }
+        
+        assert!(first.newer_than(&second.digest()).is_empty());
+        assert_eq!(first.nodes().len(), 3);
+        assert_eq!(second.nodes().len(), 3);
+    }
+    
+    #[test]
+    fn test_counter_out_of_range() {
+        let server_node = ServerNode::new().unwrap();
+        let local_peer_id = PeerId::random();
+        let mut registry = NodeRegistry::new(local_peer_id);
+        registry.set(local_peer_id, server_node.clone()).unwrap();
+        
+        // A forged entry about us can't push the clock to overflow
+        let mut forged = NodeRegistry::new(PeerId::random()).set(local_peer_id, server_node.clone()).unwrap();
+        forged.version.counter = u64::MAX;
+        assert!(matches!(registry.merge(forged.clone()), Err(RegistryError::InvalidCounter(_))));
+        
+        // Our later writes still win
+        let update = registry.set_status(local_peer_id, ServerStatus::Maintenance).unwrap().unwrap();
+        assert_eq!(update.version.counter, 2);
+        
+        // Writes fail once the clock reaches the maximum
+        forged.version.counter = MAX_COUNTER;
+        assert!(registry.merge(forged).unwrap());
+        assert!(matches!(
+            registry.set(local_peer_id, server_node),
+            Err(RegistryError::ClockExhausted)
+        ));
+    }
+}
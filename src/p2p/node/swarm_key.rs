@@ -0,0 +1,125 @@
+//! Private network swarm key
+//! 
+//! Nodes holding the same pre-shared key encrypt their TCP connections with it, so only they can
+//! connect to each other. The file uses the go-libp2p 'swarm.key' format.
+use libp2p::pnet::PreSharedKey;
+use rand::RngCore;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use super::keystore::write_secret_file;
+
+/// Default path of the swarm key file
+pub const DEFAULT_SWARM_KEY_PATH: &str = ".cache/hive/swarm.key";
+
+/// Generate a random swarm key
+/// 
+/// 
+pub fn generate_swarm_key() -> PreSharedKey {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    
+    PreSharedKey::new(bytes)
+}
+
+/// Swarm key file
+/// 
+/// 
+#[derive(Clone, Debug)]
+pub struct SwarmKeyFile {
+    pub path: PathBuf,
+}
+
+impl Default for SwarmKeyFile {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from(DEFAULT_SWARM_KEY_PATH),
+        }
+    }
+}
+
+impl SwarmKeyFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+    
+    /// Whether the key file exists
+    /// 
+    /// 
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+    
+    /// Load the swarm key
+    /// 
+    /// 
+    pub fn load(&self) -> Result<PreSharedKey, Box<dyn Error>> {
+        let content = fs::read_to_string(&self.path)?;
+        
+        Ok(content.parse()?)
+    }
+    
+    /// Save the swarm key
+    /// 
+    /// The file is only readable by the owner
+    pub fn save(&self, key: &PreSharedKey) -> Result<(), Box<dyn Error>> {
+        write_secret_file(&self.path, key.to_string().as_bytes())
+    }
+    
+    /// Generate a new swarm key and save it
+    /// 
+    /// An existing key is only replaced when forced, every node would have to get the new one
+    pub fn generate(&self, force: bool) -> Result<PreSharedKey, Box<dyn Error>> {
+        if self.exists() && !force {
+            return Err(format!("Swarm key '{}' already exists", self.path.display()).into());
+        }
+        
+        let key = generate_swarm_key();
+        self.save(&key)?;
+        
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::folder::hive_test_folder::HiveTestFolder;
+    
+    /// Swarm key file on a unique test path
+    /// 
+    /// 
+    fn test_swarm_key_file() -> SwarmKeyFile {
+        let test_folder = HiveTestFolder::default();
+        test_folder.create().unwrap();
+        
+        let mut path = PathBuf::from(&test_folder.path);
+        path.push(format!("swarm_key_{}", nanoid::nanoid!(6)));
+        path.push("swarm.key");
+        
+        SwarmKeyFile::new(path)
+    }
+    
+    #[test]
+    fn test_generate_and_load() {
+        let key_file = test_swarm_key_file();
+        
+        let key = key_file.generate(false).unwrap();
+        assert!(key_file.load().unwrap().fingerprint() == key.fingerprint());
+        
+        // The key isn't replaced by accident
+        assert!(key_file.generate(false).is_err());
+        
+        let replaced = key_file.generate(true).unwrap();
+        assert!(replaced.fingerprint() != key.fingerprint());
+    }
+    
+    #[test]
+    fn test_reject_invalid_file() {
+        let key_file = test_swarm_key_file();
+        write_secret_file(&key_file.path, b"Hello world").unwrap();
+        
+        assert!(key_file.load().is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! Task controller
+//! 
+//! Tasks run by this node are recorded as processes of an app with the same name,
+//! so their output can be stored on the 'app-output' table. The node writes through the
+//! task recorder.
+use chrono::Utc;
+use entity::{
+    app::{self, ActiveModel as AppActiveModel, Entity as AppEntity},
+    app_output::{ActiveModel as AppOutputActiveModel, Entity as AppOutputEntity},
+    process::{self, ActiveModel as ProcessActiveModel, Entity as ProcessEntity},
+};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait};
+use std::error::Error;
+
+use super::{TaskId, TaskInfo};
+
+/// App type of the tasks
+pub const HIVE_TASK_APP_TYPE: &str = "hive-task";
+
+/// Task controller
+/// 
+/// 
+pub struct TaskController {
+    pub db: DatabaseConnection,
+}
+
+impl TaskController {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+    
+    /// Record a task that has just started
+    /// 
+    /// 
+    pub async fn start(&self, task: &TaskInfo, pid: Option<u32>) -> Result<(), Box<dyn Error>> {
+        AppEntity::insert(app_active_model(task))
+            .on_conflict(
+                OnConflict::column(app::Column::Name)
+                    .update_columns([app::Column::Path, app::Column::UpdatedAt])
+                    .to_owned(),
+            )
+            .exec(&self.db)
+            .await?;
+        
+        let process = process_active_model(task, pid)?;
+        ProcessEntity::insert(process)
+            .on_conflict(
+                OnConflict::column(process::Column::Name)
+                    .update_columns([process::Column::Pid, process::Column::UpdatedAt])
+                    .to_owned(),
+            )
+            .exec(&self.db)
+            .await?;
+        
+        Ok(())
+    }
+    
+    /// Store output of the tasks
+    /// 
+    /// Every batch is a row
+    pub async fn record_output(&self, output: Vec<(TaskId, String)>) -> Result<(), Box<dyn Error>> {
+        let now = Utc::now().naive_utc();
+        
+        let app_outputs = output.into_iter().map(|(task_id, output)| AppOutputActiveModel {
+            app_name: ActiveValue::Set(process_name(&task_id)),
+            output: ActiveValue::Set(output),
+            created_at: ActiveValue::Set(Some(now)),
+            updated_at: ActiveValue::Set(Some(now)),
+            ..Default::default()
+        });
+        AppOutputEntity::insert_many(app_outputs).exec(&self.db).await?;
+        
+        Ok(())
+    }
+    
+    /// Record that a task finished
+    /// 
+    /// The process loses its pid and the result is stored as the last output
+    pub async fn finish(&self, task: &TaskInfo) -> Result<(), Box<dyn Error>> {
+        let process = ProcessActiveModel {
+            name: ActiveValue::Unchanged(process_name(&task.id)),
+            pid: ActiveValue::Set(None),
+            updated_at: ActiveValue::Set(Some(Utc::now().naive_utc())),
+            ..Default::default()
+        };
+        ProcessEntity::update(process).exec(&self.db).await?;
+        
+        self.insert_output(&task.id, result_line(task)).await
+    }
+    
+    async fn insert_output(&self, task_id: &str, output: String) -> Result<(), Box<dyn Error>> {
+        let now = Utc::now().naive_utc();
+        
+        let app_output = AppOutputActiveModel {
+            app_name: ActiveValue::Set(process_name(task_id)),
+            output: ActiveValue::Set(output),
+            created_at: ActiveValue::Set(Some(now)),
+            updated_at: ActiveValue::Set(Some(now)),
+            ..Default::default()
+        };
+        app_output.insert(&self.db).await?;
+        
+        Ok(())
+    }
+}
+
+/// Name of the process and app of a task
+/// 
+/// 
+pub fn process_name(task_id: &str) -> String {
+    format!("{HIVE_TASK_APP_TYPE}-{task_id}")
+}
+
+/// Line stored after the output of a finished task
+/// 
+/// 
+pub fn result_line(task: &TaskInfo) -> String {
+    let mut line = format!("Task {}", task.status);
+    
+    if let Some(exit_code) = task.exit_code {
+        line.push_str(&format!(" with exit code {exit_code}"));
+    }
+    if let Some(error) = &task.error {
+        line.push_str(&format!(": {error}"));
+    }
+    
+    line
+}
+
+/// Create the app of a task
+/// 
+/// 
+pub fn app_active_model(task: &TaskInfo) -> AppActiveModel {
+    let now = Utc::now().naive_utc();
+    
+    AppActiveModel {
+        name: ActiveValue::Set(process_name(&task.id)),
+        path: ActiveValue::Set(Some(task.spec.command_line())),
+        app_type: ActiveValue::Set(HIVE_TASK_APP_TYPE.to_string()),
+        created_at: ActiveValue::Set(Some(now)),
+        updated_at: ActiveValue::Set(Some(now)),
+    }
+}
+
+/// Create the process of a task
+/// 
+/// The url is the owner of the task, so it's known where the output went
+pub fn process_active_model(task: &TaskInfo, pid: Option<u32>) -> Result<ProcessActiveModel, Box<dyn Error>> {
+    let now = Utc::now().naive_utc();
+    let pid = match pid {
+        Some(pid) => Some(i32::try_from(pid)?),
+        None => None,
+    };
+    
+    Ok(ProcessActiveModel {
+        name: ActiveValue::Set(process_name(&task.id)),
+        pid: ActiveValue::Set(pid),
+        url: ActiveValue::Set(Some(format!("/p2p/{}", task.owner))),
+        app_type: ActiveValue::Set(HIVE_TASK_APP_TYPE.to_string()),
+        created_at: ActiveValue::Set(Some(now)),
+        updated_at: ActiveValue::Set(Some(now)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::PeerId;
+    use sea_orm::TryIntoModel;
+    
+    use crate::p2p::node::task::{new_task_id, TaskSpec, TaskStatus};
+    
+    #[test]
+    fn test_task_models() {
+        let mut task = TaskInfo::new(new_task_id(), PeerId::random(), PeerId::random(), TaskSpec::new("echo", &["Hello"]));
+        
+        let app = app_active_model(&task).try_into_model().unwrap();
+        let process = process_active_model(&task, Some(42)).unwrap().try_into_model().unwrap();
+        assert_eq!(app.name, process.name);
+        assert_eq!(app.path, Some("echo Hello".to_string()));
+        assert_eq!(process.pid, Some(42));
+        assert_eq!(process.app_type, HIVE_TASK_APP_TYPE);
+        
+        task.set_status(TaskStatus::Failed, Some(2), None);
+        assert_eq!(result_line(&task), "Task failed with exit code 2");
+    }
+}
@@ -0,0 +1,276 @@
+//! Distributed tasks
+//! 
+//! A node submits a task to a peer, the peer runs it on a subprocess and streams the output back.
+//! Both sides keep the task on their task book, so its status can be asked on either of them.
+use chrono::{DateTime, Utc};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use strum_macros::Display;
+
+pub mod controller;
+pub mod recorder;
+pub mod runner;
+
+use crate::server_node::resources::requirements::ResourceRequirements;
+
+/// Task id
+/// 
+/// Created by the node that submits the task
+pub type TaskId = String;
+
+/// Output chunks kept for each task, older chunks are dropped
+pub const MAX_KEPT_OUTPUT_CHUNKS: usize = 1024;
+
+/// Create a task id
+/// 
+/// 
+pub fn new_task_id() -> TaskId {
+    nanoid::nanoid!()
+}
+
+/// Task specification
+/// 
+/// What has to be run and what the node needs to run it
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct TaskSpec {
+    pub program: String,
+    pub args: Vec<String>,
+    // Added to the environment of the node
+    pub env: HashMap<String, String>,
+    // Seconds until the task is killed
+    pub timeout: Option<u64>,
+    pub requirements: ResourceRequirements,
+}
+
+impl TaskSpec {
+    pub fn new(program: &str, args: &[&str]) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+    
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout.map(Duration::from_secs)
+    }
+    
+    /// Command line
+    /// 
+    /// Only used to show the task
+    pub fn command_line(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(&self.args)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Task status
+/// 
+/// 
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, Deserialize, Serialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    /// Submitted, the peer hasn't accepted it yet
+    Pending,
+    Running,
+    /// Exited with code zero
+    Succeeded,
+    /// Exited with another code or couldn't be waited
+    Failed,
+    Cancelled,
+    TimedOut,
+    /// The peer refused to run it
+    Rejected,
+}
+
+impl TaskStatus {
+    /// Whether the task won't change anymore
+    /// 
+    /// 
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, TaskStatus::Pending | TaskStatus::Running)
+    }
+}
+
+/// Output stream
+/// 
+/// 
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Output chunk
+/// 
+/// Chunks are sent as they are read, they don't have to end on a new line
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct OutputChunk {
+    pub stream: OutputStream,
+    pub data: String,
+}
+
+/// Task information
+/// 
+/// 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TaskInfo {
+    pub id: TaskId,
+    // Node that submitted the task
+    pub owner: PeerId,
+    // Node that runs the task
+    pub runner: PeerId,
+    pub spec: TaskSpec,
+    pub status: TaskStatus,
+    pub exit_code: Option<i32>,
+    // Why the task was rejected or failed
+    pub error: Option<String>,
+    pub output: Vec<OutputChunk>,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl TaskInfo {
+    pub fn new(id: TaskId, owner: PeerId, runner: PeerId, spec: TaskSpec) -> Self {
+        Self {
+            id,
+            owner,
+            runner,
+            spec,
+            status: TaskStatus::Pending,
+            exit_code: None,
+            error: None,
+            output: Vec::new(),
+            created_at: Utc::now(),
+            finished_at: None,
+        }
+    }
+    
+    /// Set the status
+    /// 
+    /// Finished tasks keep their status
+    pub fn set_status(&mut self, status: TaskStatus, exit_code: Option<i32>, error: Option<String>) -> bool {
+        if self.status.is_finished() {
+            return false;
+        }
+        
+        self.status = status;
+        self.exit_code = exit_code;
+        self.error = error;
+        if status.is_finished() {
+            self.finished_at = Some(Utc::now());
+        }
+        
+        true
+    }
+    
+    /// Append output
+    /// 
+    /// 
+    pub fn push_output(&mut self, chunk: OutputChunk) {
+        if self.output.len() >= MAX_KEPT_OUTPUT_CHUNKS {
+            self.output.remove(0);
+        }
+        
+        self.output.push(chunk);
+    }
+}
+
+/// Task book
+/// 
+/// Tasks submitted by this node and tasks run for other nodes
+#[derive(Clone, Debug, Default)]
+pub struct TaskBook {
+    tasks: HashMap<TaskId, TaskInfo>,
+}
+
+impl TaskBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add a task
+    /// 
+    /// Returns false if there's already a task with the same id
+    pub fn insert(&mut self, task: TaskInfo) -> bool {
+        if self.tasks.contains_key(&task.id) {
+            return false;
+        }
+        
+        self.tasks.insert(task.id.clone(), task);
+        
+        true
+    }
+    
+    pub fn get(&self, task_id: &str) -> Option<&TaskInfo> {
+        self.tasks.get(task_id)
+    }
+    
+    pub fn get_mut(&mut self, task_id: &str) -> Option<&mut TaskInfo> {
+        self.tasks.get_mut(task_id)
+    }
+    
+    /// Tasks sorted by creation time
+    /// 
+    /// 
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        let mut tasks: Vec<TaskInfo> = self.tasks.values().cloned().collect();
+        tasks.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        
+        tasks
+    }
+    
+    /// Tasks being run on a peer
+    /// 
+    /// 
+    pub fn running_on(&self, runner: &PeerId) -> usize {
+        self.tasks
+            .values()
+            .filter(|task| task.runner == *runner && !task.status.is_finished())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_finished_status_is_kept() {
+        let mut task = TaskInfo::new(new_task_id(), PeerId::random(), PeerId::random(), TaskSpec::new("true", &[]));
+        
+        assert!(task.set_status(TaskStatus::Running, None, None));
+        assert!(task.finished_at.is_none());
+        
+        assert!(task.set_status(TaskStatus::Cancelled, None, None));
+        assert!(task.finished_at.is_some());
+        
+        // The exit status arrives after the cancellation
+        assert!(!task.set_status(TaskStatus::Failed, Some(137), None));
+        assert_eq!(task.status, TaskStatus::Cancelled);
+        assert_eq!(task.exit_code, None);
+    }
+    
+    #[test]
+    fn test_task_book() {
+        let runner = PeerId::random();
+        let mut task_book = TaskBook::new();
+        
+        let task = TaskInfo::new(new_task_id(), PeerId::random(), runner, TaskSpec::new("true", &[]));
+        assert!(task_book.insert(task.clone()));
+        assert!(!task_book.insert(task.clone()));
+        assert_eq!(task_book.running_on(&runner), 1);
+        
+        task_book.get_mut(&task.id).unwrap().set_status(TaskStatus::Succeeded, Some(0), None);
+        assert_eq!(task_book.running_on(&runner), 0);
+        assert_eq!(task_book.tasks().len(), 1);
+    }
+}
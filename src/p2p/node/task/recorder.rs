@@ -0,0 +1,205 @@
+//! Task recorder
+//! 
+//! Tasks are written on the database from their own tokio task, so the node event loop doesn't
+//! wait for it. Output is buffered and stored by whole lines, in batches.
+use sea_orm::DatabaseConnection;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::select;
+use tokio::sync::mpsc;
+
+use super::controller::TaskController;
+use super::{OutputChunk, OutputStream, TaskId, TaskInfo};
+
+/// Time between writes of the buffered output
+pub const OUTPUT_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Buffered bytes of a stream that are written even without a new line
+const MAX_PENDING_OUTPUT: usize = 64 * 1024;
+
+/// Task record
+/// 
+/// 
+#[derive(Debug)]
+pub enum TaskRecord {
+    Started {
+        task: TaskInfo,
+        pid: Option<u32>,
+    },
+    Output {
+        task_id: TaskId,
+        chunk: OutputChunk,
+    },
+    /// The output left is written before the result
+    Finished {
+        task: TaskInfo,
+    },
+}
+
+/// Task recorder
+/// 
+/// Records are written in order, the recorder stops once every sender is dropped
+#[derive(Clone, Debug)]
+pub struct TaskRecorder {
+    records: mpsc::UnboundedSender<TaskRecord>,
+}
+
+impl TaskRecorder {
+    /// Start writing records on a database
+    /// 
+    /// 
+    pub fn spawn(db: DatabaseConnection) -> Self {
+        let (records, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(record(TaskController::new(db), receiver));
+        
+        Self { records }
+    }
+    
+    /// Queue a record
+    /// 
+    /// 
+    pub fn record(&self, record: TaskRecord) {
+        if self.records.send(record).is_err() {
+            tracing::warn!("The task recorder stopped, a task record was lost");
+        }
+    }
+}
+
+/// Output waiting to be written
+/// 
+/// Kept for each task and stream, so lines of stdout and stderr aren't mixed
+#[derive(Debug, Default)]
+pub struct OutputBuffer {
+    pending: HashMap<(TaskId, OutputStream), String>,
+}
+
+impl OutputBuffer {
+    pub fn push(&mut self, task_id: &TaskId, chunk: &OutputChunk) {
+        self.pending
+            .entry((task_id.clone(), chunk.stream))
+            .or_default()
+            .push_str(&chunk.data);
+    }
+    
+    /// Take the complete lines
+    /// 
+    /// Every stream gives a single batch, streams without a new line keep their output until
+    /// it's too large
+    pub fn lines(&mut self) -> Vec<(TaskId, String)> {
+        let mut lines = Vec::new();
+        
+        for ((task_id, _), pending) in self.pending.iter_mut() {
+            let end = match pending.rfind('\n') {
+                Some(index) => index + 1,
+                None if pending.len() >= MAX_PENDING_OUTPUT => pending.len(),
+                None => continue,
+            };
+            
+            let rest = pending.split_off(end);
+            lines.push((task_id.clone(), std::mem::replace(pending, rest)));
+        }
+        self.pending.retain(|_, pending| !pending.is_empty());
+        
+        lines
+    }
+    
+    /// Take everything left of a task
+    /// 
+    /// 
+    pub fn finish(&mut self, task_id: &TaskId) -> Vec<(TaskId, String)> {
+        let streams: Vec<_> = self.pending
+            .keys()
+            .filter(|(id, _)| id == task_id)
+            .cloned()
+            .collect();
+        
+        streams
+            .into_iter()
+            .filter_map(|key| self.pending.remove(&key).map(|pending| (key.0, pending)))
+            .collect()
+    }
+}
+
+/// Write the records as they come
+/// 
+/// 
+async fn record(controller: TaskController, mut records: mpsc::UnboundedReceiver<TaskRecord>) {
+    let mut output = OutputBuffer::default();
+    let mut flush_timer = tokio::time::interval(OUTPUT_FLUSH_INTERVAL);
+    
+    loop {
+        select! {
+            record = records.recv() => match record {
+                Some(TaskRecord::Started { task, pid }) => {
+                    if let Err(err) = controller.start(&task, pid).await {
+                        tracing::warn!("Couldn't record task {}: {err}", task.id);
+                    }
+                }
+                Some(TaskRecord::Output { task_id, chunk }) => output.push(&task_id, &chunk),
+                Some(TaskRecord::Finished { task }) => {
+                    write_output(&controller, output.finish(&task.id)).await;
+                    
+                    if let Err(err) = controller.finish(&task).await {
+                        tracing::warn!("Couldn't record the result of task {}: {err}", task.id);
+                    }
+                }
+                None => break,
+            },
+            _ = flush_timer.tick() => write_output(&controller, output.lines()).await,
+        }
+    }
+    
+    // The node is gone, partial lines too
+    let rest = output.pending
+        .drain()
+        .map(|((task_id, _), pending)| (task_id, pending))
+        .collect();
+    write_output(&controller, rest).await;
+}
+
+async fn write_output(controller: &TaskController, output: Vec<(TaskId, String)>) {
+    if output.is_empty() {
+        return;
+    }
+    
+    if let Err(err) = controller.record_output(output).await {
+        tracing::warn!("Couldn't record the output of the tasks: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn chunk(stream: OutputStream, data: &str) -> OutputChunk {
+        OutputChunk {
+            stream,
+            data: data.to_string(),
+        }
+    }
+    
+    #[test]
+    fn test_output_is_written_by_lines() {
+        let task_id: TaskId = "task".to_string();
+        let mut output = OutputBuffer::default();
+        
+        output.push(&task_id, &chunk(OutputStream::Stdout, "first li"));
+        output.push(&task_id, &chunk(OutputStream::Stderr, "error"));
+        assert!(output.lines().is_empty());
+        
+        // Chunks of a stream are joined, the partial line waits
+        output.push(&task_id, &chunk(OutputStream::Stdout, "ne\nsecond line\nthi"));
+        assert_eq!(output.lines(), vec![(task_id.clone(), "first line\nsecond line\n".to_string())]);
+        assert!(output.lines().is_empty());
+        
+        // Long output without new lines isn't kept forever
+        output.push(&task_id, &chunk(OutputStream::Stdout, &"a".repeat(MAX_PENDING_OUTPUT)));
+        assert_eq!(output.lines()[0].1.len(), MAX_PENDING_OUTPUT + 3);
+        
+        // Whatever is left is written once the task finishes
+        let mut left = output.finish(&task_id);
+        left.sort();
+        assert_eq!(left, vec![(task_id.clone(), "error".to_string())]);
+        assert!(output.finish(&task_id).is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+//! Task runner
+//! 
+//! Runs tasks on subprocesses, what happens to them is reported through a channel
+//! so the node can forward it to the owner of the task.
+use std::collections::HashMap;
+use std::process::Stdio;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, oneshot};
+
+use super::{OutputChunk, OutputStream, TaskId, TaskSpec, TaskStatus};
+
+/// Bytes read from the output at once
+const OUTPUT_CHUNK_SIZE: usize = 4096;
+
+/// Runner event
+/// 
+/// 
+#[derive(Clone, Debug)]
+pub enum RunnerEvent {
+    Output {
+        task_id: TaskId,
+        chunk: OutputChunk,
+    },
+    /// Sent after all the output
+    Finished {
+        task_id: TaskId,
+        status: TaskStatus,
+        exit_code: Option<i32>,
+        error: Option<String>,
+    },
+}
+
+/// Task runner
+/// 
+/// Subprocesses are killed when the runner is dropped
+pub struct TaskRunner {
+    events: mpsc::UnboundedSender<RunnerEvent>,
+    // Cancels the running tasks
+    running: HashMap<TaskId, oneshot::Sender<()>>,
+}
+
+impl TaskRunner {
+    /// Create a runner and the receiver of its events
+    /// 
+    /// 
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<RunnerEvent>) {
+        let (events, receiver) = mpsc::unbounded_channel();
+        
+        let runner = Self {
+            events,
+            running: HashMap::new(),
+        };
+        
+        (runner, receiver)
+    }
+    
+    /// Start a task
+    /// 
+    /// Returns the pid of the subprocess, the task is supervised on its own tokio task
+    pub fn run(&mut self, task_id: TaskId, spec: &TaskSpec) -> Result<Option<u32>, std::io::Error> {
+        let mut child = Command::new(&spec.program)
+            .args(&spec.args)
+            .envs(&spec.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let pid = child.id();
+        
+        let (cancel, cancelled) = oneshot::channel();
+        self.running.insert(task_id.clone(), cancel);
+        
+        let outputs = [
+            child.stdout.take().map(|stdout| {
+                tokio::spawn(forward_output(task_id.clone(), OutputStream::Stdout, stdout, self.events.clone()))
+            }),
+            child.stderr.take().map(|stderr| {
+                tokio::spawn(forward_output(task_id.clone(), OutputStream::Stderr, stderr, self.events.clone()))
+            }),
+        ];
+        
+        let events = self.events.clone();
+        let timeout = spec.timeout();
+        tokio::spawn(async move {
+            let (status, exit_code, error) = supervise(&mut child, timeout, cancelled).await;
+            
+            // Everything that was written has to arrive before the task finishes
+            for output in outputs.into_iter().flatten() {
+                let _ = output.await;
+            }
+            
+            let _ = events.send(RunnerEvent::Finished {
+                task_id,
+                status,
+                exit_code,
+                error,
+            });
+        });
+        
+        Ok(pid)
+    }
+    
+    /// Cancel a task
+    /// 
+    /// Returns false if the task isn't running
+    pub fn cancel(&mut self, task_id: &str) -> bool {
+        match self.running.remove(task_id) {
+            Some(cancel) => cancel.send(()).is_ok(),
+            None => false,
+        }
+    }
+    
+    /// Forget a finished task
+    /// 
+    /// 
+    pub fn finished(&mut self, task_id: &str) {
+        self.running.remove(task_id);
+    }
+    
+    pub fn is_running(&self, task_id: &str) -> bool {
+        self.running.contains_key(task_id)
+    }
+}
+
+/// Wait until the subprocess exits, times out or is cancelled
+/// 
+/// 
+async fn supervise(
+    child: &mut Child,
+    timeout: Option<std::time::Duration>,
+    cancelled: oneshot::Receiver<()>,
+) -> (TaskStatus, Option<i32>, Option<String>) {
+    let deadline = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    
+    let status = tokio::select! {
+        result = child.wait() => {
+            return match result {
+                Ok(exit) if exit.success() => (TaskStatus::Succeeded, exit.code(), None),
+                Ok(exit) => (TaskStatus::Failed, exit.code(), None),
+                Err(err) => (TaskStatus::Failed, None, Some(err.to_string())),
+            };
+        }
+        // The runner was dropped too
+        _ = cancelled => TaskStatus::Cancelled,
+        _ = deadline => TaskStatus::TimedOut,
+    };
+    
+    if let Err(err) = child.kill().await {
+        return (TaskStatus::Failed, None, Some(format!("Couldn't kill the task: {err}")));
+    }
+    
+    (status, None, None)
+}
+
+/// Send the output of a stream as it's read
+/// 
+/// 
+async fn forward_output(
+    task_id: TaskId,
+    stream: OutputStream,
+    mut reader: impl AsyncRead + Unpin,
+    events: mpsc::UnboundedSender<RunnerEvent>,
+) {
+    let mut buffer = vec![0u8; OUTPUT_CHUNK_SIZE];
+    
+    loop {
+        let read = match reader.read(&mut buffer).await {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) => {
+                tracing::warn!("Couldn't read the {stream} of task {task_id}: {err}");
+                break;
+            }
+        };
+        
+        let chunk = OutputChunk {
+            stream,
+            data: String::from_utf8_lossy(&buffer[..read]).into_owned(),
+        };
+        let event = RunnerEvent::Output {
+            task_id: task_id.clone(),
+            chunk,
+        };
+        if events.send(event).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Collect the output until the task finishes
+    /// 
+    /// 
+    async fn wait_for_task(
+        receiver: &mut mpsc::UnboundedReceiver<RunnerEvent>,
+    ) -> (Vec<OutputChunk>, TaskStatus, Option<i32>) {
+        let mut output = Vec::new();
+        
+        loop {
+            match receiver.recv().await.unwrap() {
+                RunnerEvent::Output { chunk, .. } => output.push(chunk),
+                RunnerEvent::Finished { status, exit_code, .. } => return (output, status, exit_code),
+            }
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_run_task() {
+        let (mut runner, mut receiver) = TaskRunner::new();
+        
+        let mut spec = TaskSpec::new("sh", &["-c", "echo \"$GREETING\"; echo oops >&2; exit 3"]);
+        spec.env.insert("GREETING".to_string(), "Hello".to_string());
+        
+        let pid = runner.run("task".to_string(), &spec).unwrap();
+        assert!(pid.is_some());
+        
+        let (output, status, exit_code) = wait_for_task(&mut receiver).await;
+        assert_eq!(status, TaskStatus::Failed);
+        assert_eq!(exit_code, Some(3));
+        
+        let stdout: String = output.iter().filter(|chunk| chunk.stream == OutputStream::Stdout).map(|chunk| chunk.data.as_str()).collect();
+        let stderr: String = output.iter().filter(|chunk| chunk.stream == OutputStream::Stderr).map(|chunk| chunk.data.as_str()).collect();
+        assert_eq!(stdout, "Hello\n");
+        assert_eq!(stderr, "oops\n");
+    }
+    
+    #[tokio::test]
+    async fn test_cancel_and_timeout() {
+        let (mut runner, mut receiver) = TaskRunner::new();
+        
+        runner.run("cancelled".to_string(), &TaskSpec::new("sleep", &["30"])).unwrap();
+        assert!(runner.cancel("cancelled"));
+        assert!(!runner.cancel("cancelled"));
+        
+        let (_, status, exit_code) = wait_for_task(&mut receiver).await;
+        assert_eq!(status, TaskStatus::Cancelled);
+        assert_eq!(exit_code, None);
+        
+        let mut spec = TaskSpec::new("sleep", &["30"]);
+        spec.timeout = Some(1);
+        runner.run("timed-out".to_string(), &spec).unwrap();
+        
+        let (_, status, _) = wait_for_task(&mut receiver).await;
+        assert_eq!(status, TaskStatus::TimedOut);
+    }
+    
+    #[tokio::test]
+    async fn test_missing_program() {
+        let (mut runner, _receiver) = TaskRunner::new();
+        
+        assert!(runner.run("missing".to_string(), &TaskSpec::new("hive-missing-program", &[])).is_err());
+        assert!(!runner.is_running("missing"));
+    }
+}
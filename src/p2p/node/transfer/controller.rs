@@ -0,0 +1,98 @@
+//! Transfer controller
+//! 
+//! Transferred files are recorded on the 'file' table, a file that is transferred again
+//! updates its row.
+use chrono::Utc;
+use entity::file::{self, ActiveModel as FileActiveModel, Entity as FileEntity};
+use sea_orm::{ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use std::error::Error;
+use std::path::Path;
+
+/// Transfer controller
+/// 
+/// 
+pub struct TransferController {
+    pub db: DatabaseConnection,
+}
+
+impl TransferController {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+    
+    /// Record a complete file
+    /// 
+    /// Returns the id of its row
+    pub async fn record_file(&self, path: &Path, size: u64, md5: &str) -> Result<i64, Box<dyn Error>> {
+        let path = path.to_string_lossy().to_string();
+        let existing = FileEntity::find()
+            .filter(file::Column::Path.eq(&path))
+            .one(&self.db)
+            .await?;
+        
+        let file = file_active_model(existing, &path, size, md5)?;
+        let file = file.save(&self.db).await?;
+        
+        Ok(file.id.unwrap())
+    }
+}
+
+/// Create the row of a file
+/// 
+/// The row of a previous transfer of the same path is reused
+pub fn file_active_model(
+    existing: Option<file::Model>,
+    path: &str,
+    size: u64,
+    md5: &str,
+) -> Result<FileActiveModel, Box<dyn Error>> {
+    let now = Utc::now().naive_utc();
+    let name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    
+    let mut file: FileActiveModel = match existing {
+        Some(existing) => existing.into(),
+        None => FileActiveModel {
+            path: ActiveValue::Set(path.to_string()),
+            created: ActiveValue::Set(Some(now)),
+            ..Default::default()
+        },
+    };
+    file.name = ActiveValue::Set(name);
+    file.size = ActiveValue::Set(i64::try_from(size)?);
+    file.md5 = ActiveValue::Set(md5.to_string());
+    file.updated = ActiveValue::Set(Some(now));
+    
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_file_active_model() {
+        let file = file_active_model(None, ".cache/hive/files/report.pdf", 1024, "abc").unwrap();
+        
+        assert_eq!(file.name, ActiveValue::Set("report.pdf".to_string()));
+        assert_eq!(file.path, ActiveValue::Set(".cache/hive/files/report.pdf".to_string()));
+        assert_eq!(file.size, ActiveValue::Set(1024));
+        assert!(file.id.is_not_set());
+        
+        let existing = file::Model {
+            id: 7,
+            name: "report.pdf".to_string(),
+            size: 10,
+            path: ".cache/hive/files/report.pdf".to_string(),
+            md5: "old".to_string(),
+            created: None,
+            updated: None,
+            last_opened: None,
+        };
+        let file = file_active_model(Some(existing), ".cache/hive/files/report.pdf", 1024, "abc").unwrap();
+        assert_eq!(file.id, ActiveValue::Unchanged(7));
+        assert_eq!(file.md5, ActiveValue::Set("abc".to_string()));
+    }
+}
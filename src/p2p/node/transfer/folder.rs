@@ -0,0 +1,251 @@
+//! Transfer folder
+//! 
+//! Files sent by other nodes are written here and peers can only fetch files inside it.
+//! Incomplete files are kept next to their destination with the md5 they should have, so
+//! a transfer of the same file resumes from them.
+use std::io::SeekFrom;
+use std::path::{Component, Path, PathBuf};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use super::{file_md5, EMPTY_MD5};
+
+/// Transfer folder
+/// 
+/// 
+#[derive(Clone, Debug)]
+pub struct TransferFolder {
+    pub path: PathBuf,
+}
+
+impl TransferFolder {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+    
+    /// Path of a file inside the folder
+    /// 
+    /// Absolute paths and paths that leave the folder are rejected
+    pub fn resolve(&self, relative: &str) -> Result<PathBuf, String> {
+        let relative = Path::new(relative);
+        
+        let is_inside = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if relative.as_os_str().is_empty() || !is_inside {
+            return Err(format!("'{}' isn't inside the transfer folder", relative.display()));
+        }
+        
+        Ok(self.path.join(relative))
+    }
+}
+
+/// Whether a string is an md5, 32 hex characters
+/// 
+/// 
+pub fn is_md5(md5: &str) -> bool {
+    md5.len() == 32 && md5.chars().all(|character| character.is_ascii_hexdigit())
+}
+
+/// Path of the incomplete file
+/// 
+/// The md5 comes from the peer, anything else than an md5 could leave the transfer folder
+pub fn partial_path(destination: &Path, md5: &str) -> Result<PathBuf, String> {
+    if !is_md5(md5) {
+        return Err(format!("'{md5}' isn't an md5"));
+    }
+    let file_name = destination.file_name().unwrap_or_default().to_string_lossy();
+    
+    Ok(destination.with_file_name(format!(".{file_name}.{md5}.part")))
+}
+
+/// Bytes of a file that are already on disk
+/// 
+/// All of them if the destination already has the same content, otherwise the ones of the incomplete file
+pub async fn existing_bytes(destination: &Path, size: u64, md5: &str) -> Result<u64, String> {
+    let partial = partial_path(destination, md5)?;
+    
+    if let Ok(destination_md5) = file_md5(destination).await {
+        if destination_md5 == md5 {
+            return Ok(size);
+        }
+    }
+    
+    match fs::metadata(partial).await {
+        Ok(metadata) => Ok(metadata.len().min(size)),
+        Err(_) => Ok(0),
+    }
+}
+
+/// Read a chunk
+/// 
+/// It's shorter than the given length at the end of the file
+pub async fn read_chunk(path: &Path, offset: u64, length: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = fs::File::open(path).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    
+    let mut data = Vec::new();
+    file.take(length).read_to_end(&mut data).await?;
+    
+    Ok(data)
+}
+
+/// Append a chunk to an incomplete file
+/// 
+/// Returns the new length of the file, nothing is written if the offset isn't the end of the file
+pub async fn append_chunk(partial: &Path, offset: u64, data: &[u8]) -> std::io::Result<u64> {
+    if let Some(parent) = partial.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(partial).await?;
+    let length = file.metadata().await?.len();
+    if length != offset {
+        return Ok(length);
+    }
+    
+    file.write_all(data).await?;
+    file.flush().await?;
+    
+    Ok(length + data.len() as u64)
+}
+
+/// Move a complete file to its destination
+/// 
+/// The incomplete file is removed if its md5 doesn't match, so the next attempt starts over.
+/// Empty files don't have chunks, so they never have an incomplete file
+pub async fn complete(destination: &Path, md5: &str) -> Result<(), String> {
+    let partial = partial_path(destination, md5)?;
+    
+    if fs::metadata(&partial).await.is_err() {
+        return match file_md5(destination).await {
+            // It was already there
+            Ok(destination_md5) if destination_md5 == md5 => Ok(()),
+            _ if md5 == EMPTY_MD5 => create_empty(destination).await.map_err(|err| err.to_string()),
+            _ => Err(format!("Nothing was transferred to '{}'", destination.display())),
+        };
+    }
+    
+    let partial_md5 = file_md5(&partial).await.map_err(|err| err.to_string())?;
+    if partial_md5 != md5 {
+        let _ = fs::remove_file(&partial).await;
+        return Err(format!("The md5 of the file is {partial_md5}, expected {md5}"));
+    }
+    
+    fs::rename(&partial, destination).await.map_err(|err| err.to_string())
+}
+
+async fn create_empty(destination: &Path) -> std::io::Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    
+    fs::write(destination, b"").await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p2p::node::transfer::chunk_hash;
+    use crate::test::folder::hive_test_folder::HiveTestFolder;
+    
+    #[test]
+    fn test_resolve() {
+        let folder = TransferFolder::new(PathBuf::from("shared"));
+        
+        assert_eq!(folder.resolve("a/b.txt").unwrap(), PathBuf::from("shared/a/b.txt"));
+        assert!(folder.resolve("").is_err());
+        assert!(folder.resolve("/etc/passwd").is_err());
+        assert!(folder.resolve("../secret.key").is_err());
+        assert!(folder.resolve("a/../../secret.key").is_err());
+        
+        // The md5 of the incomplete file can't leave the folder either
+        let destination = folder.resolve("a/b.txt").unwrap();
+        let md5 = "0123456789abcdef0123456789ABCDEF";
+        assert_eq!(
+            partial_path(&destination, md5).unwrap(),
+            PathBuf::from(format!("shared/a/.b.txt.{md5}.part"))
+        );
+        assert!(partial_path(&destination, "x/../../../../tmp/evil").is_err());
+        assert!(partial_path(&destination, &format!("../{}", &md5[3..])).is_err());
+        assert!(partial_path(&destination, &"0".repeat(33)).is_err());
+        assert!(partial_path(&destination, "").is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_traversal_md5() {
+        let test_folder = HiveTestFolder::default();
+        test_folder.create().unwrap();
+        
+        let mut destination = PathBuf::from(&test_folder.path);
+        destination.push(format!("transfer_{}", nanoid::nanoid!(6)));
+        destination.push("data.bin");
+        
+        let md5 = "x/../../../../tmp/evil";
+        assert!(existing_bytes(&destination, 10, md5).await.is_err());
+        assert!(complete(&destination, md5).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_resume_and_complete() {
+        let test_folder = HiveTestFolder::default();
+        test_folder.create().unwrap();
+        
+        let mut destination = PathBuf::from(&test_folder.path);
+        destination.push(format!("transfer_{}", nanoid::nanoid!(6)));
+        destination.push("data.bin");
+        
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let md5 = format!("{:x}", <md5::Md5 as md5::Digest>::digest(&data));
+        assert_eq!(existing_bytes(&destination, 1000, &md5).await.unwrap(), 0);
+        
+        // The first half was written by a previous attempt
+        let partial = partial_path(&destination, &md5).unwrap();
+        assert_eq!(append_chunk(&partial, 0, &data[..500]).await.unwrap(), 500);
+        assert_eq!(existing_bytes(&destination, 1000, &md5).await.unwrap(), 500);
+        
+        // Writing at the wrong offset does nothing
+        assert_eq!(append_chunk(&partial, 200, &data[200..]).await.unwrap(), 500);
+        assert_eq!(append_chunk(&partial, 500, &data[500..]).await.unwrap(), 1000);
+        
+        let chunk = read_chunk(&partial, 900, 500).await.unwrap();
+        assert_eq!(chunk_hash(&chunk), chunk_hash(&data[900..]));
+        
+        complete(&destination, &md5).await.unwrap();
+        assert_eq!(std::fs::read(&destination).unwrap(), data);
+        assert_eq!(existing_bytes(&destination, 1000, &md5).await.unwrap(), 1000);
+        
+        // Already complete
+        complete(&destination, &md5).await.unwrap();
+        
+        // A corrupted file is discarded
+        let other = destination.with_file_name("other.bin");
+        let wrong = "0".repeat(32);
+        append_chunk(&partial_path(&other, &wrong).unwrap(), 0, &data).await.unwrap();
+        assert!(complete(&other, &wrong).await.is_err());
+        assert!(!partial_path(&other, &wrong).unwrap().exists());
+    }
+    
+    #[tokio::test]
+    async fn test_complete_empty_file() {
+        let test_folder = HiveTestFolder::default();
+        test_folder.create().unwrap();
+        
+        let mut destination = PathBuf::from(&test_folder.path);
+        destination.push(format!("transfer_{}", nanoid::nanoid!(6)));
+        destination.push("empty.txt");
+        
+        // Nothing is written before it's completed
+        assert_eq!(existing_bytes(&destination, 0, EMPTY_MD5).await.unwrap(), 0);
+        complete(&destination, EMPTY_MD5).await.unwrap();
+        assert_eq!(std::fs::metadata(&destination).unwrap().len(), 0);
+        
+        // Already complete
+        complete(&destination, EMPTY_MD5).await.unwrap();
+        
+        // Other files still need their chunks
+        let other = destination.with_file_name("other.txt");
+        assert!(complete(&other, &"0".repeat(32)).await.is_err());
+        assert!(!other.exists());
+    }
+}
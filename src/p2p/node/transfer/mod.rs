@@ -0,0 +1,254 @@
+//! File transfers
+//! 
+//! Files are moved between nodes in chunks, every chunk carries its hash and the whole file is
+//! checked against its md5 once it's complete. Interrupted transfers resume from the bytes that
+//! were already written.
+use chrono::{DateTime, Utc};
+use libp2p::PeerId;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use strum_macros::Display;
+use tokio::io::AsyncReadExt;
+
+pub mod controller;
+pub mod folder;
+pub mod runner;
+
+/// Transfer id
+/// 
+/// Created by the node that starts the transfer
+pub type TransferId = String;
+
+/// Bytes asked or sent at once
+pub const DEFAULT_CHUNK_SIZE: u64 = 256 * 1024;
+
+/// Biggest chunk a node serves, base64 encoded it still fits on a single request
+pub const MAX_CHUNK_SIZE: u64 = 512 * 1024;
+
+/// Times a chunk is sent again when its hash doesn't match
+pub const MAX_CHUNK_ATTEMPTS: usize = 3;
+
+/// Md5 of an empty file
+pub const EMPTY_MD5: &str = "d41d8cd98f00b204e9800998ecf8427e";
+
+/// Create a transfer id
+/// 
+/// 
+pub fn new_transfer_id() -> TransferId {
+    nanoid::nanoid!()
+}
+
+/// Transfer direction
+/// 
+/// 
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, Deserialize, Serialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum TransferDirection {
+    /// A local file is sent to the peer
+    Send,
+    /// A file of the peer is fetched
+    Fetch,
+}
+
+/// Transfer status
+/// 
+/// 
+#[derive(Clone, Copy, Debug, Display, PartialEq, Eq, Deserialize, Serialize)]
+#[strum(serialize_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    Running,
+    /// Every byte arrived and the md5 matches
+    Completed,
+    Failed,
+}
+
+impl TransferStatus {
+    pub fn is_finished(&self) -> bool {
+        *self != TransferStatus::Running
+    }
+}
+
+/// Transfer information
+/// 
+/// 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TransferInfo {
+    pub id: TransferId,
+    pub peer: PeerId,
+    pub direction: TransferDirection,
+    pub local_path: PathBuf,
+    // Relative to the transfer folder of the peer
+    pub remote_path: String,
+    // Known once the transfer has started
+    pub size: Option<u64>,
+    pub md5: Option<String>,
+    pub transferred: u64,
+    // Bytes that were already there from a previous attempt
+    pub resumed_from: u64,
+    // Bytes per second, unlimited when it's not set
+    pub rate_limit: Option<u64>,
+    pub status: TransferStatus,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl TransferInfo {
+    pub fn new(
+        id: TransferId,
+        peer: PeerId,
+        direction: TransferDirection,
+        local_path: PathBuf,
+        remote_path: String,
+        rate_limit: Option<u64>,
+    ) -> Self {
+        Self {
+            id,
+            peer,
+            direction,
+            local_path,
+            remote_path,
+            size: None,
+            md5: None,
+            transferred: 0,
+            resumed_from: 0,
+            rate_limit: rate_limit.filter(|rate_limit| *rate_limit > 0),
+            status: TransferStatus::Running,
+            error: None,
+            started_at: Utc::now(),
+            finished_at: None,
+        }
+    }
+    
+    /// Set the status
+    /// 
+    /// Finished transfers keep their status
+    pub fn set_status(&mut self, status: TransferStatus, error: Option<String>) -> bool {
+        if self.status.is_finished() {
+            return false;
+        }
+        
+        self.status = status;
+        self.error = error;
+        if status.is_finished() {
+            self.finished_at = Some(Utc::now());
+        }
+        
+        true
+    }
+    
+    /// Transferred fraction, from 0 to 1
+    /// 
+    /// 
+    pub fn progress(&self) -> Option<f64> {
+        match self.size {
+            Some(0) => Some(1.0),
+            Some(size) => Some(self.transferred as f64 / size as f64),
+            None => None,
+        }
+    }
+}
+
+/// Transfer book
+/// 
+/// Transfers started by this node, the peer only sees the requests
+#[derive(Clone, Debug, Default)]
+pub struct TransferBook {
+    transfers: HashMap<TransferId, TransferInfo>,
+}
+
+impl TransferBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add or replace a transfer
+    /// 
+    /// 
+    pub fn insert(&mut self, transfer: TransferInfo) {
+        self.transfers.insert(transfer.id.clone(), transfer);
+    }
+    
+    pub fn get(&self, transfer_id: &str) -> Option<&TransferInfo> {
+        self.transfers.get(transfer_id)
+    }
+    
+    /// Every transfer, the oldest first
+    /// 
+    /// 
+    pub fn transfers(&self) -> Vec<TransferInfo> {
+        let mut transfers: Vec<_> = self.transfers.values().cloned().collect();
+        transfers.sort_by(|a, b| a.started_at.cmp(&b.started_at).then_with(|| a.id.cmp(&b.id)));
+        
+        transfers
+    }
+}
+
+/// Hash of a chunk
+/// 
+/// Sha256 as hexadecimal
+pub fn chunk_hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Md5 of a file
+/// 
+/// The file is read in chunks, so big files aren't loaded at once
+pub async fn file_md5(path: &Path) -> std::io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Md5::new();
+    let mut buffer = vec![0; 64 * 1024];
+    
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        
+        hasher.update(&buffer[..read]);
+    }
+    
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_finished_transfers_keep_their_status() {
+        let mut transfer = TransferInfo::new(
+            new_transfer_id(),
+            PeerId::random(),
+            TransferDirection::Fetch,
+            PathBuf::from("a.txt"),
+            "a.txt".to_string(),
+            Some(0),
+        );
+        assert_eq!(transfer.rate_limit, None);
+        assert_eq!(transfer.progress(), None);
+        
+        transfer.size = Some(4);
+        transfer.transferred = 1;
+        assert_eq!(transfer.progress(), Some(0.25));
+        
+        assert!(transfer.set_status(TransferStatus::Failed, Some("Lost".to_string())));
+        assert!(!transfer.set_status(TransferStatus::Completed, None));
+        assert_eq!(transfer.status, TransferStatus::Failed);
+        assert!(transfer.finished_at.is_some());
+    }
+    
+    #[test]
+    fn test_chunk_hash() {
+        assert_eq!(
+            chunk_hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(format!("{:x}", Md5::digest(b"")), EMPTY_MD5);
+    }
+}
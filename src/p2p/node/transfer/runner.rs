@@ -0,0 +1,322 @@
+//! Transfer runner
+//! 
+//! Each transfer runs on its own tokio task, the requests to the peer are sent by the node
+//! through the events of the runner, together with the progress of the transfers.
+use libp2p::PeerId;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+
+use super::folder;
+use super::{
+    chunk_hash,
+    file_md5,
+    TransferDirection,
+    TransferInfo,
+    TransferStatus,
+    DEFAULT_CHUNK_SIZE,
+    MAX_CHUNK_ATTEMPTS,
+};
+use crate::p2p::node::protocol::file::{FileAction, FileRequest, FileResponse};
+
+/// Transfer event
+/// 
+/// 
+#[derive(Debug)]
+pub enum TransferEvent {
+    /// Send a request to the peer and reply with its response
+    Request {
+        peer_id: PeerId,
+        request: Box<FileRequest>,
+        reply: oneshot::Sender<Result<FileResponse, String>>,
+    },
+    /// The transfer made progress or finished
+    Updated(Box<TransferInfo>),
+}
+
+/// Transfer runner
+/// 
+/// 
+pub struct TransferRunner {
+    events: mpsc::UnboundedSender<TransferEvent>,
+}
+
+impl TransferRunner {
+    /// Create a runner and the receiver of its events
+    /// 
+    /// 
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<TransferEvent>) {
+        let (events, receiver) = mpsc::unbounded_channel();
+        
+        (Self { events }, receiver)
+    }
+    
+    /// Start a transfer
+    /// 
+    /// The last event of the transfer has its final status
+    pub fn start(&self, transfer: TransferInfo) {
+        let link = PeerLink {
+            peer_id: transfer.peer,
+            events: self.events.clone(),
+        };
+        
+        tokio::spawn(async move {
+            let mut transfer = transfer;
+            let result = match transfer.direction {
+                TransferDirection::Send => send(&link, &mut transfer).await,
+                TransferDirection::Fetch => fetch(&link, &mut transfer).await,
+            };
+            
+            match result {
+                Ok(()) => transfer.set_status(TransferStatus::Completed, None),
+                Err(err) => transfer.set_status(TransferStatus::Failed, Some(err)),
+            };
+            link.update(&transfer);
+        });
+    }
+}
+
+/// Talks to the peer of a transfer through the node
+/// 
+/// 
+struct PeerLink {
+    peer_id: PeerId,
+    events: mpsc::UnboundedSender<TransferEvent>,
+}
+
+impl PeerLink {
+    /// Send a request and wait for the response
+    /// 
+    /// Refusals and errors of the peer are returned as errors
+    async fn request(&self, action: FileAction) -> Result<FileResponse, String> {
+        let request = FileRequest::new(action).map_err(|err| err.to_string())?;
+        let (reply, response) = oneshot::channel();
+        
+        self.events
+            .send(TransferEvent::Request {
+                peer_id: self.peer_id,
+                request: Box::new(request),
+                reply,
+            })
+            .map_err(|_| "Node is not running".to_string())?;
+        
+        match response.await.map_err(|_| "Node stopped before replying".to_string())?? {
+            FileResponse::Unauthorized => Err(format!("Peer {} refused the request", self.peer_id)),
+            FileResponse::Error(err) => Err(format!("Peer {}: {err}", self.peer_id)),
+            response => Ok(response),
+        }
+    }
+    
+    fn update(&self, transfer: &TransferInfo) {
+        let _ = self.events.send(TransferEvent::Updated(Box::new(transfer.clone())));
+    }
+}
+
+/// Unexpected response error
+/// 
+/// 
+fn unexpected(response: FileResponse) -> String {
+    format!("Unexpected response {response:?}")
+}
+
+/// Fetch a file of the peer
+/// 
+/// 
+async fn fetch(link: &PeerLink, transfer: &mut TransferInfo) -> Result<(), String> {
+    let (size, md5) = match link.request(FileAction::Stat { path: transfer.remote_path.clone() }).await? {
+        FileResponse::Stat { size, md5 } => (size, md5),
+        response => return Err(unexpected(response)),
+    };
+    
+    let destination = transfer.local_path.clone();
+    // The md5 comes from the peer, it's checked before it's used in a path
+    let partial = folder::partial_path(&destination, &md5)?;
+    let mut offset = folder::existing_bytes(&destination, size, &md5).await?;
+    start(link, transfer, size, &md5, offset);
+    
+    let mut throttle = Throttle::new(transfer.rate_limit);
+    while offset < size {
+        let data = fetch_chunk(link, &transfer.remote_path, offset, DEFAULT_CHUNK_SIZE.min(size - offset)).await?;
+        if data.is_empty() {
+            return Err("The file changed while it was being fetched".to_string());
+        }
+        
+        offset = folder::append_chunk(&partial, offset, &data)
+            .await
+            .map_err(|err| err.to_string())?;
+        
+        transfer.transferred = offset;
+        link.update(transfer);
+        throttle.consume(data.len() as u64).await;
+    }
+    
+    folder::complete(&destination, &md5).await
+}
+
+/// Fetch a chunk
+/// 
+/// Chunks that don't match their hash are asked again
+async fn fetch_chunk(link: &PeerLink, path: &str, offset: u64, length: u64) -> Result<Vec<u8>, String> {
+    for _ in 0..MAX_CHUNK_ATTEMPTS {
+        let action = FileAction::Read {
+            path: path.to_string(),
+            offset,
+            length,
+        };
+        
+        match link.request(action).await? {
+            FileResponse::Chunk { data, hash, .. } if chunk_hash(&data) == hash => return Ok(data),
+            FileResponse::Chunk { .. } => tracing::warn!("The chunk at offset {offset} doesn't match its hash"),
+            response => return Err(unexpected(response)),
+        }
+    }
+    
+    Err(format!("The chunk at offset {offset} was corrupted {MAX_CHUNK_ATTEMPTS} times"))
+}
+
+/// Send a local file to the peer
+/// 
+/// 
+async fn send(link: &PeerLink, transfer: &mut TransferInfo) -> Result<(), String> {
+    let source = transfer.local_path.clone();
+    let metadata = tokio::fs::metadata(&source).await.map_err(|err| format!("{}: {err}", source.display()))?;
+    if !metadata.is_file() {
+        return Err(format!("'{}' isn't a file", source.display()));
+    }
+    
+    let size = metadata.len();
+    let md5 = file_md5(&source).await.map_err(|err| err.to_string())?;
+    let name = transfer.remote_path.clone();
+    
+    let offer = FileAction::Offer {
+        name: name.clone(),
+        size,
+        md5: md5.clone(),
+    };
+    let mut offset = match link.request(offer).await? {
+        FileResponse::Offset { offset } => offset.min(size),
+        response => return Err(unexpected(response)),
+    };
+    start(link, transfer, size, &md5, offset);
+    
+    let mut throttle = Throttle::new(transfer.rate_limit);
+    let mut attempts = 0;
+    while offset < size {
+        let data = folder::read_chunk(&source, offset, DEFAULT_CHUNK_SIZE)
+            .await
+            .map_err(|err| err.to_string())?;
+        if data.is_empty() {
+            return Err("The file changed while it was being sent".to_string());
+        }
+        
+        let length = data.len() as u64;
+        let write = FileAction::Write {
+            name: name.clone(),
+            md5: md5.clone(),
+            offset,
+            hash: chunk_hash(&data),
+            data,
+        };
+        
+        // The peer checks the hash of the chunk
+        let written = match link.request(write).await {
+            Ok(FileResponse::Offset { offset }) => offset,
+            Ok(response) => return Err(unexpected(response)),
+            Err(err) => {
+                attempts += 1;
+                if attempts >= MAX_CHUNK_ATTEMPTS {
+                    return Err(err);
+                }
+                
+                tracing::warn!("Sending the chunk at offset {offset} again: {err}");
+                continue;
+            }
+        };
+        attempts = 0;
+        
+        // The peer may have more or less than expected, writing continues from what it has
+        if written != offset + length {
+            tracing::debug!("Peer {} has {written} bytes, continuing from there", link.peer_id);
+        }
+        offset = written.min(size);
+        
+        transfer.transferred = offset;
+        link.update(transfer);
+        throttle.consume(length).await;
+    }
+    
+    match link.request(FileAction::Complete { name, md5 }).await? {
+        FileResponse::Completed { .. } => Ok(()),
+        response => Err(unexpected(response)),
+    }
+}
+
+/// Set what's known once the transfer has started
+/// 
+/// 
+fn start(link: &PeerLink, transfer: &mut TransferInfo, size: u64, md5: &str, offset: u64) {
+    transfer.size = Some(size);
+    transfer.md5 = Some(md5.to_string());
+    transfer.resumed_from = offset;
+    transfer.transferred = offset;
+    
+    if offset > 0 {
+        println!("Resuming transfer {} from byte {offset} of {size}", transfer.id);
+    }
+    link.update(transfer);
+}
+
+/// Throttle
+/// 
+/// Keeps the average rate of a transfer under its limit
+pub struct Throttle {
+    rate_limit: Option<u64>,
+    started: Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    pub fn new(rate_limit: Option<u64>) -> Self {
+        Self {
+            rate_limit,
+            started: Instant::now(),
+            bytes: 0,
+        }
+    }
+    
+    /// Count transferred bytes
+    /// 
+    /// Waits until the rate is under the limit again
+    pub async fn consume(&mut self, bytes: u64) {
+        self.bytes += bytes;
+        
+        let delay = throttle_delay(self.bytes, self.rate_limit, self.started.elapsed());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Time to wait so the given bytes took at least as long as the rate limit allows
+/// 
+/// 
+pub fn throttle_delay(bytes: u64, rate_limit: Option<u64>, elapsed: Duration) -> Duration {
+    match rate_limit {
+        Some(rate_limit) if rate_limit > 0 => {
+            Duration::from_secs_f64(bytes as f64 / rate_limit as f64).saturating_sub(elapsed)
+        }
+        _ => Duration::ZERO,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_throttle_delay() {
+        assert_eq!(throttle_delay(1000, None, Duration::ZERO), Duration::ZERO);
+        assert_eq!(throttle_delay(1000, Some(1000), Duration::ZERO), Duration::from_secs(1));
+        assert_eq!(throttle_delay(1000, Some(1000), Duration::from_millis(400)), Duration::from_millis(600));
+        assert_eq!(throttle_delay(1000, Some(1000), Duration::from_secs(2)), Duration::ZERO);
+    }
+}
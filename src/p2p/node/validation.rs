@@ -0,0 +1,402 @@
+//! Gossipsub message validation
+//! 
+//! Gossipsub only forwards a message after the application reports whether it's valid,
+//! peers that keep sending invalid messages lose score until they are graylisted.
+use chrono::{DateTime, TimeDelta, Utc};
+use libp2p::{gossipsub, PeerId};
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use super::access::AccessList;
+use super::message::{HiveMessage, HiveMessageError, HivePayload, MessageKind};
+use super::registry::{RegistryError, MAX_COUNTER};
+
+/// Maximum size of a message
+/// 
+/// It's also the maximum transmit size of gossipsub, a server node of a host with many cores and
+/// network interfaces has to fit
+pub const MAX_MESSAGE_SIZE: usize = 256 * 1024;
+
+/// Messages older than this are ignored
+pub const MAX_MESSAGE_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// Tolerated difference between the clocks of two peers
+pub const MAX_CLOCK_DRIFT: Duration = Duration::from_secs(30);
+
+/// Penalty for every invalid message, a couple of them are enough to be graylisted
+const INVALID_MESSAGE_WEIGHT: f64 = -50.0;
+
+/// Validation error
+/// 
+/// 
+#[derive(Debug)]
+pub enum ValidationError {
+    TooLarge {
+        size: usize,
+        max: usize,
+    },
+    Message(HiveMessageError),
+    Stale(DateTime<Utc>),
+    FromTheFuture(DateTime<Utc>),
+    /// The author is blocked, or isn't allowed on allowlist mode
+    DeniedAuthor(PeerId),
+    /// Registry delta about another node than the sender
+    ForeignRegistryEntry(PeerId),
+    Registry(RegistryError),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::TooLarge { size, max } => write!(
+                f,
+                "Message of {size} bytes is larger than {max} bytes"
+            ),
+            ValidationError::Message(err) => write!(f, "{err}"),
+            ValidationError::Stale(timestamp) => write!(f, "Message sent at {timestamp} is too old"),
+            ValidationError::FromTheFuture(timestamp) => write!(
+                f,
+                "Message sent at {timestamp} comes from the future"
+            ),
+            ValidationError::DeniedAuthor(author) => write!(f, "Message author {author} isn't allowed on the hive"),
+            ValidationError::ForeignRegistryEntry(node_id) => write!(
+                f,
+                "Registry entry of node {node_id} wasn't published by that node"
+            ),
+            ValidationError::Registry(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+/// Validation result
+/// 
+/// 
+#[derive(Debug)]
+pub enum Validation {
+    /// Valid hive message
+    Hive(Box<HiveMessage>),
+    /// Valid message of a topic subscribed through the handle, its data is opaque
+    Topic,
+    /// Invalid message, the propagation source is penalized
+    Reject(ValidationError),
+    /// The message isn't forwarded, but nobody is penalized
+    Ignore(ValidationError),
+}
+
+impl Validation {
+    /// Acceptance reported to gossipsub
+    /// 
+    /// 
+    pub fn acceptance(&self) -> gossipsub::MessageAcceptance {
+        match self {
+            Validation::Hive(_) | Validation::Topic => gossipsub::MessageAcceptance::Accept,
+            Validation::Reject(_) => gossipsub::MessageAcceptance::Reject,
+            Validation::Ignore(_) => gossipsub::MessageAcceptance::Ignore,
+        }
+    }
+}
+
+/// Message validator
+/// 
+/// 
+#[derive(Clone, Debug)]
+pub struct MessageValidator {
+    pub max_size: usize,
+    pub max_age: Duration,
+    pub max_clock_drift: Duration,
+    // Only allowed peers can author messages
+    pub allowlist: bool,
+}
+
+impl Default for MessageValidator {
+    fn default() -> Self {
+        Self {
+            max_size: MAX_MESSAGE_SIZE,
+            max_age: MAX_MESSAGE_AGE,
+            max_clock_drift: MAX_CLOCK_DRIFT,
+            allowlist: false,
+        }
+    }
+}
+
+impl MessageValidator {
+    /// Validate a gossipsub message
+    /// 
+    /// Malformed or forged messages are rejected. Messages that are too large, of a newer protocol
+    /// version or with a timestamp out of range are ignored, because honest peers may send them too.
+    /// Registry deltas are only valid from the node they're about, the rest of the entries are
+    /// pulled with anti-entropy.
+    /// Connections of denied peers are refused, but their messages can still be relayed by others,
+    /// so their authors are checked on the access list too.
+    pub fn validate(&self, message: &gossipsub::Message, access_list: &AccessList, now: DateTime<Utc>) -> Validation {
+        if message.data.len() > self.max_size {
+            return Validation::Ignore(ValidationError::TooLarge {
+                size: message.data.len(),
+                max: self.max_size,
+            });
+        }
+        
+        if let Some(author) = message.source {
+            if access_list.is_denied(&author, self.allowlist) {
+                return Validation::Reject(ValidationError::DeniedAuthor(author));
+            }
+        }
+        
+        if MessageKind::from_topic(&message.topic).is_none() {
+            return Validation::Topic;
+        }
+        
+        // Checks the envelope and that the sender is the signer of the message
+        let hive_message = match HiveMessage::from_gossipsub(message) {
+            Ok(hive_message) => hive_message,
+            Err(err @ HiveMessageError::UnsupportedVersion(_)) => {
+                return Validation::Ignore(ValidationError::Message(err));
+            }
+            Err(err) => return Validation::Reject(ValidationError::Message(err)),
+        };
+        
+        let max_age = TimeDelta::from_std(self.max_age).unwrap_or(TimeDelta::MAX);
+        if hive_message.timestamp < now - max_age {
+            return Validation::Ignore(ValidationError::Stale(hive_message.timestamp));
+        }
+        
+        let max_clock_drift = TimeDelta::from_std(self.max_clock_drift).unwrap_or(TimeDelta::MAX);
+        if hive_message.timestamp > now + max_clock_drift {
+            return Validation::Ignore(ValidationError::FromTheFuture(hive_message.timestamp));
+        }
+        
+        if let HivePayload::RegistryDelta(entry) = &hive_message.payload {
+            if entry.node_id != hive_message.sender {
+                return Validation::Reject(ValidationError::ForeignRegistryEntry(entry.node_id));
+            }
+            if entry.version.counter > MAX_COUNTER {
+                return Validation::Reject(ValidationError::Registry(RegistryError::InvalidCounter(entry.version.counter)));
+            }
+        }
+        
+        Validation::Hive(Box::new(hive_message))
+    }
+}
+
+/// Peer score parameters
+/// 
+/// Hive topics carry little traffic, so only invalid messages are penalized and
+/// mesh delivery rates are left out
+pub fn peer_score_params() -> gossipsub::PeerScoreParams {
+    let mut params = gossipsub::PeerScoreParams::default();
+    
+    for kind in MessageKind::ALL {
+        let topic_params = gossipsub::TopicScoreParams {
+            topic_weight: 1.0,
+            mesh_message_deliveries_weight: 0.0,
+            mesh_failure_penalty_weight: 0.0,
+            invalid_message_deliveries_weight: INVALID_MESSAGE_WEIGHT,
+            // Forgiven slowly, about a tenth per minute
+            invalid_message_deliveries_decay: 0.998,
+            ..Default::default()
+        };
+        
+        params.topics.insert(kind.topic().hash(), topic_params);
+    }
+    
+    params
+}
+
+/// Peer score thresholds
+/// 
+/// Peers under the graylist threshold are ignored altogether
+pub fn peer_score_thresholds() -> gossipsub::PeerScoreThresholds {
+    gossipsub::PeerScoreThresholds::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    use crate::p2p::node::access::AccessRule;
+    use crate::p2p::node::registry::NodeRegistry;
+    use crate::server_node::resources::network::NetworkInterface;
+    use crate::server_node::resources::system_core::CpuCore;
+    use crate::server_node::{ServerNode, ServerStatus};
+    
+    fn gossipsub_message(source: PeerId, kind: MessageKind, data: Vec<u8>) -> gossipsub::Message {
+        gossipsub::Message {
+            source: Some(source),
+            data,
+            sequence_number: None,
+            topic: kind.topic().hash(),
+        }
+    }
+    
+    #[test]
+    fn test_accept_valid_message() {
+        let sender = PeerId::random();
+        let message = HiveMessage::new(sender, HivePayload::StatusChange(ServerStatus::Online));
+        let received = gossipsub_message(sender, MessageKind::StatusChange, message.encode().unwrap());
+        
+        let validation = MessageValidator::default().validate(&received, &AccessList::new(), Utc::now());
+        
+        assert!(matches!(validation, Validation::Hive(_)));
+        assert!(matches!(validation.acceptance(), gossipsub::MessageAcceptance::Accept));
+    }
+    
+    #[test]
+    fn test_reject_invalid_messages() {
+        let validator = MessageValidator::default();
+        let sender = PeerId::random();
+        
+        // Junk
+        let received = gossipsub_message(sender, MessageKind::Chat, b"Hello world".to_vec());
+        assert!(matches!(validator.validate(&received, &AccessList::new(), Utc::now()), Validation::Reject(_)));
+        
+        // Impersonating another peer
+        let message = HiveMessage::new(PeerId::random(), HivePayload::Chat("Hello".to_string()));
+        let received = gossipsub_message(sender, MessageKind::Chat, message.encode().unwrap());
+        assert!(matches!(validator.validate(&received, &AccessList::new(), Utc::now()), Validation::Reject(_)));
+    }
+    
+    #[test]
+    fn test_reject_denied_authors() {
+        let blocked = PeerId::random();
+        let relay = PeerId::random();
+        let access_list = AccessList::from_rules([AccessRule::block(blocked), AccessRule::allow(relay)]);
+        
+        // Relayed by an allowed peer, the source is still the author
+        let message = HiveMessage::new(blocked, HivePayload::Chat("Hello".to_string()));
+        let received = gossipsub_message(blocked, MessageKind::Chat, message.encode().unwrap());
+        let validation = MessageValidator::default().validate(&received, &access_list, Utc::now());
+        assert!(matches!(validation, Validation::Reject(ValidationError::DeniedAuthor(author)) if author == blocked));
+        assert!(matches!(validation.acceptance(), gossipsub::MessageAcceptance::Reject));
+        
+        // On allowlist mode only allowed authors are accepted
+        let validator = MessageValidator {
+            allowlist: true,
+            ..Default::default()
+        };
+        let stranger = PeerId::random();
+        let message = HiveMessage::new(stranger, HivePayload::Chat("Hello".to_string()));
+        let received = gossipsub_message(stranger, MessageKind::Chat, message.encode().unwrap());
+        assert!(matches!(
+            validator.validate(&received, &access_list, Utc::now()),
+            Validation::Reject(ValidationError::DeniedAuthor(_))
+        ));
+        
+        let message = HiveMessage::new(relay, HivePayload::Chat("Hello".to_string()));
+        let received = gossipsub_message(relay, MessageKind::Chat, message.encode().unwrap());
+        assert!(matches!(validator.validate(&received, &access_list, Utc::now()), Validation::Hive(_)));
+    }
+    
+    #[test]
+    fn test_reject_forged_registry_deltas() {
+        let validator = MessageValidator::default();
+        let sender = PeerId::random();
+        let server_node = ServerNode::new().unwrap();
+        
+        // About another node
+        let other = PeerId::random();
+        let entry = NodeRegistry::new(sender).set(other, server_node.clone()).unwrap();
+        let message = HiveMessage::new(sender, HivePayload::RegistryDelta(Box::new(entry)));
+        let received = gossipsub_message(sender, MessageKind::Registry, message.encode().unwrap());
+        assert!(matches!(
+            validator.validate(&received, &AccessList::new(), Utc::now()),
+            Validation::Reject(ValidationError::ForeignRegistryEntry(node_id)) if node_id == other
+        ));
+        
+        // With a counter that would overflow the clocks of the peers
+        let mut entry = NodeRegistry::new(sender).set(sender, server_node).unwrap();
+        entry.version.counter = u64::MAX;
+        let message = HiveMessage::new(sender, HivePayload::RegistryDelta(Box::new(entry)));
+        let received = gossipsub_message(sender, MessageKind::Registry, message.encode().unwrap());
+        assert!(matches!(
+            validator.validate(&received, &AccessList::new(), Utc::now()),
+            Validation::Reject(ValidationError::Registry(RegistryError::InvalidCounter(_)))
+        ));
+    }
+    
+    #[test]
+    fn test_large_server_node_is_not_rejected() {
+        let validator = MessageValidator::default();
+        let sender = PeerId::random();
+        
+        // A big host, every core repeats the processor and every container adds an interface
+        let mut server_node = ServerNode::new().unwrap();
+        let core = CpuCore {
+            name: "cpu0".to_string(),
+            vendor: "GenuineIntel".to_string(),
+            brand: "Intel(R) Xeon(R) Platinum 8380 CPU @ 2.30GHz".to_string(),
+            frequency: 2300,
+            usage_percentage: 12.345678901234567,
+            free_percentage: 87.65432109876543,
+            ..Default::default()
+        };
+        server_node.resources.cpus = (0..128)
+            .map(|index| CpuCore { index, name: format!("cpu{index}"), ..core.clone() })
+            .collect();
+        server_node.resources.network_interfaces = (0..32)
+            .map(|index| NetworkInterface {
+                name: format!("veth{index:08x}"),
+                mac_address: "02:42:ac:11:00:02".to_string(),
+                ip_addresses: vec!["172.17.0.2".to_string(), "fe80::42:acff:fe11:2".to_string()],
+                bytes_received: 123_456_789_012,
+                bytes_transmitted: 123_456_789_012,
+                packets_received: 123_456_789,
+                packets_transmitted: 123_456_789,
+                receive_rate: 123_456.789_012_345,
+                transmit_rate: 123_456.789_012_345,
+            })
+            .collect();
+        
+        let entry = NodeRegistry::new(sender).set(sender, server_node).unwrap();
+        let message = HiveMessage::new(sender, HivePayload::RegistryDelta(Box::new(entry)));
+        let data = message.encode().unwrap();
+        // Over the previous limit
+        assert!(data.len() > 32 * 1024);
+        
+        let received = gossipsub_message(sender, MessageKind::Registry, data);
+        assert!(matches!(validator.validate(&received, &AccessList::new(), Utc::now()), Validation::Hive(_)));
+        
+        // Larger messages are dropped without a penalty
+        let message = HiveMessage::new(sender, HivePayload::Chat("a".repeat(MAX_MESSAGE_SIZE)));
+        let received = gossipsub_message(sender, MessageKind::Chat, message.encode().unwrap());
+        let validation = validator.validate(&received, &AccessList::new(), Utc::now());
+        assert!(matches!(validation, Validation::Ignore(ValidationError::TooLarge { .. })));
+        assert!(matches!(validation.acceptance(), gossipsub::MessageAcceptance::Ignore));
+    }
+    
+    #[test]
+    fn test_ignore_out_of_range_timestamps() {
+        let validator = MessageValidator::default();
+        let sender = PeerId::random();
+        let message = HiveMessage::new(sender, HivePayload::Chat("Hello".to_string()));
+        let received = gossipsub_message(sender, MessageKind::Chat, message.encode().unwrap());
+        
+        let later = message.timestamp + TimeDelta::minutes(10);
+        assert!(matches!(
+            validator.validate(&received, &AccessList::new(), later),
+            Validation::Ignore(ValidationError::Stale(_))
+        ));
+        
+        let earlier = message.timestamp - TimeDelta::minutes(10);
+        assert!(matches!(
+            validator.validate(&received, &AccessList::new(), earlier),
+            Validation::Ignore(ValidationError::FromTheFuture(_))
+        ));
+    }
+    
+    #[test]
+    fn test_other_topics_are_opaque() {
+        let received = gossipsub::Message {
+            source: Some(PeerId::random()),
+            data: b"Hello world".to_vec(),
+            sequence_number: None,
+            topic: gossipsub::IdentTopic::new("test-topic").hash(),
+        };
+        
+        let validation = MessageValidator::default().validate(&received, &AccessList::new(), Utc::now());
+        
+        assert!(matches!(validation, Validation::Topic));
+    }
+}
@@ -2,76 +2,37 @@
 //! 
 //! 
 use futures::StreamExt;
+use libp2p::identity::Keypair;
 use libp2p::multiaddr::Protocol;
-// Import the missing items
-use libp2p::{
-    gossipsub, mdns, noise, swarm::{SwarmEvent, NetworkBehaviour}, tcp, yamux, SwarmBuilder
-};
-use std::collections::hash_map::DefaultHasher;
+use libp2p::{gossipsub, mdns, swarm::SwarmEvent};
 use std::error::Error;
-use std::hash::{Hash, Hasher};
-use std::time::Duration;
 use tokio::{io, io::AsyncBufReadExt, select};
 use tracing_subscriber::EnvFilter;
 
-/// We create a custom network behaviour that combines Gossipsub and Mdns.
-/// 
-/// 
-#[derive(NetworkBehaviour)]
-struct MyBehavior {
-    gossipsub: gossipsub::Behaviour,
-    mdns: mdns::tokio::Behaviour,
-}
+use crate::p2p::node::behavior::MyBehaviorEvent;
+use crate::p2p::node::builder::NodeBuilder;
 
 /// Start service
 /// 
-/// 
+/// Only gossipsub and mDNS are used
 pub async fn main() -> Result<(), Box<dyn Error>> {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
         .try_init();
     
-    let mut swarm = SwarmBuilder::with_new_identity()
-        .with_tokio()
-        .with_tcp(
-            tcp::Config::default(),
-            noise::Config::new,
-            yamux::Config::default,
-        )?
-        .with_quic()
-        .with_behaviour(|key| {
-            // To content-address message, we can take the hash of message and use it as an ID.
-            let message_id_fn = |message: &gossipsub::Message| {
-                let mut s = DefaultHasher::new();
-                message.data.hash(&mut s);
-                gossipsub::MessageId::from(s.finish().to_string())
-            };
-            
-            // Set a custom gossipsub configuration
-            let gossipsub_config = gossipsub::ConfigBuilder::default()
-                .heartbeat_interval(Duration::from_secs(10)) // This is set to aid debugging by not cluttering the log space
-                .validation_mode(gossipsub::ValidationMode::Strict) // This sets the kind of message validation. The default is Strict (enforce message signing)
-                .message_id_fn(message_id_fn) // content-address messages. No two messages of the same content will be propagated.
-                .build()
-                .map_err(|msg| io::Error::new(io::ErrorKind::Other, msg))?; // Temporary hack because `build` does not return a proper `std::error::Error`.
-            
-            // build a gossipsub network behaviour
-            let gossipsub = gossipsub::Behaviour::new(
-                gossipsub::MessageAuthenticity::Signed(key.clone()),
-                gossipsub_config,
-            )?;
-            
-            let mdns =
-                mdns::tokio::Behaviour::new(mdns::Config::default(), key.public().to_peer_id())?;
-            Ok(MyBehavior { gossipsub, mdns })
-        })?
-        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
-        .build();
-    
     // Create a Gossipsub topic
     let topic = gossipsub::IdentTopic::new("test-net");
-    // subscribes to our topic
-    swarm.behaviour_mut().gossipsub.subscribe(&topic)?;
+    
+    // Only gossipsub and mDNS, subscribed to our topic
+    let mut swarm = NodeBuilder::new(Keypair::generate_ed25519())
+        .autonat(false)
+        .identify(false)
+        .kademlia(false)
+        .ping(false)
+        .relay_client(false)
+        .relay_server(false)
+        .topic(topic.clone())
+        .build_swarm()?;
     
     // Read full lines from stdin
     let mut stdin = io::BufReader::new(io::stdin()).lines();
@@ -86,10 +47,10 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
     loop {
         select! {
             Ok(Some(line)) = stdin.next_line() => {
-                if let Err(e) = swarm
-                    .behaviour_mut().gossipsub
-                    .publish(topic.clone(), line.as_bytes()) {
-                    println!("Publish error: {e:?}");
+                if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
+                    if let Err(e) = gossipsub.publish(topic.clone(), line.as_bytes()) {
+                        println!("Publish error: {e:?}");
+                    }
                 }
             }
             event = swarm.select_next_some() => match event {
@@ -112,13 +73,17 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
                                             _ => println!("Unsupported protocol"),
                                         }
                                         
-                                        swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer_id);
+                                        if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
+                                            gossipsub.add_explicit_peer(&peer_id);
+                                        }
                                     }
                                 }
                                 mdns::Event::Expired(list) => {
                                     for (peer_id, _multiaddr) in list {
                                         println!("mDNS discover peer has expired: {peer_id}");
-                                        swarm.behaviour_mut().gossipsub.remove_explicit_peer(&peer_id);
+                                        if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
+                                            gossipsub.remove_explicit_peer(&peer_id);
+                                        }
                                     }
                                 }
                             }
@@ -133,6 +98,15 @@ pub async fn main() -> Result<(), Box<dyn Error>> {
                                 "Got message: '{}' with id: {id} from peer: {peer_id}",
                                 String::from_utf8_lossy(&message.data),
                             );
+                            
+                            // Messages are only forwarded once they're validated
+                            if let Some(gossipsub) = swarm.behaviour_mut().gossipsub.as_mut() {
+                                let _ = gossipsub.report_message_validation_result(
+                                    &id,
+                                    &peer_id,
+                                    gossipsub::MessageAcceptance::Accept,
+                                );
+                            }
                         }
                         _ => {}
                     }
@@ -0,0 +1,91 @@
+//! Candidate sources
+//! 
+//! Candidates come from the latest snapshots stored on the database,
+//! or from what the hive node heard from its peers.
+use entity::server_node::Entity as ServerNodeEntity;
+use sea_orm::{DatabaseConnection, EntityTrait};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+
+use super::Candidate;
+use crate::p2p::node::handle::NodeHandle;
+use crate::p2p::node::peer_book::controller::PeerBookController;
+use crate::server_node::controller::ServerNodeController;
+use crate::server_node::ServerNode;
+
+/// Candidate source
+/// 
+/// 
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CandidateSource {
+    /// Server nodes stored on the database
+    Database,
+    /// Server nodes announced to the hive node, plus the node itself
+    Live,
+}
+
+/// Candidates from the database
+/// 
+/// Server nodes that can't be loaded are skipped
+pub async fn from_database(db: &DatabaseConnection) -> Result<Vec<Candidate>, Box<dyn Error>> {
+    let models = ServerNodeEntity::find().all(db).await?;
+    
+    // Peers whose server node is known
+    let peers: HashMap<i64, _> = match PeerBookController::new(db.clone()).load().await {
+        Ok(peer_book) => peer_book
+            .records()
+            .filter_map(|record| record.server_node_id.map(|id| (id, record.peer_id)))
+            .collect(),
+        Err(err) => {
+            tracing::warn!("Couldn't load the peer book: {err}");
+            HashMap::new()
+        }
+    };
+    
+    let mut candidates = Vec::new();
+    for model in models {
+        let id = match u32::try_from(model.id) {
+            Ok(id) => id,
+            Err(_) => continue,
+        };
+        
+        let server_node = match ServerNodeController::server_node_from_id(db.clone(), id).await {
+            Ok(server_node) => server_node,
+            Err(err) => {
+                tracing::warn!("Skipping server node {id}: {err}");
+                continue;
+            }
+        };
+        
+        let mut candidate = Candidate::new(server_node).with_server_node_id(model.id);
+        if let Some(peer_id) = peers.get(&model.id) {
+            candidate = candidate.with_peer_id(*peer_id);
+        }
+        candidates.push(candidate);
+    }
+    
+    Ok(candidates)
+}
+
+/// Candidates known by the hive node
+/// 
+/// The node keeps the resources of its own entry up to date, this computer is only sampled right
+/// away when it isn't registered yet
+pub async fn from_node(node: &NodeHandle) -> Result<Vec<Candidate>, Box<dyn Error>> {
+    let server_nodes = node.server_nodes().await?;
+    let registered = server_nodes.iter().any(|(peer_id, _)| *peer_id == node.peer_id());
+    
+    let mut candidates: Vec<Candidate> = server_nodes
+        .into_iter()
+        .map(|(peer_id, server_node)| Candidate::new(server_node).with_peer_id(peer_id))
+        .collect();
+    
+    if !registered {
+        let local = tokio::task::spawn_blocking(|| ServerNode::new().map_err(|err| err.to_string())).await??;
+        candidates.push(Candidate::new(local).with_peer_id(node.peer_id()));
+    }
+    
+    Ok(candidates)
+}
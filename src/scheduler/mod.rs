@@ -0,0 +1,362 @@
+//! Scheduler
+//! 
+//! Decides which node should run something. Nodes that don't meet the requirements are discarded,
+//! the rest are ranked by a placement strategy and every decision comes with its reasons.
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+pub mod candidates;
+pub mod strategy;
+
+use crate::server_node::resources::requirements::ResourceRequirements;
+use crate::server_node::resources::Resources;
+use crate::server_node::{ServerNode, ServerStatus};
+use strategy::{BinPacking, LeastLoaded, PlacementStrategy, RoundRobin};
+
+/// Strategy used when the request doesn't choose one
+pub const DEFAULT_STRATEGY: &str = "least-loaded";
+
+/// Candidate node
+/// 
+/// 
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub peer_id: Option<PeerId>,
+    pub server_node_id: Option<i64>,
+    pub name: String,
+    pub status: ServerStatus,
+    pub labels: Vec<String>,
+    pub resources: Resources,
+}
+
+impl Candidate {
+    pub fn new(server_node: ServerNode) -> Self {
+        Self {
+            peer_id: None,
+            server_node_id: None,
+            name: server_node.location.name,
+            status: server_node.status,
+            labels: server_node.labels,
+            resources: server_node.resources,
+        }
+    }
+    
+    pub fn with_peer_id(mut self, peer_id: PeerId) -> Self {
+        self.peer_id = Some(peer_id);
+        self
+    }
+    
+    pub fn with_server_node_id(mut self, server_node_id: i64) -> Self {
+        self.server_node_id = Some(server_node_id);
+        self
+    }
+    
+    /// Identifier shown on placements
+    /// 
+    /// The peer id when it's known, otherwise the server node id
+    pub fn id(&self) -> String {
+        match (self.peer_id, self.server_node_id) {
+            (Some(peer_id), _) => peer_id.to_string(),
+            (None, Some(server_node_id)) => format!("server-node-{server_node_id}"),
+            (None, None) => self.name.clone(),
+        }
+    }
+    
+    /// Usage of every core together, from 0 to 100
+    /// 
+    /// 
+    pub fn cpu_usage(&self) -> f64 {
+        self.resources.cpu_usage
+    }
+    
+    /// Used memory, from 0 to 1
+    /// 
+    /// 
+    pub fn memory_usage(&self) -> f64 {
+        let memory = &self.resources.memory;
+        if memory.total == 0 {
+            return 1.0;
+        }
+        
+        memory.used as f64 / memory.total as f64
+    }
+    
+    /// Why the candidate can't be used
+    /// 
+    /// Empty when it meets the requirements
+    pub fn check(&self, requirements: &ResourceRequirements, labels: &[String]) -> Vec<String> {
+        let mut reasons = Vec::new();
+        
+        if self.status != ServerStatus::Online {
+            reasons.push(format!("The node is {}", self.status));
+        }
+        
+        let missing: Vec<&str> = labels
+            .iter()
+            .filter(|label| !self.labels.contains(label))
+            .map(|label| label.as_str())
+            .collect();
+        if !missing.is_empty() {
+            reasons.push(format!("Missing labels: {}", missing.join(", ")));
+        }
+        
+        if let Err(reason) = requirements.check(&self.resources) {
+            reasons.push(reason);
+        }
+        
+        reasons
+    }
+}
+
+/// Placement request
+/// 
+/// 
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PlacementRequest {
+    #[serde(default)]
+    pub requirements: ResourceRequirements,
+    // Labels the node must have
+    #[serde(default)]
+    pub labels: Vec<String>,
+    // Defaults to least loaded
+    pub strategy: Option<String>,
+}
+
+/// Evaluation of a candidate
+/// 
+/// 
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Evaluation {
+    pub candidate: String,
+    pub name: String,
+    pub eligible: bool,
+    // Only eligible candidates are scored, the highest score is picked
+    pub score: Option<f64>,
+    pub reasons: Vec<String>,
+}
+
+/// Placement
+/// 
+/// The chosen node and why, evaluations are sorted from best to worst
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Placement {
+    pub strategy: String,
+    pub selected: Option<String>,
+    pub explanation: String,
+    pub evaluations: Vec<Evaluation>,
+}
+
+/// Scheduler
+/// 
+/// Holds the placement strategies, new ones can be registered
+pub struct Scheduler {
+    strategies: Vec<Box<dyn PlacementStrategy>>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        let mut scheduler = Self::new();
+        scheduler.register(Box::new(LeastLoaded));
+        scheduler.register(Box::new(BinPacking));
+        scheduler.register(Box::new(RoundRobin::default()));
+        
+        scheduler
+    }
+}
+
+impl Scheduler {
+    /// Scheduler without strategies
+    /// 
+    /// 
+    pub fn new() -> Self {
+        Self {
+            strategies: Vec::new(),
+        }
+    }
+    
+    /// Register a strategy
+    /// 
+    /// A strategy with the same name is replaced
+    pub fn register(&mut self, strategy: Box<dyn PlacementStrategy>) {
+        self.strategies.retain(|registered| registered.name() != strategy.name());
+        self.strategies.push(strategy);
+    }
+    
+    /// Names of the registered strategies
+    /// 
+    /// 
+    pub fn strategies(&self) -> Vec<&'static str> {
+        self.strategies.iter().map(|strategy| strategy.name()).collect()
+    }
+    
+    pub fn strategy(&self, name: &str) -> Option<&dyn PlacementStrategy> {
+        self.strategies
+            .iter()
+            .find(|strategy| strategy.name() == name)
+            .map(|strategy| strategy.as_ref())
+    }
+    
+    /// Choose a candidate
+    /// 
+    /// Fails only if the strategy doesn't exist, when no candidate is eligible nothing is selected
+    pub fn place(&self, request: &PlacementRequest, candidates: &[Candidate]) -> Result<Placement, Box<dyn Error>> {
+        let name = request.strategy.as_deref().unwrap_or(DEFAULT_STRATEGY);
+        let strategy = match self.strategy(name) {
+            Some(strategy) => strategy,
+            None => return Err(format!(
+                "Unknown strategy '{name}', expected one of: {}",
+                self.strategies().join(", ")
+            ).into()),
+        };
+        
+        let mut ranked: Vec<(&Candidate, Evaluation)> = candidates
+            .iter()
+            .map(|candidate| {
+                let reasons = candidate.check(&request.requirements, &request.labels);
+                let evaluation = match reasons.is_empty() {
+                    true => Evaluation {
+                        candidate: candidate.id(),
+                        name: candidate.name.clone(),
+                        eligible: true,
+                        score: Some(strategy.score(candidate, &request.requirements)),
+                        reasons: vec![strategy.explain(candidate, &request.requirements)],
+                    },
+                    false => Evaluation {
+                        candidate: candidate.id(),
+                        name: candidate.name.clone(),
+                        eligible: false,
+                        score: None,
+                        reasons,
+                    },
+                };
+                
+                (candidate, evaluation)
+            })
+            .collect();
+        
+        // Best score first, ties are broken by id so placements are repeatable
+        ranked.sort_by(|(_, a), (_, b)| {
+            b.score
+                .unwrap_or(f64::NEG_INFINITY)
+                .total_cmp(&a.score.unwrap_or(f64::NEG_INFINITY))
+                .then_with(|| a.candidate.cmp(&b.candidate))
+        });
+        
+        let selected = ranked
+            .first()
+            .filter(|(_, evaluation)| evaluation.eligible)
+            .map(|(candidate, evaluation)| (*candidate, evaluation.clone()));
+        let explanation = match &selected {
+            Some((candidate, evaluation)) => {
+                strategy.placed(candidate);
+                
+                let eligible = ranked.iter().filter(|(_, evaluation)| evaluation.eligible).count();
+                format!(
+                    "Picked '{}' out of {eligible} eligible nodes with {name}: {}",
+                    evaluation.name,
+                    evaluation.reasons.join(", ")
+                )
+            }
+            None if candidates.is_empty() => "There are no known nodes".to_string(),
+            None => format!("None of the {} nodes meets the requirements", candidates.len()),
+        };
+        
+        Ok(Placement {
+            strategy: name.to_string(),
+            selected: selected.map(|(candidate, _)| candidate.id()),
+            explanation,
+            evaluations: ranked.into_iter().map(|(_, evaluation)| evaluation).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use chrono::Utc;
+    
+    use crate::server_node::resources::system_core::CpuCore;
+    use crate::server_node::resources::system_memory::Memory;
+    
+    /// Create a candidate
+    /// 
+    /// Memory is given in bytes
+    pub fn candidate(name: &str, cores: usize, cpu_usage: f64, memory_total: u64, memory_used: u64) -> Candidate {
+        Candidate {
+            peer_id: None,
+            server_node_id: None,
+            name: name.to_string(),
+            status: ServerStatus::Online,
+            labels: Vec::new(),
+            resources: Resources {
+                cpus: (0..cores as u32)
+                    .map(|index| CpuCore {
+                        index,
+                        usage_percentage: cpu_usage,
+                        free_percentage: 100.0 - cpu_usage,
+                        ..Default::default()
+                    })
+                    .collect(),
+                physical_cores: None,
+                logical_cores: cores as u32,
+                cpu_usage,
+                memory: Memory { total: memory_total, used: memory_used },
+                storage: Vec::new(),
+                network_interfaces: Vec::new(),
+                eval_time: Utc::now(),
+            },
+        }
+    }
+    
+    #[test]
+    fn test_requirements_discard_candidates() {
+        let mut labeled = candidate("labeled", 4, 50.0, 1000, 500);
+        labeled.labels = vec!["gpu".to_string()];
+        let mut maintenance = candidate("maintenance", 4, 0.0, 1000, 0);
+        maintenance.labels = vec!["gpu".to_string()];
+        maintenance.status = ServerStatus::Maintenance;
+        let candidates = vec![
+            candidate("idle", 4, 0.0, 1000, 0),
+            labeled,
+            maintenance,
+        ];
+        
+        let request = PlacementRequest {
+            labels: vec!["gpu".to_string()],
+            ..Default::default()
+        };
+        let placement = Scheduler::default().place(&request, &candidates).unwrap();
+        
+        assert_eq!(placement.selected, Some("labeled".to_string()));
+        assert_eq!(placement.strategy, DEFAULT_STRATEGY);
+        assert!(placement.evaluations[0].eligible);
+        
+        let idle = placement.evaluations.iter().find(|evaluation| evaluation.name == "idle").unwrap();
+        assert_eq!(idle.reasons, vec!["Missing labels: gpu".to_string()]);
+        let maintenance = placement.evaluations.iter().find(|evaluation| evaluation.name == "maintenance").unwrap();
+        assert_eq!(maintenance.reasons, vec!["The node is Maintenance".to_string()]);
+        
+        // Nothing fits
+        let request = PlacementRequest {
+            requirements: ResourceRequirements {
+                cpu_cores: Some(64),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let placement = Scheduler::default().place(&request, &candidates).unwrap();
+        assert_eq!(placement.selected, None);
+        assert_eq!(placement.explanation, "None of the 3 nodes meets the requirements");
+    }
+    
+    #[test]
+    fn test_unknown_strategy() {
+        let request = PlacementRequest {
+            strategy: Some("random".to_string()),
+            ..Default::default()
+        };
+        
+        assert!(Scheduler::default().place(&request, &[]).is_err());
+    }
+}
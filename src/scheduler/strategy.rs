@@ -0,0 +1,191 @@
+//! Placement strategies
+//! 
+//! A strategy scores the candidates that meet the requirements, the scheduler picks the highest score.
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::Candidate;
+use crate::server_node::resources::requirements::ResourceRequirements;
+
+/// Placement strategy
+/// 
+/// Strategies are shared between requests, so they can keep state
+pub trait PlacementStrategy: Send + Sync {
+    fn name(&self) -> &'static str;
+    
+    /// Score of an eligible candidate
+    /// 
+    /// The highest score is picked
+    fn score(&self, candidate: &Candidate, requirements: &ResourceRequirements) -> f64;
+    
+    /// Why the candidate got its score
+    /// 
+    /// 
+    fn explain(&self, candidate: &Candidate, requirements: &ResourceRequirements) -> String;
+    
+    /// The candidate was picked
+    /// 
+    /// 
+    fn placed(&self, _candidate: &Candidate) {}
+}
+
+/// Least loaded
+/// 
+/// Spreads the work, the node with the lowest cpu and memory usage is picked
+pub struct LeastLoaded;
+
+impl PlacementStrategy for LeastLoaded {
+    fn name(&self) -> &'static str {
+        "least-loaded"
+    }
+    
+    fn score(&self, candidate: &Candidate, _requirements: &ResourceRequirements) -> f64 {
+        1.0 - (candidate.cpu_usage() / 100.0 + candidate.memory_usage()) / 2.0
+    }
+    
+    fn explain(&self, candidate: &Candidate, _requirements: &ResourceRequirements) -> String {
+        format!(
+            "cpu {:.1}% used, memory {:.1}% used",
+            candidate.cpu_usage(),
+            candidate.memory_usage() * 100.0
+        )
+    }
+}
+
+/// Bin packing
+/// 
+/// Fills nodes before using new ones, the node that would have the least memory left is picked
+pub struct BinPacking;
+
+impl BinPacking {
+    /// Memory usage once the requirement is placed, from 0 to 1
+    /// 
+    /// 
+    fn memory_usage_after(candidate: &Candidate, requirements: &ResourceRequirements) -> f64 {
+        let memory = &candidate.resources.memory;
+        if memory.total == 0 {
+            return 1.0;
+        }
+        
+        let used = memory.used.saturating_add(requirements.memory.unwrap_or_default());
+        used.min(memory.total) as f64 / memory.total as f64
+    }
+}
+
+impl PlacementStrategy for BinPacking {
+    fn name(&self) -> &'static str {
+        "bin-packing"
+    }
+    
+    fn score(&self, candidate: &Candidate, requirements: &ResourceRequirements) -> f64 {
+        (candidate.cpu_usage() / 100.0 + Self::memory_usage_after(candidate, requirements)) / 2.0
+    }
+    
+    fn explain(&self, candidate: &Candidate, requirements: &ResourceRequirements) -> String {
+        format!(
+            "memory would be {:.1}% used, cpu {:.1}% used",
+            Self::memory_usage_after(candidate, requirements) * 100.0,
+            candidate.cpu_usage()
+        )
+    }
+}
+
+/// Round robin
+/// 
+/// Takes turns, the node that was picked the longest time ago is picked
+#[derive(Default)]
+pub struct RoundRobin {
+    // Turn when each candidate was last picked, starting at one
+    turns: Mutex<HashMap<String, u64>>,
+}
+
+impl RoundRobin {
+    fn last_turn(&self, candidate: &Candidate) -> u64 {
+        let turns = self.turns.lock().unwrap_or_else(|err| err.into_inner());
+        
+        turns.get(&candidate.id()).copied().unwrap_or_default()
+    }
+}
+
+impl PlacementStrategy for RoundRobin {
+    fn name(&self) -> &'static str {
+        "round-robin"
+    }
+    
+    fn score(&self, candidate: &Candidate, _requirements: &ResourceRequirements) -> f64 {
+        -(self.last_turn(candidate) as f64)
+    }
+    
+    fn explain(&self, candidate: &Candidate, _requirements: &ResourceRequirements) -> String {
+        match self.last_turn(candidate) {
+            0 => "never picked before".to_string(),
+            turn => format!("last picked on turn {turn}"),
+        }
+    }
+    
+    fn placed(&self, candidate: &Candidate) {
+        let mut turns = self.turns.lock().unwrap_or_else(|err| err.into_inner());
+        
+        let turn = turns.values().max().copied().unwrap_or_default() + 1;
+        turns.insert(candidate.id(), turn);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scheduler::tests::candidate;
+    use crate::scheduler::{PlacementRequest, Scheduler};
+    
+    /// Gigabyte
+    const GB: u64 = 1024 * 1024 * 1024;
+    
+    #[test]
+    fn test_least_loaded_and_bin_packing() {
+        let candidates = vec![
+            candidate("busy", 8, 80.0, 16 * GB, 12 * GB),
+            candidate("idle", 8, 5.0, 16 * GB, 2 * GB),
+        ];
+        let scheduler = Scheduler::default();
+        
+        let request = PlacementRequest {
+            strategy: Some("least-loaded".to_string()),
+            ..Default::default()
+        };
+        let placement = scheduler.place(&request, &candidates).unwrap();
+        assert_eq!(placement.selected, Some("idle".to_string()));
+        assert_eq!(placement.evaluations[0].reasons, vec!["cpu 5.0% used, memory 12.5% used".to_string()]);
+        
+        let mut request = PlacementRequest {
+            strategy: Some("bin-packing".to_string()),
+            ..Default::default()
+        };
+        request.requirements.memory = Some(2 * GB);
+        let placement = scheduler.place(&request, &candidates).unwrap();
+        assert_eq!(placement.selected, Some("busy".to_string()));
+        
+        // The busy node doesn't have enough memory left
+        request.requirements.memory = Some(6 * GB);
+        let placement = scheduler.place(&request, &candidates).unwrap();
+        assert_eq!(placement.selected, Some("idle".to_string()));
+    }
+    
+    #[test]
+    fn test_round_robin() {
+        let candidates = vec![
+            candidate("a", 1, 0.0, GB, 0),
+            candidate("b", 1, 0.0, GB, 0),
+            candidate("c", 1, 0.0, GB, 0),
+        ];
+        let scheduler = Scheduler::default();
+        let request = PlacementRequest {
+            strategy: Some("round-robin".to_string()),
+            ..Default::default()
+        };
+        
+        let picked: Vec<String> = (0..4)
+            .map(|_| scheduler.place(&request, &candidates).unwrap().selected.unwrap())
+            .collect();
+        
+        assert_eq!(picked, vec!["a", "b", "c", "a"]);
+    }
+}
@@ -0,0 +1,42 @@
+use chrono::Utc;
+use jsonwebtoken::{encode, EncodingKey, Header};
+use std::error::Error;
+use std::time::Duration;
+
+use super::verify_token::TokenData;
+use crate::config::env::secret_token;
+
+/// Create token
+/// 
+/// Signed with the secret token, so it can be checked with 'verify_token'
+pub fn create_token(user_id: i64, valid_for: Duration) -> Result<String, Box<dyn Error>> {
+    let secret = secret_token()?;
+    
+    let exp = Utc::now().timestamp() + i64::try_from(valid_for.as_secs())?;
+    let token_data = TokenData {
+        user_id,
+        exp,
+    };
+    
+    let token = encode(
+        &Header::default(),
+        &token_data,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )?;
+    
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::verify_token::verify_token;
+    
+    #[test]
+    fn test_create_and_verify_token() {
+        let token = create_token(1, Duration::from_secs(60)).unwrap();
+        
+        assert!(verify_token(&token).unwrap());
+        assert!(verify_token("not-a-token").is_err());
+    }
+}
@@ -1 +1,2 @@
+pub mod create_token;
 pub mod verify_token;
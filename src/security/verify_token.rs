@@ -9,7 +9,7 @@ use crate::config::env::secret_token;
 /// 
 /// 
 pub fn verify_token(token: &str) -> Result<bool, Box<dyn Error>> {
-    let secret = secret_token();
+    let secret = secret_token()?;
     
     let secret_key = DecodingKey::from_secret(secret.as_bytes());
     
@@ -25,7 +25,7 @@ pub fn verify_token(token: &str) -> Result<bool, Box<dyn Error>> {
 
 /// Define the token data struct
 #[derive(Debug, Serialize, Deserialize)]
-struct TokenData {
-    user_id: i64,
-    exp: i64,
+pub(crate) struct TokenData {
+    pub user_id: i64,
+    pub exp: i64,
 }
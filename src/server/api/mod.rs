@@ -3,8 +3,13 @@ use async_trait::async_trait;
 use env_logger::Env;
 use sea_orm::DatabaseConnection;
 use std::error::Error;
+use std::time::Duration;
 
 use crate::{config::env::server_port, database::mysql_connection};
+use crate::p2p::node::handle::NodeHandle;
+use crate::scheduler::Scheduler;
+use crate::server::signal::shutdown_signal;
+use crate::server_node::resources::sampler::{ResourceSampler, ResourceWatch, DEFAULT_SAMPLE_INTERVAL};
 
 pub mod routes;
 
@@ -57,7 +62,11 @@ impl Default for StartServerOptions {
 #[derive(Clone)]
 pub struct AppState {
     // Under the hood, a sqlx::Pool is created and owned by DatabaseConnection.
-    pub db: DatabaseConnection
+    pub db: DatabaseConnection,
+    // Node running on the same process, changes are applied right away through it
+    pub node: Option<NodeHandle>,
+    // Latest resources of this computer, sampled on the background
+    pub resources: Option<ResourceWatch>,
 }
 
 /// Implement a trait that creates a new state for each connection
@@ -75,37 +84,65 @@ pub trait CreateAppState {
 impl CreateAppState for AppState {
     async fn create_state() -> Result<AppState, Box<dyn Error>> {
         let db = mysql_connection().await?;
+        let resources = ResourceSampler::spawn(Duration::from_secs(DEFAULT_SAMPLE_INTERVAL))?;
         
         Ok(AppState {
             db,
+            node: None,
+            resources: Some(resources),
         })
     }
 }
 
 /// Start rest server
 /// 
-/// 
+/// Runs until SIGINT or SIGTERM, in-flight requests are finished before closing
 pub async fn start_server(start_server_options: StartServerOptions) -> Result<(), Box<dyn Error>> {
-    let location = start_server_options.location();
-    
-    println!("Server running at {location}");
-    
     env_logger::init_from_env(Env::default().default_filter_or("info"));
     
     // Create state
     let state = AppState::create_state().await?;
     
+    serve(start_server_options, state).await
+}
+
+/// Serve the rest api with the given state
+/// 
+/// Runs until SIGINT or SIGTERM, in-flight requests are finished before closing
+pub async fn serve(start_server_options: StartServerOptions, state: AppState) -> Result<(), Box<dyn Error>> {
+    let location = start_server_options.location();
+    
+    println!("Server running at {location}");
+    
+    // Shared by every worker, strategies keep state between placements
+    let scheduler = web::Data::new(Scheduler::default());
+    
     // Start the Actix-web server
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(state.clone()))
+            .app_data(scheduler.clone())
             .wrap(Logger::default())
             .wrap(Logger::new("%a %{User-Agent}i"))
             .service(routes::main()) 
     })
+        .disable_signals()
         .bind(location)?
-        .run()
-        .await?;
+        .run();
+    
+    // Stop gracefully on shutdown signals
+    let server_handle = server.handle();
+    tokio::spawn(async move {
+        match shutdown_signal().await {
+            Ok(()) => {
+                println!("Shutting down server");
+                server_handle.stop(true).await;
+            }
+            Err(err) => eprintln!("Couldn't listen for shutdown signals: {err}"),
+        }
+    });
+    
+    server.await?;
     
     Ok(())
 }
@@ -0,0 +1,84 @@
+//! Hive leader
+//! 
+//! Leader of the subnetwork of the node, it's elected by the hive nodes so it's only available
+//! when the rest api is served from the node process.
+use actix_web::{web, HttpResponse, Responder, Scope};
+
+use crate::server::api::AppState;
+
+/// Get the leader election of the node subnetwork
+/// 
+/// 
+async fn get_leader(data: web::Data<AppState>) -> impl Responder {
+    let node = match &data.node {
+        Some(node) => node,
+        None => {
+            return HttpResponse::ServiceUnavailable()
+                .body("The leader is elected by the hive node, serve the rest api with 'hive --api-address'");
+        }
+    };
+    
+    match node.election().await {
+        Ok(election) => HttpResponse::Ok().json(election),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Couldn't get the leader: {err}")),
+    }
+}
+
+/// Main
+/// 
+/// 
+pub fn main() -> Scope {
+    web::scope("")
+        .route("", web::get().to(get_leader))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test, App};
+    use sea_orm::DatabaseConnection;
+    
+    use crate::p2p::hive::HiveParameters;
+    use crate::p2p::node::election::ElectionStatus;
+    use crate::p2p::node::Node;
+    
+    #[actix_web::test]
+    async fn test_get_leader() {
+        // Without a node
+        let state = AppState {
+            db: DatabaseConnection::Disconnected,
+            node: None,
+            resources: None,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .service(web::scope("/leader").service(main()))
+        ).await;
+        let request = test::TestRequest::get().uri("/leader").to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::SERVICE_UNAVAILABLE);
+        
+        let parameters = HiveParameters {
+            key_seed: Some(143),
+            subnetwork: Some("leader-route".to_string()),
+            ..Default::default()
+        };
+        let node = Node::new(parameters).await.unwrap().spawn().unwrap();
+        let state = AppState {
+            db: DatabaseConnection::Disconnected,
+            node: Some(node.clone()),
+            resources: None,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .service(web::scope("/leader").service(main()))
+        ).await;
+        
+        let request = test::TestRequest::get().uri("/leader").to_request();
+        let election: ElectionStatus = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(election.subnetwork, "leader-route");
+        
+        node.shutdown().await.unwrap();
+    }
+}
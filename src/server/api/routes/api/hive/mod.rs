@@ -0,0 +1,42 @@
+use actix_web::{
+    Scope,
+    web
+};
+
+pub mod leader;
+pub mod peers;
+pub mod registry;
+pub mod status;
+pub mod tasks;
+pub mod transfers;
+
+/// Main
+/// 
+/// 
+pub fn main() -> Scope {
+    web::scope("")
+        .service(
+            web::scope("/leader")
+                .service(leader::main())
+        )
+        .service(
+            web::scope("/peers")
+                .service(peers::main())
+        )
+        .service(
+            web::scope("/registry")
+                .service(registry::main())
+        )
+        .service(
+            web::scope("/status")
+                .service(status::main())
+        )
+        .service(
+            web::scope("/tasks")
+                .service(tasks::main())
+        )
+        .service(
+            web::scope("/transfers")
+                .service(transfers::main())
+        )
+}
@@ -0,0 +1,179 @@
+//! Peer access rules
+//! 
+//! When the node runs on the same process rules are applied right away, otherwise they are only
+//! stored and the node applies them on its next sync.
+use actix_web::{web, HttpResponse, Responder, Scope};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+use crate::p2p::node::access::{controller::AccessListController, AccessRule, PeerRule};
+use crate::server::api::AppState;
+
+/// Peer rule request
+/// 
+/// The body of 'put_peer'
+#[derive(Deserialize, Serialize)]
+pub struct PeerRuleRequest {
+    pub rule: PeerRule,
+    pub reason: Option<String>,
+}
+
+/// List the peer rules
+/// 
+/// 
+pub async fn list_peer_rules(state: &AppState) -> Result<Vec<AccessRule>, Box<dyn Error>> {
+    match &state.node {
+        Some(node) => node.peer_rules().await,
+        None => Ok(AccessListController::new(state.db.clone()).load().await?.rules()),
+    }
+}
+
+/// Set the rule of a peer
+/// 
+/// 
+pub async fn set_peer_rule(state: &AppState, rule: AccessRule) -> Result<(), Box<dyn Error>> {
+    match &state.node {
+        Some(node) => node.set_peer_rule(rule).await,
+        None => AccessListController::new(state.db.clone()).save(&rule).await,
+    }
+}
+
+/// Remove the rule of a peer
+/// 
+/// Returns whether the peer had a rule
+pub async fn remove_peer_rule(state: &AppState, peer_id: PeerId) -> Result<bool, Box<dyn Error>> {
+    match &state.node {
+        Some(node) => node.remove_peer_rule(peer_id).await,
+        None => AccessListController::new(state.db.clone()).remove(&peer_id).await,
+    }
+}
+
+/// Get the allowed and blocked peers
+/// 
+/// 
+async fn get_peers(data: web::Data<AppState>) -> impl Responder {
+    match list_peer_rules(&data).await {
+        Ok(rules) => HttpResponse::Ok().json(rules),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Couldn't list the peer rules: {err}")),
+    }
+}
+
+/// Allow or block a peer
+/// 
+/// 
+async fn put_peer(
+    path: web::Path<String>,
+    body: web::Json<PeerRuleRequest>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let peer_id: PeerId = match path.parse() {
+        Ok(peer_id) => peer_id,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid peer id: {err}")),
+    };
+    
+    let body = body.into_inner();
+    let rule = AccessRule::new(peer_id, body.rule, body.reason);
+    
+    match set_peer_rule(&data, rule.clone()).await {
+        Ok(()) => HttpResponse::Ok().json(rule),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Couldn't set the peer rule: {err}")),
+    }
+}
+
+/// Remove the rule of a peer
+/// 
+/// 
+async fn delete_peer(path: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
+    let peer_id: PeerId = match path.parse() {
+        Ok(peer_id) => peer_id,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid peer id: {err}")),
+    };
+    
+    match remove_peer_rule(&data, peer_id).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().body(format!("Peer {peer_id} has no rule")),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Couldn't remove the peer rule: {err}")),
+    }
+}
+
+/// Main
+/// 
+/// 
+pub fn main() -> Scope {
+    web::scope("")
+        .route("", web::get().to(get_peers))
+        .route("/{peer_id}", web::put().to(put_peer))
+        .route("/{peer_id}", web::delete().to(delete_peer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test, App};
+    use sea_orm::DatabaseConnection;
+    
+    use crate::p2p::hive::HiveParameters;
+    use crate::p2p::node::Node;
+    
+    #[actix_web::test]
+    async fn test_invalid_peer_id() {
+        let state = AppState {
+            db: DatabaseConnection::Disconnected,
+            node: None,
+            resources: None,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .service(web::scope("/peers").service(main()))
+        ).await;
+        
+        let request = test::TestRequest::put()
+            .uri("/peers/not-a-peer-id")
+            .set_json(PeerRuleRequest { rule: PeerRule::Block, reason: None })
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    
+    #[actix_web::test]
+    async fn test_rules_through_the_node() {
+        let parameters = HiveParameters {
+            key_seed: Some(130),
+            ..Default::default()
+        };
+        let node = Node::new(parameters).await.unwrap().spawn().unwrap();
+        let state = AppState {
+            db: DatabaseConnection::Disconnected,
+            node: Some(node.clone()),
+            resources: None,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .service(web::scope("/peers").service(main()))
+        ).await;
+        
+        let peer_id = PeerId::random();
+        let request = test::TestRequest::put()
+            .uri(&format!("/peers/{peer_id}"))
+            .set_json(PeerRuleRequest { rule: PeerRule::Block, reason: Some("Spam".to_string()) })
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let request = test::TestRequest::get().uri("/peers").to_request();
+        let rules: Vec<AccessRule> = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(rules, vec![AccessRule::new(peer_id, PeerRule::Block, Some("Spam".to_string()))]);
+        
+        let request = test::TestRequest::delete().uri(&format!("/peers/{peer_id}")).to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NO_CONTENT);
+        
+        let request = test::TestRequest::delete().uri(&format!("/peers/{peer_id}")).to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NOT_FOUND);
+        
+        node.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,83 @@
+//! Hive node registry
+//! 
+//! Server nodes replicated between the hive nodes, they're listed by the node itself so they're
+//! available even when the database isn't.
+use actix_web::{web, HttpResponse, Responder, Scope};
+
+use crate::server::api::AppState;
+
+/// Get the entries of the node registry
+/// 
+/// Removed nodes are included without server node
+async fn get_registry(data: web::Data<AppState>) -> impl Responder {
+    let node = match &data.node {
+        Some(node) => node,
+        None => {
+            return HttpResponse::ServiceUnavailable()
+                .body("The registry is kept by the hive node, serve the rest api with 'hive --api-address'");
+        }
+    };
+    
+    match node.registry().await {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Couldn't list the registry: {err}")),
+    }
+}
+
+/// Main
+/// 
+/// 
+pub fn main() -> Scope {
+    web::scope("")
+        .route("", web::get().to(get_registry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use sea_orm::DatabaseConnection;
+    use std::time::Duration;
+    
+    use crate::p2p::hive::HiveParameters;
+    use crate::p2p::node::registry::RegistryEntry;
+    use crate::p2p::node::Node;
+    
+    #[actix_web::test]
+    async fn test_get_registry() {
+        let parameters = HiveParameters {
+            key_seed: Some(146),
+            ..Default::default()
+        };
+        let node = Node::new(parameters).await.unwrap().spawn().unwrap();
+        let state = AppState {
+            db: DatabaseConnection::Disconnected,
+            node: Some(node.clone()),
+            resources: None,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .service(web::scope("/registry").service(main()))
+        ).await;
+        
+        // The node registers itself once it starts
+        let entries = tokio::time::timeout(Duration::from_secs(30), async {
+            loop {
+                let request = test::TestRequest::get().uri("/registry").to_request();
+                let entries: Vec<RegistryEntry> = test::call_and_read_body_json(&app, request).await;
+                if !entries.is_empty() {
+                    break entries;
+                }
+                
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(entries[0].node_id, node.peer_id());
+        assert!(entries[0].server_node.is_some());
+        
+        node.shutdown().await.unwrap();
+    }
+}
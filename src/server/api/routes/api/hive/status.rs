@@ -0,0 +1,127 @@
+//! Hive node status
+//! 
+//! Status of the node and liveness of its peers, putting the node on maintenance keeps it there
+//! until it's cleared through this route.
+use actix_web::{web, HttpResponse, Responder, Scope};
+use serde::{Deserialize, Serialize};
+
+use crate::p2p::node::liveness::PeerLiveness;
+use crate::server::api::AppState;
+use crate::server_node::ServerStatus;
+
+/// Node status
+/// 
+/// The response of 'get_status'
+#[derive(Deserialize, Serialize)]
+pub struct NodeStatus {
+    pub peer_id: String,
+    pub status: ServerStatus,
+    pub peers: Vec<PeerLiveness>,
+}
+
+/// Set status request
+/// 
+/// The body of 'set_status'
+#[derive(Deserialize, Serialize)]
+pub struct SetStatusRequest {
+    pub status: ServerStatus,
+}
+
+/// Response when the rest api isn't served by the node
+/// 
+/// 
+fn node_unavailable() -> HttpResponse {
+    HttpResponse::ServiceUnavailable()
+        .body("The status is tracked by the hive node, serve the rest api with 'hive --api-address'")
+}
+
+/// Get the status of the node and its peers
+/// 
+/// 
+async fn get_status(data: web::Data<AppState>) -> impl Responder {
+    let node = match &data.node {
+        Some(node) => node,
+        None => return node_unavailable(),
+    };
+    
+    match node.liveness().await {
+        Ok((status, peers)) => HttpResponse::Ok().json(NodeStatus {
+            peer_id: node.peer_id().to_string(),
+            status,
+            peers,
+        }),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Couldn't get the status: {err}")),
+    }
+}
+
+/// Set the status of the node
+/// 
+/// 
+async fn set_status(body: web::Json<SetStatusRequest>, data: web::Data<AppState>) -> impl Responder {
+    let node = match &data.node {
+        Some(node) => node,
+        None => return node_unavailable(),
+    };
+    
+    match node.set_status(body.into_inner().status).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Couldn't set the status: {err}")),
+    }
+}
+
+/// Main
+/// 
+/// 
+pub fn main() -> Scope {
+    web::scope("")
+        .route("", web::get().to(get_status))
+        .route("", web::put().to(set_status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test, App};
+    use sea_orm::DatabaseConnection;
+    
+    use crate::p2p::hive::HiveParameters;
+    use crate::p2p::node::Node;
+    
+    #[actix_web::test]
+    async fn test_status_requests() {
+        let parameters = HiveParameters {
+            key_seed: Some(140),
+            ..Default::default()
+        };
+        let node = Node::new(parameters).await.unwrap().spawn().unwrap();
+        let state = AppState {
+            db: DatabaseConnection::Disconnected,
+            node: Some(node.clone()),
+            resources: None,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .service(web::scope("/status").service(main()))
+        ).await;
+        
+        let request = test::TestRequest::get().uri("/status").to_request();
+        let status: NodeStatus = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(status.peer_id, node.peer_id().to_string());
+        assert_eq!(status.status, ServerStatus::Online);
+        
+        let request = test::TestRequest::put()
+            .uri("/status")
+            .set_json(SetStatusRequest {
+                status: ServerStatus::Maintenance,
+            })
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::OK);
+        
+        let request = test::TestRequest::get().uri("/status").to_request();
+        let status: NodeStatus = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(status.status, ServerStatus::Maintenance);
+        
+        node.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,248 @@
+//! Hive tasks
+//! 
+//! Tasks are run by the hive node, so they're only available when the rest api is served
+//! from the node process. Submitting and cancelling tasks needs a token.
+use actix_web::{web, HttpRequest, HttpResponse, Responder, Scope};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+
+use crate::p2p::node::task::TaskSpec;
+use crate::server::api::AppState;
+use crate::server::middleware::auth::authorize;
+
+/// Submit task request
+/// 
+/// The body of 'post_task', without a peer the task runs on the node itself
+#[derive(Deserialize, Serialize)]
+pub struct SubmitTaskRequest {
+    pub peer_id: Option<String>,
+    pub spec: TaskSpec,
+}
+
+/// Response when the rest api isn't served by the node
+/// 
+/// 
+fn node_unavailable() -> HttpResponse {
+    HttpResponse::ServiceUnavailable()
+        .body("Tasks are run by the hive node, serve the rest api with 'hive --api-address'")
+}
+
+/// Get the tasks of the node
+/// 
+/// 
+async fn get_tasks(data: web::Data<AppState>) -> impl Responder {
+    let node = match &data.node {
+        Some(node) => node,
+        None => return node_unavailable(),
+    };
+    
+    match node.tasks().await {
+        Ok(tasks) => HttpResponse::Ok().json(tasks),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Couldn't list the tasks: {err}")),
+    }
+}
+
+/// Submit a task
+/// 
+/// It runs as the user of the node, so it needs a token
+async fn post_task(request: HttpRequest, body: web::Json<SubmitTaskRequest>, data: web::Data<AppState>) -> impl Responder {
+    if let Err(err) = authorize(&request) {
+        return HttpResponse::from_error(err);
+    }
+    
+    let node = match &data.node {
+        Some(node) => node,
+        None => return node_unavailable(),
+    };
+    
+    let body = body.into_inner();
+    let peer_id: PeerId = match body.peer_id {
+        Some(peer_id) => match peer_id.parse() {
+            Ok(peer_id) => peer_id,
+            Err(err) => return HttpResponse::BadRequest().body(format!("Invalid peer id: {err}")),
+        },
+        None => node.peer_id(),
+    };
+    
+    match node.submit_task(peer_id, body.spec).await {
+        Ok(task) => HttpResponse::Ok().json(task),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Couldn't submit the task: {err}")),
+    }
+}
+
+/// Get a task
+/// 
+/// 
+async fn get_task(path: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
+    let node = match &data.node {
+        Some(node) => node,
+        None => return node_unavailable(),
+    };
+    
+    match node.task(&path).await {
+        Ok(Some(task)) => HttpResponse::Ok().json(task),
+        Ok(None) => HttpResponse::NotFound().body(format!("Task '{path}' not found")),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Couldn't get the task: {err}")),
+    }
+}
+
+/// Cancel a task
+/// 
+/// The task is cancelled once its runner kills it
+async fn cancel_task(request: HttpRequest, path: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
+    if let Err(err) = authorize(&request) {
+        return HttpResponse::from_error(err);
+    }
+    
+    let node = match &data.node {
+        Some(node) => node,
+        None => return node_unavailable(),
+    };
+    
+    match node.task(&path).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return HttpResponse::NotFound().body(format!("Task '{path}' not found")),
+        Err(err) => return HttpResponse::InternalServerError().body(format!("Couldn't get the task: {err}")),
+    };
+    
+    match node.cancel_task(&path).await {
+        Ok(()) => HttpResponse::Accepted().finish(),
+        Err(err) => HttpResponse::Conflict().body(format!("Couldn't cancel the task: {err}")),
+    }
+}
+
+/// Main
+/// 
+/// 
+pub fn main() -> Scope {
+    web::scope("")
+        .route("", web::get().to(get_tasks))
+        .route("", web::post().to(post_task))
+        .route("/{task_id}", web::get().to(get_task))
+        .route("/{task_id}/cancel", web::post().to(cancel_task))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::header::{HeaderName, AUTHORIZATION}, http::StatusCode, test, App};
+    use sea_orm::DatabaseConnection;
+    use std::time::Duration;
+    
+    use crate::p2p::hive::HiveParameters;
+    use crate::p2p::node::protocol::server_node::HIVE_NODE_USER_ID;
+    use crate::p2p::node::task::{TaskInfo, TaskStatus};
+    use crate::p2p::node::Node;
+    use crate::security::create_token::create_token;
+    
+    fn bearer() -> (HeaderName, String) {
+        let token = create_token(HIVE_NODE_USER_ID, Duration::from_secs(60)).unwrap();
+        
+        (AUTHORIZATION, format!("Bearer {token}"))
+    }
+    
+    #[actix_web::test]
+    async fn test_tasks_need_the_node() {
+        let state = AppState {
+            db: DatabaseConnection::Disconnected,
+            node: None,
+            resources: None,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .service(web::scope("/tasks").service(main()))
+        ).await;
+        
+        let request = test::TestRequest::get().uri("/tasks").to_request();
+        let response = test::call_service(&app, request).await;
+        
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+    
+    #[actix_web::test]
+    async fn test_run_and_cancel_a_task() {
+        let parameters = HiveParameters {
+            key_seed: Some(133),
+            ..Default::default()
+        };
+        let node = Node::new(parameters).await.unwrap().spawn().unwrap();
+        let state = AppState {
+            db: DatabaseConnection::Disconnected,
+            node: Some(node.clone()),
+            resources: None,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .service(web::scope("/tasks").service(main()))
+        ).await;
+        
+        // Without a token nothing runs
+        let request = test::TestRequest::post()
+            .uri("/tasks")
+            .set_json(SubmitTaskRequest {
+                peer_id: None,
+                spec: TaskSpec::new("sleep", &["30"]),
+            })
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::UNAUTHORIZED);
+        let request = test::TestRequest::post()
+            .uri("/tasks")
+            .insert_header((AUTHORIZATION, "Bearer not-a-token"))
+            .set_json(SubmitTaskRequest {
+                peer_id: None,
+                spec: TaskSpec::new("sleep", &["30"]),
+            })
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::UNAUTHORIZED);
+        let tasks: Vec<TaskInfo> = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/tasks").to_request()).await;
+        assert!(tasks.is_empty());
+        
+        let request = test::TestRequest::post()
+            .uri("/tasks")
+            .insert_header(bearer())
+            .set_json(SubmitTaskRequest {
+                peer_id: None,
+                spec: TaskSpec::new("sleep", &["30"]),
+            })
+            .to_request();
+        let task: TaskInfo = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(task.status, TaskStatus::Running);
+        assert_eq!(task.runner, node.peer_id());
+        
+        let request = test::TestRequest::post().uri(&format!("/tasks/{}/cancel", task.id)).to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::UNAUTHORIZED);
+        let request = test::TestRequest::post()
+            .uri(&format!("/tasks/{}/cancel", task.id))
+            .insert_header(bearer())
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::ACCEPTED);
+        
+        let cancelled = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let request = test::TestRequest::get().uri(&format!("/tasks/{}", task.id)).to_request();
+                let task: TaskInfo = test::call_and_read_body_json(&app, request).await;
+                if task.status.is_finished() {
+                    return task.status;
+                }
+                
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await;
+        assert_eq!(cancelled.ok(), Some(TaskStatus::Cancelled));
+        
+        // It can't be cancelled twice
+        let request = test::TestRequest::post()
+            .uri(&format!("/tasks/{}/cancel", task.id))
+            .insert_header(bearer())
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::CONFLICT);
+        
+        let request = test::TestRequest::get().uri("/tasks/unknown").to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NOT_FOUND);
+        
+        node.shutdown().await.unwrap();
+    }
+}
@@ -0,0 +1,223 @@
+//! Hive file transfers
+//! 
+//! Files are transferred by the hive node, so they're only available when the rest api is served
+//! from the node process. Local paths are paths of the computer that runs the node, so starting a
+//! transfer needs a token.
+use actix_web::{web, HttpRequest, HttpResponse, Responder, Scope};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::server::api::AppState;
+use crate::server::middleware::auth::authorize;
+
+/// Send file request
+/// 
+/// The body of 'send_file'
+#[derive(Deserialize, Serialize)]
+pub struct SendFileRequest {
+    pub peer_id: String,
+    pub path: PathBuf,
+    // Bytes per second
+    pub rate_limit: Option<u64>,
+}
+
+/// Fetch file request
+/// 
+/// The body of 'fetch_file', without an output the file is stored on the transfer folder of the node
+#[derive(Deserialize, Serialize)]
+pub struct FetchFileRequest {
+    pub peer_id: String,
+    pub remote_path: String,
+    pub output: Option<PathBuf>,
+    // Bytes per second
+    pub rate_limit: Option<u64>,
+}
+
+/// Response when the rest api isn't served by the node
+/// 
+/// 
+fn node_unavailable() -> HttpResponse {
+    HttpResponse::ServiceUnavailable()
+        .body("Files are transferred by the hive node, serve the rest api with 'hive --api-address'")
+}
+
+/// Get the transfers of the node
+/// 
+/// 
+async fn get_transfers(data: web::Data<AppState>) -> impl Responder {
+    let node = match &data.node {
+        Some(node) => node,
+        None => return node_unavailable(),
+    };
+    
+    match node.transfers().await {
+        Ok(transfers) => HttpResponse::Ok().json(transfers),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Couldn't list the transfers: {err}")),
+    }
+}
+
+/// Send a file to a peer
+/// 
+/// 
+async fn send_file(request: HttpRequest, body: web::Json<SendFileRequest>, data: web::Data<AppState>) -> impl Responder {
+    if let Err(err) = authorize(&request) {
+        return HttpResponse::from_error(err);
+    }
+    
+    let node = match &data.node {
+        Some(node) => node,
+        None => return node_unavailable(),
+    };
+    
+    let body = body.into_inner();
+    let peer_id: PeerId = match body.peer_id.parse() {
+        Ok(peer_id) => peer_id,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid peer id: {err}")),
+    };
+    
+    match node.send_file(peer_id, body.path, body.rate_limit).await {
+        Ok(transfer) => HttpResponse::Ok().json(transfer),
+        Err(err) => HttpResponse::BadRequest().body(format!("Couldn't send the file: {err}")),
+    }
+}
+
+/// Fetch a file from a peer
+/// 
+/// 
+async fn fetch_file(request: HttpRequest, body: web::Json<FetchFileRequest>, data: web::Data<AppState>) -> impl Responder {
+    if let Err(err) = authorize(&request) {
+        return HttpResponse::from_error(err);
+    }
+    
+    let node = match &data.node {
+        Some(node) => node,
+        None => return node_unavailable(),
+    };
+    
+    let body = body.into_inner();
+    let peer_id: PeerId = match body.peer_id.parse() {
+        Ok(peer_id) => peer_id,
+        Err(err) => return HttpResponse::BadRequest().body(format!("Invalid peer id: {err}")),
+    };
+    
+    match node.fetch_file(peer_id, &body.remote_path, body.output, body.rate_limit).await {
+        Ok(transfer) => HttpResponse::Ok().json(transfer),
+        Err(err) => HttpResponse::BadRequest().body(format!("Couldn't fetch the file: {err}")),
+    }
+}
+
+/// Get a transfer
+/// 
+/// 
+async fn get_transfer(path: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
+    let node = match &data.node {
+        Some(node) => node,
+        None => return node_unavailable(),
+    };
+    
+    match node.transfer(&path).await {
+        Ok(Some(transfer)) => HttpResponse::Ok().json(transfer),
+        Ok(None) => HttpResponse::NotFound().body(format!("Transfer '{path}' not found")),
+        Err(err) => HttpResponse::InternalServerError().body(format!("Couldn't get the transfer: {err}")),
+    }
+}
+
+/// Main
+/// 
+/// 
+pub fn main() -> Scope {
+    web::scope("")
+        .route("", web::get().to(get_transfers))
+        .route("/send", web::post().to(send_file))
+        .route("/fetch", web::post().to(fetch_file))
+        .route("/{transfer_id}", web::get().to(get_transfer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::header::AUTHORIZATION, http::StatusCode, test, App};
+    use sea_orm::DatabaseConnection;
+    use std::time::Duration;
+    
+    use crate::p2p::hive::HiveParameters;
+    use crate::p2p::node::protocol::server_node::HIVE_NODE_USER_ID;
+    use crate::p2p::node::transfer::TransferInfo;
+    use crate::p2p::node::Node;
+    use crate::security::create_token::create_token;
+    
+    #[actix_web::test]
+    async fn test_transfer_requests() {
+        let parameters = HiveParameters {
+            key_seed: Some(137),
+            ..Default::default()
+        };
+        let node = Node::new(parameters).await.unwrap().spawn().unwrap();
+        let state = AppState {
+            db: DatabaseConnection::Disconnected,
+            node: Some(node.clone()),
+            resources: None,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .service(web::scope("/transfers").service(main()))
+        ).await;
+        
+        let request = test::TestRequest::get().uri("/transfers").to_request();
+        let transfers: Vec<TransferInfo> = test::call_and_read_body_json(&app, request).await;
+        assert!(transfers.is_empty());
+        
+        // Local files aren't sent without a token
+        let request = test::TestRequest::post()
+            .uri("/transfers/send")
+            .set_json(SendFileRequest {
+                peer_id: PeerId::random().to_string(),
+                path: PathBuf::from("Cargo.toml"),
+                rate_limit: None,
+            })
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::UNAUTHORIZED);
+        let request = test::TestRequest::post()
+            .uri("/transfers/fetch")
+            .set_json(FetchFileRequest {
+                peer_id: PeerId::random().to_string(),
+                remote_path: "Cargo.toml".to_string(),
+                output: Some(PathBuf::from("/tmp/Cargo.toml")),
+                rate_limit: None,
+            })
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::UNAUTHORIZED);
+        
+        let token = create_token(HIVE_NODE_USER_ID, Duration::from_secs(60)).unwrap();
+        let request = test::TestRequest::post()
+            .uri("/transfers/send")
+            .insert_header((AUTHORIZATION, format!("Bearer {token}")))
+            .set_json(SendFileRequest {
+                peer_id: "not-a-peer".to_string(),
+                path: PathBuf::from("Cargo.toml"),
+                rate_limit: None,
+            })
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::BAD_REQUEST);
+        
+        // The node can't fetch from itself
+        let request = test::TestRequest::post()
+            .uri("/transfers/fetch")
+            .insert_header((AUTHORIZATION, format!("Bearer {token}")))
+            .set_json(FetchFileRequest {
+                peer_id: node.peer_id().to_string(),
+                remote_path: "Cargo.toml".to_string(),
+                output: None,
+                rate_limit: None,
+            })
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::BAD_REQUEST);
+        
+        let request = test::TestRequest::get().uri("/transfers/unknown").to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NOT_FOUND);
+        
+        node.shutdown().await.unwrap();
+    }
+}
@@ -3,6 +3,8 @@ use actix_web::{
     web
 };
 
+pub mod hive;
+pub mod scheduler;
 pub mod server_node;
 
 /// Main
@@ -10,6 +12,14 @@ pub mod server_node;
 /// 
 pub fn main() -> Scope {
     web::scope("/api")
+        .service(
+            web::scope("/hive")
+                .service(hive::main())
+        )
+        .service(
+            web::scope("/scheduler")
+                .service(scheduler::main())
+        )
         .service(
             web::scope("/server-node")
                 .service(server_node::main())
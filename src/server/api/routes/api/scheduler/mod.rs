@@ -0,0 +1,150 @@
+//! Scheduler
+//! 
+//! Explains which node should run something, without running it
+use actix_web::{web, HttpResponse, Responder, Scope};
+use serde::{Deserialize, Serialize};
+
+use crate::scheduler::candidates::{self, CandidateSource};
+use crate::scheduler::{PlacementRequest, Scheduler};
+use crate::server::api::AppState;
+
+/// Place request
+/// 
+/// The body of 'place', the candidates are the live ones when the node runs on the same process
+#[derive(Deserialize, Serialize)]
+pub struct PlaceRequest {
+    #[serde(flatten)]
+    pub placement: PlacementRequest,
+    pub source: Option<CandidateSource>,
+}
+
+/// Choose a node
+/// 
+/// 
+async fn place(
+    body: web::Json<PlaceRequest>,
+    data: web::Data<AppState>,
+    scheduler: web::Data<Scheduler>,
+) -> impl Responder {
+    let body = body.into_inner();
+    let source = match (body.source, &data.node) {
+        (Some(source), _) => source,
+        (None, Some(_)) => CandidateSource::Live,
+        (None, None) => CandidateSource::Database,
+    };
+    
+    let candidates = match (source, &data.node) {
+        (CandidateSource::Live, Some(node)) => candidates::from_node(node).await,
+        (CandidateSource::Live, None) => {
+            return HttpResponse::BadRequest()
+                .body("Live candidates are only known by the hive node, serve the rest api with 'hive --api-address'");
+        }
+        (CandidateSource::Database, _) => candidates::from_database(&data.db).await,
+    };
+    let candidates = match candidates {
+        Ok(candidates) => candidates,
+        Err(err) => return HttpResponse::InternalServerError().body(format!("Couldn't get the candidates: {err}")),
+    };
+    
+    match scheduler.place(&body.placement, &candidates) {
+        Ok(placement) => HttpResponse::Ok().json(placement),
+        Err(err) => HttpResponse::BadRequest().body(err.to_string()),
+    }
+}
+
+/// Get the strategies
+/// 
+/// 
+async fn get_strategies(scheduler: web::Data<Scheduler>) -> impl Responder {
+    HttpResponse::Ok().json(scheduler.strategies())
+}
+
+/// Main
+/// 
+/// 
+pub fn main() -> Scope {
+    web::scope("")
+        .route("/place", web::post().to(place))
+        .route("/strategies", web::get().to(get_strategies))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test, App};
+    use sea_orm::DatabaseConnection;
+    
+    use crate::p2p::hive::HiveParameters;
+    use crate::p2p::node::Node;
+    use crate::scheduler::Placement;
+    
+    #[actix_web::test]
+    async fn test_place_on_the_node() {
+        let parameters = HiveParameters {
+            key_seed: Some(136),
+            ..Default::default()
+        };
+        let node = Node::new(parameters).await.unwrap().spawn().unwrap();
+        let state = AppState {
+            db: DatabaseConnection::Disconnected,
+            node: Some(node.clone()),
+            resources: None,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .app_data(web::Data::new(Scheduler::default()))
+                .service(web::scope("/scheduler").service(main()))
+        ).await;
+        
+        let request = test::TestRequest::post()
+            .uri("/scheduler/place")
+            .set_json(serde_json::json!({ "strategy": "round-robin" }))
+            .to_request();
+        let placement: Placement = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(placement.selected, Some(node.peer_id().to_string()));
+        assert_eq!(placement.evaluations.len(), 1);
+        
+        // Labels the node doesn't have
+        let request = test::TestRequest::post()
+            .uri("/scheduler/place")
+            .set_json(serde_json::json!({ "labels": ["hive-missing-label"] }))
+            .to_request();
+        let placement: Placement = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(placement.selected, None);
+        assert_eq!(placement.evaluations[0].reasons, vec!["Missing labels: hive-missing-label".to_string()]);
+        
+        let request = test::TestRequest::post()
+            .uri("/scheduler/place")
+            .set_json(serde_json::json!({ "strategy": "random" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::BAD_REQUEST);
+        
+        node.shutdown().await.unwrap();
+    }
+    
+    #[actix_web::test]
+    async fn test_live_candidates_need_the_node() {
+        let state = AppState {
+            db: DatabaseConnection::Disconnected,
+            node: None,
+            resources: None,
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .app_data(web::Data::new(Scheduler::default()))
+                .service(web::scope("/scheduler").service(main()))
+        ).await;
+        
+        let request = test::TestRequest::post()
+            .uri("/scheduler/place")
+            .set_json(serde_json::json!({ "source": "live" }))
+            .to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::BAD_REQUEST);
+        
+        let request = test::TestRequest::get().uri("/scheduler/strategies").to_request();
+        let strategies: Vec<String> = test::call_and_read_body_json(&app, request).await;
+        assert_eq!(strategies, vec!["least-loaded", "bin-packing", "round-robin"]);
+    }
+}
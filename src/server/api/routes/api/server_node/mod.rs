@@ -1,17 +1,31 @@
 use actix_web::{web, HttpRequest, HttpResponse, Responder, Scope};
+use chrono::{DateTime, Utc};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
+use std::time::Duration;
 
 use crate::server::api::AppState;
 use crate::server_node::controller::ServerNodeController;
+use crate::server_node::resources::history::controller::ResourceHistoryController;
 use crate::server_node::ServerNode;
 
+/// Range of a resource series when it isn't given
+const DEFAULT_SERIES_RANGE: Duration = Duration::from_secs(60 * 60);
+
 /// Server node
 ///
-///
-async fn get_server_node() -> impl Responder {
-	match ServerNode::new() {
+/// The resources are the latest sample of the background sampler, when there's one
+async fn get_server_node(state: web::Data<AppState>) -> impl Responder {
+	let server_node = match &state.resources {
+		Some(resources) => match resources.latest().await {
+			Ok(resources) => ServerNode::with_resources(resources),
+			Err(err) => Err(err.into()),
+		},
+		None => ServerNode::new(),
+	};
+
+	match server_node {
 		Ok(server_node) => HttpResponse::Ok().json(server_node),
 		Err(err) => {
 			HttpResponse::InternalServerError().body(format!("Error creating ServerNode: {}", err))
@@ -29,6 +43,24 @@ pub struct LocationRequest {
 	pub location: String,
 }
 
+/// Fetch server node
+///
+/// Request the server node information of the node at the given location
+pub async fn fetch_server_node(location: &str) -> Result<ServerNode, Box<dyn Error>> {
+	let client = Client::new();
+	let response = client
+		.get(format!("{}{}", location, "/api/server-node"))
+		.send()
+		.await?;
+
+	// Get server node information
+	let server_info = response.text().await?;
+
+	let server_node: ServerNode = serde_json::from_str(&server_info)?;
+
+	Ok(server_node)
+}
+
 /// Get server node information
 ///
 /// When a server node location is given, this function can be used to retrieve node information and insert it on our database.
@@ -40,16 +72,7 @@ pub async fn get_server_node_information(
 	let location = body.location.clone();
 
 	// Process the location here
-	let client = Client::new();
-	let response = client
-		.get(format!("{}{}", location, "/api/server_node"))
-		.send()
-		.await?;
-
-	// Get server node information
-	let server_info = response.text().await?;
-
-	let server_node: ServerNode = serde_json::from_str(&server_info)?;
+	let server_node = fetch_server_node(&location).await?;
 
 	// Get the database connection
 	let db_conn = data.db.clone();
@@ -78,6 +101,40 @@ async fn post_location(
 	}
 }
 
+/// Resource history query
+///
+/// Times are RFC 3339 and the step is in seconds
+#[derive(Deserialize, Serialize)]
+pub struct ResourcesQuery {
+	pub from: Option<DateTime<Utc>>,
+	pub to: Option<DateTime<Utc>>,
+	pub step: Option<u64>,
+}
+
+/// Resource history of a server node
+///
+/// The last hour by default, with a step the points are downsampled to it
+async fn get_resources(
+	path: web::Path<i64>,
+	query: web::Query<ResourcesQuery>,
+	data: web::Data<AppState>,
+) -> impl Responder {
+	let to = query.to.unwrap_or_else(Utc::now);
+	let from = query.from.unwrap_or(to - DEFAULT_SERIES_RANGE);
+	if from > to {
+		return HttpResponse::BadRequest().body("'from' has to be before 'to'");
+	}
+	if query.step == Some(0) {
+		return HttpResponse::BadRequest().body("The step has to be at least one second");
+	}
+
+	let controller = ResourceHistoryController::new(data.db.clone());
+	match controller.series(*path, from, to, query.step.map(Duration::from_secs)).await {
+		Ok(series) => HttpResponse::Ok().json(series),
+		Err(err) => HttpResponse::InternalServerError().body(format!("Couldn't get the resource history: {err}")),
+	}
+}
+
 /// Main
 ///
 ///
@@ -85,17 +142,31 @@ pub fn main() -> Scope {
 	web::scope("")
 		.route("", web::get().to(get_server_node))
 		.route("", web::post().to(post_location))
+		.route("/{id}/resources", web::get().to(get_resources))
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
 	use crate::server_node::ServerStatus;
+	use crate::server_node::resources::sampler::ResourceSampler;
 	use actix_web::{http::StatusCode, test, App};
+	use sea_orm::DatabaseConnection;
+	use std::time::Duration;
 
 	#[actix_web::test]
 	async fn test_get_server_node() {
-		let app = test::init_service(App::new().route("/", web::get().to(get_server_node))).await;
+		let resources = ResourceSampler::spawn(Duration::from_secs(1)).unwrap();
+		let state = AppState {
+			db: DatabaseConnection::Disconnected,
+			node: None,
+			resources: Some(resources.clone()),
+		};
+		let app = test::init_service(
+			App::new()
+				.app_data(web::Data::new(state))
+				.route("/", web::get().to(get_server_node))
+		).await;
 		let req = test::TestRequest::get().uri("/").to_request();
 		let res = test::call_service(&app, req).await;
 
@@ -105,6 +176,60 @@ mod tests {
 		assert!(server_node.location.name.len() > 0);
 		assert_eq!(server_node.status, ServerStatus::Online);
 		assert!(server_node.system_info.name.len() > 0);
+
+		// The resources come from the sampler
+		assert!(server_node.resources.eval_time <= resources.current().unwrap().eval_time);
+		assert!(server_node.resources.cpus.iter().all(|cpu| cpu.usage_percentage + cpu.free_percentage > 99.9));
+
+		// Network interfaces are part of the resources
+		let value: serde_json::Value = serde_json::from_slice(&body).unwrap();
+		assert!(value["resources"]["network_interfaces"].is_array());
+		assert_eq!(
+			server_node.resources.network_interfaces.len(),
+			value["resources"]["network_interfaces"].as_array().unwrap().len()
+		);
+	}
+
+	#[actix_web::test]
+	async fn test_get_resources_validates_range() {
+		let state = AppState {
+			db: DatabaseConnection::Disconnected,
+			node: None,
+			resources: None,
+		};
+		let app = test::init_service(
+			App::new()
+				.app_data(web::Data::new(state))
+				.service(web::scope("/server-node").service(main()))
+		).await;
+
+		let req = test::TestRequest::get()
+			.uri("/server-node/1/resources?from=2026-10-18T12:00:00Z&to=2026-10-18T11:00:00Z")
+			.to_request();
+		assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+		let req = test::TestRequest::get()
+			.uri("/server-node/1/resources?step=0")
+			.to_request();
+		assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+	}
+
+	#[actix_web::test]
+	async fn test_fetch_server_node_uses_router_path() {
+		let server_node = ServerNode::new().unwrap();
+
+		let mut server = mockito::Server::new_async().await;
+		let mock = server
+			.mock("GET", "/api/server-node")
+			.with_status(200)
+			.with_body(serde_json::to_string(&server_node).unwrap())
+			.create_async()
+			.await;
+
+		let fetched = fetch_server_node(&server.url()).await.unwrap();
+
+		mock.assert_async().await;
+		assert_eq!(fetched.location.name, server_node.location.name);
 	}
 
 	#[actix_web::test]
@@ -6,3 +6,4 @@ pub mod api;
 pub mod middleware;
 pub mod multicast;
 pub mod reverse;
+pub mod signal;
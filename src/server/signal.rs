@@ -0,0 +1,26 @@
+//! Process signals
+//! 
+//! 
+use tokio::io;
+
+/// Wait for a shutdown signal
+/// 
+/// Resolves on SIGINT (ctrl-c) or SIGTERM
+pub async fn shutdown_signal() -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        
+        let mut terminate = signal(SignalKind::terminate())?;
+        
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await
+    }
+}
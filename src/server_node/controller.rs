@@ -1,17 +1,28 @@
-use entity::server_node::{ActiveModel as ServerNodeActiveModel, Entity as ServerNodeEntity};
+use entity::sea_orm_active_enums::Status;
+use entity::server_node::{self, ActiveModel as ServerNodeActiveModel, Entity as ServerNodeEntity};
 use entity::{
 	server_location::{ActiveModel as ServerLocationActiveModel, Model as ServerLocationModel},
 	server_node::Model as ServerNodeModel,
 	system_info::{ActiveModel as SystemInfoActiveModel, Model as SystemInfoModel},
 	system_resources::{ActiveModel as SystemResourcesActiveModel, Model as SystemResourcesModel},
 };
-use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait, IntoActiveModel};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+	ActiveModelTrait,
+	ActiveValue,
+	ColumnTrait,
+	Condition,
+	DatabaseConnection,
+	EntityTrait,
+	IntoActiveModel,
+	QueryFilter,
+};
 use std::error::Error;
 
 use super::resources::controller::SystemResourcesController;
 use super::server_info::{controller::ServerInfoController, ServerInfo};
 use super::system_info::{SystemInfo, controller::SystemInfoController};
-use super::{ServerNode, ServerStatus};
+use super::{labels_from_column, labels_into_column, ServerNode, ServerStatus};
 
 /// Server node controller
 ///
@@ -140,9 +151,9 @@ impl ServerNodeController {
 	pub async fn get_or_create_server_location(
 		&mut self,
 	) -> Result<ServerLocationActiveModel, Box<dyn Error>> {
-		let server_location = match self.get_server_location() {
-			Ok(location) => location.clone(),
-			Err(_) => {
+		let server_location = match self.server_location.clone() {
+			Some(location) => location,
+			None => {
 				// Get server node
 				let server_node = self.get_server_node()?;
 
@@ -172,9 +183,9 @@ impl ServerNodeController {
 	pub async fn get_or_create_system_resources(
 		&mut self,
 	) -> Result<SystemResourcesActiveModel, Box<dyn Error>> {
-		let system_resources = match self.get_system_resources() {
-			Ok(system_resources) => system_resources.clone(),
-			Err(_) => {
+		let system_resources = match self.system_resources.clone() {
+			Some(system_resources) => system_resources,
+			None => {
 				// Get server node
 				let server_node = self.get_server_node()?;
 				
@@ -205,9 +216,9 @@ impl ServerNodeController {
 	pub async fn get_or_create_system_info(
 		&mut self,
 	) -> Result<SystemInfoActiveModel, Box<dyn Error>> {
-		let system_info = match self.get_system_info() {
-			Ok(info) => info.clone(),
-			Err(_) => {
+		let system_info = match self.system_info.clone() {
+			Some(info) => info,
+			None => {
 				// Get server node
 				let server_node = self.get_server_node()?;
 
@@ -296,9 +307,9 @@ impl ServerNodeController {
 	pub async fn get_or_create_server_node_active_model(
 		&mut self,
 	) -> Result<ServerNodeActiveModel, Box<dyn Error>> {
-		let active_model = match self.get_server_node_active_model() {
-			Ok(active_model) => active_model.clone(),
-			Err(_) => self.create_server_node_active_model().await?,
+		let active_model = match self.server_node_active_model.clone() {
+			Some(active_model) => active_model,
+			None => self.create_server_node_active_model().await?,
 		};
 		
 		Ok(active_model)
@@ -388,6 +399,45 @@ impl ServerNodeController {
 		Ok(self)
 	}
 
+	/// Update a stored server node
+	///
+	/// Its rows are reused: the status, labels, location and resources are updated in place.
+	/// Returns false when there's no server node with the id.
+	pub async fn update_server_node(
+		db: &DatabaseConnection,
+		id: i64,
+		server_node: &ServerNode,
+	) -> Result<bool, Box<dyn Error>> {
+		let server_node_model = match ServerNodeEntity::find_by_id(id).one(db).await? {
+			Some(server_node_model) => server_node_model,
+			None => return Ok(false),
+		};
+
+		// Server location
+		if let Some(server_location_id) = server_node_model.server_location_id {
+			let mut server_location = server_node.location.into_active_model();
+			server_location.id = ActiveValue::Unchanged(server_location_id);
+			server_location.update(db).await?;
+		}
+
+		// System resources and their cores, memory, storage and network interfaces
+		if let Some(system_resource_id) = server_node_model.system_resource_id {
+			let mut system_resources_controller =
+				SystemResourcesController::new(db.clone(), Some(server_node.resources.clone()));
+			system_resources_controller.update(system_resource_id, db).await?;
+		}
+
+		let active_model = ServerNodeActiveModel {
+			id: ActiveValue::Unchanged(id),
+			status: ActiveValue::Set(Some(server_node.status.clone().into())),
+			labels: ActiveValue::Set(labels_into_column(&server_node.labels)),
+			..Default::default()
+		};
+		active_model.update(db).await?;
+
+		Ok(true)
+	}
+
 	/// Delete
 	///
 	///
@@ -412,6 +462,53 @@ impl ServerNodeController {
 
 		Ok(())
 	}
+
+	/// Update status by id
+	///
+	/// Only the status is changed
+	pub async fn update_status_by_id(
+		db: &DatabaseConnection,
+		id: i64,
+		status: ServerStatus,
+	) -> Result<(), Box<dyn Error>> {
+		let active_model = ServerNodeActiveModel {
+			id: ActiveValue::Unchanged(id),
+			status: ActiveValue::Set(Some(status.into())),
+			..Default::default()
+		};
+		active_model.update(db).await?;
+
+		Ok(())
+	}
+
+	/// Update status by id, unless the node is on maintenance
+	///
+	/// Used by automatic changes, maintenance is only cleared explicitly. Returns false when the node is on maintenance
+	pub async fn update_status_unless_maintenance(
+		db: &DatabaseConnection,
+		id: i64,
+		status: ServerStatus,
+	) -> Result<bool, Box<dyn Error>> {
+		let maintenance: Status = ServerStatus::Maintenance.into();
+		let result = ServerNodeEntity::update_many()
+			.col_expr(server_node::Column::Status, Expr::value(Some(Status::from(status))))
+			.filter(server_node::Column::Id.eq(id))
+			.filter(
+				Condition::any()
+					.add(server_node::Column::Status.ne(maintenance))
+					.add(server_node::Column::Status.is_null()),
+			)
+			.exec(db)
+			.await?;
+		if result.rows_affected > 0 {
+			return Ok(true);
+		}
+
+		// MySQL doesn't count rows that already had the status
+		let server_node = ServerNodeEntity::find_by_id(id).one(db).await?;
+
+		Ok(!matches!(server_node.and_then(|server_node| server_node.status), Some(Status::Maintenance)))
+	}
 }
 
 /// Anonymous functions
@@ -461,6 +558,7 @@ impl ServerNodeController {
 			Some(server_node_model) => {
 				// Take status
 				let status = server_node_model.status.clone();
+				let labels = labels_from_column(server_node_model.labels.clone());
 				
 				// Find server location
 				let server_location_model = ServerInfoController::find_by_server_node_model(
@@ -512,6 +610,7 @@ impl ServerNodeController {
 					status,
 					resources,
 					system_info,
+					labels,
 				};
 				
 				server_node
@@ -8,6 +8,8 @@ use serde::{Deserialize, Serialize};
 use std::error::Error;
 use strum_macros::Display;
 
+use crate::config::env::hive_node_labels;
+
 pub mod controller;
 pub mod resources;
 pub mod server_info;
@@ -51,21 +53,32 @@ impl From<ServerStatus> for Status {
 	}
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ServerNode {
 	pub location: ServerInfo,
 	pub status: ServerStatus,
 	pub resources: Resources,
 	pub system_info: SystemInfo,
+	// Set by the operator, tasks can require them
+	#[serde(default)]
+	pub labels: Vec<String>,
 }
 
 impl ServerNode {
 	pub fn new() -> Result<Self, Box<dyn Error>> {
+		Self::with_resources(Resources::fetch_resources()?)
+	}
+
+	/// Create with resources that were already sampled
+	///
+	///
+	pub fn with_resources(resources: Resources) -> Result<Self, Box<dyn Error>> {
 		Ok(Self {
 			location: ServerInfo::new()?,
 			status: ServerStatus::Online,
-			resources: Resources::fetch_resources()?,
+			resources,
 			system_info: SystemInfo::new(),
+			labels: hive_node_labels(),
 		})
 	}
 
@@ -83,11 +96,35 @@ impl ServerNode {
 			server_location_id: ActiveValue::Set(Some(server_location_id)),
 			system_resource_id: ActiveValue::Set(Some(resource_id)),
 			system_info_id: ActiveValue::Set(Some(system_info_id)),
+			labels: ActiveValue::Set(labels_into_column(&self.labels)),
 			..Default::default()
 		})
 	}
 }
 
+/// Labels as they are stored on the database
+///
+/// Comma separated, without labels the column is null
+pub fn labels_into_column(labels: &[String]) -> Option<String> {
+	if labels.is_empty() {
+		return None;
+	}
+	
+	Some(labels.join(","))
+}
+
+/// Labels from the database column
+///
+///
+pub fn labels_from_column(labels: Option<String>) -> Vec<String> {
+	labels
+		.unwrap_or_default()
+		.split(',')
+		.map(|label| label.trim().to_string())
+		.filter(|label| !label.is_empty())
+		.collect()
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
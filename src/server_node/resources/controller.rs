@@ -1,20 +1,21 @@
 use entity::{
 	server_node::Model as ServerNodeModel,
-	storage_device::Entity as StorageDeviceEntity,
 	system_resources::{
 		ActiveModel as SystemResourcesActiveModel, Entity as SystemResourcesEntity,
 		Model as SystemResourcesModel,
 	},
 };
 use sea_orm::{
-	ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait, ModelTrait,
-	TryIntoModel,
+	ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait, TransactionTrait,
 };
 use std::error::Error;
+use std::slice;
 
+use crate::model::reconcile::reconcile;
 use crate::server_node::resources::{
-	storage::controller::StorageController, system_core::controller::CpuCoreController,
-	system_memory::controller::MemoryController, Resources,
+	network::controller::NetworkInterfaceController, storage::controller::StorageController,
+	system_core::controller::CpuCoreController, system_memory::controller::MemoryController,
+	Resources,
 };
 
 /// System resources controller
@@ -88,12 +89,19 @@ impl SystemResourcesController {
 			.find_by_resources_id(system_resources_id)
 			.await?;
 
+		// Network interfaces
+		let network_interface_controller = NetworkInterfaceController::new(self.db.clone());
+		let network_interfaces = network_interface_controller
+			.find_by_resources_id(system_resources_id)
+			.await?;
+
 		// Create system resources from models
 		let system_resources = Resources::from_models(
 			system_resources_model.clone(),
 			cpu_cores,
 			memory,
 			storage_devices,
+			network_interfaces,
 		)?;
 
 		Ok(system_resources)
@@ -121,7 +129,7 @@ impl SystemResourcesController {
 	/// 
 	async fn insert_model(&mut self, resources: Resources) -> Result<&mut Self, Box<dyn Error>> {
 		// Create and insert resources
-		let mut local_system_resources_instance = resources.into_active_model();
+		let mut local_system_resources_instance = resources.into_active_model()?;
 		let inserted_system_resources = local_system_resources_instance
 			.clone()
 			.insert(&self.db)
@@ -171,6 +179,12 @@ impl SystemResourcesController {
 			storage_instance.save(&self.db).await?;
 		}
 
+		// Insert network interfaces
+		for network_interface in &resources.network_interfaces {
+			let network_interface_instance = network_interface.try_into_active_model(system_resources_id)?;
+			network_interface_instance.save(&self.db).await?;
+		}
+
 		Ok(self)
 	}
 	
@@ -195,7 +209,8 @@ impl SystemResourcesController {
 
 	/// Update
 	///
-	/// TODO: This function is incomplete, update cores
+	/// Cores, memory, storage devices and network interfaces are reconciled with the stored rows on
+	/// a single transaction, so rows are only inserted or deleted when the hardware changed.
 	pub async fn update(
 		&mut self,
 		system_resources_id: i64,
@@ -203,82 +218,23 @@ impl SystemResourcesController {
 	) -> Result<(), Box<dyn Error>> {
 		let resources = self.get_resources()?;
 
-		// Create and insert resources
-		let system_resources_instance = SystemResourcesActiveModel {
-			eval_time: ActiveValue::Set(resources.eval_time.naive_utc()),
-			id: ActiveValue::Unchanged(system_resources_id),
-		};
+		let mut system_resources_instance = resources.into_active_model()?;
+		system_resources_instance.id = ActiveValue::Unchanged(system_resources_id);
 
-		// Update system cores
-		let system_core_controller = CpuCoreController::new(
-			db.clone(),
-			Some(resources.clone()),
-			Some(system_resources_instance.clone()),
-		);
-		// TODO: Update cores
+		let txn = db.begin().await?;
 
-		// System resources id
-		let system_resources_id = system_core_controller.id()?;
-
-		// System memory instance
-		let system_memory_instance = resources
-			.memory
-			.try_into_active_model(system_resources_id)?;
-		system_memory_instance.save(db).await?;
-
-		// Update storage
-		// We need to know how many storage devices do we have already
-		// We know storage devices name is unique
-		let system_resources_model = system_resources_instance.clone().try_into_model()?;
-		let existing_storage_devices = system_resources_model
-			.find_related(StorageDeviceEntity)
-			.all(db)
-			.await?;
-
-		// Compare with our storage devices and remove from the database those that aren't in this structure
-		let removed_devices: Vec<&entity::storage_device::Model> = existing_storage_devices
-			.iter()
-			// Get missing storage devices to remove
-			.filter(|storage_device| {
-				let is_there = existing_storage_devices
-					.iter()
-					.filter(|existing_device| existing_device.id == storage_device.id)
-					.collect::<Vec<_>>();
-
-				if !is_there.is_empty() {
-					return true;
-				}
-
-				false
-			})
-			.collect::<Vec<_>>();
-
-		// Delete removed devices in parallel
-		let mut handles: Vec<tokio::task::JoinHandle<Result<(), anyhow::Error>>> = vec![];
-		for model in removed_devices {
-			let model: entity::storage_device::Model = model.clone();
-			let db = db.clone();
-
-			handles.push(tokio::spawn(async move {
-				model.delete(&db).await.map_err(|e| anyhow::Error::new(e))?;
-				Ok(())
-			}));
-		}
-
-		// Wait for deletions to complete
-		for handle in handles {
-			handle.await??;
-		}
-
-		// Insert storage data
-		for storage in &resources.storage {
-			let storage_instance = storage.try_into_active_model(system_resources_id)?;
-			storage_instance.save(db).await?;
-		}
+		// Cores are matched by their index, disks and interfaces by their name and there's a single
+		// memory row
+		reconcile(&txn, system_resources_id, &resources.cpus).await?;
+		reconcile(&txn, system_resources_id, slice::from_ref(&resources.memory)).await?;
+		reconcile(&txn, system_resources_id, &resources.storage).await?;
+		reconcile(&txn, system_resources_id, &resources.network_interfaces).await?;
 
 		// It's a standard operation to save this at the end of everything else
-		// Save system resources evaluation time
-		system_resources_instance.clone().save(db).await?;
+		// Save system resources evaluation time and cpu usage
+		system_resources_instance.clone().save(&txn).await?;
+
+		txn.commit().await?;
 		self.system_resources_active_model = Some(system_resources_instance);
 
 		Ok(())
@@ -409,12 +365,15 @@ mod tests {
 			},
 			storage: vec![Storage {
 				name: String::from("Updated Storage"),
+				mount_point: String::from("/"),
 				total: 1_000_000_000,
 				used: 500_000_000,
 				is_removable: true,
 				kind: DiskKind::HDD,
 			}],
 			eval_time: Utc::now(),
+			// Same core counts and usage
+			..system_resources_controller.get_resources().unwrap()
 		};
 
 		// Get the ID of the inserted system resources
@@ -495,12 +454,15 @@ mod tests {
 			},
 			storage: vec![Storage {
 				name: String::from("Updated Storage"),
+				mount_point: String::from("/"),
 				total: 1_000_000_000,
 				used: 500_000_000,
 				is_removable: true,
 				kind: DiskKind::HDD,
 			}],
 			eval_time: Utc::now(),
+			// Same core counts and usage
+			..system_resources_controller.get_resources().unwrap()
 		};
 
 		// Call the update function
@@ -577,12 +539,15 @@ mod tests {
 			},
 			storage: vec![Storage {
 				name: String::from("Updated Storage"),
+				mount_point: String::from("/"),
 				total: 1_000_000_000,
 				used: 500_000_000,
 				is_removable: true,
 				kind: DiskKind::HDD,
 			}],
 			eval_time: Utc::now(),
+			// Same core counts and usage
+			..system_resources_controller.get_resources().unwrap()
 		};
 
 		// Call the update function
@@ -0,0 +1,213 @@
+use chrono::{DateTime, TimeZone, Utc};
+use entity::resource_sample::{
+	self, ActiveModel as ResourceSampleActiveModel, Entity as ResourceSampleEntity,
+	Model as ResourceSampleModel,
+};
+use sea_orm::{
+	ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+	TransactionTrait,
+};
+use std::error::Error;
+use std::time::Duration;
+
+use super::{bucket_start, downsample, Resolution, ResourcePoint, ResourceSeries, RetentionPolicy};
+use crate::server_node::resources::{to_f32, Resources};
+
+/// Resource history controller
+///
+/// Points are stored on the 'resource-sample' table
+pub struct ResourceHistoryController {
+	pub db: DatabaseConnection,
+}
+
+impl ResourceHistoryController {
+	pub fn new(db: DatabaseConnection) -> Self {
+		Self { db }
+	}
+
+	/// Store a sample of a server node
+	///
+	///
+	pub async fn record(&self, server_node_id: i64, resources: &Resources) -> Result<(), Box<dyn Error>> {
+		let point = ResourcePoint::from_resources(resources);
+		let active_model = active_model_from_point(server_node_id, Resolution::Raw, &point)?;
+
+		ResourceSampleEntity::insert(active_model).exec(&self.db).await?;
+
+		Ok(())
+	}
+
+	/// Stored points of a server node between two times
+	///
+	/// Points of every resolution, oldest first
+	pub async fn points(
+		&self,
+		server_node_id: i64,
+		from: DateTime<Utc>,
+		to: DateTime<Utc>,
+	) -> Result<Vec<ResourcePoint>, Box<dyn Error>> {
+		let models = ResourceSampleEntity::find()
+			.filter(resource_sample::Column::ServerNodeId.eq(server_node_id))
+			.filter(resource_sample::Column::Time.between(from.naive_utc(), to.naive_utc()))
+			.order_by_asc(resource_sample::Column::Time)
+			.all(&self.db)
+			.await?;
+
+		models.into_iter().map(point_from_model).collect()
+	}
+
+	/// Time series of a server node
+	///
+	/// With a step the points are downsampled to it, so long ranges can be charted
+	pub async fn series(
+		&self,
+		server_node_id: i64,
+		from: DateTime<Utc>,
+		to: DateTime<Utc>,
+		step: Option<Duration>,
+	) -> Result<ResourceSeries, Box<dyn Error>> {
+		let points = self.points(server_node_id, from, to).await?;
+		let points = match step {
+			Some(step) => downsample(&points, step),
+			None => points,
+		};
+
+		Ok(ResourceSeries {
+			server_node_id,
+			from,
+			to,
+			step: step.map(|step| step.as_secs()),
+			points,
+		})
+	}
+
+	/// Downsample and remove the expired points of a server node
+	///
+	/// Everything is done on a transaction, so points are never lost or counted twice
+	pub async fn compact(
+		&self,
+		server_node_id: i64,
+		policy: &RetentionPolicy,
+		now: DateTime<Utc>,
+	) -> Result<(), Box<dyn Error>> {
+		let txn = self.db.begin().await?;
+
+		for resolution in [Resolution::Raw, Resolution::Minute] {
+			let next = match resolution.next() {
+				Some(next) => next,
+				None => continue,
+			};
+			let step = next.step().ok_or("Points can't be downsampled to raw samples")?;
+
+			// Whole buckets only, the rest of the bucket is downsampled later
+			let cutoff = bucket_start(now - policy.retention(resolution), step).naive_utc();
+			let expired = ResourceSampleEntity::find()
+				.filter(resource_sample::Column::ServerNodeId.eq(server_node_id))
+				.filter(resource_sample::Column::Resolution.eq(resolution.as_str()))
+				.filter(resource_sample::Column::Time.lt(cutoff))
+				.order_by_asc(resource_sample::Column::Time)
+				.all(&txn)
+				.await?;
+			if expired.is_empty() {
+				continue;
+			}
+
+			let points = expired
+				.into_iter()
+				.map(point_from_model)
+				.collect::<Result<Vec<_>, _>>()?;
+			let active_models = downsample(&points, step)
+				.iter()
+				.map(|point| active_model_from_point(server_node_id, next, point))
+				.collect::<Result<Vec<_>, _>>()?;
+
+			ResourceSampleEntity::insert_many(active_models).exec(&txn).await?;
+			ResourceSampleEntity::delete_many()
+				.filter(resource_sample::Column::ServerNodeId.eq(server_node_id))
+				.filter(resource_sample::Column::Resolution.eq(resolution.as_str()))
+				.filter(resource_sample::Column::Time.lt(cutoff))
+				.exec(&txn)
+				.await?;
+		}
+
+		// The coarsest points are removed
+		let cutoff = (now - policy.retention(Resolution::Hour)).naive_utc();
+		ResourceSampleEntity::delete_many()
+			.filter(resource_sample::Column::ServerNodeId.eq(server_node_id))
+			.filter(resource_sample::Column::Resolution.eq(Resolution::Hour.as_str()))
+			.filter(resource_sample::Column::Time.lt(cutoff))
+			.exec(&txn)
+			.await?;
+
+		txn.commit().await?;
+
+		Ok(())
+	}
+}
+
+/// Create an active model from a point
+///
+///
+pub fn active_model_from_point(
+	server_node_id: i64,
+	resolution: Resolution,
+	point: &ResourcePoint,
+) -> Result<ResourceSampleActiveModel, Box<dyn Error>> {
+	Ok(ResourceSampleActiveModel {
+		server_node_id: ActiveValue::Set(server_node_id),
+		resolution: ActiveValue::Set(resolution.to_string()),
+		time: ActiveValue::Set(point.time.naive_utc()),
+		samples: ActiveValue::Set(i32::try_from(point.samples)?),
+		cpu_usage: ActiveValue::Set(to_f32(point.cpu_usage)?),
+		cpu_usage_max: ActiveValue::Set(to_f32(point.cpu_usage_max)?),
+		memory_used: ActiveValue::Set(i64::try_from(point.memory_used)?),
+		memory_used_max: ActiveValue::Set(i64::try_from(point.memory_used_max)?),
+		memory_total: ActiveValue::Set(i64::try_from(point.memory_total)?),
+		storage_used: ActiveValue::Set(i64::try_from(point.storage_used)?),
+		storage_used_max: ActiveValue::Set(i64::try_from(point.storage_used_max)?),
+		storage_total: ActiveValue::Set(i64::try_from(point.storage_total)?),
+		..Default::default()
+	})
+}
+
+/// Create a point from a model
+///
+///
+pub fn point_from_model(model: ResourceSampleModel) -> Result<ResourcePoint, Box<dyn Error>> {
+	Ok(ResourcePoint {
+		time: Utc.from_utc_datetime(&model.time),
+		samples: u32::try_from(model.samples)?,
+		cpu_usage: model.cpu_usage as f64,
+		cpu_usage_max: model.cpu_usage_max as f64,
+		memory_used: u64::try_from(model.memory_used)?,
+		memory_used_max: u64::try_from(model.memory_used_max)?,
+		memory_total: u64::try_from(model.memory_total)?,
+		storage_used: u64::try_from(model.storage_used)?,
+		storage_used_max: u64::try_from(model.storage_used_max)?,
+		storage_total: u64::try_from(model.storage_total)?,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sea_orm::TryIntoModel;
+
+	#[test]
+	fn test_point_round_trip() {
+		let resources = Resources::fetch_resources().unwrap();
+		let point = ResourcePoint::from_resources(&resources);
+
+		let mut active_model = active_model_from_point(7, Resolution::Minute, &point).unwrap();
+		active_model.id = ActiveValue::Set(1);
+		let model = active_model.try_into_model().unwrap();
+		assert_eq!(model.server_node_id, 7);
+		assert_eq!(model.resolution, "minute");
+
+		let stored = point_from_model(model).unwrap();
+		assert_eq!(stored.time, point.time);
+		assert_eq!(stored.memory_used, point.memory_used);
+		assert_eq!(stored.storage_total, point.storage_total);
+		assert!((stored.cpu_usage - point.cpu_usage).abs() < 0.001);
+	}
+}
@@ -0,0 +1,280 @@
+//! Resource history
+//!
+//! Every sample of a node is stored as a point of a time series. Raw samples are only kept for a
+//! few hours, then they are downsampled to 1-minute points and later to 1-hour points, which keep
+//! the average and the maximum of the samples they replace.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub mod controller;
+
+use super::Resources;
+
+/// Default hours raw samples are kept
+pub const DEFAULT_RAW_RETENTION: u64 = 24;
+
+/// Time 1-minute points are kept
+pub const MINUTE_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Time 1-hour points are kept
+pub const HOUR_RETENTION: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// Time between compactions of the history
+pub const COMPACTION_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Resolution of a point
+///
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+	Raw,
+	Minute,
+	Hour,
+}
+
+impl Resolution {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Resolution::Raw => "raw",
+			Resolution::Minute => "minute",
+			Resolution::Hour => "hour",
+		}
+	}
+
+	/// Time a point covers, raw samples are instants
+	///
+	///
+	pub fn step(&self) -> Option<Duration> {
+		match self {
+			Resolution::Raw => None,
+			Resolution::Minute => Some(Duration::from_secs(60)),
+			Resolution::Hour => Some(Duration::from_secs(60 * 60)),
+		}
+	}
+
+	/// Resolution the points are downsampled to once they expire
+	///
+	/// Hour points are removed instead
+	pub fn next(&self) -> Option<Resolution> {
+		match self {
+			Resolution::Raw => Some(Resolution::Minute),
+			Resolution::Minute => Some(Resolution::Hour),
+			Resolution::Hour => None,
+		}
+	}
+}
+
+impl fmt::Display for Resolution {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
+
+impl FromStr for Resolution {
+	type Err = String;
+
+	fn from_str(value: &str) -> Result<Self, Self::Err> {
+		match value {
+			"raw" => Ok(Resolution::Raw),
+			"minute" => Ok(Resolution::Minute),
+			"hour" => Ok(Resolution::Hour),
+			_ => Err(format!("Unknown resolution '{value}'")),
+		}
+	}
+}
+
+/// How long the points of each resolution are kept
+///
+///
+#[derive(Clone, Copy, Debug)]
+pub struct RetentionPolicy {
+	pub raw: Duration,
+	pub minute: Duration,
+	pub hour: Duration,
+}
+
+impl RetentionPolicy {
+	pub fn retention(&self, resolution: Resolution) -> Duration {
+		match resolution {
+			Resolution::Raw => self.raw,
+			Resolution::Minute => self.minute,
+			Resolution::Hour => self.hour,
+		}
+	}
+}
+
+impl Default for RetentionPolicy {
+	fn default() -> Self {
+		Self {
+			raw: Duration::from_secs(DEFAULT_RAW_RETENTION * 60 * 60),
+			minute: MINUTE_RETENTION,
+			hour: HOUR_RETENTION,
+		}
+	}
+}
+
+/// Point of the resource history
+///
+/// Cpu usage is the one of every core together, memory and storage are in bytes
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ResourcePoint {
+	pub time: DateTime<Utc>,
+	// Raw samples this point summarizes
+	pub samples: u32,
+	pub cpu_usage: f64,
+	pub cpu_usage_max: f64,
+	pub memory_used: u64,
+	pub memory_used_max: u64,
+	pub memory_total: u64,
+	pub storage_used: u64,
+	pub storage_used_max: u64,
+	pub storage_total: u64,
+}
+
+impl ResourcePoint {
+	/// Create a raw point from a sample
+	///
+	///
+	pub fn from_resources(resources: &Resources) -> Self {
+		let cpu_usage = resources.cpu_usage;
+		let storage_used = resources.storage.iter().map(|storage| storage.used).sum();
+
+		Self {
+			time: resources.eval_time,
+			samples: 1,
+			cpu_usage,
+			cpu_usage_max: cpu_usage,
+			memory_used: resources.memory.used,
+			memory_used_max: resources.memory.used,
+			memory_total: resources.memory.total,
+			storage_used,
+			storage_used_max: storage_used,
+			storage_total: resources.storage.iter().map(|storage| storage.total).sum(),
+		}
+	}
+}
+
+/// Time series of a node
+///
+/// The body of '/api/server-node/{id}/resources'
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ResourceSeries {
+	pub server_node_id: i64,
+	pub from: DateTime<Utc>,
+	pub to: DateTime<Utc>,
+	// Seconds between points, without it the stored points are returned
+	pub step: Option<u64>,
+	pub points: Vec<ResourcePoint>,
+}
+
+/// Start of the bucket of the given step a time falls in
+///
+/// Buckets are aligned to the unix epoch
+pub fn bucket_start(time: DateTime<Utc>, step: Duration) -> DateTime<Utc> {
+	let step = step.as_secs().max(1) as i64;
+	let start = time.timestamp().div_euclid(step) * step;
+
+	DateTime::from_timestamp(start, 0).unwrap_or(time)
+}
+
+/// Downsample points to one point per bucket of the given step
+///
+/// Averages are weighted by the samples of each point, maxima are kept and totals are the latest
+/// ones. The points have to be sorted by time.
+pub fn downsample(points: &[ResourcePoint], step: Duration) -> Vec<ResourcePoint> {
+	let mut downsampled: Vec<ResourcePoint> = Vec::new();
+
+	for point in points {
+		let time = bucket_start(point.time, step);
+
+		match downsampled.last_mut() {
+			Some(bucket) if bucket.time == time => {
+				let (bucket_samples, point_samples) = (bucket.samples as f64, point.samples as f64);
+				let weight = |bucket_value: f64, point_value: f64| {
+					(bucket_value * bucket_samples + point_value * point_samples) / (bucket_samples + point_samples)
+				};
+
+				bucket.cpu_usage = weight(bucket.cpu_usage, point.cpu_usage);
+				bucket.memory_used = weight(bucket.memory_used as f64, point.memory_used as f64).round() as u64;
+				bucket.storage_used = weight(bucket.storage_used as f64, point.storage_used as f64).round() as u64;
+				bucket.cpu_usage_max = bucket.cpu_usage_max.max(point.cpu_usage_max);
+				bucket.memory_used_max = bucket.memory_used_max.max(point.memory_used_max);
+				bucket.storage_used_max = bucket.storage_used_max.max(point.storage_used_max);
+				bucket.memory_total = point.memory_total;
+				bucket.storage_total = point.storage_total;
+				bucket.samples += point.samples;
+			}
+			_ => downsampled.push(ResourcePoint {
+				time,
+				..point.clone()
+			}),
+		}
+	}
+
+	downsampled
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn point(seconds: i64, cpu_usage: f64, memory_used: u64) -> ResourcePoint {
+		ResourcePoint {
+			time: DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap(),
+			samples: 1,
+			cpu_usage,
+			cpu_usage_max: cpu_usage,
+			memory_used,
+			memory_used_max: memory_used,
+			memory_total: 1000,
+			storage_used: 10,
+			storage_used_max: 10,
+			storage_total: 100,
+		}
+	}
+
+	#[test]
+	fn test_downsample() {
+		// 1_700_000_000 is 20 seconds past a minute
+		let points = vec![
+			point(0, 10.0, 100),
+			point(15, 30.0, 300),
+			point(30, 50.0, 200),
+			point(45, 40.0, 400),
+		];
+
+		let minutes = downsample(&points, Duration::from_secs(60));
+		assert_eq!(minutes.len(), 2);
+		assert_eq!(minutes[0].time.timestamp(), 1_699_999_980);
+		assert_eq!(minutes[0].samples, 3);
+		assert!((minutes[0].cpu_usage - 30.0).abs() < 0.001);
+		assert_eq!(minutes[0].cpu_usage_max, 50.0);
+		assert_eq!(minutes[0].memory_used, 200);
+		assert_eq!(minutes[0].memory_used_max, 300);
+		assert_eq!(minutes[1].samples, 1);
+
+		// Averages of averages are weighted by their samples
+		let hours = downsample(&minutes, Duration::from_secs(60 * 60));
+		assert_eq!(hours.len(), 1);
+		assert_eq!(hours[0].samples, 4);
+		assert!((hours[0].cpu_usage - 32.5).abs() < 0.001);
+		assert_eq!(hours[0].memory_used, 250);
+		assert_eq!(hours[0].memory_used_max, 400);
+	}
+
+	#[test]
+	fn test_resolution() {
+		for resolution in [Resolution::Raw, Resolution::Minute, Resolution::Hour] {
+			assert_eq!(resolution.to_string().parse::<Resolution>().unwrap(), resolution);
+		}
+		assert!("second".parse::<Resolution>().is_err());
+
+		// Each resolution is coarser than the previous one
+		assert_eq!(Resolution::Raw.next().and_then(|next| next.step()), Some(Duration::from_secs(60)));
+		assert_eq!(Resolution::Hour.next(), None);
+	}
+}
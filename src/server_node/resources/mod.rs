@@ -1,5 +1,8 @@
 use chrono::{offset::LocalResult, DateTime, TimeZone, Utc};
 use entity::{
+	network_interface::{
+		ActiveModel as NetworkInterfaceActiveModel, Model as NetworkInterfaceModel,
+	},
 	storage_device::{
 		ActiveModel as StorageDevice,
 		Model as StorageDeviceModel,
@@ -17,15 +20,22 @@ use sea_orm::{
 };
 use serde::{Deserialize, Serialize};
 use std::error::Error;
-use sysinfo::{Disks, System};
+use std::time::Duration;
+use sysinfo::{Disks, Networks, System};
 
 pub mod controller;
+pub mod history;
+pub mod network;
+pub mod requirements;
+pub mod sampler;
 pub mod storage;
 pub mod system_core;
 pub mod system_memory;
 
 use crate::model::FromActiveModel;
 
+use network::NetworkInterface;
+use sampler::ResourceSampler;
 use storage::Storage;
 use system_core::CpuCore as Cpu;
 use system_memory::Memory;
@@ -42,30 +52,49 @@ pub fn to_f32(x: f64) -> Result<f32, Box<dyn Error>> {
 	}
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Resources {
 	// pub id: Option<i64>,
 	pub cpus: Vec<Cpu>,
+	// Unknown on some systems
+	#[serde(default)]
+	pub physical_cores: Option<u32>,
+	#[serde(default)]
+	pub logical_cores: u32,
+	// Usage of every core together, from 0 to 100
+	#[serde(default)]
+	pub cpu_usage: f64,
 	pub memory: Memory,
 	pub storage: Vec<Storage>,
+	#[serde(default)]
+	pub network_interfaces: Vec<NetworkInterface>,
 	pub eval_time: DateTime<Utc>,
 }
 
 impl Resources {
 	/// Fetch system resources and create a new Resources instance
 	///
-	///
+	/// Cpu usage needs two refreshes, so this blocks for a moment. Long running code should read
+	/// the snapshots of a [`sampler::ResourceSampler`] instead.
 	pub fn fetch_resources() -> Result<Resources, Box<dyn Error>> {
-		let sys = System::new_all();
+		ResourceSampler::new().sample()
+	}
 
+	/// Create from a refreshed system
+	///
+	/// Elapsed is the time since the previous refresh, network rates are computed with it
+	pub fn from_system(
+		sys: &System,
+		disks: &Disks,
+		networks: &Networks,
+		elapsed: Duration,
+	) -> Result<Resources, Box<dyn Error>> {
 		let cpus = sys
 			.cpus()
-			.into_iter()
-			.map(|cpu| Cpu {
-				usage_percentage: cpu.cpu_usage() as f64,
-				free_percentage: (1.0 - cpu.cpu_usage() as f64) * 100.0,
-			})
-			.collect();
+			.iter()
+			.zip(0..)
+			.map(|(cpu, index)| Cpu::new(index, cpu))
+			.collect::<Vec<_>>();
 
 		let memory = Memory {
 			total: sys.total_memory(),
@@ -73,29 +102,29 @@ impl Resources {
 		};
 
 		// Storage
-		let disks = Disks::new_with_refreshed_list();
 		let mut storages = Vec::new();
 
 		for disk in disks.list() {
 			let storage = Storage::new(disk)?;
 
-			// I've noticed that in the database a disk with the same name may be inserted twice
-			// However this shouldn't be possible, because the name acts like an id and if they are the same, then
-			// it's the same disk, so we need to check the id to not insert it twice.
-
-			// Check if a disk with the same ID already exists in the storages vector
+			// Disk names like 'tmpfs' repeat, the mount point is what identifies a disk, a disk
+			// mounted twice on the same place is only listed once
 			if !storages
 				.iter()
-				.any(|existing_storage: &Storage| existing_storage.name == storage.name)
+				.any(|existing_storage: &Storage| existing_storage.mount_point == storage.mount_point)
 			{
 				storages.push(storage);
 			}
 		}
 
 		Ok(Resources {
+			physical_cores: sys.physical_core_count().map(u32::try_from).transpose()?,
+			logical_cores: u32::try_from(cpus.len())?,
+			cpu_usage: (sys.global_cpu_usage() as f64).clamp(0.0, 100.0),
 			cpus,
 			memory,
 			storage: storages,
+			network_interfaces: NetworkInterface::from_networks(networks, elapsed),
 			eval_time: Utc::now(),
 		})
 	}
@@ -112,13 +141,16 @@ impl Resources {
 	/// Convert resources into active model
 	///
 	///
-	pub fn into_active_model(&self) -> SystemResourcesActiveModel {
+	pub fn into_active_model(&self) -> Result<SystemResourcesActiveModel, Box<dyn Error>> {
 		let system_resources = SystemResourcesActiveModel {
 			eval_time: ActiveValue::Set(self.eval_time.naive_utc()),
+			physical_cores: ActiveValue::Set(self.physical_cores.map(i32::try_from).transpose()?),
+			logical_cores: ActiveValue::Set(i32::try_from(self.logical_cores)?),
+			cpu_usage: ActiveValue::Set(to_f32(self.cpu_usage)?),
 			..Default::default()
 		};
 
-		system_resources
+		Ok(system_resources)
 	}
 
 	/// Create from models
@@ -129,6 +161,7 @@ impl Resources {
 		cpus: Vec<SystemCoreModel>,
 		memory: SystemMemoryModel,
 		storage_device_model: Vec<StorageDeviceModel>,
+		network_interface_model: Vec<NetworkInterfaceModel>,
 	) -> Result<Self, Box<dyn Error>> {
 		let model = model.into_active_model();
 
@@ -147,7 +180,13 @@ impl Resources {
 			storages.push(storage.into_active_model());
 		}
 		
-		let model = Self::from_active_model(model, cpu_active_models, memory, storages)?;
+		// Network interfaces
+		let network_interfaces = network_interface_model
+			.into_iter()
+			.map(|network_interface| network_interface.into_active_model())
+			.collect();
+		
+		let model = Self::from_active_model(model, cpu_active_models, memory, storages, network_interfaces)?;
 		
 		Ok(model)
 	}
@@ -160,6 +199,7 @@ impl Resources {
 		cpus_active_model: Vec<SystemCoreActiveModel>,
 		memory: SystemMemoryActiveModel,
 		storage_active_model: Vec<StorageDevice>,
+		network_interface_active_model: Vec<NetworkInterfaceActiveModel>,
 	) -> Result<Self, Box<dyn Error>> {
 		// Get evaluation time
 		let eval_time: DateTime<Utc> = match active_model.eval_time.clone().take() {
@@ -182,6 +222,21 @@ impl Resources {
 		for cpu in cpus_active_model {
 			cpus.push(Cpu::from_active_model(cpu)?);
 		}
+		cpus.sort_by_key(|cpu| cpu.index);
+		
+		// Core counts and usage
+		let physical_cores = match active_model.physical_cores.clone().take() {
+			Some(physical_cores) => physical_cores.map(u32::try_from).transpose()?,
+			None => return Err("physical_cores is missing".into()),
+		};
+		let logical_cores = match active_model.logical_cores.clone().take() {
+			Some(logical_cores) => u32::try_from(logical_cores)?,
+			None => return Err("logical_cores is missing".into()),
+		};
+		let cpu_usage = match active_model.cpu_usage.clone().take() {
+			Some(cpu_usage) => cpu_usage as f64,
+			None => return Err("cpu_usage is missing".into()),
+		};
 		
 		// Memory
 		let memory = Memory::from_active_model(memory)?;
@@ -192,10 +247,20 @@ impl Resources {
 			storages.push(Storage::from_active_model(storage)?);
 		}
 		
+		// Network interfaces
+		let mut network_interfaces: Vec<NetworkInterface> = vec![];
+		for network_interface in network_interface_active_model {
+			network_interfaces.push(NetworkInterface::from_active_model(network_interface)?);
+		}
+		
 		Ok(Resources {
 			cpus,
+			physical_cores,
+			logical_cores,
+			cpu_usage,
 			memory,
 			storage: storages,
+			network_interfaces,
 			eval_time,
 		})
 	}
@@ -0,0 +1,34 @@
+//! Network interface controller
+//!
+//!
+use entity::network_interface::{
+	self, Entity as NetworkInterfaceEntity, Model as NetworkInterfaceModel,
+};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use std::error::Error;
+
+pub struct NetworkInterfaceController {
+	pub db: DatabaseConnection,
+}
+
+impl NetworkInterfaceController {
+	pub fn new(db: DatabaseConnection) -> Self {
+		Self { db }
+	}
+
+	/// Fetch the network interfaces of the system resources
+	///
+	///
+	pub async fn find_by_resources_id(
+		&self,
+		resource_id: i64,
+	) -> Result<Vec<NetworkInterfaceModel>, Box<dyn Error>> {
+		let interfaces = NetworkInterfaceEntity::find()
+			.filter(network_interface::Column::SystemResourceId.eq(resource_id))
+			.order_by_asc(network_interface::Column::Name)
+			.all(&self.db)
+			.await?;
+
+		Ok(interfaces)
+	}
+}
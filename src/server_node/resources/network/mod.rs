@@ -0,0 +1,198 @@
+//! Network interfaces
+//!
+//! Totals are counted since the interface went up, rates are computed from the bytes moved
+//! between the last two samples.
+use entity::network_interface::{
+	ActiveModel as NetworkInterfaceActiveModel, Column as NetworkInterfaceColumn,
+	Entity as NetworkInterfaceEntity, Model as NetworkInterfaceModel,
+};
+use sea_orm::{ActiveValue, TryIntoModel};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::Duration;
+use sysinfo::{NetworkData, Networks};
+
+pub mod controller;
+
+use crate::model::{reconcile::ChildRecord, FromActiveModel};
+
+/// Network interface
+///
+///
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NetworkInterface {
+	pub name: String,
+	pub mac_address: String,
+	pub ip_addresses: Vec<String>,
+	pub bytes_received: u64,
+	pub bytes_transmitted: u64,
+	pub packets_received: u64,
+	pub packets_transmitted: u64,
+	// Bytes per second since the previous sample
+	pub receive_rate: f64,
+	pub transmit_rate: f64,
+}
+
+impl NetworkInterface {
+	/// Create from refreshed network data
+	///
+	/// Elapsed is the time since the previous refresh
+	pub fn new(name: &str, data: &NetworkData, elapsed: Duration) -> Self {
+		Self {
+			name: name.to_string(),
+			mac_address: data.mac_address().to_string(),
+			ip_addresses: data
+				.ip_networks()
+				.iter()
+				.map(|network| network.addr.to_string())
+				.collect(),
+			bytes_received: data.total_received(),
+			bytes_transmitted: data.total_transmitted(),
+			packets_received: data.total_packets_received(),
+			packets_transmitted: data.total_packets_transmitted(),
+			receive_rate: rate(data.received(), elapsed),
+			transmit_rate: rate(data.transmitted(), elapsed),
+		}
+	}
+
+	/// Every interface of the system, sorted by name
+	///
+	///
+	pub fn from_networks(networks: &Networks, elapsed: Duration) -> Vec<Self> {
+		let mut interfaces = networks
+			.iter()
+			.map(|(name, data)| Self::new(name, data, elapsed))
+			.collect::<Vec<_>>();
+		interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+
+		interfaces
+	}
+
+	/// Convert into active model
+	///
+	/// Note that the id is autogenerated
+	pub fn try_into_active_model(
+		&self,
+		system_resources_id: i64,
+	) -> Result<NetworkInterfaceActiveModel, Box<dyn Error>> {
+		Ok(NetworkInterfaceActiveModel {
+			name: ActiveValue::Set(self.name.clone()),
+			mac_address: ActiveValue::Set(self.mac_address.clone()),
+			ip_addresses: ActiveValue::Set(self.ip_addresses.join(",")),
+			bytes_received: ActiveValue::Set(i64::try_from(self.bytes_received)?),
+			bytes_transmitted: ActiveValue::Set(i64::try_from(self.bytes_transmitted)?),
+			packets_received: ActiveValue::Set(i64::try_from(self.packets_received)?),
+			packets_transmitted: ActiveValue::Set(i64::try_from(self.packets_transmitted)?),
+			receive_rate: ActiveValue::Set(self.receive_rate),
+			transmit_rate: ActiveValue::Set(self.transmit_rate),
+			system_resource_id: ActiveValue::Set(Some(system_resources_id)),
+			..Default::default()
+		})
+	}
+}
+
+/// Bytes per second
+///
+///
+fn rate(bytes: u64, elapsed: Duration) -> f64 {
+	match elapsed.as_secs_f64() {
+		seconds if seconds > 0.0 => bytes as f64 / seconds,
+		_ => 0.0,
+	}
+}
+
+impl ChildRecord for NetworkInterface {
+	type Entity = NetworkInterfaceEntity;
+	type ActiveModel = NetworkInterfaceActiveModel;
+	// Interface names are unique
+	type Key = String;
+
+	fn parent_column() -> NetworkInterfaceColumn {
+		NetworkInterfaceColumn::SystemResourceId
+	}
+
+	fn id_column() -> NetworkInterfaceColumn {
+		NetworkInterfaceColumn::Id
+	}
+
+	fn row_id(row: &NetworkInterfaceModel) -> i64 {
+		row.id
+	}
+
+	fn row_key(row: &NetworkInterfaceModel, _position: usize) -> String {
+		row.name.clone()
+	}
+
+	fn key(&self, _position: usize) -> String {
+		self.name.clone()
+	}
+
+	fn active_model(&self, parent_id: i64, id: Option<i64>) -> Result<NetworkInterfaceActiveModel, Box<dyn Error>> {
+		let mut active_model = self.try_into_active_model(parent_id)?;
+		if let Some(id) = id {
+			active_model.id = ActiveValue::Unchanged(id);
+		}
+
+		Ok(active_model)
+	}
+}
+
+impl FromActiveModel<NetworkInterfaceActiveModel, Self> for NetworkInterface {
+	fn from_active_model(active_model: NetworkInterfaceActiveModel) -> Result<Self, Box<dyn Error>> {
+		let model = active_model.try_into_model()?;
+
+		Ok(Self {
+			name: model.name,
+			mac_address: model.mac_address,
+			ip_addresses: model
+				.ip_addresses
+				.split(',')
+				.filter(|address| !address.is_empty())
+				.map(str::to_string)
+				.collect(),
+			bytes_received: u64::try_from(model.bytes_received)?,
+			bytes_transmitted: u64::try_from(model.bytes_transmitted)?,
+			packets_received: u64::try_from(model.packets_received)?,
+			packets_transmitted: u64::try_from(model.packets_transmitted)?,
+			receive_rate: model.receive_rate,
+			transmit_rate: model.transmit_rate,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_rate() {
+		assert_eq!(rate(3000, Duration::from_secs(2)), 1500.0);
+		assert_eq!(rate(3000, Duration::ZERO), 0.0);
+	}
+
+	#[test]
+	fn test_active_model_round_trip() {
+		let interface = NetworkInterface {
+			name: "eth0".to_string(),
+			mac_address: "00:11:22:33:44:55".to_string(),
+			ip_addresses: vec!["192.168.1.10".to_string(), "fe80::1".to_string()],
+			bytes_received: 1_000_000,
+			bytes_transmitted: 500_000,
+			packets_received: 1000,
+			packets_transmitted: 800,
+			receive_rate: 1250.5,
+			transmit_rate: 320.0,
+		};
+
+		let mut active_model = interface.try_into_active_model(1).unwrap();
+		active_model.id = ActiveValue::Set(1);
+		let stored = NetworkInterface::from_active_model(active_model).unwrap();
+
+		assert_eq!(stored.name, interface.name);
+		assert_eq!(stored.mac_address, interface.mac_address);
+		assert_eq!(stored.ip_addresses, interface.ip_addresses);
+		assert_eq!(stored.bytes_received, interface.bytes_received);
+		assert_eq!(stored.packets_transmitted, interface.packets_transmitted);
+		assert_eq!(stored.receive_rate, interface.receive_rate);
+	}
+}
@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+use super::Resources;
+
+/// Resource requirements
+///
+/// What a node needs to have free to run something, missing values aren't checked
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ResourceRequirements {
+	pub cpu_cores: Option<u32>,
+	// Free memory in bytes
+	pub memory: Option<u64>,
+	// Free space in bytes, on a single disk
+	pub storage: Option<u64>,
+}
+
+impl ResourceRequirements {
+	/// Whether there's nothing to check
+	///
+	///
+	pub fn is_empty(&self) -> bool {
+		self.cpu_cores.is_none() && self.memory.is_none() && self.storage.is_none()
+	}
+	
+	/// Check the requirements against the resources of a node
+	///
+	/// The error tells the first requirement that isn't met
+	pub fn check(&self, resources: &Resources) -> Result<(), String> {
+		if let Some(cpu_cores) = self.cpu_cores {
+			if resources.total_cores() < cpu_cores {
+				return Err(format!(
+					"{cpu_cores} cpu cores are required, the node has {}",
+					resources.total_cores()
+				));
+			}
+		}
+		
+		if let Some(memory) = self.memory {
+			let free = resources.memory.total.saturating_sub(resources.memory.used);
+			if free < memory {
+				return Err(format!("{memory} bytes of memory are required, {free} are free"));
+			}
+		}
+		
+		if let Some(storage) = self.storage {
+			let free = resources.storage
+				.iter()
+				.map(|storage| storage.available_space())
+				.max()
+				.unwrap_or_default();
+			if free < storage {
+				return Err(format!("{storage} bytes of storage are required, {free} are free"));
+			}
+		}
+		
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::Utc;
+	
+	use crate::server_node::resources::storage::{DiskKind, Storage};
+	use crate::server_node::resources::system_core::CpuCore;
+	use crate::server_node::resources::system_memory::Memory;
+	
+	#[test]
+	fn test_check() {
+		let resources = Resources {
+			cpus: vec![CpuCore { usage_percentage: 10.0, free_percentage: 90.0, ..Default::default() }; 4],
+			physical_cores: Some(2),
+			logical_cores: 4,
+			cpu_usage: 10.0,
+			memory: Memory { total: 1000, used: 400 },
+			storage: vec![Storage {
+				total: 500,
+				used: 100,
+				kind: DiskKind::SSD,
+				name: "disk".to_string(),
+				mount_point: "/".to_string(),
+				is_removable: false,
+			}],
+			network_interfaces: Vec::new(),
+			eval_time: Utc::now(),
+		};
+		
+		assert!(ResourceRequirements::default().check(&resources).is_ok());
+		
+		let requirements = ResourceRequirements {
+			cpu_cores: Some(4),
+			memory: Some(600),
+			storage: Some(400),
+		};
+		assert!(requirements.check(&resources).is_ok());
+		
+		let requirements = ResourceRequirements {
+			cpu_cores: Some(8),
+			..Default::default()
+		};
+		assert!(requirements.check(&resources).is_err());
+		
+		let requirements = ResourceRequirements {
+			memory: Some(601),
+			..Default::default()
+		};
+		assert!(requirements.check(&resources).is_err());
+		
+		let requirements = ResourceRequirements {
+			storage: Some(401),
+			..Default::default()
+		};
+		assert!(requirements.check(&resources).is_err());
+	}
+}
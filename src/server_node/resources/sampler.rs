@@ -0,0 +1,188 @@
+//! Resource sampler
+//!
+//! Sysinfo computes the CPU usage as the difference between two refreshes, so a system that was
+//! just created reports no usage at all. The sampler keeps the same system alive and refreshes it
+//! on an interval, and the latest snapshot is shared with everyone who needs it through a watch
+//! channel.
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+use sysinfo::{
+	CpuRefreshKind, Disks, MemoryRefreshKind, Networks, RefreshKind, System,
+	MINIMUM_CPU_UPDATE_INTERVAL,
+};
+use tokio::sync::watch;
+
+use super::Resources;
+
+/// Default time between samples, in seconds
+pub const DEFAULT_SAMPLE_INTERVAL: u64 = 15;
+
+/// Resource sampler
+///
+///
+pub struct ResourceSampler {
+	system: System,
+	disks: Disks,
+	networks: Networks,
+	// Cpu usage can't be computed again before the minimum interval
+	last_refresh: Instant,
+}
+
+impl ResourceSampler {
+	pub fn new() -> Self {
+		let system = System::new_with_specifics(
+			RefreshKind::new()
+				.with_cpu(cpu_refresh_kind())
+				.with_memory(MemoryRefreshKind::new().with_ram()),
+		);
+
+		Self {
+			system,
+			disks: Disks::new_with_refreshed_list(),
+			networks: Networks::new_with_refreshed_list(),
+			last_refresh: Instant::now(),
+		}
+	}
+
+	/// Take a snapshot of the resources
+	///
+	/// Blocks until the minimum cpu update interval has passed since the last refresh
+	pub fn sample(&mut self) -> Result<Resources, Box<dyn Error>> {
+		let elapsed = self.last_refresh.elapsed();
+		if elapsed < MINIMUM_CPU_UPDATE_INTERVAL {
+			thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL - elapsed);
+		}
+
+		self.system.refresh_cpu_specifics(cpu_refresh_kind());
+		self.system.refresh_memory();
+		// Disks and network interfaces may have been added or removed since the last sample
+		self.disks.refresh_list();
+		self.networks.refresh_list();
+		let elapsed = self.last_refresh.elapsed();
+		self.last_refresh = Instant::now();
+
+		Resources::from_system(&self.system, &self.disks, &self.networks, elapsed)
+	}
+
+	/// Sample the resources on the background
+	///
+	/// The sampler runs on its own thread, so it doesn't need a runtime and never blocks one.
+	/// It stops once every watch is dropped.
+	pub fn spawn(interval: Duration) -> Result<ResourceWatch, Box<dyn Error>> {
+		let (sender, receiver) = watch::channel(None);
+
+		thread::Builder::new()
+			.name("resource-sampler".to_string())
+			.spawn(move || {
+				let mut sampler = Self::new();
+
+				while !sender.is_closed() {
+					match sampler.sample() {
+						Ok(resources) => {
+							sender.send_replace(Some(resources));
+						}
+						Err(err) => tracing::warn!("Couldn't sample resources: {err}"),
+					}
+
+					thread::sleep(interval);
+				}
+			})?;
+
+		Ok(ResourceWatch { receiver })
+	}
+}
+
+/// Cpu usage and frequency, the rest of the cpu doesn't change
+///
+///
+fn cpu_refresh_kind() -> CpuRefreshKind {
+	CpuRefreshKind::new().with_cpu_usage().with_frequency()
+}
+
+impl Default for ResourceSampler {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Latest resources of a background sampler
+///
+///
+#[derive(Clone, Debug)]
+pub struct ResourceWatch {
+	receiver: watch::Receiver<Option<Resources>>,
+}
+
+impl ResourceWatch {
+	/// Latest snapshot, none before the first sample
+	///
+	///
+	pub fn current(&self) -> Option<Resources> {
+		self.receiver.borrow().clone()
+	}
+
+	/// Latest snapshot
+	///
+	/// Waits for the first sample
+	pub async fn latest(&self) -> Result<Resources, String> {
+		let mut receiver = self.receiver.clone();
+		let resources = receiver
+			.wait_for(Option::is_some)
+			.await
+			.map_err(|_| "The resource sampler stopped".to_string())?;
+
+		resources.clone().ok_or_else(|| "No resources sampled".to_string())
+	}
+
+	/// Wait for the next snapshot
+	///
+	/// None once the sampler stopped
+	pub async fn changed(&mut self) -> Option<Resources> {
+		self.receiver.changed().await.ok()?;
+
+		self.receiver.borrow_and_update().clone()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_cpu_usage() {
+		let mut sampler = ResourceSampler::new();
+
+		for _ in 0..2 {
+			let resources = sampler.sample().unwrap();
+			assert!(!resources.cpus.is_empty());
+			for cpu in &resources.cpus {
+				assert!((0.0..=100.0).contains(&cpu.usage_percentage));
+				assert!((cpu.usage_percentage + cpu.free_percentage - 100.0).abs() < 0.001);
+			}
+			assert!((0.0..=100.0).contains(&resources.cpu_usage));
+			assert_eq!(resources.logical_cores as usize, resources.cpus.len());
+			assert!(resources.physical_cores.unwrap_or(0) <= resources.logical_cores);
+			// Cores keep the order of the system
+			assert!(resources.cpus.iter().zip(0..).all(|(cpu, index)| cpu.index == index));
+			for interface in &resources.network_interfaces {
+				assert!(interface.receive_rate >= 0.0 && interface.transmit_rate >= 0.0);
+			}
+		}
+	}
+
+	#[tokio::test]
+	async fn test_spawn() {
+		let watch = ResourceSampler::spawn(Duration::from_millis(100)).unwrap();
+
+		let resources = watch.latest().await.unwrap();
+		assert!(resources.memory.total > 0);
+		assert!(watch.current().is_some());
+
+		// Later samples are published too
+		let mut updates = watch.clone();
+		let first = updates.changed().await.unwrap();
+		let next = updates.changed().await.unwrap();
+		assert!(next.eval_time > first.eval_time);
+	}
+}
@@ -1,7 +1,10 @@
 //! Storage model
 //! 
 //! TODO: Move down to resources folder
-use entity::storage_device::ActiveModel as StorageDeviceActiveModel;
+use entity::storage_device::{
+	ActiveModel as StorageDeviceActiveModel, Column as StorageDeviceColumn,
+	Entity as StorageDeviceEntity, Model as StorageDeviceModel,
+};
 use serde::{Deserialize, Serialize};
 use sysinfo::{
     Disk, DiskKind as SysDiskKind,
@@ -11,25 +14,27 @@ use std::error::Error;
 
 pub mod controller;
 
-use crate::model::FromActiveModel;
+use crate::model::{reconcile::ChildRecord, FromActiveModel};
 
 /// Disk kind
 /// 
 /// Sysinfo already has DiskKind however it's not serializable / deserializable
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum DiskKind {
     HDD,
     SSD,
     Unknown,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Storage {
     // In bytes
     pub total: u64,
     pub used: u64,
     pub kind: DiskKind,
     pub name: String,
+    /// Names like 'tmpfs' or 'overlay' repeat, the mount point tells the disks apart
+    pub mount_point: String,
     pub is_removable: bool,
 }
 
@@ -39,6 +44,10 @@ impl Storage {
             Ok(name) => name,
             Err(_) => return Err(std::io::Error::new(std::io::ErrorKind::Other, "Failed to convert disk name to string")),
         };
+        let mount_point = match disk.mount_point().to_str() {
+            Some(mount_point) => mount_point.to_string(),
+            None => return Err(std::io::Error::other("Failed to convert mount point to string")),
+        };
         
         Ok(Self {
             total: disk.total_space(),
@@ -49,6 +58,7 @@ impl Storage {
                 _ => DiskKind::Unknown,
             },
             name,
+            mount_point,
             is_removable: disk.is_removable(),
         })
     }
@@ -67,6 +77,7 @@ impl Storage {
 	pub fn try_into_active_model(&self, system_resources_id: i64) -> Result<StorageDeviceActiveModel, Box<dyn std::error::Error>> {
 		Ok(StorageDeviceActiveModel {
 			name: ActiveValue::Set(self.name.clone()),
+			mount_point: ActiveValue::Set(self.mount_point.clone()),
 			total: ActiveValue::Set(i64::try_from(self.total)?),
 			used: ActiveValue::Set(i64::try_from(self.used)?),
 			system_resource_id: ActiveValue::Set(Some(system_resources_id)),
@@ -77,6 +88,42 @@ impl Storage {
 	}
 }
 
+impl ChildRecord for Storage {
+	type Entity = StorageDeviceEntity;
+	type ActiveModel = StorageDeviceActiveModel;
+	// Disk names repeat, mount points don't
+	type Key = String;
+
+	fn parent_column() -> StorageDeviceColumn {
+		StorageDeviceColumn::SystemResourceId
+	}
+
+	fn id_column() -> StorageDeviceColumn {
+		StorageDeviceColumn::Id
+	}
+
+	fn row_id(row: &StorageDeviceModel) -> i64 {
+		row.id
+	}
+
+	fn row_key(row: &StorageDeviceModel, _position: usize) -> String {
+		row.mount_point.clone()
+	}
+
+	fn key(&self, _position: usize) -> String {
+		self.mount_point.clone()
+	}
+
+	fn active_model(&self, parent_id: i64, id: Option<i64>) -> Result<StorageDeviceActiveModel, Box<dyn Error>> {
+		let mut active_model = self.try_into_active_model(parent_id)?;
+		if let Some(id) = id {
+			active_model.id = ActiveValue::Unchanged(id);
+		}
+
+		Ok(active_model)
+	}
+}
+
 impl FromActiveModel<StorageDeviceActiveModel, Self> for Storage {
 	fn from_active_model(active_model: StorageDeviceActiveModel) -> Result<Self, Box<dyn Error>> {
 		// Get total
@@ -102,6 +149,12 @@ impl FromActiveModel<StorageDeviceActiveModel, Self> for Storage {
             Some(name) => name,
             None => return Err("Disk name is not provided".into()),
         };
+		
+		// Mount point
+		let mount_point = match active_model.mount_point.clone().take() {
+			Some(mount_point) => mount_point,
+			None => return Err("Mount point is not provided".into()),
+		};
         
         // Is removable
         let is_removable = match active_model.is_removable.clone().take() {
@@ -120,6 +173,7 @@ impl FromActiveModel<StorageDeviceActiveModel, Self> for Storage {
 			used,
 			kind,
 			name,
+			mount_point,
 			is_removable,
 		})
 	}
@@ -136,6 +190,7 @@ mod tests {
             used: 50,
             kind: DiskKind::HDD,
             name: "sda1".to_string(),
+            mount_point: "/".to_string(),
             is_removable: true,
         };
         assert_eq!(storage.usage_percentage(), 50.0);
@@ -148,6 +203,7 @@ mod tests {
             used: 50,
             kind: DiskKind::HDD,
             name: "sda1".to_string(),
+            mount_point: "/".to_string(),
             is_removable: true,
         };
         assert_eq!(storage.available_space(), 50);
@@ -1,25 +1,54 @@
-use entity::system_core::ActiveModel as SystemCoreActiveModel;
+use entity::system_core::{
+	ActiveModel as SystemCoreActiveModel, Column as SystemCoreColumn, Entity as SystemCoreEntity,
+	Model as SystemCoreModel,
+};
 use sea_orm::{
 	ActiveValue, TryIntoModel
 };
 use serde::{Deserialize, Serialize};
 use std::error::Error;
+use sysinfo::Cpu;
 
 pub mod controller;
 
-use crate::model::FromActiveModel;
+use crate::model::{reconcile::ChildRecord, FromActiveModel};
 use super::{to_f32, Resources};
 
 /// CPU Core
 ///
-///
-#[derive(Clone, Deserialize, Serialize)]
+/// Logical core, the processor fields are the same for every core of a processor
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
 pub struct CpuCore {
+	// Position of the core on the system
+	pub index: u32,
+	pub name: String,
+	pub vendor: String,
+	pub brand: String,
+	// Current frequency in MHz
+	pub frequency: u64,
 	pub usage_percentage: f64,
 	pub free_percentage: f64,
 }
 
 impl CpuCore {
+	/// Create from a refreshed sysinfo cpu
+	///
+	///
+	pub fn new(index: u32, cpu: &Cpu) -> Self {
+		let usage_percentage = (cpu.cpu_usage() as f64).clamp(0.0, 100.0);
+
+		Self {
+			index,
+			name: cpu.name().to_string(),
+			vendor: cpu.vendor_id().to_string(),
+			brand: cpu.brand().trim().to_string(),
+			frequency: cpu.frequency(),
+			usage_percentage,
+			free_percentage: 100.0 - usage_percentage,
+		}
+	}
+
 	/// Convert into active model
 	///
 	///
@@ -33,6 +62,11 @@ impl CpuCore {
 			usage_percentage: ActiveValue::Set(to_f32(cpu.usage_percentage)?),
 			free_percentage: ActiveValue::Set(to_f32(cpu.free_percentage)?),
 			system_resource_id: ActiveValue::Set(Some(system_resources_id)),
+			core_index: ActiveValue::Set(i32::try_from(cpu.index)?),
+			name: ActiveValue::Set(cpu.name.clone()),
+			vendor: ActiveValue::Set(cpu.vendor.clone()),
+			brand: ActiveValue::Set(cpu.brand.clone()),
+			frequency: ActiveValue::Set(i64::try_from(cpu.frequency)?),
 			..Default::default()
 		};
 		
@@ -40,13 +74,91 @@ impl CpuCore {
 	}
 }
 
+impl ChildRecord for CpuCore {
+	type Entity = SystemCoreEntity;
+	type ActiveModel = SystemCoreActiveModel;
+	// The core with the same index replaces the row
+	type Key = i64;
+
+	fn parent_column() -> SystemCoreColumn {
+		SystemCoreColumn::SystemResourceId
+	}
+
+	fn id_column() -> SystemCoreColumn {
+		SystemCoreColumn::Id
+	}
+
+	fn row_id(row: &SystemCoreModel) -> i64 {
+		row.id
+	}
+
+	fn row_key(row: &SystemCoreModel, _position: usize) -> i64 {
+		row.core_index.into()
+	}
+
+	fn key(&self, _position: usize) -> i64 {
+		self.index.into()
+	}
+
+	fn active_model(&self, parent_id: i64, id: Option<i64>) -> Result<SystemCoreActiveModel, Box<dyn Error>> {
+		let mut active_model = self.try_into_active_model(self, parent_id)?;
+		if let Some(id) = id {
+			active_model.id = ActiveValue::Unchanged(id);
+		}
+
+		Ok(active_model)
+	}
+}
+
 impl FromActiveModel<SystemCoreActiveModel, Self> for CpuCore {
 	fn from_active_model(active_model: SystemCoreActiveModel) -> Result<Self, Box<dyn Error>> {
 		let system_core_instance = active_model.try_into_model()?;
 		
         Ok(CpuCore {
+            index: u32::try_from(system_core_instance.core_index)?,
+            name: system_core_instance.name,
+            vendor: system_core_instance.vendor,
+            brand: system_core_instance.brand,
+            frequency: u64::try_from(system_core_instance.frequency)?,
             usage_percentage: system_core_instance.usage_percentage as f64,
             free_percentage: system_core_instance.free_percentage as f64,
         })
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_active_model_round_trip() {
+		let resources = Resources::fetch_resources().unwrap();
+		let cpu = resources.cpus.last().unwrap();
+
+		let active_model = cpu.active_model(1, Some(1)).unwrap();
+		let stored = CpuCore::from_active_model(active_model).unwrap();
+
+		assert_eq!(stored.index, cpu.index);
+		assert_eq!(stored.name, cpu.name);
+		assert_eq!(stored.vendor, cpu.vendor);
+		assert_eq!(stored.brand, cpu.brand);
+		assert_eq!(stored.frequency, cpu.frequency);
+		assert!((stored.usage_percentage - cpu.usage_percentage).abs() < 0.001);
+
+		// Core counts and usage are stored with the system resources
+		let mut active_model = resources.into_active_model().unwrap();
+		active_model.id = ActiveValue::Set(1);
+		let stored = Resources::from_active_model(
+			active_model,
+			vec![],
+			resources.memory.try_into_active_model(1).unwrap(),
+			vec![],
+			vec![],
+		)
+		.unwrap();
+
+		assert_eq!(stored.physical_cores, resources.physical_cores);
+		assert_eq!(stored.logical_cores, resources.logical_cores);
+		assert!((stored.cpu_usage - resources.cpu_usage).abs() < 0.001);
+	}
+}
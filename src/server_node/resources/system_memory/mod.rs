@@ -1,16 +1,19 @@
-use entity::system_memory::ActiveModel as SystemMemoryActiveModel;
+use entity::system_memory::{
+	ActiveModel as SystemMemoryActiveModel, Column as SystemMemoryColumn,
+	Entity as SystemMemoryEntity, Model as SystemMemoryModel,
+};
 use sea_orm::ActiveValue;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 
-use crate::model::FromActiveModel;
+use crate::model::{reconcile::ChildRecord, FromActiveModel};
 
 pub mod controller;
 
 /// Ram memory
 ///
 ///
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Memory {
 	pub total: u64,
 	pub used: u64,
@@ -35,6 +38,38 @@ impl Memory {
 	}
 }
 
+impl ChildRecord for Memory {
+	type Entity = SystemMemoryEntity;
+	type ActiveModel = SystemMemoryActiveModel;
+	// There's a single memory row, any other one is a leftover
+	type Key = ();
+
+	fn parent_column() -> SystemMemoryColumn {
+		SystemMemoryColumn::SystemResourceId
+	}
+
+	fn id_column() -> SystemMemoryColumn {
+		SystemMemoryColumn::Id
+	}
+
+	fn row_id(row: &SystemMemoryModel) -> i64 {
+		row.id
+	}
+
+	fn row_key(_row: &SystemMemoryModel, _position: usize) {}
+
+	fn key(&self, _position: usize) {}
+
+	fn active_model(&self, parent_id: i64, id: Option<i64>) -> Result<SystemMemoryActiveModel, Box<dyn Error>> {
+		let mut active_model = self.try_into_active_model(parent_id)?;
+		if let Some(id) = id {
+			active_model.id = ActiveValue::Unchanged(id);
+		}
+
+		Ok(active_model)
+	}
+}
+
 impl FromActiveModel<SystemMemoryActiveModel, Self> for Memory {
 	fn from_active_model(active_model: SystemMemoryActiveModel) -> Result<Self, Box<dyn Error>> {
 		// Memory
@@ -14,7 +14,8 @@ pub mod controller;
 
 /// Get computer IP v4
 ///
-///
+/// The first non-loopback address, every address of every interface is in the network interfaces
+/// of the resources
 pub fn get_computer_ip() -> Result<String, Box<dyn Error>> {
 	let interfaces = get_if_addrs()?;
 	for interface in interfaces {
@@ -29,7 +30,7 @@ pub fn get_computer_ip() -> Result<String, Box<dyn Error>> {
 	Ok("0.0.0.0".to_string())
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct IpAddress {
 	pub address: String,
 	pub port: u16,
@@ -44,13 +45,13 @@ impl IpAddress {
 	}
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum ServerLocation {
 	IpAddress(IpAddress),
 	DomainName(String),
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ServerInfo {
 	// Display name
 	pub name: String,
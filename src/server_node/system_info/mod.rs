@@ -5,7 +5,7 @@ use sysinfo::System;
 
 pub mod controller;
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SystemInfo {
 	pub name: String,
 	pub kernel_version: String,
@@ -9,7 +9,7 @@ use super::HiveFolderTestSuite;
 /// Hive server node
 /// 
 /// 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct HiveServerNode {
     pub ip: String,
     pub port: u16,
@@ -30,12 +30,10 @@ impl HiveServerNode {
             None => return Err(String::from("Failed to generate node name").into()),
         };
         
-        // Server node path
+        // Server node path, the suite folder is shared by its nodes
         let mut path = test_suite.path();
-        fs::create_dir(&path)?;
-        
         path.push(node_name.clone());
-        fs::create_dir(&path)?;
+        fs::create_dir_all(&path)?;
         
         // Create server node
         let server_node = Self { ip, port, node_name, test_suite, path };
@@ -0,0 +1,306 @@
+//! Hive simulation
+//! 
+//! Runs many nodes on the same process and tokio runtime, connected through loopback TCP.
+//! mDNS is disabled, so the tests decide which nodes know each other and nodes of other tests
+//! running at the same time aren't found.
+//! Every node writes its logs to its own folder of the test suite.
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use std::error::Error;
+use std::future::Future;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+
+use crate::p2p::hive::HiveParameters;
+use crate::p2p::node::handle::{NodeEvent, NodeHandle};
+use crate::p2p::node::message::HivePayload;
+use crate::p2p::node::Node;
+use crate::test::folder::hive_folder_test_suite::hive_server_node::HiveServerNode;
+use crate::test::folder::hive_folder_test_suite::HiveFolderTestSuite;
+use crate::test::folder::hive_test_folder::HiveTestFolder;
+
+/// Time to start a node or connect two of them
+pub const SIMULATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time between checks of a condition
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Wait until a condition holds
+/// 
+/// Returns whether it held before the time ran out
+pub async fn eventually<F, Fut>(within: Duration, mut condition: F) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let deadline = Instant::now() + within;
+    
+    loop {
+        if condition().await {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Node of a simulation
+/// 
+/// 
+pub struct SimulatedNode {
+    pub handle: NodeHandle,
+    pub server_node: HiveServerNode,
+    /// Loopback TCP address the node listens on
+    pub address: Multiaddr,
+}
+
+impl SimulatedNode {
+    pub fn peer_id(&self) -> PeerId {
+        self.handle.peer_id()
+    }
+    
+    /// Folder with the logs of this node only
+    /// 
+    /// 
+    pub fn log_folder(&self) -> PathBuf {
+        self.server_node.get_log_folder()
+    }
+}
+
+/// Many nodes running on the same process
+/// 
+/// Nodes aren't connected until the test says so
+pub struct HiveSimulation {
+    pub suite: HiveFolderTestSuite,
+    pub nodes: Vec<SimulatedNode>,
+}
+
+impl HiveSimulation {
+    /// Start nodes with default parameters
+    /// 
+    /// Their keys are derived from consecutive test key seeds, starting on the given one
+    pub async fn start(count: usize, first_seed: u8) -> Result<Self, Box<dyn Error>> {
+        Self::start_with(count, |index| HiveParameters {
+            key_seed: Some(first_seed + index as u8),
+            ..Default::default()
+        })
+        .await
+    }
+    
+    /// Start nodes with the parameters of each index
+    /// 
+    /// 
+    pub async fn start_with(
+        count: usize,
+        parameters: impl Fn(usize) -> HiveParameters,
+    ) -> Result<Self, Box<dyn Error>> {
+        let test_folder = HiveTestFolder::default();
+        test_folder.create()?;
+        
+        let mut simulation = Self {
+            suite: HiveFolderTestSuite::new(test_folder),
+            nodes: Vec::new(),
+        };
+        for index in 0..count {
+            simulation.add_node(parameters(index)).await?;
+        }
+        
+        Ok(simulation)
+    }
+    
+    /// Start one more node
+    /// 
+    /// It listens on a random loopback port, mDNS is always disabled
+    pub async fn add_node(&mut self, parameters: HiveParameters) -> Result<&SimulatedNode, Box<dyn Error>> {
+        let mut server_node = self.suite.create_server_node(Ipv4Addr::LOCALHOST.to_string(), 0)?;
+        
+        let parameters = HiveParameters {
+            no_mdns: true,
+            ..parameters
+        };
+        let handle = Node::new_with_test_handler(parameters, server_node.clone())
+            .await?
+            .spawn()?;
+        
+        let address = loopback_address(&handle).await?;
+        
+        // Keep the port the node got on its configuration
+        if let Some(Protocol::Tcp(port)) = address.iter().nth(1) {
+            server_node.port = port;
+        }
+        server_node.save_config()?;
+        
+        self.nodes.push(SimulatedNode { handle, server_node, address });
+        
+        Ok(&self.nodes[self.nodes.len() - 1])
+    }
+    
+    /// Connect two nodes
+    /// 
+    /// Returns once both are connected
+    pub async fn connect(&self, from: usize, to: usize) -> Result<(), Box<dyn Error>> {
+        let dialer = &self.nodes[from].handle;
+        let target = &self.nodes[to];
+        
+        dialer.dial(target.address.clone()).await?;
+        
+        let connected = eventually(SIMULATION_TIMEOUT, || async {
+            dialer.peers().await.is_ok_and(|peers| peers.contains(&target.peer_id()))
+        })
+        .await;
+        if !connected {
+            return Err(format!("Node {from} didn't connect to node {to}").into());
+        }
+        
+        Ok(())
+    }
+    
+    /// Connect every node with each other
+    /// 
+    /// Also waits for the nodes to exchange their subscriptions, so messages reach every peer
+    pub async fn connect_all(&self) -> Result<(), Box<dyn Error>> {
+        for from in 0..self.nodes.len() {
+            for to in (from + 1)..self.nodes.len() {
+                self.connect(from, to).await?;
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        
+        Ok(())
+    }
+    
+    /// Connect every node with the next one
+    /// 
+    /// Other connections are left to Kademlia, like on a hive where nodes only know a bootstrap peer
+    pub async fn connect_chain(&self) -> Result<(), Box<dyn Error>> {
+        for from in 1..self.nodes.len() {
+            self.connect(from - 1, from).await?;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        
+        Ok(())
+    }
+    
+    /// Publish a payload from a node and wait until every other node receives it
+    /// 
+    /// Fails with the nodes that didn't receive it in time
+    pub async fn message_reaches_all(
+        &self,
+        from: usize,
+        payload: HivePayload,
+        within: Duration,
+    ) -> Result<(), Box<dyn Error>> {
+        let deadline = Instant::now() + within;
+        let sender = self.nodes[from].peer_id();
+        let expected = serde_json::to_string(&payload)?;
+        
+        // Subscribe before publishing, events sent earlier aren't received
+        let receivers: Vec<_> = self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != from)
+            .map(|(index, node)| (index, node.handle.events()))
+            .collect();
+        
+        self.nodes[from].handle.publish(payload).await?;
+        
+        let mut missing = Vec::new();
+        for (index, mut events) in receivers {
+            let received = tokio::time::timeout_at(deadline, async {
+                loop {
+                    match events.recv().await {
+                        Ok(NodeEvent::Message(message)) if message.sender == sender => {
+                            if serde_json::to_string(&message.payload).is_ok_and(|payload| payload == expected) {
+                                return true;
+                            }
+                        }
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => return false,
+                    }
+                }
+            })
+            .await;
+            
+            if !received.unwrap_or(false) {
+                missing.push(index);
+            }
+        }
+        
+        if !missing.is_empty() {
+            return Err(format!("The message of node {from} didn't reach nodes {missing:?} within {within:?}").into());
+        }
+        
+        Ok(())
+    }
+    
+    /// Stop every node
+    /// 
+    /// Their logs are flushed once stopped
+    pub async fn shutdown(&self) -> Result<(), Box<dyn Error>> {
+        for node in &self.nodes {
+            node.handle.shutdown().await?;
+        }
+        
+        Ok(())
+    }
+}
+
+/// Wait until a node listens on a loopback TCP address
+/// 
+/// 
+pub(crate) async fn loopback_address(handle: &NodeHandle) -> Result<Multiaddr, Box<dyn Error>> {
+    let deadline = Instant::now() + SIMULATION_TIMEOUT;
+    
+    while Instant::now() < deadline {
+        let address = handle
+            .listen_addresses()
+            .await?
+            .into_iter()
+            .find(|address| {
+                let components: Vec<_> = address.iter().collect();
+                components.first() == Some(&Protocol::Ip4(Ipv4Addr::LOCALHOST))
+                    && matches!(components.get(1), Some(Protocol::Tcp(_)))
+            });
+        if let Some(address) = address {
+            return Ok(address);
+        }
+        
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    
+    Err("The node isn't listening on loopback".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_chain_of_nodes() {
+        let simulation = HiveSimulation::start(3, 149).await.unwrap();
+        simulation.connect_chain().await.unwrap();
+        
+        // The message reaches the end of the chain
+        simulation
+            .message_reaches_all(0, HivePayload::Chat("hello".to_string()), Duration::from_secs(10))
+            .await
+            .unwrap();
+        
+        simulation.shutdown().await.unwrap();
+        
+        // Every node logged to its own folder
+        let first_started = format!("Node {} started", simulation.nodes[0].peer_id());
+        for (index, node) in simulation.nodes.iter().enumerate() {
+            let logs: String = std::fs::read_dir(node.log_folder())
+                .unwrap()
+                .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+                .collect();
+            assert!(logs.contains(&format!("Node {} started", node.peer_id())));
+            assert_eq!(logs.contains(&first_started), index == 0);
+        }
+    }
+}
@@ -1 +1,3 @@
 pub mod folder;
+#[cfg(test)]
+pub mod hive_simulation;